@@ -9,6 +9,7 @@ mod start;
 
 use lumen_runtime::system;
 
+use liblumen_eir_interpreter::load::mark_remote_self_calls;
 use liblumen_eir_interpreter::VM;
 
 use libeir_diagnostics::{ColorChoice, Emitter, StandardStreamEmitter};
@@ -78,7 +79,8 @@ where
 }
 
 pub fn lower(input: &str, config: ParseConfig) -> Result<Module, ()> {
-    let (parsed, parser): (ErlAstModule, _) = parse(input, config);
+    let (mut parsed, parser): (ErlAstModule, _) = parse(input, config);
+    mark_remote_self_calls(&mut parsed);
     let (res, messages) = lower_module(&parsed);
 
     let emitter =
@@ -106,6 +108,9 @@ pub fn compile_erlang_module(text: &str) {
         fun.graph_validate_global();
     }
 
-    system::io::puts(&format!("Compiled and registered {}", eir_mod.name));
-    VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    let name = eir_mod.name.clone();
+    match VM.modules.write().unwrap().register_erlang_module(eir_mod) {
+        Ok(()) => system::io::puts(&format!("Compiled and registered {}", name)),
+        Err(error) => system::io::puts(&format!("Compiled {}, but {}", name, error)),
+    }
 }
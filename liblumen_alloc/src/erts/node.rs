@@ -7,4 +7,8 @@ impl Node {
     pub(in crate::erts) fn new(id: usize) -> Self {
         Self { id }
     }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
 }
@@ -4,6 +4,7 @@ mod flags;
 mod gc;
 mod heap;
 mod mailbox;
+mod max_heap_size;
 mod monitor;
 mod priority;
 
@@ -44,6 +45,7 @@ pub use self::flags::*;
 pub use self::gc::{GcError, RootSet};
 use self::heap::ProcessHeap;
 pub use self::mailbox::*;
+pub use self::max_heap_size::{MaxHeapSize, MaxHeapSizeReport};
 pub use self::monitor::Monitor;
 pub use self::priority::Priority;
 use crate::erts::process::alloc::heap_alloc::MakePidError;
@@ -72,15 +74,21 @@ pub struct Process {
     /// ID of the scheduler that is running the process
     scheduler_id: Mutex<Option<scheduler::ID>>,
     /// The priority of the process in `scheduler`.
-    pub priority: Priority,
+    priority: RwLock<Priority>,
     /// Process flags, e.g. `Process.flag/1`
     flags: AtomicProcessFlags,
     /// Minimum size of the heap that this process will start with
-    min_heap_size: usize,
+    min_heap_size: AtomicUsize,
     /// The maximum size of the heap allowed for this process
-    max_heap_size: usize,
+    max_heap_size: RwLock<MaxHeapSize>,
+    /// The last collection that exceeded `max_heap_size` that has not been logged yet
+    max_heap_size_report: Mutex<Option<MaxHeapSizeReport>>,
     /// Minimum virtual heap size for this process
-    min_vheap_size: usize,
+    min_vheap_size: AtomicUsize,
+    /// The module whose functions are called for undefined functions and registered names
+    error_handler: RwLock<Atom>,
+    /// The number of calls to save, as set with `process_flag(save_calls, N)`
+    save_calls: AtomicUsize,
     /// The percentage of used to unused space at which a collection is triggered
     gc_threshold: f64,
     /// The maximum number of minor collections before a full sweep occurs
@@ -129,9 +137,12 @@ impl Process {
 
         Self {
             flags: AtomicProcessFlags::new(ProcessFlags::Default),
-            min_heap_size: heap_size,
-            max_heap_size: 0,
-            min_vheap_size: 0,
+            min_heap_size: AtomicUsize::new(heap_size),
+            max_heap_size: Default::default(),
+            max_heap_size_report: Mutex::new(None),
+            min_vheap_size: AtomicUsize::new(0),
+            error_handler: RwLock::new(atom_unchecked("error_handler")),
+            save_calls: AtomicUsize::new(0),
             gc_threshold: 0.75,
             max_gen_gcs: 65535,
             off_heap,
@@ -143,7 +154,7 @@ impl Process {
             heap: Mutex::new(heap),
             code_stack: Default::default(),
             scheduler_id: Mutex::new(None),
-            priority,
+            priority: RwLock::new(priority),
             parent_pid,
             initial_module_function_arity,
             run_reductions: Default::default(),
@@ -165,6 +176,18 @@ impl Process {
         *self.scheduler_id.lock() = Some(scheduler_id);
     }
 
+    pub fn priority(&self) -> Priority {
+        *self.priority.read()
+    }
+
+    /// Changes the priority, returning the old priority.
+    ///
+    /// A process changes its own priority while it is running, so it moves to the run queue for
+    /// `priority` when its scheduler queues it again.
+    pub fn set_priority(&self, priority: Priority) -> Priority {
+        mem::replace(&mut *self.priority.write(), priority)
+    }
+
     // Flags
 
     pub fn are_flags_set(&self, flags: ProcessFlags) -> bool {
@@ -184,8 +207,32 @@ impl Process {
     }
 
     pub fn trap_exit(&self, value: bool) -> bool {
-        let flag = ProcessFlags::TrapExit;
+        self.put_flag(ProcessFlags::TrapExit, value)
+    }
 
+    pub fn traps_exit(&self) -> bool {
+        self.are_flags_set(ProcessFlags::TrapExit)
+    }
+
+    /// Sets whether messages are stored off heap, returning the old value
+    pub fn off_heap_message_queue(&self, value: bool) -> bool {
+        self.put_flag(ProcessFlags::OffHeapMessageQueue, value)
+    }
+
+    pub fn has_off_heap_message_queue(&self) -> bool {
+        self.are_flags_set(ProcessFlags::OffHeapMessageQueue)
+    }
+
+    /// Sets whether the process is sensitive, returning the old value
+    pub fn sensitive(&self, value: bool) -> bool {
+        self.put_flag(ProcessFlags::Sensitive, value)
+    }
+
+    pub fn is_sensitive(&self) -> bool {
+        self.are_flags_set(ProcessFlags::Sensitive)
+    }
+
+    fn put_flag(&self, flag: ProcessFlags, value: bool) -> bool {
         let old_flags = if value {
             self.set_flags(flag)
         } else {
@@ -195,8 +242,62 @@ impl Process {
         old_flags.are_set(flag)
     }
 
-    pub fn traps_exit(&self) -> bool {
-        self.are_flags_set(ProcessFlags::TrapExit)
+    // Settings
+
+    pub fn error_handler(&self) -> Atom {
+        *self.error_handler.read()
+    }
+
+    /// Changes the error handler module, returning the old module
+    pub fn set_error_handler(&self, error_handler: Atom) -> Atom {
+        mem::replace(&mut *self.error_handler.write(), error_handler)
+    }
+
+    /// The minimum size of the heap in words.  The heap doesn't shrink below it when collected.
+    pub fn min_heap_size(&self) -> usize {
+        self.min_heap_size.load(Ordering::Acquire)
+    }
+
+    /// Changes the minimum heap size to `min_heap_size`, rounded up to a heap size, returning the
+    /// old minimum heap size
+    pub fn set_min_heap_size(&self, min_heap_size: usize) -> usize {
+        self.min_heap_size
+            .swap(next_heap_size(min_heap_size), Ordering::AcqRel)
+    }
+
+    /// The limit on the size of the heap checked when the process is collected
+    pub fn max_heap_size(&self) -> MaxHeapSize {
+        *self.max_heap_size.read()
+    }
+
+    /// Changes the limit on the size of the heap, returning the old limit
+    pub fn set_max_heap_size(&self, max_heap_size: MaxHeapSize) -> MaxHeapSize {
+        mem::replace(&mut *self.max_heap_size.write(), max_heap_size)
+    }
+
+    /// Takes the report of the last collection that exceeded `max_heap_size` with
+    /// `error_logger` set, so that it is only logged once
+    pub fn take_max_heap_size_report(&self) -> Option<MaxHeapSizeReport> {
+        self.max_heap_size_report.lock().take()
+    }
+
+    /// The minimum size of the virtual heap of binaries in words
+    pub fn min_vheap_size(&self) -> usize {
+        self.min_vheap_size.load(Ordering::Acquire)
+    }
+
+    /// Changes the minimum size of the virtual heap of binaries, returning the old minimum size
+    pub fn set_min_vheap_size(&self, min_vheap_size: usize) -> usize {
+        self.min_vheap_size.swap(min_vheap_size, Ordering::AcqRel)
+    }
+
+    pub fn save_calls(&self) -> usize {
+        self.save_calls.load(Ordering::Acquire)
+    }
+
+    /// Changes the number of calls to save, returning the old number
+    pub fn set_save_calls(&self, save_calls: usize) -> usize {
+        self.save_calls.swap(save_calls, Ordering::AcqRel)
     }
 
     // Alloc
@@ -431,18 +532,7 @@ impl Process {
             }
         }
 
-        // status.write() scope
-        {
-            let mut writable_status = self.status.write();
-
-            if *writable_status == Status::Waiting {
-                *writable_status = Status::Runnable;
-
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+        Ok(self.stop_waiting())
     }
 
     fn send_message(&self, message: Message) {
@@ -451,6 +541,16 @@ impl Process {
 
     // Terms
 
+    pub fn append_to_writable_binary(
+        &self,
+        binary: Term,
+        bytes: &[u8],
+        bit_len: usize,
+    ) -> Result<Option<Term>, Alloc> {
+        self.acquire_heap()
+            .append_to_writable_binary(binary, bytes, bit_len)
+    }
+
     pub fn binary_from_bytes(&self, bytes: &[u8]) -> Result<Term, Alloc> {
         self.acquire_heap().binary_from_bytes(bytes)
     }
@@ -501,6 +601,16 @@ impl Process {
             .external_pid_with_node_id(node_id, number, serial)
     }
 
+    pub fn external_reference_with_node_id(
+        &self,
+        node_id: usize,
+        scheduler_id: scheduler::ID,
+        number: reference::Number,
+    ) -> Result<Term, Alloc> {
+        self.acquire_heap()
+            .external_reference_with_node_id(node_id, scheduler_id, number)
+    }
+
     pub fn float(&self, f: f64) -> Result<Term, Alloc> {
         self.acquire_heap().float(f)
     }
@@ -601,6 +711,16 @@ impl Process {
         self.acquire_heap().tuple_from_slices(slices)
     }
 
+    pub fn writable_binary_from_bytes(
+        &self,
+        bytes: &[u8],
+        bit_len: usize,
+        capacity: usize,
+    ) -> Result<Term, Alloc> {
+        self.acquire_heap()
+            .writable_binary_from_bytes(bytes, bit_len, capacity)
+    }
+
     // Process Dictionary
 
     /// Puts a new value under the given key in the process dictionary
@@ -795,9 +915,32 @@ impl Process {
         }
     }
 
+    /// Puts the process in the runnable status if it is waiting.
+    ///
+    /// Returns `true` if the process should stop waiting and be rescheduled as runnable.
+    pub fn stop_waiting(&self) -> bool {
+        let mut writable_status = self.status.write();
+
+        if *writable_status == Status::Waiting {
+            *writable_status = Status::Runnable;
+
+            true
+        } else {
+            false
+        }
+    }
+
     /// Puts the process in the waiting status
     pub fn wait(&self) {
-        *self.status.write() = Status::Waiting;
+        let mut writable_status = self.status.write();
+
+        // A process that was killed while running, such as for exceeding its `max_heap_size`,
+        // exits instead of waiting
+        match *writable_status {
+            Status::Exiting(_) => (),
+            _ => *writable_status = Status::Waiting,
+        }
+
         self.run_reductions.fetch_add(1, Ordering::AcqRel);
     }
 
@@ -815,7 +958,18 @@ impl Process {
     }
 
     pub fn exception(&self, exception: runtime::Exception) {
-        *self.status.write() = Status::Exiting(exception);
+        let mut writable_status = self.status.write();
+
+        // A process that was killed, such as for exceeding its `max_heap_size`, exits with
+        // `killed` even if it raises something else before it stops running
+        match *writable_status {
+            Status::Exiting(runtime::Exception {
+                class: runtime::Class::Exit,
+                ref reason,
+                ..
+            }) if *reason == atom_unchecked("killed") => (),
+            _ => *writable_status = Status::Exiting(exception),
+        }
     }
 
     // Code Stack
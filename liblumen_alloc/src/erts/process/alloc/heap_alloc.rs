@@ -19,7 +19,7 @@ use crate::erts::process::code::Code;
 use crate::erts::term::binary::aligned_binary::AlignedBinary;
 use crate::erts::term::binary::maybe_aligned_maybe_binary::MaybeAlignedMaybeBinary;
 use crate::erts::term::binary::IterableBitstring;
-use crate::erts::term::reference::{self, Reference};
+use crate::erts::term::reference::{self, ExternalReference, Reference};
 use crate::erts::term::resource;
 use crate::erts::term::{
     make_pid, pid, AsTerm, BinaryType, BytesFromBinaryError, Closure, Cons, ExternalPid, Float,
@@ -188,6 +188,19 @@ pub trait HeapAlloc {
         Ok(heap_external_pid)
     }
 
+    /// Creates an `ExternalReference` with the given `node`, `scheduler_id` and `number`.
+    fn external_reference_with_node_id(
+        &mut self,
+        node_id: usize,
+        scheduler_id: scheduler::ID,
+        number: reference::Number,
+    ) -> Result<Term, Alloc>
+    where
+        Self: core::marker::Sized,
+    {
+        ExternalReference::with_node_id(node_id, scheduler_id, number).clone_to_heap(self)
+    }
+
     fn float(&mut self, f: f64) -> Result<Term, Alloc> {
         let float = Float::new(f);
 
@@ -457,6 +470,84 @@ pub trait HeapAlloc {
         }
     }
 
+    /// Appends the first `bit_len` bits of `bytes` to `binary` in place, if it is a writable
+    /// `SubBinary` whose `ProcBin` has room for them, returning the new writable `SubBinary`.
+    ///
+    /// Returns `None` if `binary` cannot be appended to in place, in which case a new binary has
+    /// to be constructed, such as with `writable_binary_from_bytes`.
+    ///
+    /// NOTE: If allocation fails for some reason, `Err(Alloc)` is returned, this usually
+    /// indicates that a process needs to be garbage collected, but in some cases may indicate
+    /// that the global heap is out of space.
+    fn append_to_writable_binary(
+        &mut self,
+        binary: Term,
+        bytes: &[u8],
+        bit_len: usize,
+    ) -> Result<Option<Term>, Alloc> {
+        let is_subbinary = match binary.to_typed_term().unwrap() {
+            TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
+                TypedTerm::SubBinary(_) => true,
+                _ => false,
+            },
+            _ => false,
+        };
+
+        if !is_subbinary {
+            return Ok(None);
+        }
+
+        let subbinary = unsafe { &mut *(binary.boxed_val() as *mut SubBinary) };
+
+        match subbinary.append_in_place(bytes, bit_len) {
+            // If this allocation fails, `binary` is left unwritable, so appending to it again
+            // copies it instead of overwriting the bytes written here
+            Some(appended) => unsafe {
+                let ptr = self.alloc_layout(Layout::new::<SubBinary>())?.as_ptr() as *mut SubBinary;
+                ptr::write(ptr, appended);
+
+                Ok(Some(Term::make_boxed(ptr)))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Constructs a writable binary of the first `bit_len` bits of `bytes`, with room for
+    /// `capacity` bytes, so that it can be appended to in place, like BEAM's `bs_append`.
+    ///
+    /// The returned term is a writable `SubBinary` of a reference-counted binary, which is added
+    /// to the process's virtual binary heap.
+    ///
+    /// NOTE: If allocation fails for some reason, `Err(Alloc)` is returned, this usually
+    /// indicates that a process needs to be garbage collected, but in some cases may indicate
+    /// that the global heap is out of space.
+    fn writable_binary_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        bit_len: usize,
+        capacity: usize,
+    ) -> Result<Term, Alloc>
+    where
+        Self: VirtualAlloc,
+    {
+        assert_eq!(bytes.len(), (bit_len + 7) / 8);
+
+        // Allocates on global heap
+        let bin = ProcBin::with_capacity(bytes, capacity, BinaryType::Raw)?;
+
+        unsafe {
+            let header_ptr = self.alloc_layout(Layout::new::<ProcBin>())?.as_ptr() as *mut ProcBin;
+            ptr::write(header_ptr, bin);
+            self.virtual_alloc(&*header_ptr);
+            let original = Term::make_boxed(header_ptr);
+
+            let ptr = self.alloc_layout(Layout::new::<SubBinary>())?.as_ptr() as *mut SubBinary;
+            ptr::write(ptr, SubBinary::writable(original, bit_len));
+
+            Ok(Term::make_boxed(ptr))
+        }
+    }
+
     /// Constructs a `Tuple` from an `Iterator<Item = Term>` and accompanying `len`.
     ///
     /// Be aware that this does not allocate non-immediate terms in `elements` on the process heap,
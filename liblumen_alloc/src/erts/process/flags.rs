@@ -26,6 +26,14 @@ impl ProcessFlags {
     /// This flag indicates the processes linked to this process should send exit messages instead
     /// of causing this process to exit when they exit
     pub const TrapExit: Self = Self(1 << 6);
+    /// This flag indicates that the timer of the `receive` the process is in has timed out
+    pub const TimedOut: Self = Self(1 << 7);
+    /// This flag indicates that messages should be stored outside of the process heap until they
+    /// are received, as set with `process_flag(message_queue_data, off_heap)`
+    pub const OffHeapMessageQueue: Self = Self(1 << 8);
+    /// This flag indicates that the process is sensitive, so its data can't be inspected with
+    /// tracing or `process_info/2`
+    pub const Sensitive: Self = Self(1 << 9);
 
     pub fn are_set(&self, flags: ProcessFlags) -> bool {
         (*self & flags) == flags
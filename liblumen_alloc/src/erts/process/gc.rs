@@ -15,11 +15,6 @@ pub enum GcError {
     /// The system is out of memory, and there is not much you can do
     /// but panic, however this choice is left up to the caller
     Alloc(Alloc),
-    /// Occurs when a process is configured with a maximum heap size,
-    /// and a projected heap growth is found to exceed the limit. In
-    /// this situation the only meaningful thing to do is to kill the
-    /// process
-    MaxHeapSizeExceeded,
     /// Indicates that an allocation could not be filled without first
     /// performing a full sweep collection
     FullsweepRequired,
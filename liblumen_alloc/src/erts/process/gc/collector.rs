@@ -8,8 +8,8 @@ use liblumen_core::util::pointer::{distance_absolute, in_area};
 use super::*;
 use crate::erts::exception::system::Alloc;
 use crate::erts::process::alloc;
-use crate::erts::process::ProcessHeap;
-use crate::erts::term::{is_move_marker, ProcBin};
+use crate::erts::process::{MaxHeapSizeReport, ProcessHeap};
+use crate::erts::term::{atom_unchecked, is_move_marker, ProcBin};
 use crate::erts::*;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -118,9 +118,10 @@ impl<'p, 'h> GarbageCollector<'p, 'h> {
             new_size = alloc::next_heap_size(new_size);
         }
         // Verify that our projected heap size is not going to blow the max heap size, if set
-        // NOTE: When this happens, we will be left with no choice but to kill the process
-        if self.process.max_heap_size > 0 && self.process.max_heap_size < new_size {
-            return Err(GcError::MaxHeapSizeExceeded);
+        // NOTE: When this happens, the process is killed and/or reported as its max_heap_size says
+        let max_heap_size = self.process.max_heap_size().size;
+        if max_heap_size > 0 && max_heap_size < new_size {
+            self.exceed_max_heap_size(new_size);
         }
         // Unset heap_grow and need_fullsweep flags, because we are doing both
         self.process
@@ -244,11 +245,12 @@ impl<'p, 'h> GarbageCollector<'p, 'h> {
         } else if total_size * 3 < need_after * 4 {
             // `need_after` requires more than 75% of the current size, schedule some growth
             self.process.flags.set(ProcessFlags::GrowHeap);
-        } else if total_size > need_after * 4 && self.process.min_heap_size < total_size {
+        } else if total_size > need_after * 4 && self.process.min_heap_size() < total_size {
             // We need less than 25% of the current heap, shrink
             let wanted = need_after * 2;
-            let size = if wanted < self.process.min_heap_size {
-                self.process.min_heap_size
+            let min_heap_size = self.process.min_heap_size();
+            let size = if wanted < min_heap_size {
+                min_heap_size
             } else {
                 alloc::next_heap_size(wanted)
             };
@@ -277,7 +279,7 @@ impl<'p, 'h> GarbageCollector<'p, 'h> {
 
         // Verify that our projected heap size does not exceed
         // the max heap size, if one was configured
-        self.verify_heap_size(need, size_before, mature_size)?;
+        self.verify_heap_size(need, size_before, mature_size);
 
         // Allocate an old heap if we don't have one and one is needed
         self.ensure_old_heap(size_before, mature_size)
@@ -333,8 +335,9 @@ impl<'p, 'h> GarbageCollector<'p, 'h> {
                 }
             }
 
-            wanted = if wanted < self.process.min_heap_size {
-                self.process.min_heap_size
+            let min_heap_size = self.process.min_heap_size();
+            wanted = if wanted < min_heap_size {
+                min_heap_size
             } else {
                 alloc::next_heap_size(wanted)
             };
@@ -502,19 +505,15 @@ impl<'p, 'h> GarbageCollector<'p, 'h> {
         Ok(())
     }
 
-    /// Ensures the projected heap size does not exceed the maximum heap size configured
+    /// Checks whether the projected heap size exceeds the maximum heap size configured
     #[inline]
-    fn verify_heap_size(
-        &self,
-        need: usize,
-        size_before: usize,
-        mature_size: usize,
-    ) -> Result<(), GcError> {
+    fn verify_heap_size(&self, need: usize, size_before: usize, mature_size: usize) {
         let young = &self.heap.young;
         let old = &self.heap.old;
 
         // If a max heap size is set, make sure we're not going to exceed it
-        if self.process.max_heap_size > 0 {
+        let max_heap_size = self.process.max_heap_size().size;
+        if max_heap_size > 0 {
             // First, check if we have exceeded the max heap size
             let mut heap_size = size_before;
             // Includes unused area between stack and heap
@@ -535,13 +534,33 @@ impl<'p, 'h> GarbageCollector<'p, 'h> {
             }
             heap_size += new_heap_size;
 
-            // When this error type is returned, a full sweep will be triggered
-            if heap_size > self.process.max_heap_size {
-                return Err(GcError::MaxHeapSizeExceeded);
+            if heap_size > max_heap_size {
+                self.exceed_max_heap_size(heap_size);
             }
         }
+    }
 
-        Ok(())
+    /// Applies the `max_heap_size` of the process when the heap needs `heap_size` words for this
+    /// collection.  The process is marked as exiting with `killed` if `kill` is set, and a report is
+    /// left for the scheduler to log if `error_logger` is set.
+    ///
+    /// The collection still goes ahead, as in BEAM, so that the allocation that needed it succeeds.
+    /// A killed process stops running right after the collection and exits with `killed`, even if
+    /// it raised something else in the meantime.
+    fn exceed_max_heap_size(&self, heap_size: usize) {
+        let max_heap_size = self.process.max_heap_size();
+
+        if max_heap_size.error_logger {
+            *self.process.max_heap_size_report.lock() = Some(MaxHeapSizeReport {
+                heap_size,
+                max_heap_size,
+                module_function_arity: self.process.current_module_function_arity(),
+            });
+        }
+
+        if max_heap_size.kill && !self.process.is_exiting() {
+            self.process.exception(exit!(atom_unchecked("killed")));
+        }
     }
 
     /// Determines if the current collection requires a full sweep or not
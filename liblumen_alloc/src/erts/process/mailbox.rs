@@ -7,7 +7,7 @@ use crate::borrow::CloneToProcess;
 use crate::erts::exception::system::Alloc;
 use crate::erts::message::{self, Message};
 use crate::erts::process::Process;
-use crate::erts::term::Term;
+use crate::erts::term::{Reference, Term};
 
 #[derive(Debug)]
 pub struct Mailbox {
@@ -15,6 +15,9 @@ pub struct Mailbox {
     seen: isize,
 
     cursor: usize,
+    /// The timer of the current receive, so that it can be cancelled if a message matches before
+    /// it times out
+    timer: Option<Reference>,
 }
 
 impl Mailbox {
@@ -38,6 +41,13 @@ impl Mailbox {
             Message::HeapFragment(_) => true,
         }
     }
+    /// The message matched by the current receive, before it is removed by `recv_finish`
+    pub fn recv_last(&self) -> Term {
+        match &self.messages[self.cursor - 1] {
+            Message::Process(message::Process { data }) => *data,
+            Message::HeapFragment(message::HeapFragment { data, .. }) => *data,
+        }
+    }
     pub fn recv_increment(&mut self) {
         self.cursor += 1;
     }
@@ -45,6 +55,16 @@ impl Mailbox {
         self.remove(self.cursor - 1, proc);
         self.cursor = 0;
     }
+    pub fn recv_timeout(&mut self) {
+        self.cursor = 0;
+    }
+    pub fn recv_set_timer(&mut self, timer: Reference) {
+        debug_assert!(self.timer.is_none());
+        self.timer = Some(timer);
+    }
+    pub fn recv_take_timer(&mut self) -> Option<Reference> {
+        self.timer.take()
+    }
     // End receive implementation for the eir interpreter
 
     pub fn flush<F>(&mut self, predicate: F, process: &Process) -> bool
@@ -173,6 +193,7 @@ impl Default for Mailbox {
             messages: Default::default(),
             seen: -1,
             cursor: 0,
+            timer: None,
         }
     }
 }
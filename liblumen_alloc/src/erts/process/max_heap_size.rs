@@ -0,0 +1,36 @@
+use ::alloc::sync::Arc;
+
+use crate::erts::ModuleFunctionArity;
+
+/// The limit on the heap of a process, as set with the `max_heap_size` option of `spawn_opt/4` or
+/// `process_flag/2`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MaxHeapSize {
+    /// The maximum size of the heap in words, or `0` for no limit
+    pub size: usize,
+    /// Whether the process is killed when its heap exceeds `size`
+    pub kill: bool,
+    /// Whether an error report is logged when the heap of the process exceeds `size`
+    pub error_logger: bool,
+}
+
+impl Default for MaxHeapSize {
+    fn default() -> MaxHeapSize {
+        MaxHeapSize {
+            size: 0,
+            kill: true,
+            error_logger: true,
+        }
+    }
+}
+
+/// A collection that grew the heap of a process past its `MaxHeapSize`, left for the scheduler to
+/// log when `error_logger` is set
+#[derive(Clone, Debug)]
+pub struct MaxHeapSizeReport {
+    /// The size of the heap in words needed for the collection
+    pub heap_size: usize,
+    pub max_heap_size: MaxHeapSize,
+    /// The function the process was running when it was collected
+    pub module_function_arity: Option<Arc<ModuleFunctionArity>>,
+}
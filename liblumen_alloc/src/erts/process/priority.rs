@@ -1,7 +1,7 @@
 use core::convert::{TryFrom, TryInto};
 
 use crate::erts::exception::runtime;
-use crate::erts::term::{Atom, Term, TypedTerm};
+use crate::erts::term::{atom_unchecked, Atom, Term, TypedTerm};
 
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub enum Priority {
@@ -17,6 +17,19 @@ impl Default for Priority {
     }
 }
 
+impl Into<Term> for Priority {
+    fn into(self) -> Term {
+        let name = match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Max => "max",
+        };
+
+        atom_unchecked(name)
+    }
+}
+
 impl TryFrom<Atom> for Priority {
     type Error = runtime::Exception;
 
@@ -97,6 +97,34 @@ mod traps_exit {
     }
 }
 
+mod exception {
+    use super::*;
+
+    use crate::erts::term::atom_unchecked;
+
+    #[test]
+    fn sets_exiting_status() {
+        let process = process();
+
+        process.exception(badarg!());
+
+        assert_eq!(*process.status.read(), Status::Exiting(badarg!()));
+    }
+
+    #[test]
+    fn keeps_killed_status() {
+        let process = process();
+
+        process.exception(exit!(atom_unchecked("killed")));
+        process.exception(badarg!());
+
+        assert_eq!(
+            *process.status.read(),
+            Status::Exiting(exit!(atom_unchecked("killed")))
+        );
+    }
+}
+
 mod integer {
     use super::*;
 
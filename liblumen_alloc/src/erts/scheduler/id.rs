@@ -12,6 +12,18 @@ pub fn next() -> ID {
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct ID(usize);
+impl ID {
+    /// Given the raw ID value (as a usize), reifies it into an `ID`
+    #[inline]
+    pub unsafe fn from_raw(raw: usize) -> Self {
+        Self(raw)
+    }
+
+    #[inline]
+    pub fn as_usize(&self) -> usize {
+        self.0
+    }
+}
 
 impl Display for ID {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
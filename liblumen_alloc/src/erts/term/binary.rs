@@ -9,6 +9,8 @@ use core::mem;
 use core::ptr;
 use core::slice;
 
+use alloc::vec::Vec;
+
 use crate::borrow::CloneToProcess;
 use crate::erts::term::binary::aligned_binary::AlignedBinary;
 use crate::erts::term::binary::maybe_aligned_maybe_binary::MaybeAlignedMaybeBinary;
@@ -302,6 +304,41 @@ fn get_bit(byte: u8, offset: usize) -> u8 {
     byte >> (7 - (offset as u8)) & 1
 }
 
+/// Appends the first `bit_len` bits of `bytes` to the first `buffer_bit_len` bits of `buffer`,
+/// most significant bit first.
+///
+/// Afterwards, `buffer` holds exactly the bytes needed for all the bits, with any bits after
+/// them in the last byte cleared.
+pub fn append_bits(buffer: &mut Vec<u8>, buffer_bit_len: usize, bytes: &[u8], bit_len: usize) {
+    assert!(num_bytes(buffer_bit_len) <= buffer.len());
+    assert!(num_bytes(bit_len) <= bytes.len());
+
+    let total_bit_len = buffer_bit_len + bit_len;
+    let shift = bit_offset(buffer_bit_len);
+    buffer.truncate(num_bytes(buffer_bit_len));
+
+    if shift == 0 {
+        buffer.extend_from_slice(&bytes[..num_bytes(bit_len)]);
+    } else {
+        let last = buffer.len() - 1;
+        buffer[last] &= !make_bitmask(8 - shift as u8);
+
+        for byte in &bytes[..num_bytes(bit_len)] {
+            let last = buffer.len() - 1;
+            buffer[last] |= byte >> shift;
+            buffer.push(byte << (8 - shift));
+        }
+    }
+
+    buffer.truncate(num_bytes(total_bit_len));
+
+    let partial_byte_bit_len = bit_offset(total_bit_len);
+    if partial_byte_bit_len > 0 {
+        let last = buffer.len() - 1;
+        buffer[last] &= !make_bitmask(8 - partial_byte_bit_len as u8);
+    }
+}
+
 /// Returns the number of bytes needed to store `bits` bits
 #[inline]
 fn num_bytes(bits: usize) -> usize {
@@ -7,6 +7,7 @@ use core::str;
 
 use alloc::borrow::ToOwned;
 use alloc::string::String;
+use alloc::vec::Vec;
 
 use liblumen_core::util::pointer::distance_absolute;
 
@@ -21,7 +22,9 @@ use crate::erts::term::{
 };
 use crate::erts::HeapAlloc;
 
-use super::{byte_offset, num_bytes, Bitstring, ByteIterator, MaybePartialByte};
+use super::{
+    bit_offset, byte_offset, get_bit, num_bytes, Bitstring, ByteIterator, MaybePartialByte,
+};
 
 pub struct FullByteIter {}
 
@@ -73,17 +76,20 @@ impl MatchBuffer {
         let bin_ptr = original.boxed_val();
         let bin = unsafe { *bin_ptr };
 
-        let (base, full_byte_bit_len, byte_offset, bit_offset, partial_byte_bit_len) =
+        // Sub binaries are matched against their original binary, so that the sub binaries created
+        // by matching refer to a `ProcBin` or `HeapBin` too.
+        let (original, base, full_byte_bit_len, byte_offset, bit_offset, partial_byte_bit_len) =
             if bin.is_procbin() {
                 let pb = unsafe { &*(bin_ptr as *mut ProcBin) };
-                (pb.bytes(), pb.full_byte_len() * 8, 0, 0, 0)
+                (original, pb.bytes(), pb.full_byte_len() * 8, 0, 0, 0)
             } else if bin.is_heapbin() {
                 let hb = unsafe { &*(bin_ptr as *mut HeapBin) };
-                (hb.bytes(), hb.full_byte_len() * 8, 0, 0, 0)
+                (original, hb.bytes(), hb.full_byte_len() * 8, 0, 0, 0)
             } else {
                 assert!(bin.is_subbinary_header());
                 let sb = unsafe { &*(bin_ptr as *mut SubBinary) };
                 (
+                    sb.original(),
                     sb.bytes(),
                     sb.full_byte_len() * 8,
                     sb.byte_offset(),
@@ -139,6 +145,56 @@ impl MatchContext {
         *ptr
     }
 
+    /// The number of bits that have not been matched yet
+    #[inline]
+    pub fn remaining_bit_len(&self) -> usize {
+        self.buffer.bit_len - self.buffer.bit_offset
+    }
+
+    /// Reads the next `bit_len` bits and advances past them
+    ///
+    /// The bits are returned most significant bit first, so when `bit_len` is not a multiple of 8,
+    /// the remaining bits are in the high bits of the last byte.  Returns `None`, without
+    /// advancing, if fewer than `bit_len` bits remain.
+    ///
+    /// See `erts_bs_get_integer_2` in `erl_bits.c`
+    pub fn read_bits(&mut self, bit_len: usize) -> Option<Vec<u8>> {
+        if self.remaining_bit_len() < bit_len {
+            return None;
+        }
+
+        let offset = self.buffer.bit_offset;
+        let mut bytes = vec![0; num_bytes(bit_len)];
+
+        if bit_offset(offset) == 0 {
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    self.buffer.base.add(byte_offset(offset)),
+                    bytes.as_mut_ptr(),
+                    bytes.len(),
+                );
+            }
+
+            let partial_byte_bit_len = bit_offset(bit_len);
+            if partial_byte_bit_len > 0 {
+                let last = bytes.len() - 1;
+                bytes[last] &= !(0xFF >> partial_byte_bit_len);
+            }
+        } else {
+            for index in 0..bit_len {
+                let position = offset + index;
+                let byte = unsafe { *self.buffer.base.add(byte_offset(position)) };
+                let bit = get_bit(byte, bit_offset(position));
+
+                bytes[byte_offset(index)] |= bit << (7 - bit_offset(index));
+            }
+        }
+
+        self.buffer.bit_offset += bit_len;
+
+        Some(bytes)
+    }
+
     /// Used by garbage collection to get a pointer to the original
     /// term in order to place/modify move markers
     #[inline]
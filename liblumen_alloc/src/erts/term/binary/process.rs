@@ -33,9 +33,23 @@ use super::{
 pub struct ProcBinInner {
     refc: AtomicUsize,
     flags: usize,
+    /// The number of bytes allocated for the data, which is more than the size for writable
+    /// binaries, so that they can be appended to without reallocating
+    capacity: usize,
+    /// Whether the data may be written after it is created, which is only true for binaries
+    /// created with `ProcBin::with_capacity`
+    writable: bool,
     bytes: *mut u8,
 }
 impl ProcBinInner {
+    /// The layout of the inner header followed by `capacity` bytes of data, and the offset of the
+    /// data
+    fn layout(capacity: usize) -> (Layout, usize) {
+        Layout::new::<Self>()
+            .extend(unsafe { Layout::from_size_align_unchecked(capacity, mem::align_of::<u8>()) })
+            .unwrap()
+    }
+
     #[inline]
     fn bytes(&self) -> *mut u8 {
         self.bytes
@@ -75,6 +89,8 @@ impl Debug for ProcBinInner {
         f.debug_struct("ProcBinInner")
             .field("refc", &self.refc)
             .field("flags", &format_args!("{:#b}", self.flags))
+            .field("capacity", &self.capacity)
+            .field("writable", &self.writable)
             .field("bytes", &self.bytes)
             .finish()
     }
@@ -178,14 +194,36 @@ impl ProcBin {
 
     /// Creates a new procbin from a raw byte slice, by copying it to the heap
     pub fn from_slice(s: &[u8], binary_type: BinaryType) -> Result<Self, Alloc> {
+        Self::allocate(s, s.len(), false, binary_type)
+    }
+
+    /// Creates a new writable procbin from a raw byte slice, by copying it to the heap, with room
+    /// for `capacity` bytes in total, so that more bytes can be written with `write_at` without
+    /// reallocating
+    pub fn with_capacity(
+        s: &[u8],
+        capacity: usize,
+        binary_type: BinaryType,
+    ) -> Result<Self, Alloc> {
+        Self::allocate(s, capacity, true, binary_type)
+    }
+
+    fn allocate(
+        s: &[u8],
+        capacity: usize,
+        writable: bool,
+        binary_type: BinaryType,
+    ) -> Result<Self, Alloc> {
         use liblumen_core::sys::alloc as sys_alloc;
 
         let full_byte_len = s.len();
-        let (layout, offset) = Layout::new::<ProcBinInner>()
-            .extend(unsafe {
-                Layout::from_size_align_unchecked(full_byte_len, mem::align_of::<u8>())
-            })
-            .unwrap();
+        assert!(
+            full_byte_len <= capacity,
+            "full_byte_len ({}) > capacity ({})",
+            full_byte_len,
+            capacity
+        );
+        let (layout, offset) = ProcBinInner::layout(capacity);
 
         unsafe {
             match sys_alloc::alloc(layout) {
@@ -197,6 +235,8 @@ impl ProcBin {
                     inner_ptr.write(ProcBinInner {
                         refc: AtomicUsize::new(1),
                         flags: full_byte_len | binary_type.to_flags(),
+                        capacity,
+                        writable,
                         bytes,
                     });
                     ptr::copy_nonoverlapping(s.as_ptr(), bytes, full_byte_len);
@@ -212,6 +252,62 @@ impl ProcBin {
         }
     }
 
+    /// The number of bytes that can be held without reallocating
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.inner().capacity
+    }
+
+    /// Whether `write_at` may be used: this binary was created writable with `with_capacity` and
+    /// this `ProcBin` is the only reference to the data, so no other process can be reading it.
+    #[inline]
+    pub fn is_writable(&self) -> bool {
+        let inner = self.inner();
+
+        inner.writable && inner.refc.load(atomic::Ordering::Acquire) == 1
+    }
+
+    /// Writes `bytes` at `byte_offset`, replacing any bytes after `byte_offset`, so that the
+    /// binary ends with `bytes`.
+    ///
+    /// This is how writable binaries are appended to in place: the bytes before `byte_offset` are
+    /// left untouched, so sub binaries of them still see the same bytes.
+    ///
+    /// # Safety
+    ///
+    /// This binary must be writable, as checked by `is_writable`, so that the reference count is
+    /// `1` and no other process can see the write.  That is asserted, but the reference count can
+    /// only stay `1` while the caller holds the heap of the process owning this `ProcBin`.  No term
+    /// may refer to the bytes after `byte_offset`, such as this `ProcBin` itself or a sub binary
+    /// that extends past `byte_offset`.
+    pub unsafe fn write_at(&self, byte_offset: usize, bytes: &[u8]) {
+        assert!(self.is_writable(), "ProcBin is not writable");
+
+        let inner = self.inner.as_ptr();
+        let full_byte_len = (*inner).full_byte_len();
+        let new_full_byte_len = byte_offset + bytes.len();
+
+        assert!(
+            byte_offset <= full_byte_len,
+            "byte_offset ({}) > full_byte_len ({})",
+            byte_offset,
+            full_byte_len
+        );
+        assert!(
+            new_full_byte_len <= (*inner).capacity,
+            "new full_byte_len ({}) > capacity ({})",
+            new_full_byte_len,
+            (*inner).capacity
+        );
+
+        ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            (*inner).bytes().add(byte_offset),
+            bytes.len(),
+        );
+        (*inner).flags = new_full_byte_len | ((*inner).flags & FLAG_MASK);
+    }
+
     /// Converts this binary to a `&str` slice.
     ///
     /// This conversion does not move the string, it can be considered as
@@ -246,12 +342,9 @@ impl ProcBin {
 
         if self.inner().refc.fetch_sub(1, atomic::Ordering::Release) == 1 {
             atomic::fence(atomic::Ordering::Acquire);
-            let bytes = self.inner().bytes();
-            let size = self.inner().full_byte_len();
-            sys_alloc::free(
-                bytes,
-                Layout::from_size_align_unchecked(size, mem::align_of::<usize>()),
-            );
+            // The data is allocated inline after the inner header
+            let (layout, _) = ProcBinInner::layout(self.inner().capacity);
+            sys_alloc::free(self.inner.as_ptr() as *mut u8, layout);
         }
     }
 }
@@ -168,7 +168,7 @@ impl SubBinary {
     /// See erts_bs_get_binary_2 in erl_bits.c:460
     #[inline]
     pub fn from_match(ctx: &mut MatchContext, bit_len: usize) -> Self {
-        assert!(ctx.buffer.bit_len - ctx.buffer.bit_offset < bit_len);
+        assert!(bit_len <= ctx.buffer.bit_len - ctx.buffer.bit_offset);
 
         let original = ctx.buffer.original;
         let subbinary_byte_offset = byte_offset(ctx.buffer.bit_offset);
@@ -208,6 +208,23 @@ impl SubBinary {
         }
     }
 
+    /// A writable sub binary of the first `bit_len` bits of the `ProcBin` `original`.
+    ///
+    /// Only the latest writable sub binary of `original` may be appended to in place, so the
+    /// previous one must be made unwritable with `make_unwritable` when it is appended to.
+    #[inline]
+    pub fn writable(original: Term, bit_len: usize) -> Self {
+        Self {
+            header: Self::header(),
+            original,
+            byte_offset: 0,
+            bit_offset: 0,
+            full_byte_len: byte_offset(bit_len),
+            partial_byte_bit_len: bit_offset(bit_len) as u8,
+            writable: true,
+        }
+    }
+
     #[inline]
     pub unsafe fn from_raw(ptr: *mut SubBinary) -> Self {
         *ptr
@@ -228,6 +245,17 @@ impl SubBinary {
         self.original
     }
 
+    /// Whether the bytes after this sub binary in `original` may be overwritten when appending
+    #[inline]
+    pub fn is_writable(&self) -> bool {
+        self.writable
+    }
+
+    #[inline]
+    pub fn make_unwritable(&mut self) {
+        self.writable = false;
+    }
+
     #[inline]
     pub fn bytes(&self) -> *mut u8 {
         let real_bin_ptr = follow_moved(self.original).boxed_val();
@@ -242,6 +270,46 @@ impl SubBinary {
         }
     }
 
+    /// Appends the first `bit_len` bits of `bytes`, most significant bit first, to this writable
+    /// sub binary in place, if its `ProcBin` has room for them, and makes this sub binary
+    /// unwritable.
+    ///
+    /// Returns the writable sub binary of the result, or `None` if this sub binary is not
+    /// writable, its `ProcBin` is shared with another process or does not have room, in which
+    /// case nothing is written.
+    pub fn append_in_place(&mut self, bytes: &[u8], bit_len: usize) -> Option<Self> {
+        if !self.writable {
+            return None;
+        }
+
+        let original = follow_moved(self.original);
+        // Only `ProcBin`s are writable
+        let bin = unsafe { &*(original.boxed_val() as *const ProcBin) };
+        let total_bit_len = self.total_bit_len() + bit_len;
+
+        if !bin.is_writable() || bin.capacity() < num_bytes(total_bit_len) {
+            return None;
+        }
+
+        let mut tail = Vec::new();
+        if self.partial_byte_bit_len > 0 {
+            tail.push(bin.byte(self.full_byte_len));
+        }
+        append_bits(
+            &mut tail,
+            self.partial_byte_bit_len as usize,
+            bytes,
+            bit_len,
+        );
+
+        // Only this sub binary extends to the end of `bin` and it is being made unwritable, so no
+        // other term can see the bytes being written
+        unsafe { bin.write_at(self.full_byte_len, &tail) };
+        self.writable = false;
+
+        Some(Self::writable(original, total_bit_len))
+    }
+
     /// During garbage collection, we sometimes want to convert sub-binary terms
     /// into full-fledged heap binaries, so that the original full-size binary can be freed.
     ///
@@ -309,6 +377,9 @@ impl CloneToProcess for SubBinary {
                 // Allocate space for header and copy it
                 let ptr = heap.alloc(to_word_size(size))?.as_ptr() as *mut Self;
                 ptr::copy_nonoverlapping(self as *const Self, ptr, size);
+                // Only the original may be appended to in place, otherwise appending to both
+                // would overwrite each other's bytes
+                (*ptr).writable = false;
                 Ok(Term::make_boxed(ptr))
             }
         } else {
@@ -331,7 +402,7 @@ impl CloneToProcess for SubBinary {
                         bit_offset: self.bit_offset,
                         full_byte_len: self.full_byte_len,
                         partial_byte_bit_len: self.partial_byte_bit_len,
-                        writable: self.writable,
+                        writable: false,
                     },
                 );
 
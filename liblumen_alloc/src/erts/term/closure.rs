@@ -42,6 +42,10 @@ impl Closure {
         }
     }
 
+    pub fn creator(&self) -> Term {
+        self.creator
+    }
+
     pub fn arity(&self) -> u8 {
         self.module_function_arity.arity
     }
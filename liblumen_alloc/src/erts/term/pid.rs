@@ -116,6 +116,14 @@ impl ExternalPid {
         Self::new(node, number, serial)
     }
 
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
     fn new(node: Node, number: usize, serial: usize) -> Result<Self, OutOfRange> {
         let pid = Pid::new(number, serial)?;
         let header = Term::make_header(arity_of::<Self>(), Term::FLAG_EXTERN_PID);
@@ -17,6 +17,10 @@ impl Port {
     pub unsafe fn from_raw(port: usize) -> Self {
         Self(port)
     }
+
+    pub fn as_usize(&self) -> usize {
+        self.0
+    }
 }
 
 unsafe impl AsTerm for Port {
@@ -51,6 +55,15 @@ pub struct ExternalPort {
     next: *mut u8,
     port: Port,
 }
+impl ExternalPort {
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    pub fn port(&self) -> Port {
+        self.port
+    }
+}
 
 unsafe impl AsTerm for ExternalPort {
     #[inline]
@@ -150,6 +150,36 @@ pub struct ExternalReference {
     next: *mut u8,
     reference: Reference,
 }
+impl ExternalReference {
+    pub(in crate::erts) fn with_node_id(
+        node_id: usize,
+        scheduler_id: scheduler::ID,
+        number: Number,
+    ) -> Self {
+        let node = Node::new(node_id);
+
+        Self::new(node, scheduler_id, number)
+    }
+
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    pub fn reference(&self) -> &Reference {
+        &self.reference
+    }
+
+    fn new(node: Node, scheduler_id: scheduler::ID, number: Number) -> Self {
+        let header = Term::make_header(arity_of::<Self>(), Term::FLAG_EXTERN_REF);
+
+        Self {
+            header,
+            node,
+            next: ptr::null_mut(),
+            reference: Reference::new(scheduler_id, number),
+        }
+    }
+}
 
 unsafe impl AsTerm for ExternalReference {
     #[inline]
@@ -160,8 +190,13 @@ unsafe impl AsTerm for ExternalReference {
 
 impl CloneToProcess for ExternalReference {
     #[inline]
-    fn clone_to_heap<A: HeapAlloc>(&self, _heap: &mut A) -> Result<Term, Alloc> {
-        unimplemented!()
+    fn clone_to_heap<A: HeapAlloc>(&self, heap: &mut A) -> Result<Term, Alloc> {
+        unsafe {
+            let ptr = heap.alloc(self.size_in_words())?.as_ptr() as *mut Self;
+            ptr::write(ptr, self.clone());
+
+            Ok(Term::make_boxed(ptr))
+        }
     }
 }
 
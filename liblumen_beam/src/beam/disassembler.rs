@@ -0,0 +1,591 @@
+//! Decodes the byte code in the `"Code"` chunk into generic instructions.
+//!
+//! Each instruction is an opcode followed by as many operands as the opcode's arity.  Operands are
+//! stored in the compact term encoding, where the low 3 bits of the first byte are a tag and the
+//! value follows in the remaining bits and, for larger values, the following bytes.  Operands that
+//! refer to the `"Atom"`, `"LitT"` and `"ImpT"` chunks are resolved, so the instructions can be
+//! read without the rest of the BEAM file.
+//!
+//! # Examples
+//!
+//!     use liblumen_beam::beam::disassembler::{Disassembler, Opcode};
+//!     use liblumen_beam::beam::reader::StandardBeamFile;
+//!
+//!     let beam = StandardBeamFile::from_file("tests/testdata/reader/test.beam").unwrap();
+//!     let instructions = Disassembler::from_beam_file(&beam)
+//!         .and_then(|disassembler| disassembler.disassemble())
+//!         .unwrap();
+//!
+//!     assert_eq!(Opcode::IntCodeEnd, instructions.last().unwrap().opcode);
+//!
+//! # References
+//!
+//! - [`beam_disasm`](https://github.com/erlang/otp/blob/master/lib/compiler/src/beam_disasm.erl)
+//! - [BEAM Wisdoms - BEAM File Format: Compact Term
+//!   Encoding](http://beam-wisdoms.clau.se/en/latest/indepth-beam-file.html#beam-compact-term-encoding)
+//!
+//! # Alternative Implementations
+//!
+//! - [`org.elixir_lang.beam.chunk.code.Operation` in IntelliJ
+//!   Elixir](https://github.com/KronicDeth/intellij-elixir/blob/
+//!   2f5c826040681e258e98c3e2f02b25985cd0766b/src/org/elixir_lang/beam/chunk/code/Operation.kt) in
+//!   Kotlin
+mod opcode;
+
+#[cfg(test)]
+mod test;
+
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+
+use num::bigint::BigInt;
+
+use crate::beam::chunk::{AtomChunk, CodeChunk, ImpTChunk, LitTChunk, StandardChunk};
+use crate::beam::reader::parts;
+use crate::beam::reader::StandardBeamFile;
+use crate::serialization::etf;
+
+pub use self::opcode::Opcode;
+
+pub type Result<T> = std::result::Result<T, DisassembleError>;
+
+const TAG_U: u8 = 0;
+const TAG_I: u8 = 1;
+const TAG_A: u8 = 2;
+const TAG_X: u8 = 3;
+const TAG_Y: u8 = 4;
+const TAG_F: u8 = 5;
+const TAG_H: u8 = 6;
+const TAG_Z: u8 = 7;
+
+const EXTENDED_FLOAT: u8 = 0;
+const EXTENDED_LIST: u8 = 1;
+const EXTENDED_FLOAT_REGISTER: u8 = 2;
+const EXTENDED_ALLOC_LIST: u8 = 3;
+const EXTENDED_LITERAL: u8 = 4;
+const EXTENDED_TYPED_REGISTER: u8 = 5;
+
+#[derive(Debug)]
+pub enum DisassembleError {
+    /// A chunk needed to resolve operands is not in the BEAM file
+    MissingChunk(&'static str),
+    /// The byte code ends in the middle of an instruction
+    UnexpectedEnd {
+        offset: usize,
+    },
+    UnknownOpcode {
+        opcode: u8,
+        offset: usize,
+    },
+    /// The tag or extended tag of an operand is not valid at `offset`
+    InvalidOperand {
+        byte: u8,
+        offset: usize,
+    },
+    /// An unsigned operand does not fit in 64 bits
+    IntegerTooLarge {
+        offset: usize,
+    },
+    AtomOutOfRange(u64),
+    LiteralOutOfRange(u64),
+    ImportOutOfRange(u64),
+    InvalidLiteral(etf::DecodeError),
+}
+
+impl Display for DisassembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::DisassembleError::*;
+        match *self {
+            MissingChunk(id) => write!(f, "Missing {:?} chunk", id),
+            UnexpectedEnd { offset } => write!(f, "Unexpected end of byte code at {}", offset),
+            UnknownOpcode { opcode, offset } => {
+                write!(f, "Unknown opcode {} at {}", opcode, offset)
+            }
+            InvalidOperand { byte, offset } => {
+                write!(f, "Invalid operand tag {:#04x} at {}", byte, offset)
+            }
+            IntegerTooLarge { offset } => {
+                write!(f, "Unsigned operand at {} does not fit in 64 bits", offset)
+            }
+            AtomOutOfRange(index) => write!(f, "Atom index {} is out of range", index),
+            LiteralOutOfRange(index) => write!(f, "Literal index {} is out of range", index),
+            ImportOutOfRange(index) => write!(f, "Import index {} is out of range", index),
+            InvalidLiteral(ref x) => write!(f, "Invalid literal: {}", x),
+        }
+    }
+}
+
+impl std::error::Error for DisassembleError {}
+
+impl From<etf::DecodeError> for DisassembleError {
+    fn from(err: etf::DecodeError) -> Self {
+        DisassembleError::InvalidLiteral(err)
+    }
+}
+
+/// An operand of an [Instruction](Instruction).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// An untagged unsigned integer, such as an arity, a count or a live register count
+    Unsigned(u64),
+    Integer(BigInt),
+    Atom(String),
+    /// The atom tag with index `0`
+    Nil,
+    XRegister(u32),
+    YRegister(u32),
+    /// A label number, where `0` means no label, such as for a missing fail label
+    Label(u32),
+    Character(u32),
+    Float(f64),
+    List(Vec<Operand>),
+    FloatRegister(u32),
+    AllocList(Vec<Allocation>),
+    Literal(etf::Term),
+    /// An X or Y register annotated with an index into the `"Type"` chunk
+    TypedRegister {
+        register: Box<Operand>,
+        type_index: u64,
+    },
+    /// A resolved `"ImpT"` entry of a `call_ext*`, `bif*` or `gc_bif*` instruction
+    Import {
+        module: String,
+        function: String,
+        arity: parts::Arity,
+    },
+}
+impl Display for Operand {
+    /// Formats the operand the same as `beam_disasm`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Operand::*;
+        match *self {
+            Unsigned(value) => write!(f, "{}", value),
+            Integer(ref value) => write!(f, "{{integer,{}}}", value),
+            Atom(ref name) => write!(f, "{{atom,{}}}", etf::Atom::from(name.as_str())),
+            Nil => write!(f, "nil"),
+            XRegister(n) => write!(f, "{{x,{}}}", n),
+            YRegister(n) => write!(f, "{{y,{}}}", n),
+            Label(n) => write!(f, "{{f,{}}}", n),
+            Character(c) => write!(f, "{{char,{}}}", c),
+            Float(value) => write!(f, "{{float,{:?}}}", value),
+            List(ref operands) => {
+                write!(f, "{{list,[")?;
+                for (index, operand) in operands.iter().enumerate() {
+                    if 0 < index {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", operand)?;
+                }
+                write!(f, "]}}")
+            }
+            FloatRegister(n) => write!(f, "{{fr,{}}}", n),
+            AllocList(ref allocations) => {
+                write!(f, "{{alloc,[")?;
+                for (index, allocation) in allocations.iter().enumerate() {
+                    if 0 < index {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", allocation)?;
+                }
+                write!(f, "]}}")
+            }
+            Literal(ref term) => write!(f, "{{literal,{}}}", term),
+            TypedRegister {
+                ref register,
+                type_index,
+            } => write!(f, "{{tr,{},{}}}", register, type_index),
+            Import {
+                ref module,
+                ref function,
+                arity,
+            } => write!(
+                f,
+                "{{extfunc,{},{},{}}}",
+                etf::Atom::from(module.as_str()),
+                etf::Atom::from(function.as_str()),
+                arity
+            ),
+        }
+    }
+}
+
+/// An entry in an [alloc list](Operand::AllocList).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Allocation {
+    Words(u64),
+    Floats(u64),
+    Funs(u64),
+}
+impl Display for Allocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Allocation::Words(n) => write!(f, "{{words,{}}}", n),
+            Allocation::Floats(n) => write!(f, "{{floats,{}}}", n),
+            Allocation::Funs(n) => write!(f, "{{funs,{}}}", n),
+        }
+    }
+}
+
+/// A generic BEAM instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    /// The operands, of which there are always `opcode.arity()`
+    pub operands: Vec<Operand>,
+}
+impl Display for Instruction {
+    /// Formats the instruction the same as `beam_disasm`, such as `{move,{x,0},{y,1}}`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.operands.is_empty() {
+            write!(f, "{}", self.opcode.name())
+        } else {
+            write!(f, "{{{}", self.opcode.name())?;
+            for operand in &self.operands {
+                write!(f, ",{}", operand)?;
+            }
+            write!(f, "}}")
+        }
+    }
+}
+
+/// Decodes a [CodeChunk](CodeChunk) with the tables its operands refer to.
+pub struct Disassembler<'a> {
+    code: &'a CodeChunk,
+    atoms: &'a [parts::Atom],
+    literals: Vec<etf::Term>,
+    imports: &'a [parts::Import],
+}
+impl<'a> Disassembler<'a> {
+    /// The `literals` and `imports` chunks are optional as modules without literals or calls to
+    /// other modules do not need them.
+    pub fn new(
+        code: &'a CodeChunk,
+        atoms: &'a AtomChunk,
+        literals: Option<&'a LitTChunk>,
+        imports: Option<&'a ImpTChunk>,
+    ) -> Result<Self> {
+        let literals = match literals {
+            Some(chunk) => chunk
+                .literals
+                .iter()
+                .map(|literal| etf::Term::decode(&literal[..]))
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(Disassembler {
+            code,
+            atoms: &atoms.atoms,
+            literals,
+            imports: imports.map(|chunk| &chunk.imports[..]).unwrap_or(&[]),
+        })
+    }
+
+    pub fn from_beam_file(beam: &'a StandardBeamFile) -> Result<Self> {
+        let code = match beam.get_chunk(b"Code") {
+            Some(StandardChunk::Code(chunk)) => chunk,
+            _ => return Err(DisassembleError::MissingChunk("Code")),
+        };
+        let atoms = match beam.atoms() {
+            Some(StandardChunk::Atom(chunk)) => chunk,
+            _ => return Err(DisassembleError::MissingChunk("Atom")),
+        };
+        let literals = match beam.get_chunk(b"LitT") {
+            Some(StandardChunk::LitT(chunk)) => Some(chunk),
+            _ => None,
+        };
+        let imports = match beam.get_chunk(b"ImpT") {
+            Some(StandardChunk::ImpT(chunk)) => Some(chunk),
+            _ => None,
+        };
+
+        Self::new(code, atoms, literals, imports)
+    }
+
+    /// Decodes all instructions up to and including `int_code_end`.
+    pub fn disassemble(&self) -> Result<Vec<Instruction>> {
+        let mut reader = Reader::new(&self.code.bytecode);
+        let mut instructions = Vec::new();
+
+        while !reader.is_empty() {
+            let instruction = self.instruction(&mut reader)?;
+            let is_end = instruction.opcode == Opcode::IntCodeEnd;
+            instructions.push(instruction);
+
+            if is_end {
+                break;
+            }
+        }
+
+        Ok(instructions)
+    }
+
+    fn instruction(&self, reader: &mut Reader) -> Result<Instruction> {
+        let offset = reader.offset;
+        let number = reader.u8()?;
+        let opcode = Opcode::from_u8(number).ok_or(DisassembleError::UnknownOpcode {
+            opcode: number,
+            offset,
+        })?;
+
+        let mut operands = Vec::with_capacity(opcode.arity());
+        for _ in 0..opcode.arity() {
+            operands.push(self.operand(reader)?);
+        }
+
+        if let Some(position) = opcode.import_operand() {
+            if let Operand::Unsigned(index) = operands[position] {
+                operands[position] = self.import(index)?;
+            }
+        }
+
+        Ok(Instruction { opcode, operands })
+    }
+
+    fn operand(&self, reader: &mut Reader) -> Result<Operand> {
+        let offset = reader.offset;
+        let byte = reader.u8()?;
+
+        let operand = match byte & 0b111 {
+            TAG_U => Operand::Unsigned(reader.unsigned(byte)?),
+            TAG_I => Operand::Integer(reader.integer(byte)?),
+            TAG_A => match reader.unsigned(byte)? {
+                0 => Operand::Nil,
+                index => self.atom(index)?,
+            },
+            TAG_X => Operand::XRegister(reader.unsigned_u32(byte)?),
+            TAG_Y => Operand::YRegister(reader.unsigned_u32(byte)?),
+            TAG_F => Operand::Label(reader.unsigned_u32(byte)?),
+            TAG_H => Operand::Character(reader.unsigned_u32(byte)?),
+            TAG_Z => self.extended(byte, offset, reader)?,
+            _ => unreachable!(),
+        };
+
+        Ok(operand)
+    }
+
+    fn extended(&self, byte: u8, offset: usize, reader: &mut Reader) -> Result<Operand> {
+        // Extended tags are only ever stored in the 4 high bits of the first byte
+        if byte & 0x08 != 0 {
+            return Err(DisassembleError::InvalidOperand { byte, offset });
+        }
+
+        let operand = match byte >> 4 {
+            EXTENDED_FLOAT => {
+                let mut bits = 0u64;
+                for byte in reader.take(8)? {
+                    bits = (bits << 8) | u64::from(*byte);
+                }
+                Operand::Float(f64::from_bits(bits))
+            }
+            EXTENDED_LIST => {
+                let len = self.nested_unsigned(reader)?;
+                let mut operands = Vec::new();
+                for _ in 0..len {
+                    operands.push(self.operand(reader)?);
+                }
+                Operand::List(operands)
+            }
+            EXTENDED_FLOAT_REGISTER => {
+                let offset = reader.offset;
+                let n = self.nested_unsigned(reader)?;
+                Operand::FloatRegister(
+                    u32::try_from(n).map_err(|_| DisassembleError::IntegerTooLarge { offset })?,
+                )
+            }
+            EXTENDED_ALLOC_LIST => {
+                let len = self.nested_unsigned(reader)?;
+                let mut allocations = Vec::new();
+                for _ in 0..len {
+                    let kind_offset = reader.offset;
+                    let kind = self.nested_unsigned(reader)?;
+                    let count = self.nested_unsigned(reader)?;
+                    allocations.push(match kind {
+                        0 => Allocation::Words(count),
+                        1 => Allocation::Floats(count),
+                        2 => Allocation::Funs(count),
+                        _ => {
+                            return Err(DisassembleError::InvalidOperand {
+                                byte: reader.bytes[kind_offset],
+                                offset: kind_offset,
+                            })
+                        }
+                    });
+                }
+                Operand::AllocList(allocations)
+            }
+            EXTENDED_LITERAL => {
+                let index = self.nested_unsigned(reader)?;
+                self.literals
+                    .get(index as usize)
+                    .cloned()
+                    .map(Operand::Literal)
+                    .ok_or(DisassembleError::LiteralOutOfRange(index))?
+            }
+            EXTENDED_TYPED_REGISTER => {
+                let register_offset = reader.offset;
+                let register = match self.operand(reader)? {
+                    register @ Operand::XRegister(_) | register @ Operand::YRegister(_) => register,
+                    _ => {
+                        return Err(DisassembleError::InvalidOperand {
+                            byte: reader.bytes[register_offset],
+                            offset: register_offset,
+                        })
+                    }
+                };
+                let type_index = self.nested_unsigned(reader)?;
+                Operand::TypedRegister {
+                    register: Box::new(register),
+                    type_index,
+                }
+            }
+            _ => return Err(DisassembleError::InvalidOperand { byte, offset }),
+        };
+
+        Ok(operand)
+    }
+
+    /// Decodes an operand that must be tagged as unsigned, such as the length of a list.
+    fn nested_unsigned(&self, reader: &mut Reader) -> Result<u64> {
+        let offset = reader.offset;
+        let byte = reader.u8()?;
+
+        if byte & 0b111 == TAG_U {
+            reader.unsigned(byte)
+        } else {
+            Err(DisassembleError::InvalidOperand { byte, offset })
+        }
+    }
+
+    /// Atom indices are one-based as `0` is used for `[]`.
+    fn atom(&self, index: u64) -> Result<Operand> {
+        (index as usize)
+            .checked_sub(1)
+            .and_then(|index| self.atoms.get(index))
+            .map(|atom| Operand::Atom(atom.name.clone()))
+            .ok_or(DisassembleError::AtomOutOfRange(index))
+    }
+
+    fn atom_name(&self, id: parts::AtomId) -> Result<String> {
+        match self.atom(u64::from(id))? {
+            Operand::Atom(name) => Ok(name),
+            _ => unreachable!(),
+        }
+    }
+
+    fn import(&self, index: u64) -> Result<Operand> {
+        let import = self
+            .imports
+            .get(index as usize)
+            .ok_or(DisassembleError::ImportOutOfRange(index))?;
+
+        Ok(Operand::Import {
+            module: self.atom_name(import.module)?,
+            function: self.atom_name(import.function)?,
+            arity: import.arity,
+        })
+    }
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    offset: usize,
+}
+impl<'b> Reader<'b> {
+    fn new(bytes: &'b [u8]) -> Self {
+        Reader { bytes, offset: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.len() <= self.offset
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        let byte = *self
+            .bytes
+            .get(self.offset)
+            .ok_or(DisassembleError::UnexpectedEnd {
+                offset: self.offset,
+            })?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8]> {
+        let end = match self.offset.checked_add(len) {
+            Some(end) if end <= self.bytes.len() => end,
+            _ => {
+                return Err(DisassembleError::UnexpectedEnd {
+                    offset: self.bytes.len(),
+                })
+            }
+        };
+
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    /// Values of up to 4 bits are stored in the high bits of the tag byte, values of up to 11
+    /// bits in the 3 high bits of the tag byte and the following byte, and anything larger as a
+    /// big-endian byte string whose length is stored in the tag byte or, if even that does not
+    /// fit, as a nested unsigned operand.
+    fn value(&mut self, byte: u8) -> Result<Value<'b>> {
+        if byte & 0x08 == 0 {
+            Ok(Value::Small(u64::from(byte >> 4)))
+        } else if byte & 0x10 == 0 {
+            let low = self.u8()?;
+            Ok(Value::Small((u64::from(byte & 0xE0) << 3) | u64::from(low)))
+        } else {
+            let len = match byte >> 5 {
+                7 => {
+                    let offset = self.offset;
+                    let nested = self.u8()?;
+                    if nested & 0b111 != TAG_U {
+                        return Err(DisassembleError::InvalidOperand {
+                            byte: nested,
+                            offset,
+                        });
+                    }
+                    usize::try_from(self.unsigned(nested)?)
+                        .ok()
+                        .and_then(|len| len.checked_add(9))
+                        .ok_or(DisassembleError::IntegerTooLarge { offset })?
+                }
+                len => len as usize + 2,
+            };
+            self.take(len).map(Value::Bytes)
+        }
+    }
+
+    fn unsigned(&mut self, byte: u8) -> Result<u64> {
+        let offset = self.offset;
+        match self.value(byte)? {
+            Value::Small(value) => Ok(value),
+            Value::Bytes(bytes) => bytes.iter().try_fold(0u64, |acc, byte| {
+                acc.checked_mul(256)
+                    .map(|acc| acc | u64::from(*byte))
+                    .ok_or(DisassembleError::IntegerTooLarge { offset })
+            }),
+        }
+    }
+
+    fn unsigned_u32(&mut self, byte: u8) -> Result<u32> {
+        let offset = self.offset;
+        let value = self.unsigned(byte)?;
+        u32::try_from(value).map_err(|_| DisassembleError::IntegerTooLarge { offset })
+    }
+
+    /// Only the byte string form of integers can be negative.
+    fn integer(&mut self, byte: u8) -> Result<BigInt> {
+        match self.value(byte)? {
+            Value::Small(value) => Ok(BigInt::from(value)),
+            Value::Bytes(bytes) => Ok(BigInt::from_signed_bytes_be(bytes)),
+        }
+    }
+}
+
+enum Value<'b> {
+    Small(u64),
+    Bytes(&'b [u8]),
+}
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use crate::beam::chunk::{AtomChunk, CodeChunk};
+use crate::beam::disassembler::{DisassembleError, Disassembler, Instruction, Opcode, Operand};
+use crate::beam::reader::parts;
+use crate::beam::reader::StandardBeamFile;
+
+#[test]
+fn disassembles_test_beam() {
+    let beam = StandardBeamFile::from_file(test_file("test.beam")).unwrap();
+    let instructions = Disassembler::from_beam_file(&beam)
+        .and_then(|disassembler| disassembler.disassemble())
+        .unwrap();
+
+    for instruction in &instructions {
+        assert_eq!(instruction.opcode.arity(), instruction.operands.len());
+    }
+
+    let func_info = instructions
+        .iter()
+        .find(|instruction| instruction.opcode == Opcode::FuncInfo)
+        .unwrap();
+    assert_eq!(Operand::Atom("test".to_string()), func_info.operands[0]);
+
+    assert!(instructions
+        .iter()
+        .any(|instruction| match instruction.opcode {
+            Opcode::CallExt | Opcode::CallExtLast | Opcode::CallExtOnly =>
+                match instruction.operands[1] {
+                    Operand::Import { .. } => true,
+                    _ => false,
+                },
+            _ => false,
+        }));
+
+    assert_eq!(Opcode::IntCodeEnd, instructions.last().unwrap().opcode);
+}
+
+#[test]
+fn compact_term_encoding() {
+    let atoms = AtomChunk {
+        is_unicode: false,
+        atoms: vec![parts::Atom {
+            name: "foo".to_string(),
+        }],
+    };
+    let code = code_chunk(vec![
+        // {move,{atom,foo},{x,0}}
+        64, 0x12, 0x03, //
+        // {move,nil,{y,1}}
+        64, 0x02, 0x14, //
+        // {move,{integer,1000},{x,2047}}
+        64, 0x69, 0xE8, 0xEB, 0xFF, //
+        // {move,{integer,-1},{x,0}}
+        64, 0x19, 0xFF, 0xFF, 0x03, //
+        // {test_heap,{alloc,[{words,2},{floats,1}]},1}
+        16, 0x37, 0x20, 0x00, 0x20, 0x10, 0x10, 0x10, //
+        // {select_val,{x,0},{f,1},{list,[{atom,foo},{f,2}]}}
+        59, 0x03, 0x15, 0x17, 0x20, 0x12, 0x25, //
+        // int_code_end
+        3,
+    ]);
+
+    let instructions = Disassembler::new(&code, &atoms, None, None)
+        .and_then(|disassembler| disassembler.disassemble())
+        .unwrap();
+    let formatted: Vec<String> = instructions.iter().map(Instruction::to_string).collect();
+
+    assert_eq!(
+        vec![
+            "{move,{atom,'foo'},{x,0}}",
+            "{move,nil,{y,1}}",
+            "{move,{integer,1000},{x,2047}}",
+            "{move,{integer,-1},{x,0}}",
+            "{test_heap,{alloc,[{words,2},{floats,1}]},1}",
+            "{select_val,{x,0},{f,1},{list,[{atom,'foo'},{f,2}]}}",
+            "int_code_end",
+        ],
+        formatted
+    );
+}
+
+#[test]
+fn operand_length_that_overflows_is_too_large() {
+    let atoms = AtomChunk {
+        is_unicode: false,
+        atoms: vec![],
+    };
+    let code = code_chunk(vec![
+        // {move,{integer,_},{x,0}} whose length is the nested unsigned 2^64 - 1
+        64, 0xF9, 0xD8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x03,
+    ]);
+
+    let result = Disassembler::new(&code, &atoms, None, None)
+        .and_then(|disassembler| disassembler.disassemble());
+
+    match result {
+        Err(DisassembleError::IntegerTooLarge { offset: 2 }) => (),
+        other => panic!("expected IntegerTooLarge, got {:?}", other),
+    }
+}
+
+fn code_chunk(bytecode: Vec<u8>) -> CodeChunk {
+    CodeChunk {
+        info_size: 16,
+        version: 0,
+        opcode_max: Opcode::IntCodeEnd.number().into(),
+        label_count: 3,
+        function_count: 0,
+        bytecode,
+    }
+}
+
+fn test_file(name: &str) -> PathBuf {
+    let mut path = PathBuf::from("tests/testdata/reader");
+    path.push(name);
+    path
+}
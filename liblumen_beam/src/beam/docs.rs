@@ -0,0 +1,283 @@
+//! Decodes the [EEP-48](http://erlang.org/eeps/eep-0048.html) documentation in the `"Docs"` chunk.
+//!
+//! The `"Docs"` chunk holds a `docs_v1` tuple in the External Term Format, which is the same for
+//! every BEAM language, so documentation written in Erlang, Elixir or any other language that
+//! follows EEP-48 can be browsed without running the language's own tooling.
+//!
+//! # Examples
+//!
+//!     use liblumen_beam::beam::docs::{DocContent, DocKind, Docs};
+//!
+//!     let docs = Docs::from_beam_file("tests/testdata/reader/Elixir.Unicode.beam").unwrap();
+//!
+//!     assert_eq!("elixir", docs.beam_language);
+//!     assert_eq!(DocContent::Hidden, docs.module_doc);
+//!
+//!     let add1 = docs.find(&DocKind::Function, "add1", 1).unwrap();
+//!     assert_eq!(vec!["add1(n)".to_string()], add1.signature);
+//!
+//! # References
+//!
+//! - [EEP-48: Documentation storage and format](http://erlang.org/eeps/eep-0048.html)
+//! - [`code:get_doc/1`](http://erlang.org/doc/man/code.html#get_doc-1)
+//!
+//! # Alternative Implementations
+//!
+//! - [`org.elixir_lang.beam.chunk.Docs` in IntelliJ
+//!   Elixir](https://github.com/KronicDeth/intellij-elixir/blob/
+//!   2f5c826040681e258e98c3e2f02b25985cd0766b/src/org/elixir_lang/beam/chunk/Docs.kt) in Kotlin
+#[cfg(test)]
+mod test;
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::beam::chunk::DocsChunk;
+use crate::beam::reader::RawBeamFile;
+use crate::serialization::etf;
+use crate::serialization::etf::convert::TryAsRef;
+use crate::serialization::etf::pattern::{self, any, Or, Pattern, Union3, VarList, U32};
+use crate::syntax::ast::error::FromBeamError;
+use crate::syntax::ast::FromBeamResult;
+
+/// The decoded `docs_v1` tuple
+///
+/// ```erlang
+/// {docs_v1, Anno, BeamLanguage, Format, ModuleDoc, Metadata, Docs}
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Docs {
+    /// The `erl_anno:anno()` of the module, usually its line
+    pub anno: etf::Term,
+    /// The language the module was written in, such as `erlang` or `elixir`
+    pub beam_language: String,
+    /// The MIME type of the documentation, such as `text/markdown`
+    pub format: String,
+    pub module_doc: DocContent,
+    /// Metadata about the module, such as `since` or `deprecated`
+    pub metadata: etf::Map,
+    /// The documentation for the functions, types, callbacks and other entries of the module
+    pub docs: Vec<DocEntry>,
+}
+impl Docs {
+    /// Reads the `"Docs"` chunk from the BEAM file at `path`
+    pub fn from_beam_file<P: AsRef<Path>>(path: P) -> FromBeamResult<Self> {
+        let beam = RawBeamFile::from_file(path)?;
+        let chunk = beam.get_chunk(b"Docs").ok_or(FromBeamError::NoDocs)?;
+
+        Self::from_bytes(&chunk.data)
+    }
+
+    /// Decodes the `docs_v1` tuple in the External Term Format
+    pub fn from_bytes(bytes: &[u8]) -> FromBeamResult<Self> {
+        let term = etf::Term::decode(Cursor::new(bytes))?;
+
+        Self::from_term(&term)
+    }
+
+    /// Decodes an already decoded `docs_v1` tuple
+    pub fn from_term(term: &etf::Term) -> FromBeamResult<Self> {
+        term.as_match(DocsV1).map_err(From::from)
+    }
+
+    /// Returns the entry of `kind` for `name`/`arity`
+    pub fn find(&self, kind: &DocKind, name: &str, arity: u32) -> Option<&DocEntry> {
+        self.docs
+            .iter()
+            .find(|entry| &entry.kind == kind && entry.name == name && entry.arity == arity)
+    }
+
+    /// Returns all entries of `kind` in the order they appear in the chunk
+    pub fn entries<'a>(&'a self, kind: &'a DocKind) -> impl Iterator<Item = &'a DocEntry> + 'a {
+        self.docs.iter().filter(move |entry| &entry.kind == kind)
+    }
+}
+
+/// The documentation of a function, type, callback or other entry
+///
+/// ```erlang
+/// {{Kind, Name, Arity}, Anno, Signature, Doc, Metadata}
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocEntry {
+    pub kind: DocKind,
+    pub name: String,
+    pub arity: u32,
+    /// The `erl_anno:anno()` of the entry, usually its line
+    pub anno: etf::Term,
+    /// The signature(s) of the entry, such as `add1(n)`, for display purposes
+    pub signature: Vec<String>,
+    pub doc: DocContent,
+    /// Metadata about the entry, such as `since`, `deprecated` or `defaults`
+    pub metadata: etf::Map,
+}
+
+/// The kind of a [DocEntry]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DocKind {
+    Function,
+    Type,
+    Callback,
+    /// Elixir's macros
+    Macro,
+    /// Kinds defined by other languages
+    Other(String),
+}
+impl DocKind {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "function" => DocKind::Function,
+            "type" => DocKind::Type,
+            "callback" => DocKind::Callback,
+            "macro" => DocKind::Macro,
+            _ => DocKind::Other(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            DocKind::Function => "function",
+            DocKind::Type => "type",
+            DocKind::Callback => "callback",
+            DocKind::Macro => "macro",
+            DocKind::Other(name) => name,
+        }
+    }
+}
+
+/// The documentation of the module or an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocContent {
+    /// No documentation was written
+    None,
+    /// The documentation was explicitly hidden, such as with `@doc false` in Elixir
+    Hidden,
+    /// The documentation by language, such as `"en"`
+    Localized(BTreeMap<String, String>),
+}
+impl DocContent {
+    /// Returns the documentation in `language`, such as `"en"`
+    pub fn get(&self, language: &str) -> Option<&str> {
+        match self {
+            DocContent::Localized(by_language) => by_language.get(language).map(String::as_str),
+            _ => None,
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        *self == DocContent::Hidden
+    }
+}
+
+impl DocsChunk {
+    /// Decodes the documentation held in this chunk
+    pub fn docs(&self) -> FromBeamResult<Docs> {
+        Docs::from_bytes(&self.term)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DocsV1;
+impl<'a> Pattern<'a> for DocsV1 {
+    type Output = Docs;
+    fn try_match(&self, input: &'a etf::Term) -> pattern::Result<'a, Self::Output> {
+        let tuple: &etf::Tuple = input.try_as_ref().ok_or_else(|| self.unmatched(input))?;
+
+        // Tuple patterns only go up to 6 elements, so the 7 elements are matched individually
+        match tuple.elements.as_slice() {
+            [tag, anno, beam_language, format, module_doc, metadata, docs] => {
+                let cause = |unmatch| self.unmatched(input).cause(unmatch);
+
+                tag.as_match("docs_v1").map_err(cause)?;
+
+                Ok(Docs {
+                    anno: anno.clone(),
+                    beam_language: beam_language.as_match(AtomName).map_err(cause)?,
+                    format: format.as_match(Utf8Binary).map_err(cause)?,
+                    module_doc: module_doc.as_match(Content).map_err(cause)?,
+                    metadata: metadata.as_match(any::<etf::Map>()).map_err(cause)?.clone(),
+                    docs: docs.as_match(VarList(Entry)).map_err(cause)?,
+                })
+            }
+            _ => Err(self.unmatched(input)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry;
+impl<'a> Pattern<'a> for Entry {
+    type Output = DocEntry;
+    fn try_match(&self, input: &'a etf::Term) -> pattern::Result<'a, Self::Output> {
+        let ((kind, name, arity), anno, signature, doc, metadata) = input
+            .as_match((
+                (AtomName, AtomName, U32),
+                any::<etf::Term>(),
+                VarList(Utf8Binary),
+                Content,
+                any::<etf::Map>(),
+            ))
+            .map_err(|unmatch| self.unmatched(input).cause(unmatch))?;
+
+        Ok(DocEntry {
+            kind: DocKind::from_name(&kind),
+            name,
+            arity,
+            anno: anno.clone(),
+            signature,
+            doc,
+            metadata: metadata.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Content;
+impl<'a> Pattern<'a> for Content {
+    type Output = DocContent;
+    fn try_match(&self, input: &'a etf::Term) -> pattern::Result<'a, Self::Output> {
+        let content = input
+            .as_match(Or(("none", "hidden", any::<etf::Map>())))
+            .map_err(|unmatch| self.unmatched(input).cause(unmatch))?;
+
+        match content {
+            Union3::A(_) => Ok(DocContent::None),
+            Union3::B(_) => Ok(DocContent::Hidden),
+            Union3::C(map) => {
+                let mut by_language = BTreeMap::new();
+
+                for (language, doc) in &map.entries {
+                    let cause = |unmatch| self.unmatched(input).cause(unmatch);
+
+                    by_language.insert(
+                        language.as_match(Utf8Binary).map_err(cause)?,
+                        doc.as_match(Utf8Binary).map_err(cause)?,
+                    );
+                }
+
+                Ok(DocContent::Localized(by_language))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AtomName;
+impl<'a> Pattern<'a> for AtomName {
+    type Output = String;
+    fn try_match(&self, input: &'a etf::Term) -> pattern::Result<'a, Self::Output> {
+        let atom: &etf::Atom = input.try_as_ref().ok_or_else(|| self.unmatched(input))?;
+        Ok(atom.name.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Utf8Binary;
+impl<'a> Pattern<'a> for Utf8Binary {
+    type Output = String;
+    fn try_match(&self, input: &'a etf::Term) -> pattern::Result<'a, Self::Output> {
+        let binary: &etf::Binary = input.try_as_ref().ok_or_else(|| self.unmatched(input))?;
+        String::from_utf8(binary.bytes.clone()).map_err(|_| self.unmatched(input))
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::beam::chunk::DocsChunk;
+use crate::beam::docs::{DocContent, DocKind, Docs};
+use crate::serialization::etf;
+use crate::syntax::ast::error::FromBeamError;
+
+#[test]
+fn decodes_elixir_docs() {
+    let docs = Docs::from_beam_file(test_file("Elixir.Unicode.beam")).unwrap();
+
+    assert_eq!(etf::Term::from(etf::FixInteger::from(2)), docs.anno);
+    assert_eq!("elixir", docs.beam_language);
+    assert_eq!("text/markdown", docs.format);
+    assert!(docs.module_doc.is_hidden());
+    assert!(docs.metadata.entries.is_empty());
+
+    let names: Vec<(&str, u32)> = docs
+        .entries(&DocKind::Function)
+        .map(|entry| (entry.name.as_str(), entry.arity))
+        .collect();
+    assert_eq!(
+        vec![
+            ("add1", 1),
+            ("ascii_atom", 0),
+            ("string", 0),
+            ("utf8_atom", 0)
+        ],
+        names
+    );
+
+    let add1 = docs.find(&DocKind::Function, "add1", 1).unwrap();
+    assert_eq!(etf::Term::from(etf::FixInteger::from(8)), add1.anno);
+    assert_eq!(vec!["add1(n)".to_string()], add1.signature);
+    assert_eq!(DocContent::None, add1.doc);
+
+    assert!(docs.find(&DocKind::Macro, "add1", 1).is_none());
+}
+
+#[test]
+fn beam_without_docs_chunk() {
+    match Docs::from_beam_file(test_file("test.beam")) {
+        Err(FromBeamError::NoDocs) => (),
+        other => panic!("expected NoDocs, got {:?}", other),
+    }
+}
+
+#[test]
+fn decodes_localized_docs() {
+    let docs_v1 = tuple(vec![
+        atom("docs_v1"),
+        etf::Term::from(etf::FixInteger::from(1)),
+        atom("erlang"),
+        binary("application/erlang+html"),
+        map(vec![(binary("en"), binary("Module documentation"))]),
+        map(vec![(atom("otp_doc_vsn"), tuple(vec![]))]),
+        etf::Term::from(etf::List::from(vec![
+            tuple(vec![
+                tuple(vec![
+                    atom("type"),
+                    atom("t"),
+                    etf::Term::from(etf::FixInteger::from(0)),
+                ]),
+                etf::Term::from(etf::FixInteger::from(3)),
+                etf::Term::from(etf::List::from(vec![binary("t()")])),
+                map(vec![
+                    (binary("en"), binary("A type")),
+                    (binary("sv"), binary("En typ")),
+                ]),
+                map(vec![]),
+            ]),
+            tuple(vec![
+                tuple(vec![
+                    atom("callback"),
+                    atom("init"),
+                    etf::Term::from(etf::FixInteger::from(1)),
+                ]),
+                etf::Term::from(etf::FixInteger::from(5)),
+                etf::Term::from(etf::List::from(vec![])),
+                atom("hidden"),
+                map(vec![]),
+            ]),
+        ])),
+    ]);
+
+    let mut bytes = Vec::new();
+    docs_v1.encode(&mut bytes).unwrap();
+    let docs = DocsChunk { term: bytes }.docs().unwrap();
+
+    assert_eq!("erlang", docs.beam_language);
+    assert_eq!(Some("Module documentation"), docs.module_doc.get("en"));
+    assert_eq!(1, docs.metadata.entries.len());
+
+    let t = docs.find(&DocKind::Type, "t", 0).unwrap();
+    let mut expected = BTreeMap::new();
+    expected.insert("en".to_string(), "A type".to_string());
+    expected.insert("sv".to_string(), "En typ".to_string());
+    assert_eq!(DocContent::Localized(expected), t.doc);
+    assert_eq!(None, t.doc.get("de"));
+
+    let init = docs.find(&DocKind::Callback, "init", 1).unwrap();
+    assert!(init.signature.is_empty());
+    assert!(init.doc.is_hidden());
+}
+
+#[test]
+fn rejects_other_terms() {
+    let term = tuple(vec![atom("docs_v2")]);
+
+    match Docs::from_term(&term) {
+        Err(FromBeamError::UnexpectedTerm(_)) => (),
+        other => panic!("expected UnexpectedTerm, got {:?}", other),
+    }
+}
+
+fn atom(name: &str) -> etf::Term {
+    etf::Term::from(etf::Atom::from(name))
+}
+
+fn binary(string: &str) -> etf::Term {
+    etf::Term::from(etf::Binary::from(string.as_bytes()))
+}
+
+fn map(entries: Vec<(etf::Term, etf::Term)>) -> etf::Term {
+    etf::Term::from(etf::Map::from(entries))
+}
+
+fn tuple(elements: Vec<etf::Term>) -> etf::Term {
+    etf::Term::from(etf::Tuple::from(elements))
+}
+
+fn test_file(name: &str) -> PathBuf {
+    let mut path = PathBuf::from("tests/testdata/reader");
+    path.push(name);
+    path
+}
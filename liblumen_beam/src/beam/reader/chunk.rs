@@ -762,15 +762,8 @@ pub struct DocsChunk {
     /// The 'Docs' chunk contains embedded module documentation, such as moduledoc/doc in Elixir
     ///
     /// The value is encoded in the [External Term Format]
-    /// (http://erlang.org/doc/apps/erts/erl_ext_dist.html) and
-    /// represents a term in the following format:
-    ///
-    /// ```erlang
-    /// {Module, [{"Docs", DocsBin}]}
-    /// ```
-    ///
-    /// Where `Module` is the documented module, and `DocsBin` is a binary in External Term Format
-    /// containing the documentation. Currently, that decodes to:
+    /// (http://erlang.org/doc/apps/erts/erl_ext_dist.html) and, as described in
+    /// [EEP-48](http://erlang.org/eeps/eep-0048.html), decodes to:
     ///
     /// ```erlang
     /// {docs_v1, Anno, BeamLang, Format, ModuleDoc, Metadata, Docs}
@@ -784,6 +777,8 @@ pub struct DocsChunk {
     ///         doc_content :: map(binary(), binary()) | none | hidden,
     ///         doc_element :: {{kind :: atom(), function :: atom(), arity}, Anno, signature, doc_content(), Metadata}
     /// ```
+    ///
+    /// Use `DocsChunk::docs` to decode it into a `beam::docs::Docs`.
     pub term: parts::ExternalTermFormatBinary,
 }
 impl Chunk for DocsChunk {
@@ -16,6 +16,7 @@
 pub mod ast;
 pub mod error;
 pub mod format;
+pub mod printer;
 
 #[cfg(test)]
 mod test;
@@ -36,6 +37,20 @@ impl AST {
     pub fn from_beam_file<P: AsRef<Path>>(beam_file: P) -> FromBeamResult<Self> {
         use self::format::raw_abstract_v1::AbstractCode;
         let code = AbstractCode::from_beam_file(beam_file)?;
+        Self::from_abstract_code(code)
+    }
+
+    /// Builds AST from the contents of a BEAM file, such as the binary passed to
+    /// `code:load_binary/3`
+    pub fn from_beam_bytes(bytes: &[u8]) -> FromBeamResult<Self> {
+        use self::format::raw_abstract_v1::AbstractCode;
+        let code = AbstractCode::from_beam_bytes(bytes)?;
+        Self::from_abstract_code(code)
+    }
+
+    fn from_abstract_code(
+        code: self::format::raw_abstract_v1::AbstractCode,
+    ) -> FromBeamResult<Self> {
         let forms = code.to_forms()?;
         Ok(AST {
             module: ast::ModuleDecl { forms },
@@ -41,6 +41,9 @@ pub enum FromBeamError {
     #[fail(display = "debug info is required but not present")]
     NoDebugInfo,
 
+    #[fail(display = "documentation is required but not present")]
+    NoDocs,
+
     #[fail(display = "missing module attribute")]
     NoModuleAttribute,
 
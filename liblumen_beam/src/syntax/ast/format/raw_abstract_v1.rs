@@ -42,6 +42,13 @@ pub struct AbstractCode {
 impl AbstractCode {
     pub fn from_beam_file<P: AsRef<Path>>(path: P) -> FromBeamResult<Self> {
         let beam = crate::beam::reader::RawBeamFile::from_file(path)?;
+        Self::from_raw_beam(beam)
+    }
+    pub fn from_beam_bytes(bytes: &[u8]) -> FromBeamResult<Self> {
+        let beam = crate::beam::reader::RawBeamFile::from_reader(std::io::Cursor::new(bytes))?;
+        Self::from_raw_beam(beam)
+    }
+    fn from_raw_beam(beam: crate::beam::reader::RawBeamFile) -> FromBeamResult<Self> {
         let chunk = beam
             .chunks()
             .into_iter()
@@ -0,0 +1,1383 @@
+//! Renders AST back into Erlang source, like `erl_pp`
+//!
+//! The output is meant to be re-parsed, such as by the EIR frontend when loading a BEAM file that
+//! only has its abstract code, so operators are parenthesized by precedence and atoms are quoted
+//! whenever they would not otherwise scan as atoms.
+//!
+//! # Examples
+//!
+//!     use liblumen_beam::syntax::ast::printer;
+//!     use liblumen_beam::syntax::ast::AST;
+//!
+//!     let ast = AST::from_beam_file("tests/testdata/ast/test.beam").unwrap();
+//!     let source = printer::module_to_string(&ast.module);
+//!
+//!     assert!(source.starts_with("-file("));
+//!
+//! # References
+//!
+//! - [`erl_pp`](http://erlang.org/doc/man/erl_pp.html)
+use std::fmt::Write;
+
+use crate::serialization::etf;
+use crate::syntax::ast::ast::clause::Clause;
+use crate::syntax::ast::ast::common;
+use crate::syntax::ast::ast::expr::{self, Expression};
+use crate::syntax::ast::ast::form::{self, Form};
+use crate::syntax::ast::ast::guard::{Guard, OrGuard};
+use crate::syntax::ast::ast::literal;
+use crate::syntax::ast::ast::pat::Pattern;
+use crate::syntax::ast::ast::ty::{self, Type};
+use crate::syntax::ast::ast::{LineNum, ModuleDecl, Node};
+
+const INDENT: &str = "    ";
+
+/// Renders all forms of `module`, including types, specs and other attributes.
+pub fn module_to_string(module: &ModuleDecl) -> String {
+    let mut printer = Printer::new();
+    printer.forms(&module.forms, false);
+
+    printer.output
+}
+
+/// Renders only the forms of `module` that affect how it compiles: the module attribute, exports,
+/// imports, compile options, `-on_load`, records and functions.
+///
+/// Forms, clauses and expressions from the module's own file are rendered on the lines they had in
+/// it, so that locations in code parsed from the rendered source point into the original file.
+pub fn module_code_to_string(module: &ModuleDecl) -> String {
+    let mut printer = Printer::new();
+    printer.keep_lines = true;
+    printer.forms(&module.forms, true);
+
+    printer.output
+}
+
+pub fn form_to_string(form: &Form) -> String {
+    let mut printer = Printer::new();
+    printer.form(form);
+
+    printer.output
+}
+
+pub fn expr_to_string(expression: &Expression) -> String {
+    let mut printer = Printer::new();
+    printer.expr(expression, 0);
+
+    printer.output
+}
+
+pub fn pattern_to_string(pattern: &Pattern) -> String {
+    let mut printer = Printer::new();
+    printer.pattern(pattern, 0);
+
+    printer.output
+}
+
+pub fn type_to_string(ty: &Type) -> String {
+    let mut printer = Printer::new();
+    printer.ty(ty, 0);
+
+    printer.output
+}
+
+/// Operators bind by their `(left, precedence, right)` priorities, the same as in `erl_parse`.
+fn infix_operator_priorities(operator: &str) -> (u32, u32, u32) {
+    match operator {
+        "=" | "!" => (150, 100, 100),
+        "orelse" => (160, 150, 150),
+        "andalso" => (200, 160, 160),
+        "==" | "/=" | "=<" | "<" | ">=" | ">" | "=:=" | "=/=" => (300, 200, 300),
+        "++" | "--" => (400, 300, 300),
+        "+" | "-" | "bor" | "bxor" | "bsl" | "bsr" | "or" | "xor" => (400, 400, 500),
+        "*" | "/" | "div" | "rem" | "band" | "and" => (500, 500, 600),
+        _ => (0, 0, 0),
+    }
+}
+
+/// `(precedence, operand)` priorities of prefix operators
+fn prefix_operator_priorities(operator: &str) -> (u32, u32) {
+    match operator {
+        "catch" => (0, 100),
+        _ => (600, 700),
+    }
+}
+
+/// Type operators bind by their `(left, precedence, right)` priorities, the same as in `erl_pp`.
+/// Arithmetic operators, which are allowed in integer types, bind the same as in expressions.
+fn type_infix_operator_priorities(operator: &str) -> (u32, u32, u32) {
+    match operator {
+        "::" => (160, 150, 150),
+        "|" => (180, 170, 170),
+        ".." => (300, 200, 300),
+        _ => infix_operator_priorities(operator),
+    }
+}
+
+/// Priority that only primary expressions, such as literals, variables and calls, have
+const MAX_PRIORITY: u32 = 1000;
+/// Priority needed by the operand of `#` in record and map expressions
+const RECORD_PRIORITY: u32 = 800;
+
+pub fn atom_to_string(name: &str) -> String {
+    if is_unquoted_atom(name) {
+        name.to_string()
+    } else {
+        let mut quoted = String::with_capacity(name.len() + 2);
+        quoted.push('\'');
+
+        for c in name.chars() {
+            push_escaped_char(&mut quoted, c, Some('\''));
+        }
+
+        quoted.push('\'');
+
+        quoted
+    }
+}
+
+fn is_unquoted_atom(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(first) if first.is_ascii_lowercase() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+                && !is_reserved_word(name)
+        }
+        _ => false,
+    }
+}
+
+fn is_reserved_word(name: &str) -> bool {
+    match name {
+        "after" | "and" | "andalso" | "band" | "begin" | "bnot" | "bor" | "bsl" | "bsr"
+        | "bxor" | "case" | "catch" | "cond" | "div" | "else" | "end" | "fun" | "if" | "let"
+        | "maybe" | "not" | "of" | "or" | "orelse" | "receive" | "rem" | "try" | "when" | "xor" => {
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Pushes `c` escaped, if needed, to be in an atom or string delimited by `quote`, or in a
+/// character literal if there is no `quote`.
+fn push_escaped_char(output: &mut String, c: char, quote: Option<char>) {
+    match c {
+        '\\' => output.push_str("\\\\"),
+        '\n' => output.push_str("\\n"),
+        '\r' => output.push_str("\\r"),
+        '\t' => output.push_str("\\t"),
+        '\u{8}' => output.push_str("\\b"),
+        '\u{c}' => output.push_str("\\f"),
+        '\u{b}' => output.push_str("\\v"),
+        '\u{1b}' => output.push_str("\\e"),
+        '\u{7f}' => output.push_str("\\d"),
+        _ if Some(c) == quote => {
+            output.push('\\');
+            output.push(c);
+        }
+        _ if c.is_control() => {
+            write!(output, "\\x{{{:X}}}", c as u32).unwrap();
+        }
+        _ => output.push(c),
+    }
+}
+
+fn string_to_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+
+    for c in value.chars() {
+        push_escaped_char(&mut quoted, c, Some('"'));
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+fn char_to_string(value: char) -> String {
+    let mut output = String::from("$");
+
+    match value {
+        ' ' => output.push_str("\\s"),
+        _ => push_escaped_char(&mut output, value, None),
+    }
+
+    output
+}
+
+/// Rust formats `1e-7` without a fractional part, which Erlang does not accept.
+fn float_to_string(value: f64) -> String {
+    let formatted = format!("{:?}", value);
+
+    match formatted.find('e') {
+        Some(index) if !formatted[..index].contains('.') => {
+            format!("{}.0{}", &formatted[..index], &formatted[index..])
+        }
+        _ => formatted,
+    }
+}
+
+struct Printer {
+    output: String,
+    indent: usize,
+    /// Whether lines are only broken to get back to the lines of the original source
+    keep_lines: bool,
+    /// Whether the forms being rendered are from the module's own file instead of an included file
+    in_module_file: bool,
+    /// The line of `output` that is being rendered, which has been counted up to `counted_len`
+    line: LineNum,
+    counted_len: usize,
+}
+
+impl Printer {
+    fn new() -> Self {
+        Printer {
+            output: String::new(),
+            indent: 0,
+            keep_lines: false,
+            in_module_file: true,
+            line: 1,
+            counted_len: 0,
+        }
+    }
+
+    fn push(&mut self, s: &str) {
+        self.output.push_str(s);
+    }
+
+    /// Separates layout, such as clauses and `end`, that was not on a known line.  When keeping
+    /// lines, the line is not broken, so that it cannot get ahead of the original source.
+    fn newline(&mut self) {
+        if self.keep_lines {
+            self.push(" ");
+        } else {
+            self.break_line();
+        }
+    }
+
+    /// Separates something that was on `line` of the original source.
+    fn newline_to(&mut self, line: LineNum) {
+        if self.keep_lines {
+            if self.in_module_file && self.current_line() < line {
+                self.advance_to(line);
+            } else {
+                self.push(" ");
+            }
+        } else {
+            self.break_line();
+        }
+    }
+
+    /// When keeping lines, breaks the line until the output gets to `line` of the original source.
+    fn advance_to(&mut self, line: LineNum) {
+        if self.keep_lines && self.in_module_file {
+            while self.current_line() < line {
+                self.break_line();
+            }
+        }
+    }
+
+    fn break_line(&mut self) {
+        self.output.push('\n');
+
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    fn current_line(&mut self) -> LineNum {
+        let uncounted = &self.output[self.counted_len..];
+        self.line += uncounted.matches('\n').count() as LineNum;
+        self.counted_len = self.output.len();
+
+        self.line
+    }
+
+    fn comma_separated<T, F>(&mut self, items: &[T], mut f: F)
+    where
+        F: FnMut(&mut Self, &T),
+    {
+        for (index, item) in items.iter().enumerate() {
+            if 0 < index {
+                self.push(", ");
+            }
+
+            f(self, item);
+        }
+    }
+
+    // Forms
+
+    /// Functions are separated by blank lines from the forms before them, except from their specs.
+    fn forms(&mut self, forms: &[Form], code_only: bool) {
+        let mut previous: Option<&Form> = None;
+        let mut module_file: Option<&str> = None;
+
+        for form in forms {
+            // The first `-file` is the module's own file and later ones are to and from includes.
+            if let Form::File(ref file) = *form {
+                let original_file = file.original_file.as_str();
+                self.in_module_file = *module_file.get_or_insert(original_file) == original_file;
+            }
+
+            if code_only && !affects_code(form) {
+                continue;
+            }
+
+            if self.keep_lines {
+                self.advance_to(form.line());
+                self.form(form);
+
+                continue;
+            }
+
+            match (previous, form) {
+                (Some(Form::Spec(_)), Form::Fun(_)) => (),
+                (Some(_), Form::Fun(_)) | (Some(Form::Fun(_)), Form::Spec(_)) => self.push("\n"),
+                _ => (),
+            }
+
+            self.form(form);
+            previous = Some(form);
+        }
+    }
+
+    fn form(&mut self, form: &Form) {
+        match *form {
+            Form::Module(ref x) => {
+                self.push("-module(");
+                self.push(&atom_to_string(&x.name));
+                self.push(").\n");
+            }
+            Form::Behaviour(ref x) => {
+                self.push(if x.is_british {
+                    "-behaviour("
+                } else {
+                    "-behavior("
+                });
+                self.push(&atom_to_string(&x.name));
+                self.push(").\n");
+            }
+            Form::Export(ref x) => {
+                self.push("-export([");
+                self.comma_separated(&x.funs, |printer, export| {
+                    printer.name_arity(&export.fun, export.arity)
+                });
+                self.push("]).\n");
+            }
+            Form::Import(ref x) => {
+                self.push("-import(");
+                self.push(&atom_to_string(&x.module));
+                self.push(", [");
+                self.comma_separated(&x.funs, |printer, import| {
+                    printer.name_arity(&import.fun, import.arity)
+                });
+                self.push("]).\n");
+            }
+            Form::ExportType(ref x) => {
+                self.push("-export_type([");
+                self.comma_separated(&x.types, |printer, export_type| {
+                    printer.name_arity(&export_type.typ, export_type.arity)
+                });
+                self.push("]).\n");
+            }
+            Form::Compile(ref x) => {
+                self.push("-compile(");
+                self.term(&x.options);
+                self.push(").\n");
+            }
+            Form::File(ref x) => {
+                self.push("-file(");
+                self.push(&string_to_string(&x.original_file));
+                writeln!(self.output, ", {}).", x.original_line).unwrap();
+            }
+            Form::Record(ref x) => self.record_decl(x),
+            Form::Type(ref x) => self.type_decl(x),
+            Form::Spec(ref x) => self.spec(x),
+            // Old compilers kept the types of record fields in a separate attribute next to the
+            // untyped record, which does not re-parse.
+            Form::Attr(ref x) if is_record_field_types(x) => (),
+            // The parser only takes `-on_load(Name/Arity)`, not the tuple it is stored as.
+            Form::Attr(ref x) if x.name == "on_load" && on_load_function(x).is_some() => {
+                let (name, arity) = on_load_function(x).unwrap();
+
+                self.push("-on_load(");
+                self.name_arity(name, arity);
+                self.push(").\n");
+            }
+            Form::Attr(ref x) => {
+                self.push("-");
+                self.push(&atom_to_string(&x.name));
+                self.push("(");
+                self.term(&x.value);
+                self.push(").\n");
+            }
+            Form::Fun(ref x) => self.fun_decl(x),
+            Form::Eof(_) => (),
+        }
+    }
+
+    fn name_arity(&mut self, name: &str, arity: u32) {
+        self.push(&atom_to_string(name));
+        write!(self.output, "/{}", arity).unwrap();
+    }
+
+    /// Fields without a type are typed `any()` by the parser, so `any()` is left out.
+    fn record_decl(&mut self, record: &form::RecordDecl) {
+        self.push("-record(");
+        self.push(&atom_to_string(&record.name));
+        self.push(", {");
+        self.comma_separated(&record.fields, |printer, field| {
+            printer.push(&atom_to_string(&field.name));
+            printer.push(" = ");
+            printer.expr(&field.default_value, 0);
+
+            if !is_any(&field.ty) {
+                printer.push(" :: ");
+                printer.ty(&field.ty, 0);
+            }
+        });
+        self.push("}).\n");
+    }
+
+    fn type_decl(&mut self, type_decl: &form::TypeDecl) {
+        self.push(if type_decl.is_opaque {
+            "-opaque "
+        } else {
+            "-type "
+        });
+        self.push(&atom_to_string(&type_decl.name));
+        self.push("(");
+        self.comma_separated(&type_decl.vars, |printer, var| printer.push(&var.name));
+        self.push(") :: ");
+        self.ty(&type_decl.ty, 0);
+        self.push(".\n");
+    }
+
+    /// `-spec name(Args) -> Return; (Args) -> Return.`, with each clause on its own line
+    fn spec(&mut self, spec: &form::FunSpec) {
+        self.push(if spec.is_callback {
+            "-callback "
+        } else {
+            "-spec "
+        });
+
+        if let Some(ref module) = spec.module {
+            self.push(&atom_to_string(module));
+            self.push(":");
+        }
+
+        self.push(&atom_to_string(&spec.name));
+
+        for (index, fun) in spec.types.iter().enumerate() {
+            if 0 < index {
+                self.push(";");
+                self.newline();
+                self.push(INDENT);
+            }
+
+            self.fun_type(fun);
+        }
+
+        self.push(".\n");
+    }
+
+    fn fun_decl(&mut self, fun: &form::FunDecl) {
+        for (index, clause) in fun.clauses.iter().enumerate() {
+            if 0 < index {
+                self.push(";");
+                self.newline_to(clause.line());
+            }
+
+            self.push(&atom_to_string(&fun.name));
+            self.clause_head_and_body(clause);
+        }
+
+        self.push(".\n");
+    }
+
+    // Clauses
+
+    /// `(Patterns) when Guards -> Body`
+    fn clause_head_and_body(&mut self, clause: &Clause) {
+        self.push("(");
+        self.comma_separated(&clause.patterns, |printer, pattern| {
+            printer.pattern(pattern, 0)
+        });
+        self.push(")");
+        self.guards_and_body(clause);
+    }
+
+    fn guards_and_body(&mut self, clause: &Clause) {
+        self.guards(&clause.guards);
+        self.push(" ->");
+        self.body(&clause.body);
+    }
+
+    fn guards(&mut self, guards: &[OrGuard]) {
+        if !guards.is_empty() {
+            self.push(" when ");
+            self.guard_sequence(guards);
+        }
+    }
+
+    fn guard_sequence(&mut self, guards: &[OrGuard]) {
+        for (index, or_guard) in guards.iter().enumerate() {
+            if 0 < index {
+                self.push("; ");
+            }
+
+            self.comma_separated(&or_guard.and_guards, |printer, guard| {
+                printer.guard(guard, 0)
+            });
+        }
+    }
+
+    /// Each expression in `body` on its own line, indented one level from the clause head.
+    fn body(&mut self, body: &[Expression]) {
+        self.indent += 1;
+
+        for (index, expression) in body.iter().enumerate() {
+            if 0 < index {
+                self.push(",");
+            }
+
+            self.newline_to(expression.line());
+            self.expr(expression, 0);
+        }
+
+        self.indent -= 1;
+    }
+
+    fn clauses<F>(&mut self, clauses: &[Clause], mut head: F)
+    where
+        F: FnMut(&mut Self, &Clause),
+    {
+        self.indent += 1;
+
+        for (index, clause) in clauses.iter().enumerate() {
+            if 0 < index {
+                self.push(";");
+            }
+
+            self.newline_to(clause.line());
+            head(self, clause);
+            self.guards_and_body(clause);
+        }
+
+        self.indent -= 1;
+    }
+
+    fn case_clauses(&mut self, clauses: &[Clause]) {
+        self.clauses(clauses, |printer, clause| {
+            printer.pattern(&clause.patterns[0], 0)
+        });
+    }
+
+    /// Catch clauses have a single `{Class, Reason, Stacktrace}` tuple pattern, which is printed as
+    /// `Class:Reason:Stacktrace`.
+    fn catch_clauses(&mut self, clauses: &[Clause]) {
+        self.clauses(clauses, |printer, clause| match clause.patterns[0] {
+            Pattern::Tuple(ref tuple) if tuple.elements.len() == 3 => {
+                printer.pattern(&tuple.elements[0], MAX_PRIORITY);
+                printer.push(":");
+                printer.pattern(&tuple.elements[1], MAX_PRIORITY);
+
+                match tuple.elements[2] {
+                    Pattern::Var(ref var) if var.is_anonymous() => (),
+                    ref stacktrace => {
+                        printer.push(":");
+                        printer.pattern(stacktrace, MAX_PRIORITY);
+                    }
+                }
+            }
+            ref pattern => printer.pattern(pattern, 0),
+        });
+    }
+
+    fn end(&mut self) {
+        self.newline();
+        self.push("end");
+    }
+
+    // Expressions
+
+    fn expr(&mut self, expression: &Expression, priority: u32) {
+        self.advance_to(expression.line());
+
+        match *expression {
+            Expression::Integer(ref x) => self.integer(x),
+            Expression::Float(ref x) => self.push(&float_to_string(x.value)),
+            Expression::String(ref x) => self.push(&string_to_string(&x.value)),
+            Expression::Char(ref x) => self.push(&char_to_string(x.value)),
+            Expression::Atom(ref x) => self.push(&atom_to_string(&x.value)),
+            Expression::Match(ref x) => {
+                let (left, own, right) = infix_operator_priorities("=");
+
+                self.parenthesize(own < priority, |printer| {
+                    printer.pattern(&x.left, left);
+                    printer.push(" = ");
+                    printer.expr(&x.right, right);
+                });
+            }
+            Expression::Var(ref x) => self.push(&x.name),
+            Expression::Tuple(ref x) => self.tuple(&x.elements, Self::expr),
+            Expression::Nil(_) => self.push("[]"),
+            Expression::Cons(ref x) => self.cons(
+                x,
+                Self::expr,
+                |tail| match *tail {
+                    Expression::Cons(ref cons) => Some(&**cons),
+                    _ => None,
+                },
+                |tail| match *tail {
+                    Expression::Nil(_) => true,
+                    _ => false,
+                },
+            ),
+            Expression::Binary(ref x) => self.binary(x, Self::expr),
+            Expression::UnaryOp(ref x) => self.unary_op(x, priority, Self::expr),
+            Expression::BinaryOp(ref x) => self.binary_op(x, priority, Self::expr),
+            Expression::Record(ref x) => self.record(x, Self::expr),
+            Expression::RecordIndex(ref x) => self.record_index(x, Self::expr),
+            Expression::Map(ref x) => self.map(x, Self::expr),
+            Expression::Catch(ref x) => {
+                let (own, operand) = prefix_operator_priorities("catch");
+
+                self.parenthesize(own < priority, |printer| {
+                    printer.push("catch ");
+                    printer.expr(&x.expr, operand);
+                });
+            }
+            Expression::LocalCall(ref x) => self.local_call(x, Self::expr),
+            Expression::RemoteCall(ref x) => self.remote_call(x, Self::expr),
+            Expression::Comprehension(ref x) => self.comprehension(x),
+            Expression::Block(ref x) => {
+                self.push("begin");
+                self.body(&x.body);
+                self.end();
+            }
+            Expression::If(ref x) => self.if_expr(x),
+            Expression::Case(ref x) => {
+                self.push("case ");
+                self.expr(&x.expr, 0);
+                self.push(" of");
+                self.case_clauses(&x.clauses);
+                self.end();
+            }
+            Expression::Try(ref x) => self.try_expr(x),
+            Expression::Receive(ref x) => self.receive(x),
+            Expression::InternalFun(ref x) => {
+                self.push("fun ");
+                self.name_arity(&x.function, x.arity);
+            }
+            Expression::ExternalFun(ref x) => {
+                self.push("fun ");
+                self.expr(&x.module, MAX_PRIORITY);
+                self.push(":");
+                self.expr(&x.function, MAX_PRIORITY);
+                self.push("/");
+                self.expr(&x.arity, MAX_PRIORITY);
+            }
+            Expression::AnonymousFun(ref x) => self.anonymous_fun(x),
+        }
+    }
+
+    fn integer(&mut self, integer: &literal::Integer) {
+        write!(self.output, "{}", integer.value).unwrap();
+    }
+
+    fn parenthesize<F>(&mut self, needed: bool, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        if needed {
+            self.push("(");
+            f(self);
+            self.push(")");
+        } else {
+            f(self);
+        }
+    }
+
+    fn tuple<T, F>(&mut self, elements: &[T], mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        self.push("{");
+        self.comma_separated(elements, |printer, element| f(printer, element, 0));
+        self.push("}");
+    }
+
+    /// Prints `[H1, H2 | T]`, flattening nested cons cells in the tail.
+    fn cons<T, F, C, N>(&mut self, cons: &common::Cons<T>, mut f: F, as_cons: C, is_nil: N)
+    where
+        F: FnMut(&mut Self, &T, u32),
+        C: Fn(&T) -> Option<&common::Cons<T>>,
+        N: Fn(&T) -> bool,
+    {
+        self.push("[");
+        f(self, &cons.head, 0);
+
+        let mut tail = &cons.tail;
+
+        loop {
+            if let Some(tail_cons) = as_cons(tail) {
+                self.push(", ");
+                f(self, &tail_cons.head, 0);
+                tail = &tail_cons.tail;
+            } else {
+                if !is_nil(tail) {
+                    self.push(" | ");
+                    f(self, tail, 0);
+                }
+
+                break;
+            }
+        }
+
+        self.push("]");
+    }
+
+    fn binary<T, F>(&mut self, binary: &common::Binary<T>, mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        self.push("<<");
+        self.comma_separated(&binary.elements, |printer, element| {
+            // Only primary expressions are allowed before `:` and `/`
+            f(printer, &element.element, MAX_PRIORITY);
+
+            if let Some(ref size) = element.size {
+                printer.push(":");
+                f(printer, size, MAX_PRIORITY);
+            }
+
+            if let Some(ref type_specs) = element.tsl {
+                printer.push("/");
+
+                for (index, type_spec) in type_specs.iter().enumerate() {
+                    if 0 < index {
+                        printer.push("-");
+                    }
+
+                    printer.push(&type_spec.name);
+
+                    if let Some(value) = type_spec.value {
+                        write!(printer.output, ":{}", value).unwrap();
+                    }
+                }
+            }
+        });
+        self.push(">>");
+    }
+
+    fn unary_op<T, F>(&mut self, unary_op: &common::UnaryOp<T>, priority: u32, mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        let (own, operand) = prefix_operator_priorities(&unary_op.operator);
+
+        self.parenthesize(own < priority, |printer| {
+            printer.push(&unary_op.operator);
+
+            let is_word = unary_op.operator.chars().all(|c| c.is_ascii_alphabetic());
+
+            if is_word {
+                printer.push(" ");
+            }
+
+            let start = printer.output.len();
+            f(printer, &unary_op.operand, operand);
+
+            // `- -1` must not be printed as the `--` operator
+            let first = printer.output[start..].chars().next();
+
+            if !is_word && (first == Some('-') || first == Some('+')) {
+                printer.output.insert(start, ' ');
+            }
+        });
+    }
+
+    fn binary_op<T, F>(&mut self, binary_op: &common::BinaryOp<T>, priority: u32, mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        let (left, own, right) = infix_operator_priorities(&binary_op.operator);
+
+        self.parenthesize(own < priority, |printer| {
+            f(printer, &binary_op.left_operand, left);
+            printer.push(" ");
+            printer.push(&binary_op.operator);
+            printer.push(" ");
+            f(printer, &binary_op.right_operand, right);
+        });
+    }
+
+    fn record<T, F>(&mut self, record: &common::Record<T>, mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        if let Some(ref base) = record.base {
+            self.expr(base, RECORD_PRIORITY);
+        }
+
+        self.push("#");
+        self.push(&atom_to_string(&record.name));
+        self.push("{");
+        self.comma_separated(&record.fields, |printer, field| {
+            match field.name {
+                Some(ref name) => printer.push(&atom_to_string(name)),
+                None => printer.push("_"),
+            }
+
+            printer.push(" = ");
+            f(printer, &field.value, 0);
+        });
+        self.push("}");
+    }
+
+    fn record_index<T, F>(&mut self, record_index: &common::RecordIndex<T>, mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        if let Some(ref base) = record_index.base {
+            f(self, base, RECORD_PRIORITY);
+        }
+
+        self.push("#");
+        self.push(&atom_to_string(&record_index.record));
+        self.push(".");
+        self.push(&atom_to_string(&record_index.field));
+    }
+
+    fn map<T, F>(&mut self, map: &common::Map<T>, mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        if let Some(ref base) = map.base {
+            self.expr(base, RECORD_PRIORITY);
+        }
+
+        self.push("#{");
+        self.comma_separated(&map.pairs, |printer, pair| {
+            f(printer, &pair.key, 0);
+            printer.push(if pair.is_assoc { " => " } else { " := " });
+            f(printer, &pair.value, 0);
+        });
+        self.push("}");
+    }
+
+    fn local_call<T, F>(&mut self, call: &common::LocalCall<T>, mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        f(self, &call.function, MAX_PRIORITY);
+        self.arguments(&call.args, f);
+    }
+
+    fn remote_call<T, F>(&mut self, call: &common::RemoteCall<T>, mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        f(self, &call.module, MAX_PRIORITY);
+        self.push(":");
+        f(self, &call.function, MAX_PRIORITY);
+        self.arguments(&call.args, f);
+    }
+
+    fn arguments<T, F>(&mut self, arguments: &[T], mut f: F)
+    where
+        F: FnMut(&mut Self, &T, u32),
+    {
+        self.push("(");
+        self.comma_separated(arguments, |printer, argument| f(printer, argument, 0));
+        self.push(")");
+    }
+
+    fn comprehension(&mut self, comprehension: &expr::Comprehension) {
+        let (open, close) = if comprehension.is_list {
+            ("[", "]")
+        } else {
+            ("<<", ">>")
+        };
+
+        self.push(open);
+        // A binary comprehension's expression must be a binary, which is already delimited
+        self.expr(&comprehension.expr, 0);
+        self.push(" || ");
+        self.comma_separated(
+            &comprehension.qualifiers,
+            |printer, qualifier| match *qualifier {
+                expr::Qualifier::Generator(ref generator) => printer.generator(generator, " <- "),
+                expr::Qualifier::BitStringGenerator(ref generator) => {
+                    printer.generator(generator, " <= ")
+                }
+                expr::Qualifier::Filter(ref filter) => printer.expr(filter, 0),
+            },
+        );
+        self.push(close);
+    }
+
+    fn generator(&mut self, generator: &expr::Generator, arrow: &str) {
+        self.pattern(&generator.pattern, 0);
+        self.push(arrow);
+        self.expr(&generator.expr, 0);
+    }
+
+    /// `if` clauses are only guards, without `when`
+    fn if_expr(&mut self, if_expr: &expr::If) {
+        self.push("if");
+        self.indent += 1;
+
+        for (index, clause) in if_expr.clauses.iter().enumerate() {
+            if 0 < index {
+                self.push(";");
+            }
+
+            self.newline_to(clause.line());
+
+            if clause.guards.is_empty() {
+                self.push("true");
+            } else {
+                self.guard_sequence(&clause.guards);
+            }
+
+            self.push(" ->");
+            self.body(&clause.body);
+        }
+
+        self.indent -= 1;
+        self.end();
+    }
+
+    fn try_expr(&mut self, try_expr: &expr::Try) {
+        self.push("try");
+        self.body(&try_expr.body);
+
+        if !try_expr.case_clauses.is_empty() {
+            self.newline();
+            self.push("of");
+            self.case_clauses(&try_expr.case_clauses);
+        }
+
+        if !try_expr.catch_clauses.is_empty() {
+            self.newline();
+            self.push("catch");
+            self.catch_clauses(&try_expr.catch_clauses);
+        }
+
+        if !try_expr.after.is_empty() {
+            self.newline();
+            self.push("after");
+            self.body(&try_expr.after);
+        }
+
+        self.end();
+    }
+
+    fn receive(&mut self, receive: &expr::Receive) {
+        self.push("receive");
+        self.case_clauses(&receive.clauses);
+
+        if let Some(ref timeout) = receive.timeout {
+            self.newline();
+            self.push("after");
+            self.indent += 1;
+            self.newline();
+            self.expr(timeout, 0);
+            self.push(" ->");
+            self.body(&receive.after);
+            self.indent -= 1;
+        }
+
+        self.end();
+    }
+
+    fn anonymous_fun(&mut self, fun: &expr::AnonymousFun) {
+        self.push("fun");
+
+        match fun.name {
+            Some(ref name) => {
+                self.clauses(&fun.clauses, |printer, clause| {
+                    printer.push(name);
+                    printer.pattern_arguments(&clause.patterns);
+                });
+            }
+            None => {
+                self.push(" ");
+
+                for (index, clause) in fun.clauses.iter().enumerate() {
+                    if 0 < index {
+                        self.push(";");
+                        self.newline();
+                        self.push(INDENT);
+                    }
+
+                    self.pattern_arguments(&clause.patterns);
+                    self.guards_and_body(clause);
+                }
+            }
+        }
+
+        self.end();
+    }
+
+    fn pattern_arguments(&mut self, patterns: &[Pattern]) {
+        self.push("(");
+        self.comma_separated(patterns, |printer, pattern| printer.pattern(pattern, 0));
+        self.push(")");
+    }
+
+    // Patterns
+
+    fn pattern(&mut self, pattern: &Pattern, priority: u32) {
+        match *pattern {
+            Pattern::Integer(ref x) => self.integer(x),
+            Pattern::Float(ref x) => self.push(&float_to_string(x.value)),
+            Pattern::String(ref x) => self.push(&string_to_string(&x.value)),
+            Pattern::Char(ref x) => self.push(&char_to_string(x.value)),
+            Pattern::Atom(ref x) => self.push(&atom_to_string(&x.value)),
+            Pattern::Var(ref x) => self.push(&x.name),
+            Pattern::Match(ref x) => {
+                let (left, own, right) = infix_operator_priorities("=");
+
+                self.parenthesize(own < priority, |printer| {
+                    printer.pattern(&x.left, left);
+                    printer.push(" = ");
+                    printer.pattern(&x.right, right);
+                });
+            }
+            Pattern::Tuple(ref x) => self.tuple(&x.elements, Self::pattern),
+            Pattern::Nil(_) => self.push("[]"),
+            Pattern::Cons(ref x) => self.cons(
+                x,
+                Self::pattern,
+                |tail| match *tail {
+                    Pattern::Cons(ref cons) => Some(&**cons),
+                    _ => None,
+                },
+                |tail| match *tail {
+                    Pattern::Nil(_) => true,
+                    _ => false,
+                },
+            ),
+            Pattern::Binary(ref x) => self.binary(x, Self::pattern),
+            Pattern::UnaryOp(ref x) => self.unary_op(x, priority, Self::pattern),
+            Pattern::BinaryOp(ref x) => self.binary_op(x, priority, Self::pattern),
+            Pattern::Record(ref x) => self.record(x, Self::pattern),
+            Pattern::RecordIndex(ref x) => self.record_index(x, Self::pattern),
+            Pattern::Map(ref x) => self.map(x, Self::pattern),
+        }
+    }
+
+    // Guards
+
+    fn guard(&mut self, guard: &Guard, priority: u32) {
+        match *guard {
+            Guard::Integer(ref x) => self.integer(x),
+            Guard::Float(ref x) => self.push(&float_to_string(x.value)),
+            Guard::String(ref x) => self.push(&string_to_string(&x.value)),
+            Guard::Char(ref x) => self.push(&char_to_string(x.value)),
+            Guard::Atom(ref x) => self.push(&atom_to_string(&x.value)),
+            Guard::Var(ref x) => self.push(&x.name),
+            Guard::Tuple(ref x) => self.tuple(&x.elements, Self::guard),
+            Guard::Nil(_) => self.push("[]"),
+            Guard::Cons(ref x) => self.cons(
+                x,
+                Self::guard,
+                |tail| match *tail {
+                    Guard::Cons(ref cons) => Some(&**cons),
+                    _ => None,
+                },
+                |tail| match *tail {
+                    Guard::Nil(_) => true,
+                    _ => false,
+                },
+            ),
+            Guard::Binary(ref x) => self.binary(x, Self::guard),
+            Guard::UnaryOp(ref x) => self.unary_op(x, priority, Self::guard),
+            Guard::BinaryOp(ref x) => self.binary_op(x, priority, Self::guard),
+            Guard::Record(ref x) => self.record(x, Self::guard),
+            Guard::RecordIndex(ref x) => self.record_index(x, Self::guard),
+            Guard::LocalCall(ref x) => self.local_call(x, Self::guard),
+            Guard::RemoteCall(ref x) => self.remote_call(x, Self::guard),
+        }
+    }
+
+    // Types
+
+    fn ty(&mut self, ty: &Type, priority: u32) {
+        match *ty {
+            Type::Atom(ref x) => self.push(&atom_to_string(&x.value)),
+            Type::Integer(ref x) => self.integer(x),
+            Type::Var(ref x) => self.push(&x.name),
+            Type::Annotated(ref x) => {
+                let (_, own, right) = type_infix_operator_priorities("::");
+
+                self.parenthesize(own < priority, |printer| {
+                    printer.push(&x.name.name);
+                    printer.push(" :: ");
+                    printer.ty(&x.ty, right);
+                });
+            }
+            Type::UnaryOp(ref x) => self.unary_op(x, priority, Self::ty),
+            Type::BinaryOp(ref x) => self.binary_op(x, priority, Self::ty),
+            Type::BitString(ref x) => self.bit_string_type(x),
+            Type::Nil(_) => self.push("[]"),
+            Type::AnyFun(ref x) => {
+                self.push("fun(");
+
+                if let Some(ref return_type) = x.return_type {
+                    self.push("(...) -> ");
+                    self.ty(return_type, 0);
+                }
+
+                self.push(")");
+            }
+            Type::Function(ref x) => {
+                self.push("fun(");
+                self.fun_type(x);
+                self.push(")");
+            }
+            Type::Range(ref x) => {
+                let (left, own, right) = type_infix_operator_priorities("..");
+
+                self.parenthesize(own < priority, |printer| {
+                    printer.ty(&x.low, left);
+                    printer.push("..");
+                    printer.ty(&x.high, right);
+                });
+            }
+            Type::Map(ref x) => {
+                self.push("#{");
+                self.comma_separated(&x.pairs, |printer, pair| {
+                    printer.ty(&pair.key, 0);
+                    printer.push(" => ");
+                    printer.ty(&pair.value, 0);
+                });
+                self.push("}");
+            }
+            Type::BuiltIn(ref x) => {
+                self.push(&atom_to_string(&x.name));
+                self.type_arguments(&x.args);
+            }
+            Type::Record(ref x) => {
+                self.push("#");
+                self.push(&atom_to_string(&x.name));
+                self.push("{");
+                self.comma_separated(&x.fields, |printer, field| {
+                    printer.push(&atom_to_string(&field.name));
+                    printer.push(" :: ");
+                    printer.ty(&field.ty, 0);
+                });
+                self.push("}");
+            }
+            Type::Remote(ref x) => {
+                self.push(&atom_to_string(&x.module));
+                self.push(":");
+                self.push(&atom_to_string(&x.function));
+                self.type_arguments(&x.args);
+            }
+            Type::AnyTuple(_) => self.push("tuple()"),
+            Type::Tuple(ref x) => self.tuple(&x.elements, Self::ty),
+            Type::Union(ref x) => {
+                let (left, own, right) = type_infix_operator_priorities("|");
+
+                self.parenthesize(own < priority, |printer| {
+                    for (index, ty) in x.types.iter().enumerate() {
+                        if 0 < index {
+                            printer.push(" | ");
+                        }
+
+                        printer.ty(ty, if 0 < index { right } else { left });
+                    }
+                });
+            }
+            Type::User(ref x) => {
+                self.push(&atom_to_string(&x.name));
+                self.type_arguments(&x.args);
+            }
+        }
+    }
+
+    fn type_arguments(&mut self, arguments: &[Type]) {
+        self.push("(");
+        self.comma_separated(arguments, |printer, argument| printer.ty(argument, 0));
+        self.push(")");
+    }
+
+    /// `(Args) -> Return when Constraints`, as in specs and `fun()` types
+    fn fun_type(&mut self, fun: &ty::Fun) {
+        self.type_arguments(&fun.args);
+        self.push(" -> ");
+        self.ty(&fun.return_type, 0);
+
+        if !fun.constraints.is_empty() {
+            self.push(" when ");
+            self.comma_separated(&fun.constraints, |printer, constraint| {
+                printer.push(&constraint.var.name);
+                printer.push(" :: ");
+                printer.ty(&constraint.subtype, 0);
+            });
+        }
+    }
+
+    /// `<<>>`, `<<_:M>>`, `<<_:_*N>>` or `<<_:M, _:_*N>>`
+    fn bit_string_type(&mut self, bit_string: &ty::BitString) {
+        self.push("<<");
+
+        if bit_string.bytes != 0 {
+            write!(self.output, "_:{}", bit_string.bytes).unwrap();
+        }
+
+        if bit_string.tail_bits != 0 {
+            if bit_string.bytes != 0 {
+                self.push(", ");
+            }
+
+            write!(self.output, "_:_*{}", bit_string.tail_bits).unwrap();
+        }
+
+        self.push(">>");
+    }
+
+    // Terms
+
+    /// Attribute values are terms instead of abstract code, so they are printed like `io:format`'s
+    /// `~p`, but on one line.
+    fn term(&mut self, term: &etf::Term) {
+        match *term {
+            etf::Term::Atom(ref x) => self.push(&atom_to_string(&x.name)),
+            etf::Term::FixInteger(ref x) => write!(self.output, "{}", x.value).unwrap(),
+            etf::Term::BigInteger(ref x) => write!(self.output, "{}", x.value).unwrap(),
+            etf::Term::Float(ref x) => self.push(&float_to_string(x.value)),
+            etf::Term::Binary(ref x) => match std::str::from_utf8(&x.bytes) {
+                Ok(string) if is_printable(string) => {
+                    self.push("<<");
+                    self.push(&string_to_string(string));
+                    self.push(">>");
+                }
+                _ => {
+                    self.push("<<");
+                    self.comma_separated(&x.bytes, |printer, byte| {
+                        write!(printer.output, "{}", byte).unwrap()
+                    });
+                    self.push(">>");
+                }
+            },
+            etf::Term::List(ref x) => match printable_list(&x.elements) {
+                Some(string) => self.push(&string_to_string(&string)),
+                None => {
+                    self.push("[");
+                    self.comma_separated(&x.elements, Self::term);
+                    self.push("]");
+                }
+            },
+            etf::Term::ImproperList(ref x) => {
+                self.push("[");
+                self.comma_separated(&x.elements, Self::term);
+                self.push(" | ");
+                self.term(&x.last);
+                self.push("]");
+            }
+            etf::Term::Tuple(ref x) => {
+                self.push("{");
+                self.comma_separated(&x.elements, Self::term);
+                self.push("}");
+            }
+            etf::Term::Map(ref x) => {
+                self.push("#{");
+                self.comma_separated(&x.entries, |printer, (key, value)| {
+                    printer.term(key);
+                    printer.push(" => ");
+                    printer.term(value);
+                });
+                self.push("}");
+            }
+            // Pids, ports, references and funs cannot be written in source, so they can only be
+            // printed for reading.
+            ref other => write!(self.output, "{}", other).unwrap(),
+        }
+    }
+}
+
+/// Whether `form` changes the compiled code.  Compile options, such as `export_all` and
+/// `no_auto_import`, and `-on_load` do.  Types, specs, the original source locations and other
+/// attributes do not.
+fn affects_code(form: &Form) -> bool {
+    match *form {
+        Form::Module(_) | Form::Behaviour(_) | Form::Export(_) | Form::Import(_) => true,
+        Form::Compile(_) | Form::Record(_) | Form::Fun(_) => true,
+        Form::Attr(ref attr) => attr.name == "on_load",
+        Form::ExportType(_) | Form::File(_) | Form::Type(_) | Form::Spec(_) | Form::Eof(_) => false,
+    }
+}
+
+/// The `{Name, Arity}` of `-on_load(Name/Arity)`
+fn on_load_function(attr: &form::WildAttr) -> Option<(&str, u32)> {
+    match attr.value {
+        etf::Term::Tuple(ref tuple) => match tuple.elements.as_slice() {
+            [etf::Term::Atom(ref name), etf::Term::FixInteger(ref arity)] if 0 <= arity.value => {
+                Some((&name.name, arity.value as u32))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `-type({{record, Name}, Fields, []})`
+fn is_record_field_types(attr: &form::WildAttr) -> bool {
+    if attr.name != "type" {
+        return false;
+    }
+
+    match attr.value {
+        etf::Term::Tuple(ref tuple) => match tuple.elements.first() {
+            Some(etf::Term::Tuple(ref key)) => match key.elements.first() {
+                Some(etf::Term::Atom(ref tag)) => tag.name == "record",
+                _ => false,
+            },
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// `any()`, which is also the type of untyped record fields
+fn is_any(ty: &Type) -> bool {
+    match *ty {
+        Type::BuiltIn(ref built_in) => built_in.name == "any" && built_in.args.is_empty(),
+        _ => false,
+    }
+}
+
+fn is_printable(string: &str) -> bool {
+    string
+        .chars()
+        .all(|c| !c.is_control() || c == '\n' || c == '\t' || c == '\r')
+}
+
+/// Lists of printable characters are printed as strings, like `io_lib:printable_list/1`
+fn printable_list(elements: &[etf::Term]) -> Option<String> {
+    if elements.is_empty() {
+        return None;
+    }
+
+    let string: String = elements
+        .iter()
+        .map(|element| match *element {
+            etf::Term::FixInteger(ref x) if 0 <= x.value => std::char::from_u32(x.value as u32),
+            _ => None,
+        })
+        .collect::<Option<String>>()?;
+
+    if is_printable(&string) {
+        Some(string)
+    } else {
+        None
+    }
+}
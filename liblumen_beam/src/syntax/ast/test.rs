@@ -1,3 +1,4 @@
+use crate::serialization::etf;
 use crate::syntax::ast::*;
 
 #[test]
@@ -9,3 +10,127 @@ fn it_works() {
         })
         .unwrap();
 }
+
+#[test]
+fn from_beam_bytes_is_the_same_as_from_beam_file() {
+    let bytes = std::fs::read("tests/testdata/ast/test.beam").unwrap();
+    let from_bytes = AST::from_beam_bytes(&bytes).unwrap();
+    let from_file = AST::from_beam_file("tests/testdata/ast/test.beam").unwrap();
+
+    assert_eq!(
+        printer::module_to_string(&from_bytes.module),
+        printer::module_to_string(&from_file.module)
+    );
+}
+
+#[test]
+fn printer_renders_loadable_source() {
+    let ast = AST::from_beam_file("tests/testdata/ast/test.beam").unwrap();
+    let source = printer::module_code_to_string(&ast.module);
+
+    assert!(source.starts_with("-module(test).\n"));
+    assert!(source.contains("-export([literals/0])."));
+    assert!(source.contains("-import(lists, [usort/1])."));
+    // types and specs do not affect compilation, so they are left out
+    assert!(!source.contains("-spec"));
+    assert!(!source.contains("-type"));
+}
+
+#[test]
+fn printer_renders_loadable_source_with_compile_options() {
+    let ast = AST::from_beam_file("tests/testdata/ast/export_all.beam").unwrap();
+    let source = printer::module_code_to_string(&ast.module);
+
+    assert_eq!(
+        source.lines().collect::<Vec<_>>(),
+        vec![
+            "-module(export_all).",
+            "",
+            "-compile(export_all).",
+            "",
+            "answer() -> 42.",
+            "",
+            "double(X) -> X * 2.",
+        ]
+    );
+}
+
+#[test]
+fn printer_renders_char_escapes() {
+    let ast = AST::from_beam_file("tests/testdata/ast/char_escapes.beam").unwrap();
+    let source = printer::module_code_to_string(&ast.module);
+
+    assert!(source.contains("chars() -> [$\\x{0}, $\\s, $\\n, $', $\"]."));
+}
+
+#[test]
+fn printer_renders_on_load_as_name_and_arity() {
+    let on_load = ast::form::WildAttr::new(
+        1,
+        "on_load".to_string(),
+        etf::Tuple::from(vec![
+            etf::Atom::from("init").into(),
+            etf::FixInteger::from(0).into(),
+        ])
+        .into(),
+    );
+    let form = ast::form::Form::Attr(on_load);
+
+    assert_eq!(printer::form_to_string(&form), "-on_load(init/0).\n");
+    assert!(
+        printer::module_code_to_string(&ast::ModuleDecl { forms: vec![form] })
+            .contains("-on_load(init/0).")
+    );
+}
+
+#[test]
+fn printer_renders_loadable_source_on_original_lines() {
+    let ast = AST::from_beam_file("tests/testdata/ast/test.beam").unwrap();
+    let source = printer::module_code_to_string(&ast.module);
+    let lines: Vec<&str> = source.lines().collect();
+
+    assert_eq!(lines[9 - 1], "-export([literals/0]).");
+    assert!(lines[53 - 1].starts_with("hello(<<Name/binary>>) ->"));
+    assert!(lines[54 - 1].contains("io:format("));
+    assert!(lines[72 - 1].starts_with("to_my_list([]) ->"));
+    assert!(lines[73 - 1].starts_with("to_my_list([H | T]) ->"));
+}
+
+#[test]
+fn printer_renders_types_specs_and_attributes() {
+    let ast = AST::from_beam_file("tests/testdata/ast/test.beam").unwrap();
+    let source = printer::module_to_string(&ast.module);
+
+    assert!(source.starts_with("-file(\"test.erl\", 1).\n-module(test).\n"));
+    assert!(source.contains("-compile(debug_info).\n"));
+    assert!(source.contains("-foo_attribute(bar).\n"));
+    assert!(source.contains("-export_type([my_list/1]).\n"));
+    assert!(source.contains("-opaque my_list(E) :: my_cons(E, my_list(E)) | nil.\n"));
+    assert!(source.contains("-type my_cons(H, T) :: {H, T}.\n"));
+    assert!(
+        source.contains("-callback hello(Name :: binary()) -> ok | {error, Reason :: term()}.\n")
+    );
+    assert!(source.contains(
+        "-spec cons(H, T) -> my_cons(H, T) when H :: term(), T :: term().\ncons(H, T) ->\n"
+    ));
+    assert!(source.contains(
+        "-spec guard(integer() | atom()) -> integer() | atom();\n    (1..99) -> float();\n"
+    ));
+    assert!(source.contains("-spec my_record() -> #my_record{c :: pid()}.\n"));
+}
+
+#[test]
+fn printer_renders_types() {
+    let ast = AST::from_beam_file("tests/testdata/ast/test.beam").unwrap();
+    let types: Vec<String> = ast
+        .module
+        .forms
+        .iter()
+        .filter_map(|form| match form {
+            ast::form::Form::Type(type_decl) => Some(printer::type_to_string(&type_decl.ty)),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(vec!["my_cons(E, my_list(E)) | nil", "{H, T}"], types);
+}
@@ -0,0 +1,5 @@
+-module(char_escapes).
+
+-export([chars/0]).
+
+chars() -> [$\0, $\s, $\n, $', $"].
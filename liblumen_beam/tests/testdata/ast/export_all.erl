@@ -0,0 +1,7 @@
+-module(export_all).
+
+-compile(export_all).
+
+answer() -> 42.
+
+double(X) -> X * 2.
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
 use failure::{format_err, Error};
 
@@ -15,7 +17,7 @@ use libeir_ir::Module;
 use libeir_passes::PassManager;
 use libeir_syntax_erl::{ParseConfig, Parser};
 
-pub use super::config::{CompilerMode, CompilerSettings, Verbosity};
+pub use super::config::{CompilerMode, CompilerSettings, OutputType, Verbosity};
 pub use super::errors::CompilerError;
 
 /// The result produced by compiler functions
@@ -32,6 +34,16 @@ impl CompilationInfo {
             compilation_time: 0,
         }
     }
+
+    /// The number of modules compiled
+    pub fn num_modules(&self) -> usize {
+        self.num_modules
+    }
+
+    /// The time taken by the last compilation, in milliseconds
+    pub fn compilation_time(&self) -> usize {
+        self.compilation_time
+    }
 }
 
 pub struct Compiler {
@@ -58,16 +70,41 @@ impl Compiler {
         }
     }
 
+    /// Parses and lowers all modules under the source directory, runs
+    /// the default passes on them, and writes them to the output
+    /// directory.
+    ///
+    /// Diagnostics are reported as they are found, and compilation
+    /// fails after all modules have been parsed if any of them had
+    /// errors, or warnings when warnings are treated as errors.
     pub fn compile(&mut self) -> Result<(), Error> {
-        unimplemented!()
+        let start = Instant::now();
+
+        let modules = self.parse_modules()?;
+
+        fs::create_dir_all(self.output_dir())?;
+        for module in modules.values() {
+            self.write_module(module)?;
+        }
+
+        self.info.num_modules = modules.len();
+        self.info.compilation_time = start.elapsed().as_millis() as usize;
+        self.write_summary();
+
+        Ok(())
     }
 
-    // Parses all modules into a map. The map uses the module name symbol
-    // as the key, and the AST for the module as the value.
-    fn parse_modules(&mut self) -> Result<HashMap<Ident, Module>, Error> {
-        use walkdir::{DirEntry, WalkDir};
+    pub fn compilation_info(&self) -> &CompilationInfo {
+        &self.info
+    }
 
-        let mut parser = Parser::new(self.config.clone().into());
+    /// Parses all modules into a map. The map uses the module name symbol
+    /// as the key, and the IR for the module as the value.
+    ///
+    /// This is the front half of `compile`, for consumers such as the
+    /// interpreter that run the IR instead of writing it out.
+    pub fn parse_modules(&mut self) -> Result<HashMap<Ident, Module>, Error> {
+        use walkdir::{DirEntry, WalkDir};
 
         let extension = match self.config.mode {
             CompilerMode::Erlang => "erl",
@@ -91,54 +128,130 @@ impl Compiler {
             }
         }
 
+        // Only hidden entries are pruned, as pruning directories would
+        // skip the source files inside them
         let walker = WalkDir::new(self.config.source_dir.clone())
             .follow_links(true)
-            .into_iter();
+            .into_iter()
+            .filter_entry(|e| !is_hidden(e));
 
-        let mut modules = HashMap::new();
-        let config = ParseConfig::default();
+        let config: ParseConfig = self.config.clone().into();
         let mut parser = Parser::new(config);
 
-        for entry in walker.filter_entry(|e| !is_hidden(e) && is_source_file(e, extension)) {
-            let entry = entry.unwrap();
+        let mut modules = HashMap::new();
+        let mut files: HashMap<Ident, PathBuf> = HashMap::new();
+        let mut failed = false;
+
+        for entry in walker {
+            let entry = entry?;
+            if !is_source_file(&entry, extension) {
+                continue;
+            }
             let file = entry.path();
 
-            let mut module = match self.config.mode {
-                CompilerMode::Erlang => self.parse_erl(&mut parser, file)?,
+            let result = match self.config.mode {
+                CompilerMode::Erlang => self.parse_erl(&mut parser, file),
+            };
+            let module = match result {
+                Some(module) => module,
+                None => {
+                    failed = true;
+                    continue;
+                }
             };
 
+            if let Some(other) = files.insert(module.name.clone(), file.to_path_buf()) {
+                return Err(format_err!(
+                    "module {} is defined in both {} and {}",
+                    module.name,
+                    other.display(),
+                    file.display()
+                ));
+            }
             modules.insert(module.name.clone(), module);
         }
 
+        if failed {
+            return Err(CompilerError::Failed.into());
+        }
+
         Ok(modules)
     }
 
-    // Compiles a .erl file to Erlang AST
-    fn parse_erl(&self, parser: &mut Parser, file: &Path) -> Result<Module, Error> {
+    // Compiles a .erl file to EIR, returning `None` if it had errors,
+    // which have already been reported
+    fn parse_erl(&self, parser: &mut Parser, file: &Path) -> Option<Module> {
         use libeir_syntax_erl::ast;
         match parser.parse_file::<&Path, ast::Module>(file) {
             Ok(ast) => {
                 let (res, messages) = libeir_syntax_erl::lower_module(&ast);
+                let mut failed = false;
                 for msg in messages.iter() {
-                    self.diagnostic(&msg.to_diagnostic());
+                    failed |= self.report(&msg.to_diagnostic());
                 }
                 match res.ok() {
-                    Some(mut ir) => {
+                    Some(mut ir) if !failed => {
                         let mut pass_manager = PassManager::default();
                         pass_manager.run(&mut ir);
-                        Ok(ir)
+                        Some(ir)
                     }
-                    None => Err(CompilerError::Failed.into()),
+                    _ => None,
                 }
             }
-            Err(errs) => Err(CompilerError::Parser {
-                codemap: self.config.codemap.clone(),
-                errs,
+            Err(errs) => {
+                for err in errs.iter() {
+                    self.report(&err.to_diagnostic());
+                }
+                None
+            }
+        }
+    }
+
+    // Emits a diagnostic, unless it is a warning and warnings are
+    // disabled. Returns true if the diagnostic fails compilation.
+    fn report(&self, diagnostic: &Diagnostic) -> bool {
+        match diagnostic.severity {
+            Severity::Bug | Severity::Error => {
+                self.diagnostic(diagnostic);
+                true
+            }
+            Severity::Warning if self.no_warn() => false,
+            Severity::Warning => {
+                self.diagnostic(diagnostic);
+                self.warnings_as_errors()
+            }
+            _ => {
+                self.diagnostic(diagnostic);
+                false
             }
-            .into()),
         }
     }
 
+    fn write_module(&self, module: &Module) -> Result<(), Error> {
+        let path = match self.config.output_type {
+            OutputType::EirText => {
+                let path = self.output_dir().join(format!("{}.eir", module.name));
+                fs::write(&path, module.to_text())?;
+                path
+            }
+        };
+
+        self.debug(format!("Wrote {}\n", path.display()));
+
+        Ok(())
+    }
+
+    fn write_summary(&self) {
+        self.write_info(green_bold(), "Compiled ");
+        self.write_info(
+            green(),
+            format!(
+                "{} module(s) in {}ms\n",
+                self.info.num_modules, self.info.compilation_time
+            ),
+        );
+    }
+
     #[inline]
     fn write_warning<M: Display>(&self, color: ColorSpec, message: M) {
         self.emitter
@@ -7,6 +7,8 @@ use std::sync::{Arc, Mutex};
 use failure::{format_err, Error};
 
 use libeir_diagnostics::{CodeMap, ColorChoice};
+use libeir_intern::Symbol;
+use libeir_syntax_erl::preprocessor::MacroDef;
 use libeir_syntax_erl::ParseConfig;
 
 /// Determines which type of compilation to perform,
@@ -27,6 +29,27 @@ impl FromStr for CompilerMode {
     }
 }
 
+/// Determines what is written to the output directory
+/// for each compiled module.
+///
+/// There is no serialized EIR output yet, because `libeir_ir::Module`
+/// can't be serialized or read back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputType {
+    /// EIR in its textual form, as `<module>.eir`
+    EirText,
+}
+impl FromStr for OutputType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eir" => Ok(OutputType::EirText),
+            _ => Err(format_err!("invalid output type {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Verbosity {
     Debug,
@@ -58,7 +81,8 @@ pub struct CompilerSettings {
     pub color: ColorChoice,
     pub source_dir: PathBuf,
     pub output_dir: PathBuf,
-    //pub defines: HashMap<Symbol, MacroDef>,
+    pub output_type: OutputType,
+    pub defines: HashMap<Symbol, MacroDef>,
     pub warnings_as_errors: bool,
     pub no_warn: bool,
     pub verbosity: Verbosity,
@@ -66,15 +90,37 @@ pub struct CompilerSettings {
     pub include_path: VecDeque<PathBuf>,
     pub codemap: Arc<Mutex<CodeMap>>,
 }
+impl CompilerSettings {
+    /// Defines a macro from a `-D` flag, either `NAME`, which
+    /// defines `NAME` as `true`, or `NAME=VALUE`, which
+    /// defines `NAME` as the string `VALUE`
+    pub fn define(&mut self, define: &str) {
+        let (name, value) = match define.find('=') {
+            None => (define, MacroDef::Boolean(true)),
+            Some(index) => (
+                &define[..index],
+                MacroDef::String(Symbol::intern(&define[index + 1..])),
+            ),
+        };
+
+        self.defines.insert(Symbol::intern(name), value);
+    }
+}
 impl Into<ParseConfig> for CompilerSettings {
     fn into(self) -> ParseConfig {
-        ParseConfig {
+        let mut config = ParseConfig {
             codemap: self.codemap.clone(),
             warnings_as_errors: self.warnings_as_errors,
             no_warn: self.no_warn,
             code_paths: self.code_path.clone().into(),
             include_paths: self.include_path.clone(),
             macros: None,
+        };
+
+        for (name, value) in self.defines.iter() {
+            config.define(*name, value.clone());
         }
+
+        config
     }
 }
@@ -1,57 +1,25 @@
-use std::path::Path;
+use std::process;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 
-use libeir_diagnostics::{ColorChoice, Emitter, StandardStreamEmitter};
+use libeir_ir::FunctionIdent;
 
-use libeir_ir::{FunctionIdent, Module};
-
-use libeir_passes::PassManager;
-
-use libeir_syntax_erl::ast::Module as ErlAstModule;
-use libeir_syntax_erl::lower_module;
-use libeir_syntax_erl::{Parse, ParseConfig, Parser};
-
-use liblumen_eir_interpreter::call_result::call_run_erlang;
+use liblumen_eir_interpreter::call_result::{call_run_erlang, exit_code};
+use liblumen_eir_interpreter::debugger::{self, Breakpoint};
+use liblumen_eir_interpreter::literal;
+use liblumen_eir_interpreter::load::{register_beam_module, register_erl_module};
+use liblumen_eir_interpreter::print;
+use liblumen_eir_interpreter::trace;
 use liblumen_eir_interpreter::VM;
 
-use liblumen_alloc::erts::term::Atom;
+use liblumen_alloc::erts::term::{Atom, Pid};
 
 use lumen_runtime::scheduler::Scheduler;
 
-fn parse_file<T, P>(path: P, config: ParseConfig) -> (T, Parser)
-where
-    T: Parse<T>,
-    P: AsRef<Path>,
-{
-    let parser = Parser::new(config);
-    let errs = match parser.parse_file::<_, T>(path) {
-        Ok(ast) => return (ast, parser),
-        Err(errs) => errs,
-    };
-    let emitter =
-        StandardStreamEmitter::new(ColorChoice::Auto).set_codemap(parser.config.codemap.clone());
-    for err in errs.iter() {
-        emitter.diagnostic(&err.to_diagnostic()).unwrap();
-    }
-    panic!("parse failed");
-}
-
-fn lower_file<P>(path: P, config: ParseConfig) -> Result<Module, ()>
-where
-    P: AsRef<Path>,
-{
-    let (parsed, parser): (ErlAstModule, _) = parse_file(path, config);
-    let (res, messages) = lower_module(&parsed);
-
-    let emitter =
-        StandardStreamEmitter::new(ColorChoice::Auto).set_codemap(parser.config.codemap.clone());
-    for err in messages.iter() {
-        emitter.diagnostic(&err.to_diagnostic()).unwrap();
-    }
-
-    res
-}
+/// The status to exit with when an argument is invalid, such as an `--arg` that is not a term
+/// literal or fewer of them than the arity of the function, the same as `EX_USAGE` in
+/// `sysexits.h`
+const USAGE_EXIT_CODE: i32 = 64;
 
 fn main() {
     let matches = App::new("Lumen Eir Interpreter CLI")
@@ -61,37 +29,195 @@ fn main() {
                 .multiple(true)
                 .required(false),
         )
+        .arg(
+            Arg::from_usage("[LOAD_BEAM_FILES] -b,--beam <BEAM_FILES> 'load BEAM files compiled with debug_info'")
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::from_usage("<FUN_IDENT> -i,--ident <IDENT> 'select single function'")
                 .required(true),
         )
+        .arg(
+            Arg::from_usage("[ARGS] -a,--arg <TERM> 'pass an Erlang term literal as the next argument of the function, exiting with 64 if it is not valid or there are not as many as the arity'")
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true),
+        )
+        .arg(Arg::from_usage(
+            "[EXIT_CODE] --exit-code 'exit with a status code for the result of the function'",
+        ))
+        .arg(
+            Arg::from_usage("[TRACE] --trace <SINK> 'trace execution to stderr'")
+                .possible_values(&["silent", "human", "json"])
+                .default_value("silent"),
+        )
+        .arg(
+            Arg::from_usage("[TRACE_MODULES] --trace-module <MODULE> 'only trace calls to MODULE'")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::from_usage("[TRACE_FUNCTIONS] --trace-function <FUNCTION> 'only trace calls to FUNCTION or FUNCTION/ARITY'")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::from_usage("[TRACE_PIDS] --trace-pid <PID> 'only trace the process with PID, such as <0.42.0>'")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(Arg::from_usage(
+            "[DEBUG] --debug 'pause in the debugger when the function is called'",
+        ))
+        .arg(
+            Arg::from_usage("[BREAKPOINTS] --break <SPEC> 'pause in the debugger at module:function, module:function/arity or module:line'")
+                .multiple(true)
+                .number_of_values(1),
+        )
         .get_matches();
 
     let ident = FunctionIdent::parse(matches.value_of("FUN_IDENT").unwrap()).unwrap();
 
     &*VM;
 
+    let sink: Option<Box<dyn trace::Sink>> = match matches.value_of("TRACE").unwrap() {
+        "human" => Some(Box::new(trace::Human::stderr())),
+        "json" => Some(Box::new(trace::JsonLines::stderr())),
+        _ => None,
+    };
+    if let Some(sink) = sink {
+        trace::set(sink, trace_filter(&matches));
+    }
+
+    if matches.is_present("DEBUG") || matches.is_present("BREAKPOINTS") {
+        debugger::attach(Box::new(debugger::Console));
+
+        if matches.is_present("DEBUG") {
+            debugger::add_breakpoint(Breakpoint::Function {
+                module: Atom::try_from_str(&ident.module.as_str()).unwrap(),
+                function: Atom::try_from_str(&ident.name.as_str()).unwrap(),
+                arity: Some(ident.arity),
+            });
+        }
+
+        for spec in matches.values_of("BREAKPOINTS").into_iter().flatten() {
+            match Breakpoint::parse(spec) {
+                Some(breakpoint) => debugger::add_breakpoint(breakpoint),
+                None => usage_error(format!(
+                    "invalid --break {}, expected module:function, module:function/arity or module:line",
+                    spec
+                )),
+            }
+        }
+    }
+
     let arc_scheduler = Scheduler::current();
     let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
 
     let module = Atom::try_from_str(&ident.module.as_str()).unwrap();
     let function = Atom::try_from_str(&ident.name.as_str()).unwrap();
-    assert!(ident.arity == 0);
 
-    for file in matches.values_of("LOAD_ERL_FILES").unwrap() {
-        let config = ParseConfig::default();
-        let mut eir_mod = lower_file(file, config).unwrap();
+    let mut args = Vec::new();
+    for arg in matches.values_of("ARGS").into_iter().flatten() {
+        match literal::parse(&init_arc_process, arg) {
+            Ok(term) => args.push(term),
+            Err(error) => usage_error(format!("invalid argument {}: {}", arg, error)),
+        }
+    }
+    if args.len() != ident.arity {
+        usage_error(format!(
+            "{}:{}/{} takes {} arguments, but {} were given",
+            ident.module,
+            ident.name,
+            ident.arity,
+            ident.arity,
+            args.len()
+        ));
+    }
+
+    for file in matches.values_of("LOAD_BEAM_FILES").into_iter().flatten() {
+        if let Err(error) = register_beam_module(file) {
+            eprintln!("failed to load {}: {}", file, error);
+            process::exit(1);
+        }
+    }
 
-        for fun in eir_mod.functions.values() {
-            fun.graph_validate_global();
+    for file in matches.values_of("LOAD_ERL_FILES").into_iter().flatten() {
+        if let Err(error) = register_erl_module(file) {
+            eprintln!("failed to load {}: {}", file, error);
+            process::exit(1);
         }
+    }
+
+    let res = call_run_erlang(init_arc_process, module, function, &args);
+    match res.result {
+        Ok(ret) => println!("{}", print::print(ret)),
+        Err((class, reason, stacktrace)) => eprintln!(
+            "** exception {}: {}\n   stacktrace: {}",
+            print::print(class),
+            print::print(reason),
+            print::print(stacktrace)
+        ),
+    }
 
-        let mut pass_manager = PassManager::default();
-        pass_manager.run(&mut eir_mod);
+    if matches.is_present("EXIT_CODE") {
+        process::exit(exit_code(res.result));
+    }
+}
+
+fn trace_filter(matches: &ArgMatches) -> trace::Filter {
+    let mut filter = trace::Filter::default();
+
+    for module in matches.values_of("TRACE_MODULES").into_iter().flatten() {
+        filter.modules.push(Atom::try_from_str(module).unwrap());
+    }
+
+    for function in matches.values_of("TRACE_FUNCTIONS").into_iter().flatten() {
+        let (name, arity) = match function.rfind('/') {
+            Some(index) => match function[index + 1..].parse() {
+                Ok(arity) => (&function[..index], Some(arity)),
+                Err(_) => usage_error(format!("invalid arity in --trace-function {}", function)),
+            },
+            None => (function, None),
+        };
+
+        filter
+            .functions
+            .push((Atom::try_from_str(name).unwrap(), arity));
+    }
 
-        VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    for pid in matches.values_of("TRACE_PIDS").into_iter().flatten() {
+        match parse_pid(pid) {
+            Some(parsed) => filter.pids.push(parsed),
+            None => usage_error(format!(
+                "invalid --trace-pid {}, expected <0.Number.Serial>",
+                pid
+            )),
+        }
     }
 
-    let res = call_run_erlang(init_arc_process, module, function, &[]);
-    println!("Returned with {:?}", res.result);
+    filter
+}
+
+/// Writes `message` about an invalid argument to stderr and exits with `USAGE_EXIT_CODE`
+fn usage_error(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(USAGE_EXIT_CODE);
+}
+
+/// `<0.Number.Serial>`, with or without the angle brackets
+fn parse_pid(pid: &str) -> Option<Pid> {
+    let pid = pid.trim_start_matches('<').trim_end_matches('>');
+    let parts: Vec<&str> = pid.split('.').collect();
+
+    match parts.as_slice() {
+        ["0", number, serial] => {
+            let number = number.parse().ok()?;
+            let serial = serial.parse().ok()?;
+
+            Pid::new(number, serial).ok()
+        }
+        _ => None,
+    }
 }
@@ -3,18 +3,20 @@ use core::ptr::NonNull;
 use std::convert::TryInto;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use liblumen_alloc::borrow::clone_to_process::CloneToProcess;
-use liblumen_alloc::erts::exception::runtime;
 use liblumen_alloc::erts::process::code;
 use liblumen_alloc::erts::process::{Process, Status};
 use liblumen_alloc::erts::term::resource::Reference as ResourceReference;
-use liblumen_alloc::erts::term::{Atom, Boxed, Closure, Term, TypedTerm};
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Boxed, Closure, Term, TypedTerm};
 use liblumen_alloc::erts::{HeapFragment, ModuleFunctionArity};
 
 use lumen_runtime::process::spawn::options::Options;
 use lumen_runtime::scheduler::Scheduler;
-use lumen_runtime::system;
+
+use crate::trace::{self, Event};
 
 /// A sort of ghetto-future used to get the result from a process
 /// spawn.
@@ -56,10 +58,11 @@ pub fn call_run_erlang(
             }
             Status::Waiting => {
                 if ran {
-                    system::io::puts(&format!(
-                        "WAITING Run queues len = {:?}",
-                        Scheduler::current().run_queues_len()
-                    ));
+                    // Other processes may still send it a message
+                } else if !Scheduler::current().hierarchy.read().is_empty() {
+                    // Nothing can run until a timer, such as that of a `receive ... after`,
+                    // times out
+                    thread::sleep(Duration::from_millis(1));
                 } else {
                     panic!(
                         "{:?} did not run.  Deadlock likely in {:#?}",
@@ -68,17 +71,55 @@ pub fn call_run_erlang(
                     );
                 }
             }
-            Status::Runnable => {
-                system::io::puts(&format!(
-                    "RUNNABLE Run queues len = {:?}",
-                    Scheduler::current().run_queues_len()
-                ));
+            Status::Runnable | Status::Running => (),
+        }
+    }
+}
+
+/// The status to exit with for the `result` of the function a program runs.
+///
+/// Returning `ok`, `{ok, ...}` or any other term, except those below, or exiting with `normal`
+/// is success (`0`).  Returning `error` or `{error, ...}` is failure (`1`).  An integer from
+/// `0` to `255` is returned as is.  Any other uncaught exception is `2`.
+pub fn exit_code(result: Result<Term, (Term, Term, Term)>) -> i32 {
+    match result {
+        Ok(ret) => {
+            let tag = match ret.to_typed_term().unwrap() {
+                TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
+                    TypedTerm::Tuple(tuple) if !tuple.is_empty() => tuple[0],
+                    _ => ret,
+                },
+                TypedTerm::SmallInteger(small_integer) => {
+                    let status: isize = small_integer.into();
+
+                    if 0 <= status && status <= 255 {
+                        return status as i32;
+                    }
+
+                    ret
+                }
+                _ => ret,
+            };
+
+            match tag.to_typed_term().unwrap() {
+                TypedTerm::Atom(atom) if atom.name() == "error" => 1,
+                _ => 0,
             }
-            Status::Running => {
-                system::io::puts(&format!(
-                    "RUNNING Run queues len = {:?}",
-                    Scheduler::current().run_queues_len()
-                ));
+        }
+        Err((class, reason, _)) => {
+            let exit = Atom::try_from_str("exit").unwrap();
+            let normal = Atom::try_from_str("normal").unwrap();
+
+            match (
+                class.to_typed_term().unwrap(),
+                reason.to_typed_term().unwrap(),
+            ) {
+                (TypedTerm::Atom(class), TypedTerm::Atom(reason))
+                    if class == exit && reason == normal =>
+                {
+                    0
+                }
+                _ => 2,
             }
         }
     }
@@ -154,7 +195,9 @@ fn return_ok(arc_process: &Arc<Process>) -> code::Result {
     let sender_any: ResourceReference = closure.env_slice()[0].try_into().unwrap();
     let sender: &ProcessResultSender = sender_any.downcast_ref().unwrap();
 
-    let mut fragment = unsafe { HeapFragment::new_from_word_size(100) }.unwrap();
+    // Results, such as the values of expressions in the shell, can be larger than a fixed size
+    let need_in_words = argument_vec[0].size_in_words();
+    let mut fragment = unsafe { HeapFragment::new_from_word_size(need_in_words) }.unwrap();
     let frag_mut = unsafe { fragment.as_mut() };
     let ret = argument_vec[0].clone_to_heap(frag_mut).unwrap();
 
@@ -166,6 +209,13 @@ fn return_ok(arc_process: &Arc<Process>) -> code::Result {
         })
         .unwrap();
 
+    trace::emit(
+        arc_process,
+        Event::Exit {
+            reason: atom_unchecked("normal"),
+        },
+    );
+
     Ok(arc_process.return_from_call(argument_vec[0])?)
 }
 
@@ -190,7 +240,11 @@ fn return_throw(arc_process: &Arc<Process>) -> code::Result {
     let sender_any: ResourceReference = closure.env_slice()[0].try_into().unwrap();
     let sender: &ProcessResultSender = sender_any.downcast_ref().unwrap();
 
-    let mut fragment = unsafe { HeapFragment::new_from_word_size(100) }.unwrap();
+    let need_in_words = argument_vec[..3]
+        .iter()
+        .map(|term| term.size_in_words())
+        .sum();
+    let mut fragment = unsafe { HeapFragment::new_from_word_size(need_in_words) }.unwrap();
     let frag_mut = unsafe { fragment.as_mut() };
 
     let ret_type = argument_vec[0].clone_to_heap(frag_mut).unwrap();
@@ -205,21 +259,18 @@ fn return_throw(arc_process: &Arc<Process>) -> code::Result {
         })
         .unwrap();
 
-    let class: Atom = argument_vec[0].try_into().unwrap();
-    let class = match class.name() {
-        "EXIT" => runtime::Class::Exit,
-        "throw" => runtime::Class::Throw,
-        "error" => runtime::Class::Error { arguments: None },
-        k => unreachable!("{:?}", k),
-    };
-
-    let exc = runtime::Exception {
-        class,
-        reason: argument_vec[1],
-        stacktrace: Some(argument_vec[2]),
-        file: "",
-        line: 0,
-        column: 0,
-    };
-    code::result_from_exception(arc_process, exc.into())
+    let exception = crate::code::uncaught_exception(
+        arc_process,
+        argument_vec[0],
+        argument_vec[1],
+        argument_vec[2],
+    )?;
+    trace::emit(
+        arc_process,
+        Event::Exit {
+            reason: exception.reason,
+        },
+    );
+
+    code::result_from_exception(arc_process, exception.into())
 }
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::convert::TryInto;
 use std::sync::Arc;
 
@@ -5,17 +6,72 @@ use cranelift_entity::EntityRef;
 use libeir_ir::Block;
 
 use liblumen_alloc::erts::exception::runtime;
+use liblumen_alloc::erts::exception::system::Alloc;
 use liblumen_alloc::erts::process::code::result_from_exception;
 use liblumen_alloc::erts::process::code::stack::frame::Frame;
 use liblumen_alloc::erts::process::code::Result;
-use liblumen_alloc::erts::process::Process;
-use liblumen_alloc::erts::term::{Atom, Boxed, Closure, Term, TypedTerm};
+use liblumen_alloc::erts::process::{Process, Status};
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Boxed, Closure, Term, TypedTerm};
 use liblumen_alloc::erts::ModuleFunctionArity;
 
 use crate::exec::CallExecutor;
+use crate::trace::{self, Event};
+
+thread_local! {
+    /// Whether [`run_until_reduced`] is running the frames of a process on this thread
+    static RUNNING: Cell<bool> = Cell::new(false);
+}
+
+/// Runs the frames that interpreted code replaces its own frame with, until the process has spent
+/// its reductions, waits or exits, and only then returns to the scheduler.
+///
+/// Interpreted code calls into the next function or continuation by replacing its frame, so each
+/// frame returns before the next one is run, unlike native frames that call `Process::call_code`
+/// recursively.  Only the outermost call runs frames, so the native stack stays flat however many
+/// calls are made in a run.
+pub(crate) fn run_until_reduced(arc_process: &Arc<Process>) -> Result {
+    if RUNNING.with(|running| running.replace(true)) {
+        return Ok(());
+    }
+
+    let result = run_frames(arc_process);
+    RUNNING.with(|running| running.set(false));
+
+    result
+}
+
+fn run_frames(arc_process: &Arc<Process>) -> Result {
+    while arc_process.code_stack_len() > 0
+        && !arc_process.is_reduced()
+        && *arc_process.status.read() == Status::Running
+    {
+        Process::call_code(arc_process)?;
+    }
+
+    Ok(())
+}
 
 pub fn return_clean(arc_process: &Arc<Process>) -> Result {
     let argument_list = arc_process.stack_pop().unwrap();
+
+    if trace::enabled() {
+        // Spawned processes return cleanly with both the value of the ok continuation and the
+        // class, reason and stacktrace of the throw continuation
+        let arguments: Vec<Term> = match argument_list.to_typed_term().unwrap() {
+            TypedTerm::List(argument_cons) => argument_cons
+                .into_iter()
+                .map(|result| result.unwrap())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let reason = match arguments.len() {
+            3 => arguments[1],
+            _ => atom_unchecked("normal"),
+        };
+
+        trace::emit(arc_process, Event::Exit { reason });
+    }
+
     arc_process.return_from_call(argument_list)?;
     Process::call_code(arc_process)
 }
@@ -37,6 +93,13 @@ pub fn return_ok(arc_process: &Arc<Process>) -> Result {
     }
     assert!(argument_vec.len() == 1);
 
+    trace::emit(
+        arc_process,
+        Event::Exit {
+            reason: atom_unchecked("normal"),
+        },
+    );
+
     Ok(arc_process.return_from_call(argument_vec[0])?)
 }
 
@@ -56,23 +119,51 @@ pub fn return_throw(arc_process: &Arc<Process>) -> Result {
         _ => panic!(),
     }
 
-    let class: Atom = argument_vec[0].try_into().unwrap();
-    let class = match class.name() {
-        "EXIT" => runtime::Class::Exit,
-        "throw" => runtime::Class::Throw,
-        "error" => runtime::Class::Error { arguments: None },
+    let exception = uncaught_exception(
+        arc_process,
+        argument_vec[0],
+        argument_vec[1],
+        argument_vec[2],
+    )?;
+    trace::emit(
+        arc_process,
+        Event::Exit {
+            reason: exception.reason,
+        },
+    );
+
+    result_from_exception(arc_process, exception.into())
+}
+
+/// The exception that ends a process when the exception thrown to its throw continuation with
+/// `class`, `reason` and `stacktrace` is not caught.
+///
+/// Like BEAM, an uncaught `throw` is an `error` with `{nocatch, Reason}` as the reason.
+pub fn uncaught_exception(
+    arc_process: &Process,
+    class: Term,
+    reason: Term,
+    stacktrace: Term,
+) -> std::result::Result<runtime::Exception, Alloc> {
+    let class: Atom = class.try_into().unwrap();
+    let (class, reason) = match class.name() {
+        "exit" => (runtime::Class::Exit, reason),
+        "throw" => (
+            runtime::Class::Error { arguments: None },
+            arc_process.tuple_from_slice(&[atom_unchecked("nocatch"), reason])?,
+        ),
+        "error" => (runtime::Class::Error { arguments: None }, reason),
         k => unreachable!("{:?}", k),
     };
 
-    let exc = runtime::Exception {
+    Ok(runtime::Exception {
         class,
-        reason: argument_vec[1],
-        stacktrace: Some(argument_vec[2]),
+        reason,
+        stacktrace: Some(stacktrace),
         file: "",
         line: 0,
         column: 0,
-    };
-    result_from_exception(arc_process, exc.into())
+    })
 }
 
 /// Expects the following on stack:
@@ -105,16 +196,59 @@ pub fn interpreter_mfa_code(arc_process: &Arc<Process>) -> Result {
         mfa.function,
         argument_vec.len() - 2,
         &mut argument_vec,
+        None,
     );
 
-    Ok(())
+    run_until_reduced(arc_process)
+}
+
+/// Expects the following on stack:
+/// * argument list
+/// * closure with the module version integer as its environment
+pub fn interpreter_local_code(arc_process: &Arc<Process>) -> Result {
+    let argument_list = arc_process.stack_pop().unwrap();
+    let closure_term = arc_process.stack_pop().unwrap();
+
+    let closure: Boxed<Closure> = closure_term.try_into().unwrap();
+
+    let mfa = arc_process.current_module_function_arity().unwrap();
+
+    let version: usize = closure.env_slice()[0].try_into().unwrap();
+
+    let mut argument_vec: Vec<Term> = Vec::new();
+    match argument_list.to_typed_term().unwrap() {
+        TypedTerm::Nil => (),
+        TypedTerm::List(argument_cons) => {
+            for result in argument_cons.into_iter() {
+                let element = result.unwrap();
+
+                argument_vec.push(element);
+            }
+        }
+        _ => panic!(),
+    }
+    assert!(mfa.arity as usize == argument_vec.len() - 2);
+
+    let mut exec = CallExecutor::new();
+    exec.call(
+        &crate::VM,
+        arc_process,
+        mfa.module,
+        mfa.function,
+        argument_vec.len() - 2,
+        &mut argument_vec,
+        Some(version),
+    );
+
+    run_until_reduced(arc_process)
 }
 
 /// Expects the following on stack:
-/// * arity integer
 /// * argument list
-/// * block id integer
-/// * environment list
+/// * closure with the following environment:
+///   * module version integer
+///   * block id integer
+///   * live values
 pub fn interpreter_closure_code(arc_process: &Arc<Process>) -> Result {
     let argument_list = arc_process.stack_pop().unwrap();
     let closure_term = arc_process.stack_pop().unwrap();
@@ -124,7 +258,8 @@ pub fn interpreter_closure_code(arc_process: &Arc<Process>) -> Result {
     let mfa = arc_process.current_module_function_arity().unwrap();
     let arity = mfa.arity;
 
-    let block_id: usize = closure.env_slice()[0].try_into().unwrap();
+    let version: usize = closure.env_slice()[0].try_into().unwrap();
+    let block_id: usize = closure.env_slice()[1].try_into().unwrap();
     let block = Block::new(block_id);
 
     let mut argument_vec: Vec<Term> = Vec::new();
@@ -140,7 +275,7 @@ pub fn interpreter_closure_code(arc_process: &Arc<Process>) -> Result {
         _ => panic!(),
     }
 
-    let mut environment_vec: Vec<Term> = closure.env_slice()[1..].to_owned();
+    let mut environment_vec: Vec<Term> = closure.env_slice()[2..].to_owned();
 
     let mut exec = CallExecutor::new();
     exec.call_block(
@@ -150,11 +285,12 @@ pub fn interpreter_closure_code(arc_process: &Arc<Process>) -> Result {
         mfa.function,
         arity as usize,
         &mut argument_vec,
+        version,
         block,
         &mut environment_vec,
     );
 
-    Ok(())
+    run_until_reduced(arc_process)
 }
 
 pub fn apply(arc_process: &Arc<Process>) -> Result {
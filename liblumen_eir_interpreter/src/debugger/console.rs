@@ -0,0 +1,123 @@
+use std::io::{self, BufRead, Write};
+
+use crate::print;
+
+use super::{add_breakpoint, breakpoints, remove_breakpoint, Breakpoint, Frontend, Paused, Resume};
+
+const HELP: &str = "\
+s, step              step into the next block, including the first block of a call
+n, next              step over calls to the next block of this function
+c, continue          run until the next breakpoint
+v, bindings          print the values bound in the function
+m, mailbox           print the messages in the mailbox
+heap                 dump the heap of the process
+w, where             print where the process is paused
+break SPEC           pause at module:function, module:function/arity or module:line
+delete SPEC          remove a breakpoint
+breakpoints          list the breakpoints
+h, help              print this help";
+
+/// A line-oriented frontend that reads commands from stdin while a process is paused.  The end of
+/// input continues the process.
+pub struct Console;
+
+impl Frontend for Console {
+    fn paused(&self, paused: &Paused) -> Resume {
+        println!("{}", location(paused));
+
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+
+        loop {
+            print!("(debug) ");
+            io::stdout().flush().unwrap();
+
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                _ => return Resume::Continue,
+            };
+
+            match command(paused, line.trim()) {
+                Reply::Resume(resume) => return resume,
+                Reply::Output(output) => {
+                    if !output.is_empty() {
+                        println!("{}", output)
+                    }
+                }
+            }
+        }
+    }
+}
+
+enum Reply {
+    Resume(Resume),
+    /// The output of a command that inspects the process or changes breakpoints
+    Output(String),
+}
+
+fn command(paused: &Paused, line: &str) -> Reply {
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let argument = words.next();
+
+    let output = match (name, argument) {
+        ("s", None) | ("step", None) => return Reply::Resume(Resume::StepInto),
+        ("n", None) | ("next", None) => return Reply::Resume(Resume::StepOver),
+        ("c", None) | ("continue", None) => return Reply::Resume(Resume::Continue),
+        ("v", None) | ("bindings", None) => paused
+            .bindings()
+            .into_iter()
+            .map(|(value, term)| format!("{} = {}", value, print::print(term)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ("m", None) | ("mailbox", None) => paused
+            .mailbox()
+            .into_iter()
+            .map(print::print)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ("heap", None) => paused.heap(),
+        ("w", None) | ("where", None) => location(paused),
+        ("break", Some(spec)) => match Breakpoint::parse(spec) {
+            Some(breakpoint) => {
+                add_breakpoint(breakpoint);
+
+                String::new()
+            }
+            None => format!("invalid breakpoint {}", spec),
+        },
+        ("delete", Some(spec)) => match Breakpoint::parse(spec) {
+            Some(ref breakpoint) if remove_breakpoint(breakpoint) => String::new(),
+            _ => format!("no breakpoint {}", spec),
+        },
+        ("breakpoints", None) => breakpoints()
+            .iter()
+            .map(|breakpoint| breakpoint.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ("", None) => String::new(),
+        ("h", None) | ("help", None) => HELP.to_string(),
+        _ => format!("unknown command {}, try help", line),
+    };
+
+    Reply::Output(output)
+}
+
+/// Such as `<0.42.0> paused at fib:fib/1 block3 (fib.erl:5) on breakpoint fib:fib/1`
+fn location(paused: &Paused) -> String {
+    let mut output = format!(
+        "{} paused at {}:{}/{} {}",
+        print::print(paused.process.pid_term()),
+        print::atom(paused.module),
+        print::atom(paused.function),
+        paused.arity,
+        paused.block
+    );
+
+    if let Some((file, line)) = &paused.location {
+        output.push_str(&format!(" ({}:{})", file, line));
+    }
+    output.push_str(&format!(" on {}", paused.reason));
+
+    output
+}
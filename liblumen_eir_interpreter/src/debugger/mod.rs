@@ -0,0 +1,344 @@
+//! A debugger for interpreted processes.
+//!
+//! A process pauses at the boundary of an EIR block when it reaches a [`Breakpoint`], when it
+//! was asked to [`pause`], or when it is stepping.  While it is paused, the [`Frontend`] that was
+//! [`attach`]ed is given the [`Paused`] state of the process to inspect, and decides how the
+//! process [`Resume`]s.  The frontend is called on the scheduler thread of the process, so the
+//! other processes of the scheduler are paused too.
+//!
+//! Interpreted code is in continuation passing style, so there are no stack frames to step over.
+//! Instead, stepping over a call wraps its continuations, so that the process pauses again when
+//! the call returns or throws, wherever that is.  Funs are entered without a call, so they are
+//! always stepped into.
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use cranelift_entity::EntityRef;
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+
+use libeir_ir::{Block, Value};
+
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{Atom, Pid, Term};
+
+use crate::module::ErlangFunction;
+
+mod console;
+
+pub use self::console::Console;
+
+static ATTACHED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref STATE: Mutex<State> = Mutex::new(State::default());
+}
+
+#[derive(Default)]
+struct State {
+    frontend: Option<Arc<dyn Frontend>>,
+    breakpoints: Vec<Breakpoint>,
+    steps: HashMap<Pid, Step>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+    /// Pause at the next block
+    Into,
+    /// Pause at the next block, unless a call is made first
+    Over,
+    /// A call is being stepped over, so don't pause until it returns
+    Returning,
+}
+
+/// Decides how a paused process resumes
+pub trait Frontend: Send + Sync {
+    fn paused(&self, paused: &Paused) -> Resume;
+}
+
+impl<F> Frontend for F
+where
+    F: Fn(&Paused) -> Resume + Send + Sync,
+{
+    fn paused(&self, paused: &Paused) -> Resume {
+        self(paused)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resume {
+    /// Run until the next breakpoint
+    Continue,
+    /// Pause at the next block, including the first block of a call
+    StepInto,
+    /// Pause at the next block, but run any call that is made first until it returns
+    StepOver,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Breakpoint {
+    /// Pauses when `module:function` is called with any arity, or only with `arity`
+    Function {
+        module: Atom,
+        function: Atom,
+        arity: Option<usize>,
+    },
+    /// Pauses when execution enters `line` of `module`, including when a call on the line
+    /// returns to it
+    Line { module: Atom, line: u32 },
+}
+
+impl Breakpoint {
+    /// `module:function`, `module:function/arity` or `module:line`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let colon = spec.find(':')?;
+        let module = Atom::try_from_str(&spec[..colon]).ok()?;
+        let rest = &spec[colon + 1..];
+
+        if let Ok(line) = rest.parse() {
+            return Some(Breakpoint::Line { module, line });
+        }
+
+        let (function, arity) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], Some(rest[slash + 1..].parse().ok()?)),
+            None => (rest, None),
+        };
+        if function.is_empty() {
+            return None;
+        }
+
+        Some(Breakpoint::Function {
+            module,
+            function: Atom::try_from_str(function).ok()?,
+            arity,
+        })
+    }
+
+    fn is_at(&self, at: &Paused, entry: bool, entered_line: bool) -> bool {
+        match self {
+            Breakpoint::Function {
+                module,
+                function,
+                arity,
+            } => {
+                entry
+                    && *module == at.module
+                    && *function == at.function
+                    && arity.map_or(true, |arity| arity == at.arity)
+            }
+            Breakpoint::Line { module, line } => {
+                entered_line
+                    && *module == at.module
+                    && at.location.as_ref().map(|(_, l)| l) == Some(line)
+            }
+        }
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::Function {
+                module,
+                function,
+                arity: Some(arity),
+            } => write!(f, "{}:{}/{}", module.name(), function.name(), arity),
+            Breakpoint::Function {
+                module,
+                function,
+                arity: None,
+            } => write!(f, "{}:{}", module.name(), function.name()),
+            Breakpoint::Line { module, line } => write!(f, "{}:{}", module.name(), line),
+        }
+    }
+}
+
+/// Why a process paused
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reason {
+    Breakpoint(Breakpoint),
+    /// The process was stepping or asked to pause
+    Step,
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Breakpoint(breakpoint) => write!(f, "breakpoint {}", breakpoint),
+            Reason::Step => write!(f, "step"),
+        }
+    }
+}
+
+/// A process paused before executing `block` of `module:function/arity`
+pub struct Paused<'a> {
+    pub process: &'a Process,
+    pub module: Atom,
+    pub function: Atom,
+    pub arity: usize,
+    pub block: Block,
+    /// The file and line of `block`, if known
+    pub location: Option<(String, u32)>,
+    pub reason: Reason,
+    bindings: &'a HashMap<Value, Term>,
+}
+
+impl<'a> Paused<'a> {
+    /// The values bound in the function so far, including the arguments of `block`, in the
+    /// order they were defined
+    pub fn bindings(&self) -> Vec<(String, Term)> {
+        let mut bindings: Vec<(Value, Term)> = self
+            .bindings
+            .iter()
+            .map(|(value, term)| (*value, *term))
+            .collect();
+        bindings.sort_by_key(|(value, _)| value.index());
+
+        bindings
+            .into_iter()
+            .map(|(value, term)| (format!("%{}", value.index()), term))
+            .collect()
+    }
+
+    /// The messages in the mailbox, oldest first
+    pub fn mailbox(&self) -> Vec<Term> {
+        let mailbox_lock = self.process.mailbox.lock();
+        let mailbox = mailbox_lock.borrow();
+
+        mailbox.iter().map(|message| *message.data()).collect()
+    }
+
+    /// A dump of the heap of the process
+    pub fn heap(&self) -> String {
+        format!("{:?}", self.process.acquire_heap())
+    }
+}
+
+/// Pauses processes with `frontend` from now on
+pub fn attach(frontend: Box<dyn Frontend>) {
+    STATE.lock().unwrap().frontend = Some(frontend.into());
+    ATTACHED.store(true, Ordering::SeqCst);
+}
+
+/// Stops pausing processes, and removes all breakpoints
+pub fn detach() {
+    ATTACHED.store(false, Ordering::SeqCst);
+    *STATE.lock().unwrap() = State::default();
+}
+
+pub fn add_breakpoint(breakpoint: Breakpoint) {
+    let mut state = STATE.lock().unwrap();
+
+    if !state.breakpoints.contains(&breakpoint) {
+        state.breakpoints.push(breakpoint);
+    }
+}
+
+/// Returns whether `breakpoint` was set
+pub fn remove_breakpoint(breakpoint: &Breakpoint) -> bool {
+    let mut state = STATE.lock().unwrap();
+    let len = state.breakpoints.len();
+    state.breakpoints.retain(|b| b != breakpoint);
+
+    state.breakpoints.len() < len
+}
+
+pub fn breakpoints() -> Vec<Breakpoint> {
+    STATE.lock().unwrap().breakpoints.clone()
+}
+
+/// Pauses the process with `pid` at the next block it executes
+pub fn pause(pid: Pid) {
+    STATE.lock().unwrap().steps.insert(pid, Step::Into);
+}
+
+pub(crate) fn attached() -> bool {
+    ATTACHED.load(Ordering::SeqCst)
+}
+
+/// Called before `block` of `fun` is executed, with the values bound so far.  `entered_line` is
+/// whether the location of `block` is on a different line than the block before it.
+pub(crate) fn block_boundary(
+    process: &Process,
+    fun: &ErlangFunction,
+    block: Block,
+    bindings: &HashMap<Value, Term>,
+    location: Option<(String, u32)>,
+    entered_line: bool,
+) {
+    let ident = fun.fun.ident();
+    let mut paused = Paused {
+        process,
+        module: Atom::try_from_str(&ident.module.as_str()).unwrap(),
+        function: Atom::try_from_str(&ident.name.as_str()).unwrap(),
+        arity: ident.arity,
+        block,
+        location,
+        reason: Reason::Step,
+        bindings,
+    };
+    let pid = process.pid();
+
+    let frontend = {
+        let state = STATE.lock().unwrap();
+        let frontend = match &state.frontend {
+            Some(frontend) => frontend.clone(),
+            None => return,
+        };
+
+        match state.steps.get(&pid) {
+            Some(Step::Into) | Some(Step::Over) => (),
+            _ => {
+                let entry = block == fun.fun.block_entry();
+                match state
+                    .breakpoints
+                    .iter()
+                    .find(|breakpoint| breakpoint.is_at(&paused, entry, entered_line))
+                {
+                    Some(breakpoint) => paused.reason = Reason::Breakpoint(breakpoint.clone()),
+                    None => return,
+                }
+            }
+        }
+
+        frontend
+    };
+
+    // The state is not locked while paused, so that the frontend can change breakpoints
+    let resume = frontend.paused(&paused);
+
+    let mut state = STATE.lock().unwrap();
+    match resume {
+        Resume::Continue => state.steps.remove(&pid),
+        Resume::StepInto => state.steps.insert(pid, Step::Into),
+        Resume::StepOver => state.steps.insert(pid, Step::Over),
+    };
+}
+
+/// Whether `process` is stepping over the call it is making, in which case the continuations of
+/// the call must be wrapped to call [`stepped_over`] when it returns
+pub(crate) fn steps_over_call(process: &Process) -> bool {
+    let mut state = STATE.lock().unwrap();
+
+    match state.steps.get_mut(&process.pid()) {
+        Some(step) if *step == Step::Over => {
+            *step = Step::Returning;
+
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The call that `process` stepped over returned, so it pauses at the next block, unless it was
+/// continued in the meantime
+pub(crate) fn stepped_over(process: &Process) {
+    let mut state = STATE.lock().unwrap();
+
+    if let Some(step) = state.steps.get_mut(&process.pid()) {
+        if *step == Step::Returning {
+            *step = Step::Into;
+        }
+    }
+}
@@ -0,0 +1,530 @@
+//! Binary matching and construction, one segment at a time.
+//!
+//! Each `MatchKind::Binary` branch matches a single segment from the start of a binary and
+//! continues with the value of the segment and the rest of the binary.  Binary and bitstring
+//! segments and the rest are sub binaries of the matched binary, so the matched binary is never
+//! copied.
+//!
+//! Each `OpKind::BinaryPush` appends a single segment to the end of the binary under
+//! construction.  Like BEAM's `bs_append`, appending to a non-empty binary makes a writable binary
+//! with room to grow, which the next segment is written into in place, so building a binary in a
+//! loop only copies it when it runs out of room.
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use num_bigint::{BigInt, Sign};
+
+use libeir_ir::{BinaryEntrySpecifier, Endianness};
+
+use liblumen_alloc::borrow::clone_to_process::CloneToProcess;
+use liblumen_alloc::erts::exception::system;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{append_bits, MatchContext, SubBinary, Term, TypedTerm};
+
+/// Integer segments are limited to the size of BEAM's largest integers, so that a size read from a
+/// variable cannot make a segment that takes all of memory.
+const MAX_INTEGER_BIT_LEN: usize = 1 << 25;
+
+/// Matches the segment described by `specifier` and `size` at the start of `binary`, returning
+/// the value of the segment and the rest of `binary`, or `None` if the segment does not match.
+///
+/// `size` is in units of the segment's unit.  Without a size, integers are 8 bits, floats are 64
+/// bits, and binaries and bitstrings are the rest of `binary`.
+pub fn match_segment(
+    proc: &Arc<Process>,
+    binary: Term,
+    specifier: &BinaryEntrySpecifier,
+    size: Option<Term>,
+) -> Result<Option<(Term, Term)>, system::Exception> {
+    let mut ctx = match start_match(binary) {
+        Some(ctx) => ctx,
+        None => return Ok(None),
+    };
+
+    let size: Option<usize> = match size {
+        Some(term) => match term.try_into() {
+            Ok(size) => Some(size),
+            Err(_) => return Ok(None),
+        },
+        None => None,
+    };
+
+    let value = match specifier {
+        BinaryEntrySpecifier::Integer {
+            signed,
+            endianness,
+            unit,
+        } => {
+            let bit_len = match segment_bit_len(size, 8, *unit) {
+                Some(bit_len) => bit_len,
+                None => return Ok(None),
+            };
+
+            match ctx.read_bits(bit_len) {
+                Some(bytes) => {
+                    let integer = integer_from_bits(&bytes, bit_len, *signed, endianness);
+
+                    proc.integer(integer)?
+                }
+                None => return Ok(None),
+            }
+        }
+        BinaryEntrySpecifier::Float { endianness, unit } => {
+            let bit_len = match segment_bit_len(size, 64, *unit) {
+                Some(bit_len @ 32) | Some(bit_len @ 64) => bit_len,
+                _ => return Ok(None),
+            };
+
+            match ctx
+                .read_bits(bit_len)
+                .and_then(|bytes| float_from_bytes(&bytes, endianness))
+            {
+                Some(float) => proc.float(float)?,
+                None => return Ok(None),
+            }
+        }
+        BinaryEntrySpecifier::Bytes { unit } | BinaryEntrySpecifier::Bits { unit } => {
+            let unit = *unit as usize;
+            let bit_len = match size {
+                Some(size) => match size.checked_mul(unit) {
+                    Some(bit_len) => bit_len,
+                    None => return Ok(None),
+                },
+                None => ctx.remaining_bit_len(),
+            };
+
+            if ctx.remaining_bit_len() < bit_len || (unit != 0 && bit_len % unit != 0) {
+                return Ok(None);
+            }
+
+            sub_binary(proc, &mut ctx, bit_len)?
+        }
+        BinaryEntrySpecifier::Utf8 => match read_utf8(&mut ctx) {
+            Some(c) => proc.integer(c)?,
+            None => return Ok(None),
+        },
+        BinaryEntrySpecifier::Utf16 { endianness } => match read_utf16(&mut ctx, endianness) {
+            Some(c) => proc.integer(c)?,
+            None => return Ok(None),
+        },
+        BinaryEntrySpecifier::Utf32 { endianness } => {
+            match ctx
+                .read_bits(32)
+                .and_then(|bytes| std::char::from_u32(u32_from_bytes(&bytes, endianness)))
+            {
+                Some(c) => proc.integer(c)?,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let rest_bit_len = ctx.remaining_bit_len();
+    let rest = sub_binary(proc, &mut ctx, rest_bit_len)?;
+
+    Ok(Some((value, rest)))
+}
+
+/// Appends the segment described by `specifier` and `size` holding `value` to the end of
+/// `binary`, returning the new binary, or `None` if `binary`, `value` or `size` are not valid for
+/// the segment, which is a `badarg`.
+///
+/// `size` is in units of the segment's unit.  Without a size, integers are 8 bits, floats are 64
+/// bits, and binaries and bitstrings are all of `value`.
+pub fn push_segment(
+    proc: &Arc<Process>,
+    binary: Term,
+    specifier: &BinaryEntrySpecifier,
+    value: Term,
+    size: Option<Term>,
+) -> Result<Option<Term>, system::Exception> {
+    let binary_bit_len = match start_match(binary) {
+        Some(ctx) => ctx.remaining_bit_len(),
+        None => return Ok(None),
+    };
+
+    let size: Option<usize> = match size {
+        Some(term) => match term.try_into() {
+            Ok(size) => Some(size),
+            Err(_) => return Ok(None),
+        },
+        None => None,
+    };
+
+    let (bytes, bit_len) = match encode_segment(specifier, value, size) {
+        Some(segment) => segment,
+        None => return Ok(None),
+    };
+
+    if let Some(appended) = proc.append_to_writable_binary(binary, &bytes, bit_len)? {
+        return Ok(Some(appended));
+    }
+
+    let total_bit_len = binary_bit_len + bit_len;
+    let mut buffer = if binary_bit_len == 0 {
+        Vec::new()
+    } else {
+        start_match(binary)
+            .unwrap()
+            .read_bits(binary_bit_len)
+            .unwrap()
+    };
+    append_bits(&mut buffer, binary_bit_len, &bytes, bit_len);
+
+    let appended = if binary_bit_len == 0 {
+        // Most binaries are never appended to after they are constructed, so the first segment
+        // only gets the room it needs
+        let original = proc.binary_from_bytes(&buffer)?;
+
+        if total_bit_len % 8 == 0 {
+            original
+        } else {
+            proc.subbinary_from_original(
+                original,
+                0,
+                0,
+                total_bit_len / 8,
+                (total_bit_len % 8) as u8,
+            )?
+        }
+    } else {
+        // The same growth as `erts_bs_append`
+        let capacity = std::cmp::max(2 * buffer.len(), 256);
+
+        proc.writable_binary_from_bytes(&buffer, total_bit_len, capacity)?
+    };
+
+    Ok(Some(appended))
+}
+
+/// The bits of a segment, most significant bit first, and the number of bits
+fn encode_segment(
+    specifier: &BinaryEntrySpecifier,
+    value: Term,
+    size: Option<usize>,
+) -> Option<(Vec<u8>, usize)> {
+    match specifier {
+        BinaryEntrySpecifier::Integer {
+            endianness, unit, ..
+        } => {
+            let integer: BigInt = value.try_into().ok()?;
+            let bit_len = segment_bit_len(size, 8, *unit)?;
+            if MAX_INTEGER_BIT_LEN < bit_len {
+                return None;
+            }
+
+            Some((integer_to_bits(&integer, bit_len, endianness), bit_len))
+        }
+        BinaryEntrySpecifier::Float { endianness, unit } => {
+            let float: f64 = match value.try_into() {
+                Ok(float) => float,
+                Err(_) => {
+                    let integer: isize = value.try_into().ok()?;
+
+                    integer as f64
+                }
+            };
+            let bit_len = segment_bit_len(size, 64, *unit)?;
+
+            let bytes = match bit_len {
+                32 => {
+                    let float = float as f32;
+                    if !float.is_finite() {
+                        return None;
+                    }
+
+                    u32_to_bytes(float.to_bits(), endianness).to_vec()
+                }
+                64 => {
+                    let bits = float.to_bits();
+
+                    if is_little(endianness) {
+                        bits.to_le_bytes().to_vec()
+                    } else {
+                        bits.to_be_bytes().to_vec()
+                    }
+                }
+                _ => return None,
+            };
+
+            Some((bytes, bit_len))
+        }
+        BinaryEntrySpecifier::Bytes { unit } | BinaryEntrySpecifier::Bits { unit } => {
+            let mut ctx = start_match(value)?;
+            let unit = *unit as usize;
+            let bit_len = match size {
+                Some(size) => size.checked_mul(unit)?,
+                None => ctx.remaining_bit_len(),
+            };
+
+            if ctx.remaining_bit_len() < bit_len || (unit != 0 && bit_len % unit != 0) {
+                return None;
+            }
+
+            Some((ctx.read_bits(bit_len)?, bit_len))
+        }
+        BinaryEntrySpecifier::Utf8 => {
+            let c: char = value.try_into().ok()?;
+            let mut bytes = vec![0; c.len_utf8()];
+            c.encode_utf8(&mut bytes);
+            let bit_len = 8 * bytes.len();
+
+            Some((bytes, bit_len))
+        }
+        BinaryEntrySpecifier::Utf16 { endianness } => {
+            let c: char = value.try_into().ok()?;
+            let mut units = [0; 2];
+            let mut bytes = Vec::new();
+
+            for unit in c.encode_utf16(&mut units) {
+                if is_little(endianness) {
+                    bytes.extend_from_slice(&unit.to_le_bytes());
+                } else {
+                    bytes.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            let bit_len = 8 * bytes.len();
+
+            Some((bytes, bit_len))
+        }
+        BinaryEntrySpecifier::Utf32 { endianness } => {
+            let c: char = value.try_into().ok()?;
+
+            Some((u32_to_bytes(c as u32, endianness).to_vec(), 32))
+        }
+    }
+}
+
+/// `size` units of `unit` bits, or `default_bit_len` without a size, or `None` if that many bits
+/// does not fit in a `usize`
+fn segment_bit_len(size: Option<usize>, default_bit_len: usize, unit: u8) -> Option<usize> {
+    match size {
+        Some(size) => size.checked_mul(unit as usize),
+        None => Some(default_bit_len * unit as usize),
+    }
+}
+
+fn start_match(binary: Term) -> Option<MatchContext> {
+    match binary.to_typed_term().unwrap() {
+        TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
+            TypedTerm::ProcBin(_) | TypedTerm::HeapBinary(_) | TypedTerm::SubBinary(_) => {
+                Some(MatchContext::new(binary))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The next `bit_len` bits as a sub binary on the heap of `proc`
+fn sub_binary(
+    proc: &Arc<Process>,
+    ctx: &mut MatchContext,
+    bit_len: usize,
+) -> Result<Term, system::Exception> {
+    let sub_binary = SubBinary::from_match(ctx, bit_len);
+    let term = sub_binary.clone_to_heap(&mut proc.acquire_heap())?;
+
+    Ok(term)
+}
+
+/// `bytes` holds `bit_len` bits, most significant bit first.  For little endian, the full bytes
+/// are least significant first and any remaining bits are the most significant, the same as
+/// `erts_bs_get_integer_2`.
+fn integer_from_bits(
+    bytes: &[u8],
+    bit_len: usize,
+    signed: bool,
+    endianness: &Endianness,
+) -> BigInt {
+    let partial_byte_bit_len = bit_len % 8;
+
+    // Big endian bytes of the unsigned value, right-aligned
+    let mut magnitude: Vec<u8> = if is_little(endianness) {
+        let full_byte_len = bit_len / 8;
+        let mut magnitude = Vec::with_capacity(bytes.len());
+
+        if partial_byte_bit_len > 0 {
+            magnitude.push(bytes[full_byte_len] >> (8 - partial_byte_bit_len));
+        }
+        magnitude.extend(bytes[..full_byte_len].iter().rev());
+
+        magnitude
+    } else if partial_byte_bit_len > 0 {
+        // Shift the whole bit string right so the last bit is the least significant
+        let shift = 8 - partial_byte_bit_len;
+        let mut magnitude = Vec::with_capacity(bytes.len());
+        let mut carry = 0;
+
+        for byte in bytes {
+            magnitude.push(carry | (byte >> shift));
+            carry = byte << partial_byte_bit_len;
+        }
+
+        magnitude
+    } else {
+        bytes.to_vec()
+    };
+
+    if magnitude.is_empty() {
+        magnitude.push(0);
+    }
+
+    if signed && 0 < bit_len {
+        // Extend the sign bit over the unused high bits, so the bytes are the two's complement
+        let unused_bit_len = magnitude.len() * 8 - bit_len;
+
+        if magnitude[0] & (0x80 >> unused_bit_len) != 0 {
+            magnitude[0] |= !(0xFF >> unused_bit_len);
+        }
+
+        BigInt::from_signed_bytes_be(&magnitude)
+    } else {
+        BigInt::from_bytes_be(Sign::Plus, &magnitude)
+    }
+}
+
+/// The inverse of `integer_from_bits`: the lowest `bit_len` bits of the two's complement of
+/// `integer`, most significant bit first.
+fn integer_to_bits(integer: &BigInt, bit_len: usize, endianness: &Endianness) -> Vec<u8> {
+    let byte_len = (bit_len + 7) / 8;
+    let partial_byte_bit_len = bit_len % 8;
+
+    // Little endian bytes of the two's complement, sign extended or truncated to `byte_len`.  Any
+    // bits above `bit_len` in the last byte are shifted out below.
+    let mut little = integer.to_signed_bytes_le();
+    let sign_extension = if integer.sign() == Sign::Minus {
+        0xFF
+    } else {
+        0
+    };
+    little.resize(byte_len, sign_extension);
+
+    if is_little(endianness) {
+        // The full bytes are least significant first, then the remaining most significant bits
+        if partial_byte_bit_len > 0 {
+            little[byte_len - 1] <<= 8 - partial_byte_bit_len;
+        }
+
+        little
+    } else {
+        let big: Vec<u8> = little.into_iter().rev().collect();
+
+        if partial_byte_bit_len > 0 {
+            // Shift the whole bit string left so the first bit is the most significant
+            let shift = 8 - partial_byte_bit_len;
+
+            (0..byte_len)
+                .map(|index| {
+                    let next = big
+                        .get(index + 1)
+                        .map_or(0, |next| next >> partial_byte_bit_len);
+
+                    (big[index] << shift) | next
+                })
+                .collect()
+        } else {
+            big
+        }
+    }
+}
+
+fn float_from_bytes(bytes: &[u8], endianness: &Endianness) -> Option<f64> {
+    let float = match bytes.len() {
+        4 => f32::from_bits(u32_from_bytes(bytes, endianness)) as f64,
+        8 => {
+            let mut array = [0; 8];
+            array.copy_from_slice(bytes);
+
+            f64::from_bits(if is_little(endianness) {
+                u64::from_le_bytes(array)
+            } else {
+                u64::from_be_bytes(array)
+            })
+        }
+        _ => return None,
+    };
+
+    // Erlang has no infinities or NaNs, so they do not match
+    if float.is_finite() {
+        Some(float)
+    } else {
+        None
+    }
+}
+
+fn u32_from_bytes(bytes: &[u8], endianness: &Endianness) -> u32 {
+    let mut array = [0; 4];
+    array.copy_from_slice(bytes);
+
+    if is_little(endianness) {
+        u32::from_le_bytes(array)
+    } else {
+        u32::from_be_bytes(array)
+    }
+}
+
+fn u32_to_bytes(u: u32, endianness: &Endianness) -> [u8; 4] {
+    if is_little(endianness) {
+        u.to_le_bytes()
+    } else {
+        u.to_be_bytes()
+    }
+}
+
+fn u16_from_bytes(bytes: &[u8], endianness: &Endianness) -> u16 {
+    let mut array = [0; 2];
+    array.copy_from_slice(bytes);
+
+    if is_little(endianness) {
+        u16::from_le_bytes(array)
+    } else {
+        u16::from_be_bytes(array)
+    }
+}
+
+fn read_utf8(ctx: &mut MatchContext) -> Option<char> {
+    let first = ctx.read_bits(8)?[0];
+    let len = match first {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return None,
+    };
+
+    let mut bytes = vec![first];
+    if len > 1 {
+        bytes.extend(ctx.read_bits(8 * (len - 1))?);
+    }
+
+    std::str::from_utf8(&bytes).ok()?.chars().next()
+}
+
+fn read_utf16(ctx: &mut MatchContext, endianness: &Endianness) -> Option<char> {
+    let first = u16_from_bytes(&ctx.read_bits(16)?, endianness);
+
+    let code_point = match first {
+        0xD800..=0xDBFF => {
+            let second = u16_from_bytes(&ctx.read_bits(16)?, endianness);
+
+            match second {
+                0xDC00..=0xDFFF => {
+                    0x10000 + (((first as u32) - 0xD800) << 10) + ((second as u32) - 0xDC00)
+                }
+                _ => return None,
+            }
+        }
+        0xDC00..=0xDFFF => return None,
+        _ => first as u32,
+    };
+
+    std::char::from_u32(code_point)
+}
+
+fn is_little(endianness: &Endianness) -> bool {
+    match *endianness {
+        Endianness::Big => false,
+        Endianness::Little => true,
+        Endianness::Native => cfg!(target_endian = "little"),
+    }
+}
@@ -6,7 +6,7 @@ use liblumen_alloc::erts::exception::system;
 use liblumen_alloc::erts::process::Process;
 use liblumen_alloc::erts::term::TypedTerm;
 
-use super::{CallExecutor, OpResult};
+use super::{binary, CallExecutor, OpResult};
 use crate::module::ErlangFunction;
 
 pub fn match_op(
@@ -89,6 +89,21 @@ pub fn match_op(
                     _ => (),
                 }
             }
+            MatchKind::Binary(specifier) => {
+                let size = match branch_args.len() {
+                    0 => None,
+                    1 => Some(exec.make_term(proc, fun, branch_args[0]).unwrap()),
+                    _ => unreachable!(),
+                };
+
+                if let Some((value, rest)) =
+                    binary::match_segment(proc, unpack_term, specifier, size)?
+                {
+                    exec.next_args.push(value);
+                    exec.next_args.push(rest);
+                    return exec.val_call(proc, fun, *branch);
+                }
+            }
             MatchKind::Wildcard => {
                 assert!(branch_args.len() == 0);
                 return exec.val_call(proc, fun, *branch);
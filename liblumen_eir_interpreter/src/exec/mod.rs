@@ -6,9 +6,7 @@ use hashbrown::HashMap;
 use cranelift_entity::EntityRef;
 use libeir_intern::Symbol;
 use libeir_ir::constant::{AtomicTerm, Const, ConstKind};
-use libeir_ir::{
-    BinOp, BinaryEntrySpecifier, Block, LogicOp, MapPutUpdate, OpKind, PrimOpKind, Value, ValueKind,
-};
+use libeir_ir::{BinOp, Block, LogicOp, MapPutUpdate, OpKind, PrimOpKind, Value, ValueKind};
 
 use liblumen_alloc::erts::exception::runtime;
 use liblumen_alloc::erts::exception::system;
@@ -16,17 +14,25 @@ use liblumen_alloc::erts::exception::Exception;
 use liblumen_alloc::erts::process::code::Result;
 use liblumen_alloc::erts::process::RootSet;
 use liblumen_alloc::erts::process::{Process, ProcessFlags};
-use liblumen_alloc::erts::term::{atom_unchecked, Atom, Boxed, Map, Term, TypedTerm};
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Boxed, Closure, Map, Term, TypedTerm};
 use liblumen_alloc::erts::ModuleFunctionArity;
 
-use crate::module::{ErlangFunction, NativeFunctionKind, ResolvedFunction};
+use lumen_runtime::time::monotonic::{self, Milliseconds};
+use lumen_runtime::timer;
+
+use crate::debugger;
+use crate::load;
+use crate::module::{ErlangFunction, ModuleRegistry, NativeFunctionKind, ResolvedFunction};
+use crate::trace::{self, Event};
 use crate::vm::VMState;
 
-mod r#match;
+use self::stacktrace::Caller;
 
-macro_rules! trace {
-    ($($t:tt)*) => (lumen_runtime::system::io::puts(&format_args!($($t)*).to_string()))
-}
+mod binary;
+mod r#match;
+mod return_trace;
+mod stacktrace;
+mod step_over;
 
 const VALUE_LIST_MARKER: &str = "eir_value_list_marker_df8gy43h";
 
@@ -105,9 +111,7 @@ where
                 // Terms are in root set
                 unsafe { terms.add(&mut rootset) };
 
-                lumen_runtime::system::io::puts(
-                    "=================================================== GC",
-                );
+                trace::emit(proc, Event::GarbageCollect { full: false });
                 match heap.garbage_collect(proc, 0, rootset) {
                     Ok(_) => (),
                     Err(_) => {
@@ -119,9 +123,7 @@ where
                         // Terms are in root set
                         unsafe { terms.add(&mut rootset) };
 
-                        lumen_runtime::system::io::puts(
-                            "=================================================== FULL GC",
-                        );
+                        trace::emit(proc, Event::GarbageCollect { full: true });
                         match heap.garbage_collect(proc, 0, rootset) {
                             Ok(_) => (),
                             Err(_) => panic!(),
@@ -175,6 +177,58 @@ fn call_closure_inner(
     }
 }
 
+/// Whether `callee` is a captured native function, such as a BIF
+fn calls_native(modules: &ModuleRegistry, callee: Term) -> bool {
+    let closure: Boxed<Closure> = match callee.try_into() {
+        Ok(closure) => closure,
+        Err(_) => return false,
+    };
+    let mfa = closure.module_function_arity();
+
+    match modules.lookup_function(mfa.module, mfa.function, mfa.arity as usize) {
+        Some(ResolvedFunction::Native(_)) => true,
+        _ => false,
+    }
+}
+
+/// The class, reason and stacktrace to throw to the throw continuation in `args` for
+/// `exception`, which was raised by the native function `module:function` called with `args`,
+/// tail called by `tail_caller`, if any.
+fn thrown_exception(
+    proc: &Arc<Process>,
+    modules: &ModuleRegistry,
+    module: Atom,
+    function: Atom,
+    args: &[Term],
+    tail_caller: Option<Caller>,
+    exception: runtime::Exception,
+) -> std::result::Result<[Term; 3], system::Exception> {
+    let class = match exception.class {
+        runtime::Class::Error { .. } => atom_unchecked("error"),
+        runtime::Class::Exit => atom_unchecked("exit"),
+        runtime::Class::Throw => atom_unchecked("throw"),
+    };
+
+    let stacktrace = match exception.stacktrace {
+        // Re-raised with its original stacktrace by `erlang:raise/3`
+        Some(stacktrace) => stacktrace,
+        None => {
+            // Like BEAM, the functions that only raise are not in the stacktrace, while BIFs that
+            // fail, such as with `badarg`, are
+            let called = match (module.name(), function.name()) {
+                ("erlang", "error") | ("erlang", "exit") | ("erlang", "throw") => None,
+                _ => Some((module, function, &args[2..])),
+            };
+            let current =
+                tail_caller.and_then(|caller| stacktrace::resolve_caller(modules, caller));
+
+            stacktrace::make_stacktrace(proc, modules, called, current, args[0])?
+        }
+    };
+
+    Ok([class, exception.reason, stacktrace])
+}
+
 impl CallExecutor {
     pub fn new() -> Self {
         CallExecutor {
@@ -184,6 +238,9 @@ impl CallExecutor {
     }
 
     /// Calls the given MFA with args. Will call the entry block.
+    ///
+    /// `version` is the version of `module` that a local call is made in, or `None` to call the
+    /// current version.
     pub fn call(
         &mut self,
         vm: &VMState,
@@ -191,10 +248,11 @@ impl CallExecutor {
         module: Atom,
         function: Atom,
         arity: usize,
-        args: &mut [Term],
+        mut args: &mut [Term],
+        version: Option<usize>,
     ) {
-        trace!("======== RUN {} ========", proc.pid());
         let modules = vm.modules.read().unwrap();
+        let tail_caller = stacktrace::take_tail_caller(proc);
 
         // Make sure no non-heap terms make it into the process
         {
@@ -203,30 +261,60 @@ impl CallExecutor {
                 if arg.is_boxed() {
                     use liblumen_alloc::erts::process::alloc::HeapAlloc;
                     if !heap.is_owner(arg.boxed_val()) {
-                        println!("NON HEAP BOXED: {:?}", arg);
-                        panic!();
+                        panic!("NON HEAP BOXED: {:?}", arg);
                     }
                 }
             }
         }
 
-        match modules.lookup_function(module, function, arity) {
-            None => {
-                self.fun_not_found(proc, args[1], module, function, arity)
-                    .unwrap();
-            }
-            Some(ResolvedFunction::Native(native)) => {
-                assert!(arity + 2 == args.len());
-                self.run_native(vm, proc, native, args);
-            }
+        trace::emit(
+            proc,
+            Event::Call {
+                module,
+                function,
+                arguments: &args[2..],
+            },
+        );
+
+        if debugger::attached() && debugger::steps_over_call(proc) {
+            try_gc(proc, &mut args, &mut |args| {
+                step_over::wrap_continuations(proc, args)
+            });
+        }
+
+        // A process killed by a collection, such as for exceeding its `max_heap_size`, doesn't
+        // run any further
+        if proc.is_exiting() {
+            return;
+        }
+
+        let resolved = match version {
+            None => modules.lookup_function(module, function, arity),
+            Some(version) => modules.lookup_function_version(module, function, arity, version),
+        };
+        let native = match resolved {
+            None => return self.fun_not_found(proc, &modules, module, function, args, tail_caller),
+            Some(ResolvedFunction::Native(native)) => native,
             Some(ResolvedFunction::Erlang(fun)) => {
+                if trace::traces_return(proc, module, function, arity) {
+                    try_gc(proc, &mut args, &mut |args| {
+                        return_trace::wrap_continuations(proc, module, function, arity, args)
+                    });
+                }
+
                 let entry = fun.fun.block_entry();
-                self.run_erlang(vm, proc, fun, entry, args);
+                return self.run_erlang(vm, proc, &modules, fun, entry, args);
             }
-        }
+        };
+
+        // Natives, such as `code:load_binary/3`, can change the registered modules
+        std::mem::drop(modules);
+
+        assert!(arity + 2 == args.len());
+        self.run_native(vm, proc, module, function, native, args, tail_caller);
     }
 
-    /// Calls a block in the given MFA with an environment.
+    /// Calls a block in `version` of the given MFA with an environment.
     pub fn call_block(
         &mut self,
         vm: &VMState,
@@ -235,15 +323,13 @@ impl CallExecutor {
         function: Atom,
         arity: usize,
         args: &mut [Term],
+        version: usize,
         block: Block,
         env: &mut [Term],
     ) {
-        trace!("======== RUN {} ========", proc.pid());
         let modules = vm.modules.read().unwrap();
-        match modules.lookup_function(module, function, arity) {
-            None => self
-                .fun_not_found(proc, args[1], module, function, arity)
-                .unwrap(),
+        match modules.lookup_function_version(module, function, arity, version) {
+            None => self.fun_not_found(proc, &modules, module, function, args, None),
             Some(ResolvedFunction::Native(_ptr)) => unreachable!(),
             Some(ResolvedFunction::Erlang(fun)) => {
                 let live = &fun.live.live[&block];
@@ -253,66 +339,93 @@ impl CallExecutor {
                     self.binds.insert(v, *t);
                 }
 
-                self.run_erlang(vm, proc, fun, block, args);
+                self.run_erlang(vm, proc, &modules, fun, block, args);
             }
         }
     }
 
+    /// Throws `undef` to the throw continuation in `args`
     fn fun_not_found(
         &self,
-        _proc: &Arc<Process>,
-        _throw_cont: Term,
+        proc: &Arc<Process>,
+        modules: &ModuleRegistry,
         module: Atom,
         function: Atom,
-        arity: usize,
-    ) -> Result {
-        panic!("Undef: {} {} {}", module, function, arity);
-        //let exit_atom = atom_unchecked("EXIT");
-        //let undef_atom = atom_unchecked("undef");
-        //let trace_atom = atom_unchecked("trace");
-        //self.call_closure(proc, throw_cont, &[exit_atom, undef_atom, trace_atom])
+        mut args: &mut [Term],
+        tail_caller: Option<Caller>,
+    ) {
+        let current = tail_caller.and_then(|caller| stacktrace::resolve_caller(modules, caller));
+
+        try_gc(proc, &mut args, &mut |args| {
+            let stacktrace = stacktrace::make_stacktrace(
+                proc,
+                modules,
+                Some((module, function, &args[2..])),
+                current,
+                args[0],
+            )?;
+
+            Ok(call_closure(
+                proc,
+                args[1],
+                &mut [atom_unchecked("error"), atom_unchecked("undef"), stacktrace],
+            ))
+        })
     }
 
     fn run_native(
         &mut self,
-        _vm: &VMState,
+        vm: &VMState,
         proc: &Arc<Process>,
+        module: Atom,
+        function: Atom,
         native: NativeFunctionKind,
         mut args: &mut [Term],
+        tail_caller: Option<Caller>,
     ) {
         try_gc(proc, &mut args, &mut |args| match native {
+            // Killed by the collection for the previous try, so the native isn't run again
+            _ if proc.is_exiting() => Ok(()),
             NativeFunctionKind::Simple(ptr) => match ptr(proc, &args[2..]) {
-                Ok(ret) => Ok(call_closure(proc, args[0], &mut [ret])),
-                Err(err) => match err {
-                    Exception::System(err) => return Err(err),
-                    Exception::Runtime(runtime::Exception {
-                        class: runtime::Class::Throw,
-                        reason,
-                        ..
-                    }) => Ok(call_closure(
+                Ok(ret) => {
+                    trace::emit(
                         proc,
-                        args[1],
-                        &mut [atom_unchecked("throw"), reason, atom_unchecked("trace")],
-                    )),
-                    Exception::Runtime(runtime::Exception {
-                        class: runtime::Class::Exit,
-                        reason,
-                        ..
-                    }) => Ok(call_closure(
+                        Event::Return {
+                            module,
+                            function,
+                            arity: args.len() - 2,
+                            value: ret,
+                        },
+                    );
+
+                    Ok(call_closure(proc, args[0], &mut [ret]))
+                }
+                Err(Exception::System(err)) => Err(err),
+                Err(Exception::Runtime(exception)) => {
+                    let modules = vm.modules.read().unwrap();
+                    let mut thrown = thrown_exception(
                         proc,
-                        args[1],
-                        &mut [atom_unchecked("EXIT"), reason, atom_unchecked("trace")],
-                    )),
-                    Exception::Runtime(runtime::Exception {
-                        class: runtime::Class::Error { .. },
-                        reason,
-                        ..
-                    }) => Ok(call_closure(
+                        &modules,
+                        module,
+                        function,
+                        args,
+                        tail_caller,
+                        exception,
+                    )?;
+
+                    trace::emit(
                         proc,
-                        args[1],
-                        &mut [atom_unchecked("error"), reason, atom_unchecked("trace")],
-                    )),
-                },
+                        Event::ExceptionFrom {
+                            module,
+                            function,
+                            arity: args.len() - 2,
+                            class: thrown[0],
+                            reason: thrown[1],
+                        },
+                    );
+
+                    Ok(call_closure(proc, args[1], &mut thrown))
+                }
             },
             NativeFunctionKind::Yielding(ptr) => ptr(proc, args),
         })
@@ -322,6 +435,7 @@ impl CallExecutor {
         &mut self,
         vm: &VMState,
         proc: &Arc<Process>,
+        modules: &ModuleRegistry,
         fun: &ErlangFunction,
         mut block: Block,
         args: &mut [Term],
@@ -329,11 +443,11 @@ impl CallExecutor {
         self.next_args.extend(args.iter().cloned());
 
         let mut exec = self;
+        let mut previous_line = None;
         // Outer loop for optimized execution within the current function
         'outer: loop {
             // Insert block argument into environment
             let block_arg_vals = fun.fun.block_args(block);
-            //trace!("{:?} {:?}", &block_arg_vals, &exec.next_args);
             assert!(
                 block_arg_vals.len() == exec.next_args.len(),
                 "{} == {}",
@@ -344,10 +458,32 @@ impl CallExecutor {
                 exec.binds.insert(*v, t.clone());
             }
 
+            if debugger::attached() {
+                let location = stacktrace::location(fun, block);
+                let line = location.as_ref().map(|(_, line)| *line);
+
+                debugger::block_boundary(
+                    proc,
+                    fun,
+                    block,
+                    &exec.binds,
+                    location,
+                    line != previous_line,
+                );
+                previous_line = line;
+            }
+
             match try_gc(proc, &mut exec, &mut |exec| {
                 exec.next_args.clear();
-                exec.run_erlang_op(vm, proc, fun, block)
+                exec.run_erlang_op(vm, proc, modules, fun, block)
             }) {
+                // Killed by a collection during the op, so the process exits without continuing
+                _ if proc.is_exiting() => break,
+                OpResult::Block(b) if proc.is_reduced() => {
+                    // Yield to the scheduler, continuing in `b` when the process runs again
+                    let cont = try_gc(proc, &mut exec, &mut |exec| exec.make_closure(proc, fun, b));
+                    break call_closure(proc, cont, &mut exec.next_args);
+                }
                 OpResult::Block(b) => {
                     block = b;
                     continue;
@@ -416,6 +552,7 @@ impl CallExecutor {
 
         // FIXME vec alloc
         let mut env = Vec::new();
+        env.push(proc.integer(fun.version)?);
         env.push(proc.integer(block.index())?);
         for v in live.iter(&fun.live.pool) {
             assert!(fun.fun.value_argument(v).is_some());
@@ -438,6 +575,61 @@ impl CallExecutor {
         Ok(closure)
     }
 
+    /// Captures `module:function/arity` in `fun`.
+    ///
+    /// A capture of a function in the same module as `fun` is local, so, like BEAM, calling it
+    /// stays in the version of the module that `fun` is in, even after a new version is loaded.
+    /// EIR does not distinguish `?MODULE:function(...)` from `function(...)`, so the loader marks
+    /// fully-qualified calls to the same module with another `module`, which are remote calls to
+    /// the current version instead, so that a running process can upgrade its own code.  A
+    /// `module` that is not a constant, such as a variable, is also remote.
+    fn capture_function(
+        &self,
+        proc: &Arc<Process>,
+        fun: &ErlangFunction,
+        module: Value,
+        function: Atom,
+        arity: usize,
+    ) -> std::result::Result<Term, system::Exception> {
+        let constant = match fun.fun.value_kind(module) {
+            ValueKind::Const(_) => true,
+            _ => false,
+        };
+        let module: Atom = self.make_term(proc, fun, module)?.try_into().unwrap();
+        let (module, local) = match load::unmark_remote_self_module(module.name()) {
+            Some(name) => (Atom::try_from_str(name).unwrap(), false),
+            None => (
+                module,
+                constant && module.name() == &*fun.fun.ident().module.as_str(),
+            ),
+        };
+        let mfa = ModuleFunctionArity {
+            module,
+            function,
+            arity: arity as u8,
+        };
+
+        let closure = if local {
+            let version = proc.integer(fun.version)?;
+
+            proc.closure_with_env_from_slice(
+                mfa.into(),
+                crate::code::interpreter_local_code,
+                proc.pid_term(),
+                &[version],
+            )?
+        } else {
+            proc.closure_with_env_from_slice(
+                mfa.into(),
+                crate::code::interpreter_mfa_code,
+                proc.pid_term(),
+                &[],
+            )?
+        };
+
+        Ok(closure)
+    }
+
     fn make_term(
         &self,
         proc: &Arc<Process>,
@@ -500,23 +692,11 @@ impl CallExecutor {
                         Ok((lhs == rhs).into())
                     }
                     PrimOpKind::CaptureFunction => {
-                        let module: Atom = self.make_term(proc, fun, reads[0])?.try_into().unwrap();
                         let function: Atom =
                             self.make_term(proc, fun, reads[1])?.try_into().unwrap();
                         let arity: usize = self.make_term(proc, fun, reads[2])?.try_into().unwrap();
 
-                        let mfa = ModuleFunctionArity {
-                            module,
-                            function,
-                            arity: arity as u8,
-                        };
-
-                        Ok(proc.closure_with_env_from_slice(
-                            mfa.into(),
-                            crate::code::interpreter_mfa_code,
-                            proc.pid_term(),
-                            &[],
-                        )?)
+                        self.capture_function(proc, fun, reads[0], function, arity)
                     }
                     kind => unimplemented!("{:?}", kind),
                 }
@@ -524,6 +704,44 @@ impl CallExecutor {
         }
     }
 
+    /// Raises `error:reason` from `block` of `fun` to its throw continuation
+    fn raise_error(
+        &mut self,
+        proc: &Arc<Process>,
+        modules: &ModuleRegistry,
+        fun: &ErlangFunction,
+        block: Block,
+        reason: Term,
+    ) -> std::result::Result<OpResult, system::Exception> {
+        let entry_args = fun.fun.block_args(fun.fun.block_entry());
+        let return_continuation = self.binds[&entry_args[0]];
+        let throw_continuation = self.binds[&entry_args[1]];
+
+        let stacktrace = stacktrace::make_stacktrace(
+            proc,
+            modules,
+            None,
+            Some((fun, block)),
+            return_continuation,
+        )?;
+
+        self.next_args
+            .extend_from_slice(&[atom_unchecked("error"), reason, stacktrace]);
+        Ok(OpResult::Term(throw_continuation))
+    }
+
+    /// Whether the call being made passes on the return continuation of `fun` itself
+    fn is_tail_call(&self, fun: &ErlangFunction) -> bool {
+        let return_value = fun.fun.block_args(fun.fun.block_entry())[0];
+
+        match (self.next_args.get(0), self.binds.get(&return_value)) {
+            (Some(continuation), Some(return_continuation)) => {
+                continuation.as_usize() == return_continuation.as_usize()
+            }
+            _ => false,
+        }
+    }
+
     fn val_call(
         &mut self,
         proc: &Arc<Process>,
@@ -542,12 +760,12 @@ impl CallExecutor {
         &mut self,
         _vm: &VMState,
         proc: &Arc<Process>,
+        modules: &ModuleRegistry,
         fun: &ErlangFunction,
         block: Block,
     ) -> std::result::Result<OpResult, system::Exception> {
         let reads = fun.fun.block_reads(block);
         let kind = fun.fun.block_kind(block).unwrap();
-        trace!("OP: {:?} {}", kind, block);
 
         proc.reduce();
 
@@ -557,7 +775,15 @@ impl CallExecutor {
                     let term = self.make_term(proc, fun, *read)?;
                     self.next_args.push(term);
                 }
-                self.val_call(proc, fun, reads[0])
+                let result = self.val_call(proc, fun, reads[0])?;
+
+                if let OpResult::Term(callee) = result {
+                    if self.is_tail_call(fun) && calls_native(modules, callee) {
+                        stacktrace::set_tail_caller(proc, fun, block);
+                    }
+                }
+
+                Ok(result)
             }
             OpKind::UnpackValueList(num) => {
                 assert!(reads.len() == 2);
@@ -589,22 +815,10 @@ impl CallExecutor {
                 }
             }
             OpKind::CaptureFunction => {
-                let module: Atom = self.make_term(proc, fun, reads[1])?.try_into().unwrap();
                 let function: Atom = self.make_term(proc, fun, reads[2])?.try_into().unwrap();
                 let arity: usize = self.make_term(proc, fun, reads[3])?.try_into().unwrap();
 
-                let mfa = ModuleFunctionArity {
-                    module,
-                    function,
-                    arity: arity as u8,
-                };
-
-                let closure = proc.closure_with_env_from_slice(
-                    mfa.into(),
-                    crate::code::interpreter_mfa_code,
-                    proc.pid_term(),
-                    &[],
-                )?;
+                let closure = self.capture_function(proc, fun, reads[1], function, arity)?;
 
                 self.next_args.push(closure);
                 self.val_call(proc, fun, reads[0])
@@ -686,10 +900,46 @@ impl CallExecutor {
                 assert!(reads.len() == 2);
 
                 let timeout = self.make_term(proc, fun, reads[1])?;
-                // Only infinity supported
-                assert!(timeout == atom_unchecked("infinity"));
+                let milliseconds: Option<Milliseconds> = if timeout == atom_unchecked("infinity") {
+                    None
+                } else {
+                    match timeout.try_into() {
+                        Ok(milliseconds) => Some(milliseconds),
+                        Err(_) => {
+                            return self.raise_error(
+                                proc,
+                                modules,
+                                fun,
+                                block,
+                                atom_unchecked("timeout_value"),
+                            )
+                        }
+                    }
+                };
+
+                proc.clear_flags(ProcessFlags::TimedOut);
 
-                proc.mailbox.lock().borrow_mut().recv_start();
+                let mailbox_lock = proc.mailbox.lock();
+                let mut mailbox = mailbox_lock.borrow_mut();
+                mailbox.recv_start();
+
+                match milliseconds {
+                    None => (),
+                    // `after 0` only checks the messages already in the mailbox
+                    Some(0) => {
+                        proc.set_flags(ProcessFlags::TimedOut);
+                    }
+                    Some(milliseconds) => {
+                        let timer = timer::start_receive(
+                            monotonic::time_in_milliseconds() + milliseconds,
+                            proc,
+                        );
+                        mailbox.recv_set_timer(timer);
+                    }
+                }
+
+                std::mem::drop(mailbox);
+                std::mem::drop(mailbox_lock);
 
                 self.next_args.push(Term::NIL);
                 self.val_call(proc, fun, reads[0])
@@ -709,6 +959,17 @@ impl CallExecutor {
 
                     self.next_args.push(msg_term);
                     self.val_call(proc, fun, reads[1])
+                } else if proc.are_flags_set(ProcessFlags::TimedOut) {
+                    // No message matched before the timeout, so continue in the `after` branch
+                    mailbox.recv_timeout();
+                    mailbox.recv_take_timer();
+
+                    std::mem::drop(mailbox);
+                    std::mem::drop(mailbox_lock);
+
+                    proc.clear_flags(ProcessFlags::TimedOut);
+
+                    self.val_call(proc, fun, reads[0])
                 } else {
                     // If there are no messages, schedule a call
                     // to the current block for later.
@@ -733,28 +994,49 @@ impl CallExecutor {
                     }
                 }
 
+                trace::emit(
+                    proc,
+                    Event::Receive {
+                        message: mailbox.recv_last(),
+                    },
+                );
                 mailbox.recv_finish(proc);
 
+                // A message matched first, so the `after` branch is not taken even if the timer
+                // has already timed out
+                if let Some(timer) = mailbox.recv_take_timer() {
+                    timer::cancel(&timer);
+                }
+
+                std::mem::drop(mailbox);
+                std::mem::drop(mailbox_lock);
+
+                proc.clear_flags(ProcessFlags::TimedOut);
+
                 self.val_call(proc, fun, reads[0])
             }
-            OpKind::BinaryPush {
-                specifier: BinaryEntrySpecifier::Bytes { .. },
-            } => {
-                assert!(reads.len() == 4);
-                let head = self.make_term(proc, fun, reads[2])?;
-                let tail = self.make_term(proc, fun, reads[3])?;
-
-                let mut head_bin: Vec<u8> = head.try_into().unwrap();
-                let tail_bin: Vec<u8> = tail.try_into().unwrap();
-                head_bin.extend(tail_bin.iter());
-
-                self.next_args.push(proc.binary_from_bytes(&head_bin)?);
-                self.val_call(proc, fun, reads[0])
+            OpKind::BinaryPush { specifier } => {
+                let bin = self.make_term(proc, fun, reads[2])?;
+                let value = self.make_term(proc, fun, reads[3])?;
+                let size = match reads.len() {
+                    4 => None,
+                    5 => Some(self.make_term(proc, fun, reads[4])?),
+                    _ => unreachable!(),
+                };
+
+                match binary::push_segment(proc, bin, specifier, value, size)? {
+                    Some(appended) => {
+                        self.next_args.push(appended);
+                        self.val_call(proc, fun, reads[0])
+                    }
+                    None => {
+                        self.next_args.push(atom_unchecked("badarg"));
+                        self.val_call(proc, fun, reads[1])
+                    }
+                }
             }
             OpKind::Unreachable => {
-                println!("==== Reached OpKind::Unreachable! ====");
-                println!("Fun: {} Block: {}", fun.fun.ident(), block);
-                unreachable!();
+                unreachable!("Fun: {} Block: {}", fun.fun.ident(), block);
             }
             kind => unimplemented!("{:?}", kind),
         }
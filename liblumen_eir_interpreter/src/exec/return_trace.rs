@@ -0,0 +1,163 @@
+//! Tracing the returns of interpreted functions.
+//!
+//! Interpreted functions return by calling their ok or throw continuation, which may happen in
+//! a function they tail call, such as a BIF, instead of in their own code.  Like BEAM's
+//! `return_trace`, the continuations of a traced call are wrapped in closures that trace the
+//! return before calling the original continuation, so traced calls are not tail calls.
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use liblumen_alloc::erts::exception::system;
+use liblumen_alloc::erts::process::code;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Boxed, Closure, Term, TypedTerm};
+use liblumen_alloc::erts::ModuleFunctionArity;
+
+use crate::trace::{self, Event};
+
+const MODULE: &str = "lumen_eir_interpreter_intrinsics";
+const RETURN_TRACE: &str = "return_trace";
+const EXCEPTION_TRACE: &str = "exception_trace";
+
+/// Replaces the ok and throw continuations at the start of `args` with closures that trace the
+/// return of `module:function/arity`
+pub fn wrap_continuations(
+    proc: &Arc<Process>,
+    module: Atom,
+    function: Atom,
+    arity: usize,
+    args: &mut [Term],
+) -> Result<(), system::Exception> {
+    let module = atom_unchecked(module.name());
+    let function = atom_unchecked(function.name());
+    let arity = proc.integer(arity)?;
+
+    let ok = wrap(
+        proc,
+        RETURN_TRACE,
+        1,
+        return_trace_code,
+        &[args[0], module, function, arity],
+    )?;
+    let throw = wrap(
+        proc,
+        EXCEPTION_TRACE,
+        3,
+        exception_trace_code,
+        &[args[1], module, function, arity],
+    )?;
+
+    args[0] = ok;
+    args[1] = throw;
+
+    Ok(())
+}
+
+/// The continuation wrapped by `continuation` if it traces a return, so that stacktraces can
+/// follow the original continuations
+pub fn unwrap_continuation(continuation: Term) -> Term {
+    let closure: Boxed<Closure> = match continuation.try_into() {
+        Ok(closure) => closure,
+        Err(_) => return continuation,
+    };
+
+    let mfa = closure.module_function_arity();
+    if mfa.module.name() == MODULE
+        && (mfa.function.name() == RETURN_TRACE || mfa.function.name() == EXCEPTION_TRACE)
+    {
+        unwrap_continuation(closure.env_slice()[0])
+    } else {
+        continuation
+    }
+}
+
+/// A closure of `code` with `env`, which is the original continuation followed by the module,
+/// function and arity of the traced function
+fn wrap(
+    proc: &Arc<Process>,
+    name: &str,
+    closure_arity: u8,
+    code: code::Code,
+    env: &[Term],
+) -> Result<Term, system::Exception> {
+    let mfa = ModuleFunctionArity {
+        module: Atom::try_from_str(MODULE).unwrap(),
+        function: Atom::try_from_str(name).unwrap(),
+        arity: closure_arity,
+    };
+
+    Ok(proc.closure_with_env_from_slice(mfa.into(), code, proc.pid_term(), env)?)
+}
+
+/// Expects the following on stack:
+/// * argument list of the return value
+/// * closure with the original continuation, module, function and arity as its environment
+fn return_trace_code(arc_process: &Arc<Process>) -> code::Result {
+    let (mut arguments, env) = pop_call(arc_process);
+    let (module, function, arity) = traced_function(&env);
+
+    trace::emit(
+        arc_process,
+        Event::Return {
+            module,
+            function,
+            arity,
+            value: arguments[0],
+        },
+    );
+
+    super::call_closure(arc_process, env[0], &mut arguments);
+
+    crate::code::run_until_reduced(arc_process)
+}
+
+/// Expects the following on stack:
+/// * argument list of the class, reason and stacktrace
+/// * closure with the original continuation, module, function and arity as its environment
+fn exception_trace_code(arc_process: &Arc<Process>) -> code::Result {
+    let (mut arguments, env) = pop_call(arc_process);
+    let (module, function, arity) = traced_function(&env);
+
+    trace::emit(
+        arc_process,
+        Event::ExceptionFrom {
+            module,
+            function,
+            arity,
+            class: arguments[0],
+            reason: arguments[1],
+        },
+    );
+
+    super::call_closure(arc_process, env[0], &mut arguments);
+
+    crate::code::run_until_reduced(arc_process)
+}
+
+fn pop_call(arc_process: &Arc<Process>) -> (Vec<Term>, Vec<Term>) {
+    let argument_list = arc_process.stack_pop().unwrap();
+    let closure_term = arc_process.stack_pop().unwrap();
+
+    let mut argument_vec: Vec<Term> = Vec::new();
+    match argument_list.to_typed_term().unwrap() {
+        TypedTerm::Nil => (),
+        TypedTerm::List(argument_cons) => {
+            for result in argument_cons.into_iter() {
+                argument_vec.push(result.unwrap());
+            }
+        }
+        _ => panic!(),
+    }
+
+    let closure: Boxed<Closure> = closure_term.try_into().unwrap();
+
+    (argument_vec, closure.env_slice().to_vec())
+}
+
+fn traced_function(env: &[Term]) -> (Atom, Atom, usize) {
+    (
+        env[1].try_into().unwrap(),
+        env[2].try_into().unwrap(),
+        env[3].try_into().unwrap(),
+    )
+}
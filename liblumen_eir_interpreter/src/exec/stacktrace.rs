@@ -0,0 +1,220 @@
+//! Stacktraces of interpreted code.
+//!
+//! Interpreted functions are in continuation passing style, so there is no call stack to walk.
+//! Instead, a non-tail call passes a closure of the block to return to as its return
+//! continuation, and that block keeps the return continuation of its own function alive, so the
+//! callers are found by following return continuations, the same as following return addresses
+//! on a stack.  Tail calls reuse the return continuation of the caller, so, like BEAM, they are
+//! not in the stacktrace, except for the function that raised the exception, which is always the
+//! first entry after any BIF that raised it.
+use std::cell::RefCell;
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+
+use cranelift_entity::EntityRef;
+use libeir_ir::Block;
+
+use liblumen_alloc::erts::exception::system;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Boxed, Closure, Pid, Term};
+
+use crate::module::{ErlangFunction, ModuleRegistry, ResolvedFunction};
+
+use super::{return_trace, step_over};
+
+/// The same as the default of `erlang:system_flag(backtrace_depth, Depth)`
+const MAX_DEPTH: usize = 8;
+
+thread_local! {
+    /// The interpreted function and block that tail called a native function in each process
+    /// running on this thread, from the call until the native function starts
+    static TAIL_CALLERS: RefCell<HashMap<Pid, Caller>> = RefCell::new(HashMap::new());
+}
+
+/// An interpreted function and the block in it that made a call
+#[derive(Clone, Copy)]
+pub struct Caller {
+    module: Atom,
+    function: Atom,
+    arity: usize,
+    version: usize,
+    block: Block,
+}
+
+/// Records that `block` of `fun` is tail calling a native function in `proc`.  A native function
+/// does not get a return continuation of its own, so without this the function that called it
+/// would be missing from the stacktrace of any exception it raises.
+pub fn set_tail_caller(proc: &Process, fun: &ErlangFunction, block: Block) {
+    let ident = fun.fun.ident();
+    let caller = Caller {
+        module: Atom::try_from_str(&ident.module.as_str()).unwrap(),
+        function: Atom::try_from_str(&ident.name.as_str()).unwrap(),
+        arity: ident.arity,
+        version: fun.version,
+        block,
+    };
+
+    TAIL_CALLERS.with(|callers| callers.borrow_mut().insert(proc.pid(), caller));
+}
+
+/// The caller recorded by [set_tail_caller] for the function `proc` is now calling, if it was a
+/// tail call
+pub fn take_tail_caller(proc: &Process) -> Option<Caller> {
+    TAIL_CALLERS.with(|callers| callers.borrow_mut().remove(&proc.pid()))
+}
+
+/// The function and block of `caller`, if its version of the module is still loaded
+pub fn resolve_caller(
+    modules: &ModuleRegistry,
+    caller: Caller,
+) -> Option<(&ErlangFunction, Block)> {
+    match modules.lookup_function_version(
+        caller.module,
+        caller.function,
+        caller.arity,
+        caller.version,
+    )? {
+        ResolvedFunction::Erlang(fun) => Some((fun, caller.block)),
+        ResolvedFunction::Native(_) => None,
+    }
+}
+
+/// The stacktrace of an exception raised in a call that returns to `return_continuation`.
+///
+/// The stacktrace is a list of `{Module, Function, Arity | Arguments, Location}`, where
+/// `Location` is `[{file, File}, {line, Line}]` when the location of the call is known.
+/// `called` is the function that raised the exception, with its arguments, if it should be the
+/// first entry, such as a BIF that raised `badarg`.  `current` is the interpreted function and
+/// block that raised the exception or made that call, if it does not return to
+/// `return_continuation` itself.
+pub fn make_stacktrace(
+    proc: &Arc<Process>,
+    modules: &ModuleRegistry,
+    called: Option<(Atom, Atom, &[Term])>,
+    current: Option<(&ErlangFunction, Block)>,
+    return_continuation: Term,
+) -> Result<Term, system::Exception> {
+    let mut entries = Vec::new();
+
+    if let Some((module, function, arguments)) = called {
+        let arguments = proc.list_from_slice(arguments)?;
+        entries.push(proc.tuple_from_slice(&[
+            atom_unchecked(module.name()),
+            atom_unchecked(function.name()),
+            arguments,
+            Term::NIL,
+        ])?);
+    }
+
+    if let Some((fun, block)) = current {
+        entries.push(entry(proc, fun, block)?);
+    }
+
+    let mut continuation = return_continuation;
+    while entries.len() < MAX_DEPTH {
+        let continuation = unwrap_continuation(continuation);
+        let (fun, block, closure) = match interpreted_continuation(modules, continuation) {
+            Some(found) => found,
+            None => break,
+        };
+
+        entries.push(entry(proc, fun, block)?);
+
+        continuation = match own_return_continuation(fun, block, &closure) {
+            Some(continuation) => continuation,
+            None => break,
+        };
+    }
+
+    Ok(proc.list_from_slice(&entries)?)
+}
+
+/// `{Module, Function, Arity, Location}` of `block` in `fun`
+fn entry(proc: &Process, fun: &ErlangFunction, block: Block) -> Result<Term, system::Exception> {
+    let ident = fun.fun.ident();
+    let location = match location(fun, block) {
+        Some((file, line)) => {
+            let file = proc.charlist_from_str(&file)?;
+            let file = proc.tuple_from_slice(&[atom_unchecked("file"), file])?;
+            let line = proc.integer(line)?;
+            let line = proc.tuple_from_slice(&[atom_unchecked("line"), line])?;
+
+            proc.list_from_slice(&[file, line])?
+        }
+        None => Term::NIL,
+    };
+
+    let entry = proc.tuple_from_slice(&[
+        atom_unchecked(&ident.module.as_str()),
+        atom_unchecked(&ident.name.as_str()),
+        proc.integer(ident.arity)?,
+        location,
+    ])?;
+
+    Ok(entry)
+}
+
+/// The original continuation of `continuation` if it was wrapped to trace a return or to step
+/// over a call, which can both happen to the same call
+fn unwrap_continuation(continuation: Term) -> Term {
+    let unwrapped = step_over::unwrap_continuation(return_trace::unwrap_continuation(continuation));
+
+    if unwrapped.as_usize() == continuation.as_usize() {
+        continuation
+    } else {
+        unwrap_continuation(unwrapped)
+    }
+}
+
+/// The interpreted function and block that `continuation` returns to, or `None` if it returns to
+/// native code, such as the continuation that ends a process.
+fn interpreted_continuation(
+    modules: &ModuleRegistry,
+    continuation: Term,
+) -> Option<(&ErlangFunction, Block, Boxed<Closure>)> {
+    let closure: Boxed<Closure> = continuation.try_into().ok()?;
+
+    // Closures of blocks have the module version and block as the first elements of their
+    // environment, while captured functions have at most the module version
+    let version: usize = (*closure.env_slice().get(0)?).try_into().ok()?;
+    let block_index: usize = (*closure.env_slice().get(1)?).try_into().ok()?;
+
+    let mfa = closure.module_function_arity();
+    match modules.lookup_function_version(mfa.module, mfa.function, mfa.arity as usize, version)? {
+        ResolvedFunction::Erlang(fun) => Some((fun, Block::new(block_index), closure)),
+        ResolvedFunction::Native(_) => None,
+    }
+}
+
+/// The return continuation of the function `block` is in, if `block` keeps it alive in
+/// `closure`'s environment
+fn own_return_continuation(
+    fun: &ErlangFunction,
+    block: Block,
+    closure: &Boxed<Closure>,
+) -> Option<Term> {
+    let return_value = fun.fun.block_args(fun.fun.block_entry())[0];
+    let live = fun.live.live.get(&block)?;
+
+    live.iter(&fun.live.pool)
+        .position(|value| value == return_value)
+        .map(|index| closure.env_slice()[2 + index])
+}
+
+/// The file and line of `block`
+pub fn location(fun: &ErlangFunction, block: Block) -> Option<(String, u32)> {
+    let location = fun.fun.block_location(block);
+
+    // Inlined locations are outermost first, so the last is the location in `fun`
+    fun.fun
+        .locations
+        .lookup(&location)
+        .into_iter()
+        .rev()
+        .find_map(|term| match (term.file, term.line) {
+            (Some(file), Some(line)) => Some((file, line)),
+            _ => None,
+        })
+}
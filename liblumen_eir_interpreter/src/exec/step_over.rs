@@ -0,0 +1,95 @@
+//! Stepping over calls in the debugger.
+//!
+//! The ok and throw continuations of a call that is stepped over are wrapped in closures that
+//! tell the debugger the call returned before calling the original continuation, so the process
+//! pauses at the block it returns to, even if that is in the caller because it was a tail call.
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use liblumen_alloc::erts::exception::system;
+use liblumen_alloc::erts::process::code;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{Atom, Boxed, Closure, Term, TypedTerm};
+use liblumen_alloc::erts::ModuleFunctionArity;
+
+use crate::debugger;
+
+const MODULE: &str = "lumen_eir_interpreter_intrinsics";
+const STEP_OVER_RETURN: &str = "step_over_return";
+const STEP_OVER_THROW: &str = "step_over_throw";
+
+/// Replaces the ok and throw continuations at the start of `args` with closures that pause the
+/// process when the call returns
+pub fn wrap_continuations(proc: &Arc<Process>, args: &mut [Term]) -> Result<(), system::Exception> {
+    let ok = wrap(proc, STEP_OVER_RETURN, 1, args[0])?;
+    let throw = wrap(proc, STEP_OVER_THROW, 3, args[1])?;
+
+    args[0] = ok;
+    args[1] = throw;
+
+    Ok(())
+}
+
+/// The continuation wrapped by `continuation` if it steps over a call
+pub fn unwrap_continuation(continuation: Term) -> Term {
+    let closure: Boxed<Closure> = match continuation.try_into() {
+        Ok(closure) => closure,
+        Err(_) => return continuation,
+    };
+
+    let mfa = closure.module_function_arity();
+    if mfa.module.name() == MODULE
+        && (mfa.function.name() == STEP_OVER_RETURN || mfa.function.name() == STEP_OVER_THROW)
+    {
+        closure.env_slice()[0]
+    } else {
+        continuation
+    }
+}
+
+fn wrap(
+    proc: &Arc<Process>,
+    name: &str,
+    closure_arity: u8,
+    continuation: Term,
+) -> Result<Term, system::Exception> {
+    let mfa = ModuleFunctionArity {
+        module: Atom::try_from_str(MODULE).unwrap(),
+        function: Atom::try_from_str(name).unwrap(),
+        arity: closure_arity,
+    };
+
+    Ok(proc.closure_with_env_from_slice(
+        mfa.into(),
+        step_over_code,
+        proc.pid_term(),
+        &[continuation],
+    )?)
+}
+
+/// Expects the following on stack:
+/// * argument list of the return value, or of the class, reason and stacktrace
+/// * closure with the original continuation as its environment
+fn step_over_code(arc_process: &Arc<Process>) -> code::Result {
+    let argument_list = arc_process.stack_pop().unwrap();
+    let closure_term = arc_process.stack_pop().unwrap();
+
+    let mut argument_vec: Vec<Term> = Vec::new();
+    match argument_list.to_typed_term().unwrap() {
+        TypedTerm::Nil => (),
+        TypedTerm::List(argument_cons) => {
+            for result in argument_cons.into_iter() {
+                argument_vec.push(result.unwrap());
+            }
+        }
+        _ => panic!(),
+    }
+
+    let closure: Boxed<Closure> = closure_term.try_into().unwrap();
+    let continuation = closure.env_slice()[0];
+
+    debugger::stepped_over(arc_process);
+    super::call_closure(arc_process, continuation, &mut argument_vec);
+
+    crate::code::run_until_reduced(arc_process)
+}
@@ -0,0 +1,526 @@
+//! Formats arguments with control sequences, the same as `io_lib:format/2`.
+//!
+//! A control sequence is `~F.P.PadModC`: an optional field width `F`, precision `P` and pad
+//! character `Pad`, the modifiers `t`, for Unicode, and `l`, to not detect strings, and the
+//! control character `C`.  The field width and precision may be `*` to take them from the
+//! arguments, and a field width starting with `-` left-adjusts the field.
+use std::convert::TryInto;
+use std::iter::Peekable;
+
+use num_bigint::BigInt;
+
+use liblumen_alloc::badarg;
+use liblumen_alloc::erts::exception::runtime::Exception;
+use liblumen_alloc::erts::term::{Term, TypedTerm};
+
+use crate::print::{self, Style};
+
+/// The line length of `~p` when there is no field width
+const LINE_LENGTH: usize = 80;
+
+/// The largest field width or precision, so that a control sequence such as `~1000000000c` is
+/// `badarg` instead of a string that doesn't fit in memory
+const MAX_FIELD: isize = 1 << 16;
+
+type Result<T> = std::result::Result<T, Exception>;
+
+/// `format` with its control sequences replaced by `arguments`.  A format that doesn't match the
+/// arguments is `badarg`.
+pub fn format(format: &[char], arguments: &[Term]) -> Result<String> {
+    let mut output = String::new();
+    let mut format = format.iter().cloned().peekable();
+    let mut arguments = arguments.iter().cloned();
+
+    while let Some(c) = format.next() {
+        if c == '~' {
+            let control = Control::parse(&mut format, &mut arguments)?;
+            control.write(&mut output, &mut arguments)?;
+        } else {
+            output.push(c);
+        }
+    }
+
+    if arguments.next().is_some() {
+        return Err(badarg!());
+    }
+
+    Ok(output)
+}
+
+/// `format/2` with a format and arguments as terms, as `io:format/2` and `io_lib:format/2` take
+/// them
+pub fn format_terms(format_term: Term, arguments: Term) -> Result<String> {
+    format(&chars(format_term, true)?, &list(arguments)?)
+}
+
+/// The characters of `term`, which is a format or the argument of `~s`: an atom, or a possibly
+/// deep list of characters and binaries.  Binaries are UTF-8 if `unicode`, and Latin-1 otherwise.
+pub fn chars(term: Term, unicode: bool) -> Result<Vec<char>> {
+    match term.to_typed_term().unwrap() {
+        TypedTerm::Atom(atom) => Ok(atom.name().chars().collect()),
+        _ => {
+            let mut chars = Vec::new();
+            push_chars(&mut chars, term, unicode)?;
+
+            Ok(chars)
+        }
+    }
+}
+
+/// The elements of the proper list `list`
+pub fn list(list: Term) -> Result<Vec<Term>> {
+    match list.to_typed_term().unwrap() {
+        TypedTerm::Nil => Ok(Vec::new()),
+        TypedTerm::List(cons) => cons
+            .into_iter()
+            .map(|result| result.map_err(|_| badarg!()))
+            .collect(),
+        _ => Err(badarg!()),
+    }
+}
+
+fn push_chars(chars: &mut Vec<char>, term: Term, unicode: bool) -> Result<()> {
+    match term.to_typed_term().unwrap() {
+        TypedTerm::Nil => Ok(()),
+        TypedTerm::List(cons) => {
+            for result in cons.into_iter() {
+                push_chars(chars, result.map_err(|_| badarg!())?, unicode)?;
+            }
+
+            Ok(())
+        }
+        TypedTerm::SmallInteger(small_integer) => {
+            let code_point: isize = small_integer.into();
+
+            match std::char::from_u32(code_point as u32) {
+                Some(c) if 0 <= code_point => {
+                    chars.push(c);
+
+                    Ok(())
+                }
+                _ => Err(badarg!()),
+            }
+        }
+        _ => {
+            let bytes: Vec<u8> = term.try_into()?;
+
+            if unicode {
+                let s = String::from_utf8(bytes).map_err(|_| badarg!())?;
+                chars.extend(s.chars());
+            } else {
+                chars.extend(bytes.into_iter().map(char::from));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Adjust {
+    Left,
+    Right,
+}
+
+/// A parsed control sequence
+struct Control {
+    field_width: Option<usize>,
+    adjust: Adjust,
+    precision: Option<usize>,
+    pad: char,
+    unicode: bool,
+    strings: bool,
+    character: char,
+}
+
+impl Control {
+    /// Parses the control sequence after a `~`, taking `*` field widths and precisions from
+    /// `arguments`
+    fn parse(
+        format: &mut Peekable<impl Iterator<Item = char>>,
+        arguments: &mut impl Iterator<Item = Term>,
+    ) -> Result<Self> {
+        let mut adjust = Adjust::Right;
+        if format.peek() == Some(&'-') {
+            format.next();
+            adjust = Adjust::Left;
+        }
+
+        let field_width = match number(format, arguments)? {
+            Some(width) if width < 0 => {
+                adjust = Adjust::Left;
+
+                Some(-width as usize)
+            }
+            width => width.map(|width| width as usize),
+        };
+
+        let mut precision = None;
+        let mut pad = ' ';
+        if format.peek() == Some(&'.') {
+            format.next();
+
+            precision = match number(format, arguments)? {
+                Some(precision) if precision < 0 => return Err(badarg!()),
+                precision => precision.map(|precision| precision as usize),
+            };
+
+            if format.peek() == Some(&'.') {
+                format.next();
+
+                pad = match format.next() {
+                    Some('*') => convert(arguments.next())?,
+                    Some(pad) => pad,
+                    None => return Err(badarg!()),
+                };
+            }
+        }
+
+        let mut unicode = false;
+        let mut strings = true;
+        loop {
+            match format.peek() {
+                Some('t') => unicode = true,
+                Some('l') => strings = false,
+                _ => break,
+            }
+            format.next();
+        }
+
+        let character = format.next().ok_or_else(|| badarg!())?;
+
+        Ok(Control {
+            field_width,
+            adjust,
+            precision,
+            pad,
+            unicode,
+            strings,
+            character,
+        })
+    }
+
+    fn write(&self, output: &mut String, arguments: &mut impl Iterator<Item = Term>) -> Result<()> {
+        let mut argument = || arguments.next().ok_or_else(|| badarg!());
+
+        let field = match self.character {
+            '~' => "~".to_string(),
+            'n' => "\n".to_string(),
+            'c' => self.char(argument()?)?,
+            's' => self.string(chars(argument()?, self.unicode)?)?,
+            'w' => self.term(print::write(argument()?).chars().collect()),
+            'W' => {
+                let term = argument()?;
+                let depth = depth(argument()?)?;
+
+                self.term(print::flat(term, print::WRITE, depth).chars().collect())
+            }
+            'p' => self.print(output, argument()?, None),
+            'P' => {
+                let term = argument()?;
+                let depth = depth(argument()?)?;
+
+                self.print(output, term, depth)
+            }
+            'b' | 'B' => self.integer(argument()?, "", false)?,
+            'x' | 'X' => {
+                let term = argument()?;
+                let prefix: String = chars(argument()?, self.unicode)?.into_iter().collect();
+
+                self.integer(term, &prefix, false)?
+            }
+            '#' | '+' => self.integer(argument()?, "", true)?,
+            'e' => self.exponential(float(argument()?)?, self.precision.unwrap_or(6))?,
+            'f' => self.fixed(float(argument()?)?, self.precision.unwrap_or(6))?,
+            'g' => self.general(float(argument()?)?)?,
+            'i' => {
+                argument()?;
+
+                String::new()
+            }
+            _ => return Err(badarg!()),
+        };
+
+        output.push_str(&field);
+
+        Ok(())
+    }
+
+    /// `~c`: the character repeated the precision times, which defaults to the field width
+    fn char(&self, argument: Term) -> Result<String> {
+        let code_point: u32 = convert(Some(argument))?;
+        let code_point = if self.unicode {
+            code_point
+        } else {
+            code_point & 0xff
+        };
+        let c = std::char::from_u32(code_point).ok_or_else(|| badarg!())?;
+
+        match (self.field_width, self.precision) {
+            (None, None) => Ok(c.to_string()),
+            (Some(count), None) | (None, Some(count)) => Ok(repeat(c, count)),
+            (Some(width), Some(count)) if count <= width => {
+                Ok(self.adjust(repeat(c, count), width - count))
+            }
+            _ => Err(badarg!()),
+        }
+    }
+
+    /// `~s`: the characters are truncated to the precision and padded to the field width
+    fn string(&self, chars: Vec<char>) -> Result<String> {
+        if !self.unicode && chars.iter().any(|c| '\u{ff}' < *c) {
+            return Err(badarg!());
+        }
+
+        let len = chars.len();
+        let string = |count: usize| chars.iter().take(count).collect::<String>();
+
+        match (self.field_width, self.precision) {
+            (None, None) => Ok(string(len)),
+            (Some(width), None) => Ok(self.field(string(width), len, width)),
+            (None, Some(precision)) => Ok(Control {
+                adjust: Adjust::Left,
+                ..*self
+            }
+            .field(string(precision), len, precision)),
+            (Some(width), Some(precision)) if precision <= width => {
+                let mut s = string(precision);
+                for _ in len..precision {
+                    s.push(self.pad);
+                }
+
+                Ok(self.adjust(s, width - precision))
+            }
+            _ => Err(badarg!()),
+        }
+    }
+
+    /// `truncated`, which is at most `width` of `len` characters, padded to `width`
+    fn field(&self, truncated: String, len: usize, width: usize) -> String {
+        if width <= len {
+            truncated
+        } else {
+            self.adjust(truncated, width - len)
+        }
+    }
+
+    /// `~p` and `~P`: the field width is the line length, and the precision is the column the
+    /// term starts in, which defaults to the column of the output so far
+    fn print(&self, output: &str, term: Term, depth: Option<usize>) -> String {
+        let style = Style {
+            strings: self.strings,
+            unicode: self.unicode,
+        };
+        let column = match self.precision {
+            Some(precision) => precision.saturating_sub(1),
+            None => output.chars().rev().take_while(|c| *c != '\n').count(),
+        };
+
+        print::pretty(
+            term,
+            style,
+            depth,
+            column,
+            self.field_width.unwrap_or(LINE_LENGTH),
+        )
+    }
+
+    /// `~b`, `~x` and `~#` in the base of the precision, with letters in uppercase for `~B`, `~X`
+    /// and `~#` and in lowercase for `~b`, `~x` and `~+`.  `prefix` is written after the sign, as
+    /// is the base if `base_prefix`.
+    fn integer(&self, argument: Term, prefix: &str, base_prefix: bool) -> Result<String> {
+        let integer: BigInt = convert(Some(argument))?;
+        let base = self.precision.unwrap_or(10);
+        if base < 2 || 36 < base {
+            return Err(badarg!());
+        }
+
+        let radix = integer.to_str_radix(base as u32);
+        let digits = match self.character {
+            'b' | 'x' | '+' => radix.trim_start_matches('-').to_string(),
+            _ => radix.trim_start_matches('-').to_uppercase(),
+        };
+
+        let mut s = String::new();
+        if radix.starts_with('-') {
+            s.push('-');
+        }
+        s.push_str(prefix);
+        if base_prefix {
+            s.push_str(&format!("{}#", base));
+        }
+        s.push_str(&digits);
+
+        Ok(self.number(s.chars().collect()))
+    }
+
+    /// `~e`: `[-]d.ddde+dd` with the precision as the number of digits
+    fn exponential(&self, float: f64, precision: usize) -> Result<String> {
+        if precision < 2 {
+            return Err(badarg!());
+        }
+
+        let s = format!("{:.*e}", precision - 1, float);
+        let s = match s.find('e') {
+            Some(e) if !s[e + 1..].starts_with('-') => format!("{}e+{}", &s[..e], &s[e + 1..]),
+            _ => s,
+        };
+
+        Ok(self.number(s.chars().collect()))
+    }
+
+    /// `~f`: `[-]ddd.ddd` with the precision as the number of digits after the decimal point
+    fn fixed(&self, float: f64, precision: usize) -> Result<String> {
+        if precision < 1 {
+            return Err(badarg!());
+        }
+
+        Ok(self.number(format!("{:.*}", precision, float).chars().collect()))
+    }
+
+    /// `~g`: `~f` with the precision as the number of significant digits if the float is at least
+    /// 0.1 and less than 10000.0, and `~e` otherwise
+    fn general(&self, float: f64) -> Result<String> {
+        let precision = self.precision.unwrap_or(6);
+        if precision < 1 {
+            return Err(badarg!());
+        }
+
+        let magnitude = float.abs();
+
+        // The exponent of the float if it is in the range of `~f`
+        let exponent: Option<isize> = if magnitude < 0.1 {
+            None
+        } else if magnitude < 1.0 {
+            Some(-1)
+        } else if magnitude < 10.0 {
+            Some(0)
+        } else if magnitude < 100.0 {
+            Some(1)
+        } else if magnitude < 1000.0 {
+            Some(2)
+        } else if magnitude < 10000.0 {
+            Some(3)
+        } else {
+            None
+        };
+
+        match exponent {
+            Some(-1) if precision <= 1 => self.fixed(float, 1),
+            Some(exponent) if exponent < precision as isize - 1 => {
+                self.fixed(float, (precision as isize - 1 - exponent) as usize)
+            }
+            _ if precision <= 1 => self.exponential(float, 2),
+            _ => self.exponential(float, precision),
+        }
+    }
+
+    /// `chars` of a term padded to the field width, or `*`s if they don't fit in it or the
+    /// precision
+    fn term(&self, chars: Vec<char>) -> String {
+        self.field_of(chars, self.precision)
+    }
+
+    /// `chars` of a number, whose precision is its base or digits, so only the field width
+    /// limits it
+    fn number(&self, chars: Vec<char>) -> String {
+        self.field_of(chars, None)
+    }
+
+    fn field_of(&self, chars: Vec<char>, precision: Option<usize>) -> String {
+        let width = match (self.field_width, precision) {
+            (None, None) => return chars.into_iter().collect(),
+            (Some(width), _) => width,
+            (None, Some(precision)) => precision,
+        };
+        let fits = match precision {
+            Some(precision) => precision.min(width),
+            None => width,
+        };
+
+        if fits < chars.len() {
+            self.adjust(repeat('*', fits), width - fits)
+        } else {
+            let len = chars.len();
+            self.adjust(chars.into_iter().collect(), width - len)
+        }
+    }
+
+    /// `s` with `count` pad characters before it, or after it if the field is left-adjusted
+    fn adjust(&self, s: String, count: usize) -> String {
+        let padding = repeat(self.pad, count);
+
+        match self.adjust {
+            Adjust::Left => s + &padding,
+            Adjust::Right => padding + &s,
+        }
+    }
+}
+
+/// A field width or precision, which is either digits or a `*` for the next argument.  One
+/// larger than `MAX_FIELD` is `badarg`.
+fn number(
+    format: &mut Peekable<impl Iterator<Item = char>>,
+    arguments: &mut impl Iterator<Item = Term>,
+) -> Result<Option<isize>> {
+    let number = if format.peek() == Some(&'*') {
+        format.next();
+
+        Some(convert(arguments.next())?)
+    } else {
+        let mut number = None;
+        while let Some(digit) = format.peek().and_then(|c| c.to_digit(10)) {
+            format.next();
+            number = Some(
+                number
+                    .unwrap_or(0isize)
+                    .checked_mul(10)
+                    .and_then(|number| number.checked_add(digit as isize))
+                    .ok_or_else(|| badarg!())?,
+            );
+        }
+
+        number
+    };
+
+    match number {
+        Some(number) if !(-MAX_FIELD..=MAX_FIELD).contains(&number) => Err(badarg!()),
+        number => Ok(number),
+    }
+}
+
+/// An argument converted to a Rust type, such as an integer or a character
+fn convert<T>(argument: Option<Term>) -> Result<T>
+where
+    Term: TryInto<T>,
+{
+    argument
+        .ok_or_else(|| badarg!())?
+        .try_into()
+        .map_err(|_| badarg!())
+}
+
+fn float(argument: Term) -> Result<f64> {
+    match argument.to_typed_term().unwrap() {
+        TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
+            TypedTerm::Float(float) => Ok(float.into()),
+            _ => Err(badarg!()),
+        },
+        _ => Err(badarg!()),
+    }
+}
+
+/// The depth of `~P` and `~W`, where `-1` is unlimited
+fn depth(argument: Term) -> Result<Option<usize>> {
+    let depth: isize = convert(Some(argument))?;
+
+    match depth {
+        -1 => Ok(None),
+        depth if 0 <= depth => Ok(Some(depth as usize)),
+        _ => Err(badarg!()),
+    }
+}
+
+fn repeat(c: char, count: usize) -> String {
+    std::iter::repeat(c).take(count).collect()
+}
@@ -1,11 +1,19 @@
 #![deny(warnings)]
 
 pub mod code;
+pub mod debugger;
 mod exec;
+pub mod format;
+pub mod literal;
+pub mod load;
 mod module;
-pub use module::NativeModule;
+pub use module::{NativeModule, NotPurged};
 pub mod call_result;
 mod native;
+pub mod print;
+mod scan;
+pub mod shell;
+pub mod trace;
 mod vm;
 
 #[cfg(test)]
@@ -0,0 +1,280 @@
+//! Parses Erlang term literals, such as the arguments given to the interpreter on the command
+//! line, into the heap of a process.
+//!
+//! Atoms, numbers, characters, strings, lists, tuples, maps and binaries of bytes and strings
+//! are supported.  Pids, references and funs don't have a literal syntax, so they can't be given.
+use std::fmt::{self, Display};
+
+use num_bigint::BigInt;
+
+use liblumen_alloc::erts::exception::system::Alloc;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{AsTerm, Atom, AtomError, Term};
+
+use crate::scan::{self, Token};
+
+#[derive(Debug)]
+pub enum LiteralError {
+    /// The source ends inside a string, quoted atom or character, or has an invalid escape
+    Scan,
+    /// Something other than `expected` was found, or nothing if the source ended
+    Syntax {
+        expected: &'static str,
+        found: Option<String>,
+    },
+    Atom(AtomError),
+    Alloc(Alloc),
+}
+
+impl Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LiteralError::Scan => write!(f, "unterminated string, atom or character"),
+            LiteralError::Syntax {
+                expected,
+                found: Some(found),
+            } => write!(f, "expected {}, but found {}", expected, found),
+            LiteralError::Syntax {
+                expected,
+                found: None,
+            } => write!(f, "expected {}, but found the end of input", expected),
+            LiteralError::Atom(error) => write!(f, "{}", error),
+            LiteralError::Alloc(alloc) => write!(f, "{:?}", alloc),
+        }
+    }
+}
+
+impl From<Alloc> for LiteralError {
+    fn from(alloc: Alloc) -> Self {
+        LiteralError::Alloc(alloc)
+    }
+}
+
+impl From<AtomError> for LiteralError {
+    fn from(error: AtomError) -> Self {
+        LiteralError::Atom(error)
+    }
+}
+
+type Result<T> = std::result::Result<T, LiteralError>;
+
+/// Parses the single term literal in `source`, such as `{ok, [1, 2.0, <<"three">>]}`, without a
+/// trailing `.`
+pub fn parse(process: &Process, source: &str) -> Result<Term> {
+    let tokens = scan::tokens(source).ok_or(LiteralError::Scan)?;
+    let mut parser = Parser {
+        process,
+        tokens,
+        index: 0,
+    };
+
+    let term = parser.term()?;
+
+    match parser.next() {
+        None => Ok(term),
+        found => Err(syntax("the end of input", found)),
+    }
+}
+
+struct Parser<'a> {
+    process: &'a Process,
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+
+        token
+    }
+
+    /// Consumes the next token if it is `punct`
+    fn next_if(&mut self, punct: &'static str) -> bool {
+        if self.tokens.get(self.index) == Some(&Token::Punct(punct)) {
+            self.index += 1;
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<()> {
+        match self.next() {
+            Some(Token::Punct(found)) if found == punct => Ok(()),
+            found => Err(syntax(punct, found)),
+        }
+    }
+
+    fn term(&mut self) -> Result<Term> {
+        match self.next() {
+            Some(Token::Name(name)) | Some(Token::QuotedAtom(name)) => {
+                let atom = Atom::try_from_str(name)?;
+
+                Ok(unsafe { atom.as_term() })
+            }
+            Some(Token::Integer(digits)) => self.integer(&digits, false),
+            Some(Token::Float(digits)) => self.float(&digits, false),
+            Some(Token::Punct("-")) => match self.next() {
+                Some(Token::Integer(digits)) => self.integer(&digits, true),
+                Some(Token::Float(digits)) => self.float(&digits, true),
+                found => Err(syntax("a number", found)),
+            },
+            Some(Token::Char(c)) => Ok(self.process.integer(c)?),
+            Some(Token::String(s)) => Ok(self.process.charlist_from_str(&s)?),
+            Some(Token::Punct("[")) => self.list(),
+            Some(Token::Punct("{")) => {
+                let elements = self.elements("}")?;
+
+                Ok(self.process.tuple_from_slice(&elements)?)
+            }
+            Some(Token::Punct("#")) => {
+                self.expect("{")?;
+                self.map()
+            }
+            Some(Token::Punct("<<")) => self.binary(),
+            found => Err(syntax("a term", found)),
+        }
+    }
+
+    fn integer(&self, digits: &str, negative: bool) -> Result<Term> {
+        let (radix, digits) = match digits.find('#') {
+            Some(index) => match digits[..index].parse() {
+                Ok(radix) if 2 <= radix && radix <= 36 => (radix, &digits[index + 1..]),
+                _ => return Err(invalid("a base from 2 to 36", digits)),
+            },
+            None => (10, digits),
+        };
+
+        match BigInt::parse_bytes(digits.as_bytes(), radix) {
+            Some(integer) if negative => Ok(self.process.integer(-integer)?),
+            Some(integer) => Ok(self.process.integer(integer)?),
+            None => Err(invalid("an integer", digits)),
+        }
+    }
+
+    fn float(&self, digits: &str, negative: bool) -> Result<Term> {
+        match digits.parse::<f64>() {
+            Ok(float) if negative => Ok(self.process.float(-float)?),
+            Ok(float) => Ok(self.process.float(float)?),
+            Err(_) => Err(invalid("a float", digits)),
+        }
+    }
+
+    /// The terms separated by `,` up to `close`
+    fn elements(&mut self, close: &'static str) -> Result<Vec<Term>> {
+        let mut elements = Vec::new();
+
+        if !self.next_if(close) {
+            loop {
+                elements.push(self.term()?);
+
+                if self.next_if(close) {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        Ok(elements)
+    }
+
+    fn list(&mut self) -> Result<Term> {
+        let mut elements = Vec::new();
+
+        if self.next_if("]") {
+            return Ok(Term::NIL);
+        }
+
+        loop {
+            elements.push(self.term()?);
+
+            if self.next_if("]") {
+                return Ok(self.process.list_from_slice(&elements)?);
+            } else if self.next_if("|") {
+                let tail = self.term()?;
+                self.expect("]")?;
+
+                return Ok(self.process.improper_list_from_slice(&elements, tail)?);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn map(&mut self) -> Result<Term> {
+        let mut entries = Vec::new();
+
+        if !self.next_if("}") {
+            loop {
+                let key = self.term()?;
+                self.expect("=>")?;
+                let value = self.term()?;
+                entries.push((key, value));
+
+                if self.next_if("}") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        Ok(self.process.map_from_slice(&entries)?)
+    }
+
+    /// A binary of bytes, characters and strings, such as `<<"abc", 0, $d>>`
+    fn binary(&mut self) -> Result<Term> {
+        let mut bytes = Vec::new();
+
+        if !self.next_if(">>") {
+            loop {
+                match self.next() {
+                    Some(Token::Integer(digits)) => match digits.parse::<u8>() {
+                        Ok(byte) => bytes.push(byte),
+                        Err(_) => return Err(invalid("a byte", &digits)),
+                    },
+                    Some(Token::Char(c)) => bytes.push(latin1(c)?),
+                    Some(Token::String(s)) => {
+                        for c in s.chars() {
+                            bytes.push(latin1(c)?);
+                        }
+                    }
+                    found => return Err(syntax("a byte or string", found)),
+                }
+
+                if self.next_if(">>") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        Ok(self.process.binary_from_bytes(&bytes)?)
+    }
+}
+
+/// Like the default `/integer` type of binary segments, characters are 8 bits
+fn latin1(c: char) -> Result<u8> {
+    let code_point = c as u32;
+
+    if code_point <= 0xFF {
+        Ok(code_point as u8)
+    } else {
+        Err(invalid("a latin1 character", &c.to_string()))
+    }
+}
+
+fn syntax(expected: &'static str, found: Option<Token>) -> LiteralError {
+    LiteralError::Syntax {
+        expected,
+        found: found.map(|token| token.to_string()),
+    }
+}
+
+fn invalid(expected: &'static str, found: &str) -> LiteralError {
+    LiteralError::Syntax {
+        expected,
+        found: Some(found.to_string()),
+    }
+}
@@ -0,0 +1,347 @@
+//! Loads compiled `.beam` files and Erlang source into the interpreter.
+//!
+//! The interpreter runs EIR, so a BEAM file is loaded from the abstract code in its debug info:
+//! the abstract code is rendered back to Erlang source, keeping the lines of the original source,
+//! and then lowered the same way as a `.erl` file.  BEAM files compiled without `debug_info` cannot
+//! be loaded.
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use libeir_diagnostics::{ColorChoice, Emitter, StandardStreamEmitter};
+
+use libeir_intern::Symbol;
+
+use libeir_ir::Module;
+
+use libeir_passes::PassManager;
+
+use libeir_syntax_erl::ast::{self, Expr, Literal, Module as ErlAstModule};
+use libeir_syntax_erl::lower_module;
+use libeir_syntax_erl::{ParseConfig, Parser};
+
+use liblumen_beam::syntax::ast::error::FromBeamError;
+use liblumen_beam::syntax::ast::printer;
+use liblumen_beam::syntax::ast::AST;
+
+use crate::module::NotPurged;
+use crate::VM;
+
+/// The prefix of the module that [mark_remote_self_calls] qualifies fully-qualified calls of a
+/// module to itself with
+const REMOTE_SELF_PREFIX: &str = "$lumen_remote:";
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// The BEAM file could not be read or does not have abstract code
+    FromBeam(FromBeamError),
+    /// The `.erl` file could not be read
+    Read(io::Error),
+    /// The source, such as that rendered from the abstract code, could not be parsed.
+    /// Diagnostics have already been emitted to stderr.
+    Parse,
+    /// The parsed module could not be lowered to EIR.  Diagnostics have already been emitted to
+    /// stderr.
+    Lower,
+    /// The module was lowered, but could not be registered because the old version of the module
+    /// has not been purged
+    NotPurged(NotPurged),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::FromBeam(error) => write!(f, "{}", error),
+            LoadError::Read(error) => write!(f, "{}", error),
+            LoadError::Parse => write!(f, "source could not be parsed"),
+            LoadError::Lower => write!(f, "source could not be lowered to EIR"),
+            LoadError::NotPurged(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<FromBeamError> for LoadError {
+    fn from(error: FromBeamError) -> Self {
+        LoadError::FromBeam(error)
+    }
+}
+
+impl From<NotPurged> for LoadError {
+    fn from(error: NotPurged) -> Self {
+        LoadError::NotPurged(error)
+    }
+}
+
+/// Loads the `.beam` file at `path` and registers its module with the [VM](crate::VM).  Any
+/// module already registered with the same name becomes the old version of the module, so, like
+/// `code:load_binary/3`, loading fails if there is an old version that has not been purged.
+pub fn register_beam_module<P: AsRef<Path>>(path: P) -> Result<(), LoadError> {
+    let module = beam_to_eir(path)?;
+    VM.modules.write().unwrap().register_erlang_module(module)?;
+
+    Ok(())
+}
+
+/// Loads the `.erl` file at `path` and registers its module with the [VM](crate::VM), the same
+/// as [register_beam_module].
+pub fn register_erl_module<P: AsRef<Path>>(path: P) -> Result<(), LoadError> {
+    let module = erl_to_eir(path)?;
+    VM.modules.write().unwrap().register_erlang_module(module)?;
+
+    Ok(())
+}
+
+/// Lowers the abstract code in the `.beam` file at `path` to an EIR module that has been through
+/// the default passes.
+pub fn beam_to_eir<P: AsRef<Path>>(path: P) -> Result<Module, LoadError> {
+    let ast = AST::from_beam_file(path)?;
+
+    ast_to_eir(&ast)
+}
+
+/// Lowers the abstract code in the contents of a `.beam` file, such as the binary passed to
+/// `code:load_binary/3`, the same as [beam_to_eir].
+pub fn beam_bytes_to_eir(bytes: &[u8]) -> Result<Module, LoadError> {
+    let ast = AST::from_beam_bytes(bytes)?;
+
+    ast_to_eir(&ast)
+}
+
+/// Lowers the Erlang source in the `.erl` file at `path`, the same as [beam_to_eir].
+pub fn erl_to_eir<P: AsRef<Path>>(path: P) -> Result<Module, LoadError> {
+    let source = fs::read_to_string(path).map_err(LoadError::Read)?;
+
+    source_to_eir(&source)
+}
+
+fn ast_to_eir(ast: &AST) -> Result<Module, LoadError> {
+    let source = printer::module_code_to_string(&ast.module);
+
+    source_to_eir(&source)
+}
+
+/// Lowers the source of an Erlang module to an EIR module that has been through the default
+/// passes.
+pub fn source_to_eir(source: &str) -> Result<Module, LoadError> {
+    let mut eir_mod = lower_source(source, ParseConfig::default())?;
+
+    for fun in eir_mod.functions.values() {
+        fun.graph_validate_global();
+    }
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    Ok(eir_mod)
+}
+
+fn lower_source(source: &str, config: ParseConfig) -> Result<Module, LoadError> {
+    let parser = Parser::new(config);
+    let emitter =
+        StandardStreamEmitter::new(ColorChoice::Auto).set_codemap(parser.config.codemap.clone());
+
+    let mut parsed: ErlAstModule = match parser.parse_string::<&str, ErlAstModule>(source) {
+        Ok(parsed) => parsed,
+        Err(errs) => {
+            for err in errs.iter() {
+                emitter.diagnostic(&err.to_diagnostic()).unwrap();
+            }
+
+            return Err(LoadError::Parse);
+        }
+    };
+
+    mark_remote_self_calls(&mut parsed);
+    let (res, messages) = lower_module(&parsed);
+
+    for err in messages.iter() {
+        emitter.diagnostic(&err.to_diagnostic()).unwrap();
+    }
+
+    res.map_err(|_| LoadError::Lower)
+}
+
+/// Qualifies the fully-qualified calls in `module` to itself, such as `?MODULE:loop()`, with
+/// [remote_self_module] of the module instead, so that they can still be told apart from local
+/// calls once lowered.
+///
+/// Like BEAM, a fully-qualified call goes to the current version of the module, while a local call
+/// stays in the version it is made in, but EIR lowers both to the same capture of the function.
+/// Calls whose module is not an atom, such as through a variable bound to the module, are remote
+/// already, as the capture reads the module at run time.
+pub fn mark_remote_self_calls(module: &mut ErlAstModule) {
+    let name = module.name.name;
+    let remote = Symbol::intern(&remote_self_module(&name.as_str()));
+
+    for function in module.functions.values_mut() {
+        mark_function_clauses(&mut function.clauses, name, remote);
+    }
+}
+
+/// The module that [mark_remote_self_calls] qualifies the fully-qualified calls of `module` to
+/// itself with
+pub fn remote_self_module(module: &str) -> String {
+    format!("{}{}", REMOTE_SELF_PREFIX, module)
+}
+
+/// The module that is called if `module` was qualified by [mark_remote_self_calls]
+pub fn unmark_remote_self_module(module: &str) -> Option<&str> {
+    if module.starts_with(REMOTE_SELF_PREFIX) {
+        Some(&module[REMOTE_SELF_PREFIX.len()..])
+    } else {
+        None
+    }
+}
+
+fn mark_function_clauses(clauses: &mut [ast::FunctionClause], module: Symbol, remote: Symbol) {
+    for clause in clauses {
+        mark_exprs(&mut clause.body, module, remote);
+    }
+}
+
+fn mark_clauses(clauses: &mut [ast::Clause], module: Symbol, remote: Symbol) {
+    for clause in clauses {
+        mark_exprs(&mut clause.body, module, remote);
+    }
+}
+
+fn mark_exprs(exprs: &mut [Expr], module: Symbol, remote: Symbol) {
+    for expr in exprs {
+        mark_expr(expr, module, remote);
+    }
+}
+
+fn mark_map_fields(fields: &mut [ast::MapField], module: Symbol, remote: Symbol) {
+    for field in fields {
+        match field {
+            ast::MapField::Assoc { value, .. } | ast::MapField::Exact { value, .. } => {
+                mark_expr(value, module, remote)
+            }
+        }
+    }
+}
+
+fn mark_record_fields(fields: &mut [ast::RecordField], module: Symbol, remote: Symbol) {
+    for field in fields {
+        if let Some(value) = &mut field.value {
+            mark_expr(value, module, remote);
+        }
+    }
+}
+
+/// Marks the calls to `module` in `expr`.  Patterns and guards are skipped, as they can't call
+/// functions of the module.
+fn mark_expr(expr: &mut Expr, module: Symbol, remote: Symbol) {
+    match expr {
+        Expr::Remote(ast::Remote {
+            module: callee_module,
+            function,
+            ..
+        }) => {
+            if let Expr::Literal(Literal::Atom(.., name)) = &mut **callee_module {
+                if name.name == module {
+                    name.name = remote;
+                }
+            } else {
+                mark_expr(callee_module, module, remote);
+            }
+            mark_expr(function, module, remote);
+        }
+        Expr::Apply(ast::Apply { callee, args, .. }) => {
+            mark_expr(callee, module, remote);
+            mark_exprs(args, module, remote);
+        }
+        Expr::Cons(ast::Cons { head, tail, .. }) => {
+            mark_expr(head, module, remote);
+            mark_expr(tail, module, remote);
+        }
+        Expr::Tuple(ast::Tuple { elements, .. }) => mark_exprs(elements, module, remote),
+        Expr::Map(ast::Map { fields, .. }) => mark_map_fields(fields, module, remote),
+        Expr::MapUpdate(ast::MapUpdate { map, updates, .. }) => {
+            mark_expr(map, module, remote);
+            mark_map_fields(updates, module, remote);
+        }
+        Expr::Binary(ast::Binary { elements, .. }) => {
+            for element in elements {
+                mark_expr(&mut element.bit_expr, module, remote);
+                if let Some(bit_size) = &mut element.bit_size {
+                    mark_expr(bit_size, module, remote);
+                }
+            }
+        }
+        Expr::Record(ast::Record { fields, .. }) => mark_record_fields(fields, module, remote),
+        Expr::RecordAccess(ast::RecordAccess { record, .. }) => mark_expr(record, module, remote),
+        Expr::RecordUpdate(ast::RecordUpdate {
+            record, updates, ..
+        }) => {
+            mark_expr(record, module, remote);
+            mark_record_fields(updates, module, remote);
+        }
+        Expr::ListComprehension(ast::ListComprehension {
+            body, qualifiers, ..
+        })
+        | Expr::BinaryComprehension(ast::BinaryComprehension {
+            body, qualifiers, ..
+        }) => {
+            mark_expr(body, module, remote);
+            mark_exprs(qualifiers, module, remote);
+        }
+        Expr::Generator(ast::Generator { expr, .. })
+        | Expr::BinaryGenerator(ast::BinaryGenerator { expr, .. }) => {
+            mark_expr(expr, module, remote)
+        }
+        Expr::Begin(ast::Begin { body, .. }) => mark_exprs(body, module, remote),
+        Expr::BinaryExpr(ast::BinaryExpr { lhs, rhs, .. }) => {
+            mark_expr(lhs, module, remote);
+            mark_expr(rhs, module, remote);
+        }
+        Expr::UnaryExpr(ast::UnaryExpr { operand, .. }) => mark_expr(operand, module, remote),
+        Expr::Match(ast::Match { expr, .. }) | Expr::Catch(ast::Catch { expr, .. }) => {
+            mark_expr(expr, module, remote)
+        }
+        Expr::If(ast::If { clauses, .. }) => {
+            for clause in clauses {
+                mark_exprs(&mut clause.body, module, remote);
+            }
+        }
+        Expr::Case(ast::Case { expr, clauses, .. }) => {
+            mark_expr(expr, module, remote);
+            mark_clauses(clauses, module, remote);
+        }
+        Expr::Receive(ast::Receive { clauses, after, .. }) => {
+            if let Some(clauses) = clauses {
+                mark_clauses(clauses, module, remote);
+            }
+            if let Some(after) = after {
+                mark_expr(&mut after.timeout, module, remote);
+                mark_exprs(&mut after.body, module, remote);
+            }
+        }
+        Expr::Try(ast::Try {
+            exprs,
+            clauses,
+            catch_clauses,
+            after,
+            ..
+        }) => {
+            mark_exprs(exprs, module, remote);
+            if let Some(clauses) = clauses {
+                mark_clauses(clauses, module, remote);
+            }
+            if let Some(catch_clauses) = catch_clauses {
+                for clause in catch_clauses {
+                    mark_exprs(&mut clause.body, module, remote);
+                }
+            }
+            if let Some(after) = after {
+                mark_exprs(after, module, remote);
+            }
+        }
+        Expr::Fun(ast::Function::Named(ast::NamedFunction { clauses, .. }))
+        | Expr::Fun(ast::Function::Unnamed(ast::Lambda { clauses, .. })) => {
+            mark_function_clauses(clauses, module, remote)
+        }
+        _ => (),
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt::{self, Display};
 use std::sync::Arc;
 
 use libeir_ir::{Function, LiveValues, Module};
@@ -8,40 +9,112 @@ use liblumen_alloc::erts::process::code::Result;
 use liblumen_alloc::erts::process::Process;
 use liblumen_alloc::erts::term::{Atom, Term};
 
-macro_rules! trace {
-    ($($t:tt)*) => (lumen_runtime::system::io::puts(&format_args!($($t)*).to_string()))
-}
-//macro_rules! trace {
-//    ($($t:tt)*) => ()
-//}
-
 pub enum ResolvedFunction<'a> {
     Native(NativeFunctionKind),
     Erlang(&'a ErlangFunction),
 }
 
+/// The modules that can be called.
+///
+/// Like BEAM, there can be two versions of an Erlang module: the current version and the old
+/// version it replaced.  Fully-qualified calls always go to the current version, while local
+/// calls and the continuations of running code stay in the version they were made in until the
+/// old version is purged.
 pub struct ModuleRegistry {
     map: HashMap<Atom, ModuleType>,
+    /// The old version of the Erlang modules that were replaced or deleted, but not purged yet
+    old: HashMap<Atom, ErlangModule>,
+    next_version: usize,
 }
 
 impl ModuleRegistry {
     pub fn new() -> Self {
         ModuleRegistry {
             map: HashMap::new(),
+            old: HashMap::new(),
+            next_version: 0,
         }
     }
 
-    pub fn register_erlang_module(&mut self, module: Module) {
-        let erl_module = ErlangModule::from_eir(module);
-        match self.map.remove(&erl_module.name) {
-            None => self
-                .map
-                .insert(erl_module.name, ModuleType::Erlang(erl_module)),
-            Some(ModuleType::Native(native)) => self
-                .map
-                .insert(erl_module.name, ModuleType::Overlayed(erl_module, native)),
-            _ => panic!(),
+    /// Registers `module` as the current version.  The current version, if any, becomes the old
+    /// version, so, like `code:load_binary/3`, any old version has to be purged first.
+    pub fn register_erlang_module(&mut self, module: Module) -> std::result::Result<(), NotPurged> {
+        let name = Atom::try_from_str(module.name.as_str()).unwrap();
+        if self.has_old_code(name) {
+            return Err(NotPurged(name));
+        }
+
+        let version = self.next_version;
+        self.next_version += 1;
+
+        let erl_module = ErlangModule::from_eir(module, version);
+        let (current, native) = self.take(name);
+
+        if let Some(current) = current {
+            self.old.insert(name, current);
+        }
+
+        self.put(name, Some(erl_module), native);
+
+        Ok(())
+    }
+
+    /// Makes the current version of `module` the old version, so that there is no current
+    /// version to call.
+    ///
+    /// Returns `false` if there is no current version or if the old version must be purged first.
+    pub fn delete(&mut self, module: Atom) -> bool {
+        if self.has_old_code(module) {
+            return false;
+        }
+
+        let (current, native) = self.take(module);
+        let deleted = match current {
+            Some(current) => {
+                self.old.insert(module, current);
+
+                true
+            }
+            None => false,
+        };
+
+        self.put(module, None, native);
+
+        deleted
+    }
+
+    /// Removes the old version of `module`.  Returns `false` if there was no old version.
+    pub fn purge(&mut self, module: Atom) -> bool {
+        self.old.remove(&module).is_some()
+    }
+
+    pub fn has_old_code(&self, module: Atom) -> bool {
+        self.old.contains_key(&module)
+    }
+
+    /// The version of the old version of `module`, if any
+    pub fn old_version(&self, module: Atom) -> Option<usize> {
+        self.old.get(&module).map(|old| old.version)
+    }
+
+    fn take(&mut self, module: Atom) -> (Option<ErlangModule>, Option<NativeModule>) {
+        match self.map.remove(&module) {
+            None => (None, None),
+            Some(ModuleType::Erlang(erl)) => (Some(erl), None),
+            Some(ModuleType::Overlayed(erl, nat)) => (Some(erl), Some(nat)),
+            Some(ModuleType::Native(nat)) => (None, Some(nat)),
+        }
+    }
+
+    fn put(&mut self, module: Atom, erl: Option<ErlangModule>, nat: Option<NativeModule>) {
+        let module_type = match (erl, nat) {
+            (None, None) => return,
+            (Some(erl), None) => ModuleType::Erlang(erl),
+            (Some(erl), Some(nat)) => ModuleType::Overlayed(erl, nat),
+            (None, Some(nat)) => ModuleType::Native(nat),
         };
+
+        self.map.insert(module, module_type);
     }
 
     pub fn register_native_module(&mut self, native: NativeModule) {
@@ -60,7 +133,6 @@ impl ModuleRegistry {
         function: Atom,
         arity: usize,
     ) -> Option<ResolvedFunction> {
-        trace!("LOOKUP {}:{}/{}", module, function, arity);
         match self.map.get(&module) {
             None => None,
             Some(ModuleType::Erlang(erl)) => erl
@@ -83,6 +155,52 @@ impl ModuleRegistry {
             }
         }
     }
+
+    /// Looks up a function in `version` of `module`, such as for a local call, instead of in the
+    /// current version.  Native functions are not versioned, so they are always found.
+    pub fn lookup_function_version(
+        &self,
+        module: Atom,
+        function: Atom,
+        arity: usize,
+        version: usize,
+    ) -> Option<ResolvedFunction> {
+        let erl = match self.map.get(&module) {
+            Some(ModuleType::Native(nat)) | Some(ModuleType::Overlayed(_, nat))
+                if nat.functions.contains_key(&(function, arity)) =>
+            {
+                return nat
+                    .functions
+                    .get(&(function, arity))
+                    .cloned()
+                    .map(ResolvedFunction::Native)
+            }
+            Some(ModuleType::Erlang(erl)) | Some(ModuleType::Overlayed(erl, _))
+                if erl.version == version =>
+            {
+                erl
+            }
+            _ => self.old.get(&module).filter(|old| old.version == version)?,
+        };
+
+        erl.functions
+            .get(&(function, arity))
+            .map(ResolvedFunction::Erlang)
+    }
+}
+
+/// A module could not be registered because its old version has not been purged
+#[derive(Debug)]
+pub struct NotPurged(pub Atom);
+
+impl Display for NotPurged {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the old version of {} has to be purged before it can be loaded again",
+            self.0.name()
+        )
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -127,15 +245,20 @@ impl NativeModule {
 pub struct ErlangFunction {
     pub fun: Function,
     pub live: LiveValues,
+    /// The version of the module the function is in
+    pub version: usize,
 }
 
 pub struct ErlangModule {
     pub name: Atom,
     pub functions: HashMap<(Atom, usize), ErlangFunction>,
+    /// Unique for every module loaded, so that closures can tell which version of a module they
+    /// were made in
+    pub version: usize,
 }
 
 impl ErlangModule {
-    pub fn from_eir(module: Module) -> Self {
+    pub fn from_eir(module: Module, version: usize) -> Self {
         let name_atom = Atom::try_from_str(module.name.as_str()).unwrap();
         let functions = module
             .functions
@@ -144,6 +267,7 @@ impl ErlangModule {
                 let nfun = ErlangFunction {
                     live: fun.live_values(),
                     fun: fun.clone(),
+                    version,
                 };
                 let name = Atom::try_from_str(fun.ident().name.as_str()).unwrap();
                 ((name, fun.ident().arity), nfun)
@@ -152,6 +276,7 @@ impl ErlangModule {
         ErlangModule {
             name: name_atom,
             functions,
+            version,
         }
     }
 }
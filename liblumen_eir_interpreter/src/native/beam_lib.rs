@@ -0,0 +1,59 @@
+use liblumen_alloc::erts::term::Atom;
+
+use lumen_runtime::otp::beam_lib;
+
+use crate::module::NativeModule;
+
+pub fn make_beam_lib() -> NativeModule {
+    let mut native = NativeModule::new(Atom::try_from_str("beam_lib").unwrap());
+
+    native.add_simple(
+        Atom::try_from_str("all_chunks").unwrap(),
+        1,
+        |proc, args| beam_lib::all_chunks_1(args[0], proc),
+    );
+
+    native.add_simple(Atom::try_from_str("chunks").unwrap(), 2, |proc, args| {
+        beam_lib::chunks_2(args[0], args[1], proc)
+    });
+
+    native.add_simple(Atom::try_from_str("chunks").unwrap(), 3, |proc, args| {
+        beam_lib::chunks_3(args[0], args[1], args[2], proc)
+    });
+
+    native.add_simple(Atom::try_from_str("cmp").unwrap(), 2, |proc, args| {
+        beam_lib::cmp_2(args[0], args[1], proc)
+    });
+
+    native.add_simple(Atom::try_from_str("diff_dirs").unwrap(), 2, |proc, args| {
+        beam_lib::diff_dirs_2(args[0], args[1], proc)
+    });
+
+    native.add_simple(Atom::try_from_str("info").unwrap(), 1, |proc, args| {
+        beam_lib::info_1(args[0], proc)
+    });
+
+    native.add_simple(Atom::try_from_str("md5").unwrap(), 1, |proc, args| {
+        beam_lib::md5_1(args[0], proc)
+    });
+
+    native.add_simple(Atom::try_from_str("strip").unwrap(), 1, |proc, args| {
+        beam_lib::strip_1(args[0], proc)
+    });
+
+    native.add_simple(Atom::try_from_str("strip").unwrap(), 2, |proc, args| {
+        beam_lib::strip_2(args[0], args[1], proc)
+    });
+
+    native.add_simple(
+        Atom::try_from_str("strip_files").unwrap(),
+        1,
+        |proc, args| beam_lib::strip_files_1(args[0], proc),
+    );
+
+    native.add_simple(Atom::try_from_str("version").unwrap(), 1, |proc, args| {
+        beam_lib::version_1(args[0], proc)
+    });
+
+    native
+}
@@ -0,0 +1,219 @@
+//! Loads, deletes and purges the versions of Erlang modules in the [VM](crate::VM).
+use std::collections::HashSet;
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use liblumen_alloc::erts::exception::Exception;
+use liblumen_alloc::erts::process::{Process, StackPrimitives};
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Closure, Pid, Term, TypedTerm};
+use liblumen_alloc::exit;
+
+use lumen_runtime::registry;
+use lumen_runtime::scheduler::Scheduled;
+
+use crate::load;
+use crate::module::{NativeModule, NotPurged};
+use crate::VM;
+
+pub fn make_code() -> NativeModule {
+    let mut native = NativeModule::new(Atom::try_from_str("code").unwrap());
+
+    native.add_simple(Atom::try_from_str("delete").unwrap(), 1, |_proc, args| {
+        let module: Atom = args[0].try_into()?;
+
+        Ok(VM.modules.write().unwrap().delete(module).into())
+    });
+
+    native.add_simple(
+        Atom::try_from_str("load_binary").unwrap(),
+        3,
+        |proc, args| load_binary_3(proc, args[0], args[2]),
+    );
+
+    native.add_simple(Atom::try_from_str("purge").unwrap(), 1, |proc, args| {
+        purge_1(proc, args[0])
+    });
+
+    native.add_simple(
+        Atom::try_from_str("soft_purge").unwrap(),
+        1,
+        |proc, args| soft_purge_1(proc, args[0]),
+    );
+
+    native
+}
+
+/// `erlang:check_process_code/2`
+pub fn check_process_code_2(
+    proc: &Arc<Process>,
+    pid: Term,
+    module: Term,
+) -> Result<Term, Exception> {
+    let pid: Pid = pid.try_into()?;
+    let module: Atom = module.try_into()?;
+
+    let old_version = VM.modules.read().unwrap().old_version(module);
+    let runs_old_code = match (old_version, registry::pid_to_self_or_process(pid, proc)) {
+        (Some(version), Some(arc_process)) => runs_version(&arc_process, module, version),
+        _ => false,
+    };
+
+    Ok(runs_old_code.into())
+}
+
+/// The `.beam` file contents in `binary` are loaded as the current version of `module`.  The
+/// file name is not used, as modules are not reloaded from disk.
+fn load_binary_3(proc: &Arc<Process>, module: Term, binary: Term) -> Result<Term, Exception> {
+    let module_atom: Atom = module.try_into()?;
+    let bytes = proc.bytes_from_binary(binary)?;
+
+    let eir_module = match load::beam_bytes_to_eir(bytes) {
+        Ok(eir_module) if &*eir_module.name.as_str() == module_atom.name() => eir_module,
+        _ => return error_tuple(proc, "badfile"),
+    };
+
+    let registered = VM
+        .modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_module);
+    match registered {
+        Ok(()) => Ok(proc.tuple_from_slice(&[atom_unchecked("module"), module])?),
+        Err(NotPurged(_)) => error_tuple(proc, "not_purged"),
+    }
+}
+
+fn purge_1(proc: &Arc<Process>, module: Term) -> Result<Term, Exception> {
+    let module: Atom = module.try_into()?;
+
+    Ok(purge(proc, module).into())
+}
+
+fn soft_purge_1(proc: &Arc<Process>, module: Term) -> Result<Term, Exception> {
+    let module: Atom = module.try_into()?;
+
+    Ok(soft_purge(proc, module, &[]).into())
+}
+
+/// `code:purge/1`: kills the processes running the old version of `module` and then removes it.
+/// Returns whether any process was killed.
+///
+/// `proc` is never killed, even if it runs the old version.
+pub fn purge(proc: &Arc<Process>, module: Atom) -> bool {
+    let mut modules = VM.modules.write().unwrap();
+    let mut killed = false;
+
+    if let Some(version) = modules.old_version(module) {
+        for arc_process in registry::processes() {
+            if !Arc::ptr_eq(&arc_process, proc)
+                && !arc_process.is_exiting()
+                && runs_version(&arc_process, module, version)
+            {
+                kill(&arc_process);
+                killed = true;
+            }
+        }
+
+        modules.purge(module);
+    }
+
+    killed
+}
+
+/// `code:soft_purge/1`: removes the old version of `module` only if no process other than `proc`
+/// is running it and none of `terms`, which are kept outside of any process, refers to it.
+/// Returns whether the old version, if any, was removed.
+pub fn soft_purge(proc: &Arc<Process>, module: Atom, terms: &[Term]) -> bool {
+    let mut modules = VM.modules.write().unwrap();
+    let lingering = match modules.old_version(module) {
+        Some(version) => {
+            registry::processes().iter().any(|arc_process| {
+                !Arc::ptr_eq(arc_process, proc)
+                    && !arc_process.is_exiting()
+                    && runs_version(arc_process, module, version)
+            }) || terms.iter().any(|term| {
+                references_version(
+                    term.to_typed_term().unwrap(),
+                    module,
+                    version,
+                    &mut HashSet::new(),
+                )
+            })
+        }
+        None => false,
+    };
+
+    if !lingering {
+        modules.purge(module);
+    }
+
+    !lingering
+}
+
+fn error_tuple(proc: &Arc<Process>, reason: &str) -> Result<Term, Exception> {
+    Ok(proc.tuple_from_slice(&[atom_unchecked("error"), atom_unchecked(reason)])?)
+}
+
+/// Exits `arc_process` with `killed`, the same as `exit(Pid, kill)`
+fn kill(arc_process: &Arc<Process>) {
+    arc_process.exception(exit!(atom_unchecked("killed")));
+
+    // A waiting process has to be scheduled again to exit
+    if let Some(scheduler) = arc_process.scheduler() {
+        scheduler.stop_waiting(arc_process);
+    }
+}
+
+/// Whether `version` of `module` is still used by `arc_process`: whether a closure that continues
+/// or calls into it is reachable from the stack of `arc_process`.
+fn runs_version(arc_process: &Process, module: Atom, version: usize) -> bool {
+    let mut heap = arc_process.acquire_heap();
+    let stack: Vec<Term> = (1..=heap.stack_used())
+        .filter_map(|slot| heap.stack_slot(slot))
+        .collect();
+
+    let mut visited = HashSet::new();
+
+    stack.into_iter().any(|term| {
+        references_version(term.to_typed_term().unwrap(), module, version, &mut visited)
+    })
+}
+
+fn references_version(
+    typed_term: TypedTerm,
+    module: Atom,
+    version: usize,
+    visited: &mut HashSet<*const Closure>,
+) -> bool {
+    match typed_term {
+        TypedTerm::Boxed(boxed) => {
+            references_version(boxed.to_typed_term().unwrap(), module, version, visited)
+        }
+        TypedTerm::Closure(closure) => {
+            if !visited.insert(&*closure as *const Closure) {
+                return false;
+            }
+
+            // Interpreted closures have the version of their module as the first element of
+            // their environment, except for captures of functions in other modules
+            let env = closure.env_slice();
+            let closure_version: Option<usize> =
+                env.get(0).and_then(|term| (*term).try_into().ok());
+
+            (closure.module_function_arity().module == module && closure_version == Some(version))
+                || env.iter().any(|term| {
+                    references_version(term.to_typed_term().unwrap(), module, version, visited)
+                })
+        }
+        TypedTerm::Tuple(tuple) => tuple.iter().any(|element| {
+            references_version(element.to_typed_term().unwrap(), module, version, visited)
+        }),
+        TypedTerm::List(cons) => cons.into_iter().any(|result| match result {
+            Ok(element) => {
+                references_version(element.to_typed_term().unwrap(), module, version, visited)
+            }
+            Err(_) => false,
+        }),
+        _ => false,
+    }
+}
@@ -1,39 +1,138 @@
-use liblumen_alloc::erts::term::{atom_unchecked, Atom, TypedTerm};
+use liblumen_alloc::erts::exception;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Term, TypedTerm};
 use liblumen_alloc::erts::ModuleFunctionArity;
-use lumen_runtime::otp::erlang;
+use liblumen_alloc::exit;
+use lumen_runtime::otp::{erlang, maps};
 
 use crate::module::NativeModule;
+use crate::trace::{self, Event};
+
+use super::io;
 
 pub fn make_erlang() -> NativeModule {
     let mut native = NativeModule::new(Atom::try_from_str("erlang").unwrap());
 
-    native.add_simple(Atom::try_from_str("*").unwrap(), 2, |proc, args| {
-        erlang::multiply_2(args[0], args[1], proc)
-    });
-
-    native.add_simple(Atom::try_from_str("/").unwrap(), 2, |proc, args| {
-        erlang::div_2(args[0], args[1], proc)
-    });
-
-    native.add_simple(Atom::try_from_str("<").unwrap(), 2, |_proc, args| {
-        Ok(erlang::is_less_than_2(args[0], args[1]))
-    });
-    native.add_simple(Atom::try_from_str(">").unwrap(), 2, |_proc, args| {
-        Ok(erlang::is_greater_than_2(args[0], args[1]))
-    });
-    native.add_simple(Atom::try_from_str("=<").unwrap(), 2, |_proc, args| {
-        Ok(erlang::is_equal_or_less_than_2(args[0], args[1]))
-    });
-    native.add_simple(Atom::try_from_str(">=").unwrap(), 2, |_proc, args| {
-        Ok(erlang::is_greater_than_or_equal_2(args[0], args[1]))
-    });
-    native.add_simple(Atom::try_from_str("==").unwrap(), 2, |_proc, args| {
-        Ok(erlang::are_equal_after_conversion_2(args[0], args[1]))
-    });
-    native.add_simple(Atom::try_from_str("=:=").unwrap(), 2, |_proc, args| {
-        Ok(erlang::are_exactly_equal_2(args[0], args[1]))
+    bifs!(native, {
+        "abs" => erlang::abs_1(number; process),
+        "and" => erlang::and_2(left, right),
+        "append_element" => erlang::append_element_2(tuple, element; process),
+        "==" => erlang::are_equal_after_conversion_2(left, right),
+        "=:=" => erlang::are_exactly_equal_2(left, right),
+        "=/=" => erlang::are_exactly_not_equal_2(left, right),
+        "/=" => erlang::are_not_equal_after_conversion_2(left, right),
+        "atom_to_binary" => erlang::atom_to_binary_2(atom, encoding; process),
+        "atom_to_list" => erlang::atom_to_list_1(atom; process),
+        "band" => erlang::band_2(left, right; process),
+        "binary_part" => erlang::binary_part_2(binary, start_length; process),
+        "binary_part" => erlang::binary_part_3(binary, start, length; process),
+        "binary_to_atom" => erlang::binary_to_atom_2(binary, encoding),
+        "binary_to_existing_atom" => erlang::binary_to_existing_atom_2(binary, encoding),
+        "binary_to_float" => erlang::binary_to_float_1(binary; process),
+        "binary_to_integer" => erlang::binary_to_integer_1(binary; process),
+        "binary_to_integer" => erlang::binary_to_integer_2(binary, base; process),
+        "binary_to_list" => erlang::binary_to_list_1(binary; process),
+        "binary_to_list" => erlang::binary_to_list_3(binary, start, stop; process),
+        "binary_to_term" => erlang::binary_to_term_1(binary; process),
+        "binary_to_term" => erlang::binary_to_term_2(binary, options; process),
+        "bit_size" => erlang::bit_size_1(bitstring; process),
+        "bitstring_to_list" => erlang::bitstring_to_list_1(bitstring; process),
+        "bnot" => erlang::bnot_1(integer; process),
+        "bor" => erlang::bor_2(left, right; process),
+        "bsl" => erlang::bsl_2(integer, shift; process),
+        "bsr" => erlang::bsr_2(integer, shift; process),
+        "bxor" => erlang::bxor_2(left, right; process),
+        "byte_size" => erlang::byte_size_1(bitstring; process),
+        "cancel_timer" => erlang::cancel_timer_1(reference; process),
+        "cancel_timer" => erlang::cancel_timer_2(reference, options; process),
+        "ceil" => erlang::ceil_1(number; process),
+        "++" => erlang::concatenate_2(list, term; process),
+        "convert_time_unit" => erlang::convert_time_unit_3::native(process; time, from, to),
+        "delete_element" => erlang::delete_element_2(index, tuple; process),
+        "demonitor" => erlang::demonitor_2::native(process; reference, options),
+        "div" => erlang::div_2(dividend, divisor; process),
+        "/" => erlang::divide_2(dividend, divisor; process),
+        "element" => erlang::element_2(index, tuple),
+        "error" => erlang::error_1(reason),
+        "error" => erlang::error_2(reason, arguments),
+        "hd" => erlang::hd_1(list),
+        "insert_element" => erlang::insert_element_3(index, tuple, element; process),
+        "is_alive" => erlang::is_alive_0(),
+        "is_atom" => erlang::is_atom_1(term),
+        "is_binary" => erlang::is_binary_1(term),
+        "is_bitstring" => erlang::is_bitstring_1(term),
+        "is_boolean" => erlang::is_boolean_1(term),
+        "=<" => erlang::is_equal_or_less_than_2(left, right),
+        "is_float" => erlang::is_float_1(term),
+        "is_function" => erlang::is_function_1(term),
+        "is_function" => erlang::is_function_2::native(term, arity),
+        ">" => erlang::is_greater_than_2(left, right),
+        ">=" => erlang::is_greater_than_or_equal_2(left, right),
+        "is_integer" => erlang::is_integer_1(term),
+        "<" => erlang::is_less_than_2(left, right),
+        "is_list" => erlang::is_list_1(term),
+        "is_map" => erlang::is_map_1(term),
+        "is_map_key" => maps::is_key_2::native(process; key, map),
+        "is_number" => erlang::is_number_1(term),
+        "is_pid" => erlang::is_pid_1(term),
+        "is_record" => erlang::is_record_2(term, record_tag),
+        "is_record" => erlang::is_record_3(term, record_tag, size),
+        "is_reference" => erlang::is_reference_1(term),
+        "is_tuple" => erlang::is_tuple_1(term),
+        "length" => erlang::length_1(list; process),
+        "link" => erlang::link_1::native(process; pid_or_port),
+        "list_to_atom" => erlang::list_to_atom_1(string),
+        "list_to_binary" => erlang::list_to_binary_1(iolist; process),
+        "list_to_bitstring" => erlang::list_to_bitstring_1(iolist; process),
+        "list_to_existing_atom" => erlang::list_to_existing_atom_1(string),
+        "list_to_pid" => erlang::list_to_pid_1(string; process),
+        "list_to_tuple" => erlang::list_to_tuple_1(list; process),
+        "make_ref" => erlang::make_ref_0(; process),
+        "map_get" => erlang::map_get_2(key, map; process),
+        "map_size" => erlang::map_size_1(map; process),
+        "max" => erlang::max_2(left, right),
+        "min" => erlang::min_2(left, right),
+        "monitor" => erlang::monitor_2::native(process; kind, item),
+        "monotonic_time" => erlang::monotonic_time_0::native(process;),
+        "monotonic_time" => erlang::monotonic_time_1(unit; process),
+        "*" => erlang::multiply_2(multiplier, multiplicand; process),
+        "-" => erlang::negate_1(number; process),
+        "-" => erlang::subtract_2::native(process; minuend, subtrahend),
+        "+" => erlang::number_or_badarith_1::native(number),
+        "+" => erlang::add_2::native(process; augend, addend),
+        "node" => erlang::node_0(),
+        "not" => erlang::not_1(boolean),
+        "or" => erlang::or_2(left, right),
+        "process_flag" => erlang::process_flag_2::native(process; flag, value),
+        "process_info" => erlang::process_info_2::native(process; pid, item),
+        "raise" => erlang::raise_3(class, reason, stacktrace),
+        "read_timer" => erlang::read_timer_1(reference; process),
+        "read_timer" => erlang::read_timer_2(reference, options; process),
+        "register" => erlang::register_2(name, pid_or_port; arc_process),
+        "registered" => erlang::registered_0(; process),
+        "rem" => erlang::rem_2(dividend, divisor; process),
+        "send_after" => erlang::send_after_3(time, destination, message; arc_process),
+        "send_after" => erlang::send_after_4(time, destination, message, options; arc_process),
+        "setelement" => erlang::setelement_3(index, tuple, value; process),
+        "size" => erlang::size_1(binary_or_tuple; process),
+        "split_binary" => erlang::split_binary_2(binary, position; process),
+        "start_timer" => erlang::start_timer_3(time, destination, message; arc_process),
+        "start_timer" => erlang::start_timer_4(time, destination, message, options; arc_process),
+        "--" => erlang::subtract_list_2(minuend, subtrahend; process),
+        "term_to_binary" => erlang::term_to_binary_1(term; process),
+        "term_to_binary" => erlang::term_to_binary_2(term, options; process),
+        "throw" => erlang::throw_1(reason),
+        "tl" => erlang::tl_1(list),
+        "tuple_size" => erlang::tuple_size_1(tuple; process),
+        "tuple_to_list" => erlang::tuple_to_list_1(tuple; process),
+        "unlink" => erlang::unlink_1::native(process; pid_or_port),
+        "unregister" => erlang::unregister_1(name),
+        "whereis" => erlang::whereis_1(name),
+        "xor" => erlang::xor_2(left, right),
     });
 
+    // The BIFs that are not in the runtime, or that need the interpreter, such as to spawn
+    // interpreted functions or to trace
     native.add_simple(Atom::try_from_str("spawn_opt").unwrap(), 4, |proc, args| {
         match args[3].to_typed_term().unwrap() {
             TypedTerm::List(cons) => {
@@ -61,26 +160,13 @@ pub fn make_erlang() -> NativeModule {
         let inner_args = proc.cons(ret, proc.cons(ret, args[2])?)?;
 
         let res = erlang::spawn_link_3::native(proc, args[0], args[1], inner_args)?;
+        spawned(proc, res, args);
+
         Ok(res)
     });
 
     native.add_simple(Atom::try_from_str("spawn").unwrap(), 3, |proc, args| {
-        let ret = {
-            let mfa = ModuleFunctionArity {
-                module: Atom::try_from_str("lumen_eir_interpreter_intrinsics").unwrap(),
-                function: Atom::try_from_str("return_clean").unwrap(),
-                arity: 1,
-            };
-            proc.closure_with_env_from_slice(
-                mfa.into(),
-                crate::code::return_clean,
-                proc.pid_term(),
-                &[],
-            )?
-        };
-
-        let inner_args = proc.cons(ret, proc.cons(ret, args[2])?)?;
-        erlang::spawn_3::native(proc, args[0], args[1], inner_args)
+        spawn(proc, args[0], args[1], args[2])
     });
 
     native.add_simple(
@@ -102,104 +188,49 @@ pub fn make_erlang() -> NativeModule {
             };
 
             let inner_args = proc.cons(ret, proc.cons(ret, args[2])?)?;
-            erlang::spawn_link_3::native(proc, args[0], args[1], inner_args)
+            let res = erlang::spawn_link_3::native(proc, args[0], args[1], inner_args)?;
+            spawned(proc, res, args);
+
+            Ok(res)
         },
     );
 
     native.add_simple(Atom::try_from_str("exit").unwrap(), 1, |_proc, args| {
-        panic!("{:?}", args[0]);
-        //Ok(erlang::exit_1::native(args[0]).unwrap())
+        Err(exit!(args[0]).into())
     });
 
-    native.add_simple(Atom::try_from_str("monitor").unwrap(), 2, |proc, args| {
-        erlang::monitor_2::native(proc, args[0], args[1])
-    });
-    native.add_simple(Atom::try_from_str("demonitor").unwrap(), 2, |proc, args| {
-        erlang::demonitor_2::native(proc, args[0], args[1])
-    });
-
-    native.add_simple(Atom::try_from_str("register").unwrap(), 2, |proc, args| {
-        erlang::register_2(args[0], args[1], proc.clone())
-    });
     native.add_simple(
-        Atom::try_from_str("process_flag").unwrap(),
+        Atom::try_from_str("check_process_code").unwrap(),
         2,
-        |proc, args| erlang::process_flag_2::native(proc, args[0], args[1]),
+        |proc, args| super::code::check_process_code_2(proc, args[0], args[1]),
     );
 
     native.add_simple(Atom::try_from_str("send").unwrap(), 2, |proc, args| {
+        trace_send(proc, args);
         erlang::send_2(args[0], args[1], proc)
     });
     native.add_simple(Atom::try_from_str("send").unwrap(), 3, |proc, args| {
-        erlang::send_2(args[0], args[1], proc)
+        trace_send(proc, args);
+        erlang::send_3(args[0], args[1], args[2], proc)
     });
     native.add_simple(Atom::try_from_str("!").unwrap(), 2, |proc, args| {
+        trace_send(proc, args);
         erlang::send_2(args[0], args[1], proc)
     });
 
-    native.add_simple(Atom::try_from_str("-").unwrap(), 2, |proc, args| {
-        erlang::subtract_2::native(proc, args[0], args[1])
-    });
-
-    native.add_simple(Atom::try_from_str("+").unwrap(), 2, |proc, args| {
-        erlang::add_2::native(proc, args[0], args[1])
-    });
-
     native.add_simple(Atom::try_from_str("self").unwrap(), 0, |proc, _args| {
         Ok(proc.pid_term())
     });
 
     native.add_simple(
-        Atom::try_from_str("is_integer").unwrap(),
-        1,
-        |_proc, args| {
-            assert!(args.len() == 1);
-            Ok(erlang::is_integer_1(args[0]))
-        },
-    );
-    native.add_simple(Atom::try_from_str("is_list").unwrap(), 1, |_proc, args| {
-        Ok(erlang::is_list_1(args[0]))
-    });
-    native.add_simple(
-        Atom::try_from_str("is_binary").unwrap(),
-        1,
-        |_proc, args| Ok(erlang::is_binary_1(args[0])),
-    );
-    native.add_simple(Atom::try_from_str("is_atom").unwrap(), 1, |_proc, args| {
-        Ok(erlang::is_atom_1(args[0]))
-    });
-    native.add_simple(Atom::try_from_str("is_pid").unwrap(), 1, |_proc, args| {
-        Ok(erlang::is_pid_1(args[0]))
-    });
-    native.add_simple(
-        Atom::try_from_str("is_function").unwrap(),
-        1,
-        |_proc, args| Ok(erlang::is_function_1(args[0])),
+        Atom::try_from_str("group_leader").unwrap(),
+        0,
+        |proc, _args| io::group_leader_0(proc),
     );
     native.add_simple(
-        Atom::try_from_str("is_function").unwrap(),
+        Atom::try_from_str("group_leader").unwrap(),
         2,
-        |_proc, args| Ok(erlang::is_function_1(args[0])),
-    );
-    native.add_simple(Atom::try_from_str("is_tuple").unwrap(), 1, |_proc, args| {
-        Ok(erlang::is_tuple_1(args[0]))
-    });
-    native.add_simple(Atom::try_from_str("is_map").unwrap(), 1, |_proc, args| {
-        Ok(erlang::is_map_1(args[0]))
-    });
-    native.add_simple(
-        Atom::try_from_str("is_bitstring").unwrap(),
-        1,
-        |_proc, args| Ok(erlang::is_bitstring_1(args[0])),
-    );
-    native.add_simple(Atom::try_from_str("is_float").unwrap(), 1, |_proc, args| {
-        Ok(erlang::is_bitstring_1(args[0]))
-    });
-
-    native.add_simple(
-        Atom::try_from_str("monotonic_time").unwrap(),
-        0,
-        |proc, _args| erlang::monotonic_time_0::native(proc),
+        |_proc, args| io::group_leader_2(args[0], args[1]),
     );
 
     native.add_yielding(Atom::try_from_str("apply").unwrap(), 3, |proc, args| {
@@ -212,21 +243,9 @@ pub fn make_erlang() -> NativeModule {
         crate::code::apply(proc)
     });
 
-    native.add_simple(Atom::try_from_str("node").unwrap(), 0, |_proc, _args| {
-        Ok(erlang::node_0())
-    });
     native.add_simple(Atom::try_from_str("node").unwrap(), 1, |_proc, _args| {
         Ok(atom_unchecked("nonode@nohost"))
     });
-    native.add_simple(Atom::try_from_str("whereis").unwrap(), 1, |_proc, args| {
-        erlang::whereis_1(args[0])
-    });
-
-    native.add_simple(
-        Atom::try_from_str("process_info").unwrap(),
-        2,
-        |proc, args| erlang::process_info_2::native(proc, args[0], args[1]),
-    );
 
     native.add_simple(Atom::try_from_str("get").unwrap(), 1, |proc, args| {
         Ok(proc.get(args[0]))
@@ -235,15 +254,60 @@ pub fn make_erlang() -> NativeModule {
         Ok(proc.put(args[0], args[1])?)
     });
 
-    native.add_simple(
-        Atom::try_from_str("convert_time_unit").unwrap(),
-        3,
-        |proc, args| erlang::convert_time_unit_3::native(proc, args[0], args[1], args[2]),
+    native
+}
+
+/// `args` are the arguments of `erlang:send/2,3` or `erlang:'!'/2`
+fn trace_send(proc: &Process, args: &[Term]) {
+    trace::emit(
+        proc,
+        Event::Send {
+            destination: args[0],
+            message: args[1],
+        },
     );
+}
 
-    native.add_simple(Atom::try_from_str("element").unwrap(), 2, |_proc, args| {
-        erlang::element_2(args[0], args[1])
-    });
+/// Spawns a process that runs `apply(module, function, arguments)` and ends when it returns
+pub(super) fn spawn(
+    proc: &Process,
+    module: Term,
+    function: Term,
+    arguments: Term,
+) -> exception::Result {
+    let ret = {
+        let mfa = ModuleFunctionArity {
+            module: Atom::try_from_str("lumen_eir_interpreter_intrinsics").unwrap(),
+            function: Atom::try_from_str("return_clean").unwrap(),
+            arity: 1,
+        };
+        proc.closure_with_env_from_slice(
+            mfa.into(),
+            crate::code::return_clean,
+            proc.pid_term(),
+            &[],
+        )?
+    };
 
-    native
+    let inner_args = proc.cons(ret, proc.cons(ret, arguments)?)?;
+    let res = erlang::spawn_3::native(proc, module, function, inner_args)?;
+    spawned(proc, res, &[module, function, arguments]);
+
+    Ok(res)
+}
+
+/// Traces the spawn of `pid` by `proc` and gives it the group leader of `proc`.  `args` are the
+/// module, function and argument list given to spawn `pid`.
+fn spawned(proc: &Process, pid: Term, args: &[Term]) {
+    io::inherit_group_leader(proc, pid);
+
+    trace::emit(
+        proc,
+        Event::Spawn {
+            pid,
+            module: args[0],
+            function: args[1],
+            arguments: args[2],
+        },
+    );
 }
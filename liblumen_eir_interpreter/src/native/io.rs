@@ -0,0 +1,176 @@
+//! Output through group leaders.
+//!
+//! Like BEAM, `io` sends each request to an io server, by default the group leader of the calling
+//! process, and waits for its reply.  The runtime doesn't have group leaders, so they are kept
+//! here: a process has the group leader it was given with `group_leader/2` or inherited when it
+//! was spawned, or else the `user` io server, which writes to the console.
+//!
+//! `io` and `user` wait for messages, so they are written in Erlang and loaded with the
+//! [VM](crate::VM).  `user` writes the output of each request as soon as it gets it, so nothing is
+//! held back when the process that made the request exits.
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+use liblumen_alloc::erts::exception;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{atom_unchecked, Pid, Term};
+
+use lumen_runtime::registry;
+
+use crate::format;
+
+/// The source of `io`, the client side of the io protocol
+pub const IO_SOURCE: &str = "
+-module(io).
+
+format(Format) -> format(standard_io, Format, []).
+format(Format, Arguments) -> format(standard_io, Format, Arguments).
+format(Device, Format, Arguments) ->
+    put_chars(Device, io_lib:format(Format, Arguments)).
+
+fwrite(Format) -> format(Format).
+fwrite(Format, Arguments) -> format(Format, Arguments).
+fwrite(Device, Format, Arguments) -> format(Device, Format, Arguments).
+
+put_chars(Chars) -> put_chars(standard_io, Chars).
+put_chars(Device, Chars) -> request(Device, {put_chars, unicode, Chars}).
+
+nl() -> nl(standard_io).
+nl(Device) -> put_chars(Device, \"\\n\").
+
+request(Device, Request) ->
+    Server = server(Device),
+    Monitor = erlang:monitor(process, Server),
+    Server ! {io_request, self(), Monitor, Request},
+    receive
+        {io_reply, Monitor, {error, Reason}} ->
+            erlang:demonitor(Monitor, [flush]),
+            erlang:error(Reason);
+        {io_reply, Monitor, Reply} ->
+            erlang:demonitor(Monitor, [flush]),
+            Reply;
+        {'DOWN', Monitor, _, _, _} ->
+            erlang:error(terminated)
+    end.
+
+server(standard_io) -> erlang:group_leader();
+server(standard_error) -> lumen_intrinsics:user();
+server(user) -> lumen_intrinsics:user();
+server(Device) when is_pid(Device); is_atom(Device) -> Device;
+server(_) -> erlang:error(badarg).
+";
+
+/// The source of the `user` io server
+pub const USER_SOURCE: &str = "
+-module(lumen_io_user).
+
+loop() ->
+    receive
+        {io_request, From, ReplyAs, Request} ->
+            From ! {io_reply, ReplyAs, request(Request)},
+            loop()
+    end.
+
+request({put_chars, _Encoding, Chars}) ->
+    lumen_intrinsics:put_chars(Chars);
+request({put_chars, _Encoding, Module, Function, Arguments}) ->
+    lumen_intrinsics:put_chars(erlang:apply(Module, Function, Arguments));
+request({requests, Requests}) ->
+    requests(Requests, ok);
+request(_) ->
+    {error, request}.
+
+requests([], Reply) -> Reply;
+requests([Request | Requests], ok) -> requests(Requests, request(Request));
+requests(_, Reply) -> Reply.
+";
+
+lazy_static! {
+    /// The group leaders of the processes that were given one or inherited one.  The processes
+    /// that have exited are removed whenever a process is given a group leader.
+    static ref GROUP_LEADERS: Mutex<HashMap<Pid, Term>> = Mutex::new(HashMap::new());
+    /// The `user` io server, once it has been started
+    static ref USER: Mutex<Option<Term>> = Mutex::new(None);
+}
+
+/// `erlang:group_leader/0`
+pub fn group_leader_0(proc: &Process) -> exception::Result {
+    let leader = GROUP_LEADERS.lock().unwrap().get(&proc.pid()).cloned();
+
+    match leader {
+        Some(leader) => Ok(leader),
+        None => user_0(proc),
+    }
+}
+
+/// `erlang:group_leader/2`
+pub fn group_leader_2(leader: Term, pid: Term) -> exception::Result {
+    let _: Pid = leader.try_into()?;
+    let pid: Pid = pid.try_into()?;
+
+    let mut leaders = GROUP_LEADERS.lock().unwrap();
+    leaders.retain(|pid, _| registry::pid_to_process(pid).is_some());
+    leaders.insert(pid, leader);
+
+    Ok(true.into())
+}
+
+/// Gives the process `pid` spawned by `parent` the group leader of `parent`
+pub fn inherit_group_leader(parent: &Process, pid: Term) {
+    let pid: Pid = match pid.try_into() {
+        Ok(pid) => pid,
+        Err(_) => return,
+    };
+
+    let mut leaders = GROUP_LEADERS.lock().unwrap();
+    if let Some(leader) = leaders.get(&parent.pid()).cloned() {
+        leaders.insert(pid, leader);
+    }
+}
+
+/// `lumen_intrinsics:user/0`: the `user` io server, which is started the first time it is needed
+/// or if it has exited
+pub fn user_0(proc: &Process) -> exception::Result {
+    let mut user = USER.lock().unwrap();
+
+    if let Some(user) = *user {
+        let pid: Pid = user.try_into().unwrap();
+
+        if registry::pid_to_process(&pid).is_some() {
+            return Ok(user);
+        }
+    }
+
+    let pid = super::erlang::spawn(
+        proc,
+        atom_unchecked("lumen_io_user"),
+        atom_unchecked("loop"),
+        Term::NIL,
+    )?;
+    *user = Some(pid);
+
+    Ok(pid)
+}
+
+/// `lumen_intrinsics:put_chars/1`: writes `chars` to the console.  The console only takes whole
+/// lines, so any characters after the last newline are written as a line of their own.
+pub fn put_chars_1(chars: Term) -> exception::Result {
+    let chars: String = format::chars(chars, true)?.into_iter().collect();
+
+    if !chars.is_empty() {
+        let chars = if chars.ends_with('\n') {
+            &chars[..chars.len() - 1]
+        } else {
+            &chars[..]
+        };
+
+        for line in chars.split('\n') {
+            lumen_runtime::system::io::puts(line);
+        }
+    }
+
+    Ok(atom_unchecked("ok"))
+}
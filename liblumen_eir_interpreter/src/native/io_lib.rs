@@ -0,0 +1,18 @@
+use liblumen_alloc::erts::term::Atom;
+
+use crate::format;
+use crate::module::NativeModule;
+
+pub fn make_io_lib() -> NativeModule {
+    let mut native = NativeModule::new(Atom::try_from_str("io_lib").unwrap());
+
+    for name in &["format", "fwrite"] {
+        native.add_simple(Atom::try_from_str(name).unwrap(), 2, |proc, args| {
+            let output = format::format_terms(args[0], args[1])?;
+
+            Ok(proc.charlist_from_str(&output)?)
+        });
+    }
+
+    native
+}
@@ -16,6 +16,16 @@ pub fn make_lumen_intrinsics() -> NativeModule {
         Ok(term)
     });
 
+    native.add_simple(Atom::try_from_str("user").unwrap(), 0, |proc, _args| {
+        super::io::user_0(proc)
+    });
+
+    native.add_simple(
+        Atom::try_from_str("put_chars").unwrap(),
+        1,
+        |_proc, args| super::io::put_chars_1(args[0]),
+    );
+
     native.add_simple(
         Atom::try_from_str("dump_process_heap").unwrap(),
         0,
@@ -1,6 +1,99 @@
+use liblumen_alloc::erts::exception;
+use liblumen_alloc::erts::term::Term;
+
+/// Adds runtime BIFs to a `NativeModule` as simple functions, written as
+/// `"name" => path::to::function(arguments)`.  The arity is the number of `arguments`, which are
+/// passed in order.  The calling process is passed after them with `; process` or, as an owned
+/// `Arc`, with `; arc_process`, and before them with `process;`, as the `native` functions of the
+/// BIF modules take it.  BIFs that can't fail may return a bare `Term`.
+macro_rules! bifs {
+    (@add $native:expr, $name:expr, $($function:ident)::+, process; $($argument:ident),*) => {
+        $native.add_simple(
+            liblumen_alloc::erts::term::Atom::try_from_str($name).unwrap(),
+            <[&str]>::len(&[$(stringify!($argument)),*]),
+            |process, arguments| match *arguments {
+                [$($argument),*] => {
+                    crate::native::IntoResult::into_result($($function)::+(process, $($argument),*))
+                }
+                _ => unreachable!(),
+            },
+        );
+    };
+    (@add $native:expr, $name:expr, $($function:ident)::+, $($argument:ident),*; process) => {
+        $native.add_simple(
+            liblumen_alloc::erts::term::Atom::try_from_str($name).unwrap(),
+            <[&str]>::len(&[$(stringify!($argument)),*]),
+            |process, arguments| match *arguments {
+                [$($argument),*] => {
+                    crate::native::IntoResult::into_result($($function)::+($($argument,)* process))
+                }
+                _ => unreachable!(),
+            },
+        );
+    };
+    (@add $native:expr, $name:expr, $($function:ident)::+, $($argument:ident),*; arc_process) => {
+        $native.add_simple(
+            liblumen_alloc::erts::term::Atom::try_from_str($name).unwrap(),
+            <[&str]>::len(&[$(stringify!($argument)),*]),
+            |process, arguments| match *arguments {
+                [$($argument),*] => crate::native::IntoResult::into_result(
+                    $($function)::+($($argument,)* process.clone()),
+                ),
+                _ => unreachable!(),
+            },
+        );
+    };
+    (@add $native:expr, $name:expr, $($function:ident)::+, $($argument:ident),*) => {
+        $native.add_simple(
+            liblumen_alloc::erts::term::Atom::try_from_str($name).unwrap(),
+            <[&str]>::len(&[$(stringify!($argument)),*]),
+            |_process, arguments| match *arguments {
+                [$($argument),*] => {
+                    crate::native::IntoResult::into_result($($function)::+($($argument),*))
+                }
+                _ => unreachable!(),
+            },
+        );
+    };
+    ($native:expr, { $($name:expr => $($function:ident)::+($($arguments:tt)*)),* $(,)? }) => {
+        $(
+            bifs!(@add $native, $name, $($function)::+, $($arguments)*);
+        )*
+    };
+}
+
+/// The return value of a BIF, which is a bare `Term` for BIFs that can't fail
+trait IntoResult {
+    fn into_result(self) -> exception::Result;
+}
+
+impl IntoResult for Term {
+    fn into_result(self) -> exception::Result {
+        Ok(self)
+    }
+}
+
+impl IntoResult for exception::Result {
+    fn into_result(self) -> exception::Result {
+        self
+    }
+}
+
+mod beam_lib;
+pub use beam_lib::make_beam_lib;
+
+mod code;
+pub use code::{make_code, purge, soft_purge};
+
 mod erlang;
 pub use erlang::make_erlang;
 
+mod io;
+pub use io::{IO_SOURCE, USER_SOURCE};
+
+mod io_lib;
+pub use io_lib::make_io_lib;
+
 mod lists;
 pub use lists::make_lists;
 
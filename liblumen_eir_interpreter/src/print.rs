@@ -0,0 +1,518 @@
+//! Prints terms in Erlang syntax, the same as `io_lib:format("~p", [Term])` and
+//! `io_lib:format("~w", [Term])` without line breaks.  [`pretty`] breaks terms over lines and
+//! [`flat`] limits their depth, as `io_lib:format/2` needs for `~p`, `~P` and `~W`.
+//!
+//! The `Display` implementations of terms in `liblumen_alloc` use Elixir syntax, so they can't be
+//! used for output that Erlang code and users of the shell expect.
+use std::fmt::Write;
+
+use liblumen_alloc::erts::term::binary::aligned_binary::AlignedBinary;
+use liblumen_alloc::erts::term::binary::maybe_aligned_maybe_binary::MaybeAlignedMaybeBinary;
+use liblumen_alloc::erts::term::{Atom, Boxed, Cons, IterableBitstring, Term, TypedTerm};
+
+const RESERVED_WORDS: &[&str] = &[
+    "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor", "case",
+    "catch", "cond", "div", "end", "fun", "if", "let", "not", "of", "or", "orelse", "receive",
+    "rem", "try", "when", "xor",
+];
+
+/// How terms are printed
+#[derive(Clone, Copy)]
+pub struct Style {
+    /// Whether lists and binaries of printable characters are printed as strings, as with `~p`
+    pub strings: bool,
+    /// Whether all printable Unicode characters are printed as strings instead of only Latin-1,
+    /// as with `~tp`
+    pub unicode: bool,
+}
+
+/// The style of `~p`
+pub const PRINT: Style = Style {
+    strings: true,
+    unicode: false,
+};
+
+/// The style of `~w`
+pub const WRITE: Style = Style {
+    strings: false,
+    unicode: false,
+};
+
+/// `~p`: lists of printable characters are printed as strings and binaries of printable
+/// characters as `<<"...">>`.
+pub fn print(term: Term) -> String {
+    flat(term, PRINT, None)
+}
+
+/// `~w`: lists and binaries are always printed as their elements.
+pub fn write(term: Term) -> String {
+    flat(term, WRITE, None)
+}
+
+/// `term` on one line.  Like `io_lib:write/2`, elements of lists, tuples and maps are printed up to
+/// `depth`, counting each element as a level deeper than the one before it, and the rest are
+/// printed as `...`.
+pub fn flat(term: Term, style: Style, depth: Option<usize>) -> String {
+    let mut output = String::new();
+    write_term(&mut output, term, style, depth);
+
+    output
+}
+
+/// `term` broken over lines so that lines are at most `width` characters, if possible, when it is
+/// printed starting at `column`.  Lists of short elements fill each line, while the elements of
+/// other lists, tuples and maps that don't fit are each put on their own line, aligned after the
+/// opening bracket.
+pub fn pretty(
+    term: Term,
+    style: Style,
+    depth: Option<usize>,
+    column: usize,
+    width: usize,
+) -> String {
+    let mut output = String::new();
+    write_pretty(&mut output, term, style, depth, column, width);
+
+    output
+}
+
+/// `atom` quoted if it would not be read back as an atom without quotes
+pub fn atom(atom: Atom) -> String {
+    let name = atom.name();
+    let mut chars = name.chars();
+
+    let bare = match chars.next() {
+        Some(first) => {
+            first.is_ascii_lowercase()
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+                && !RESERVED_WORDS.contains(&name)
+        }
+        None => false,
+    };
+
+    if bare {
+        name.to_string()
+    } else {
+        let mut quoted = String::with_capacity(name.len() + 2);
+        quoted.push('\'');
+        for c in name.chars() {
+            push_escaped(&mut quoted, c, '\'');
+        }
+        quoted.push('\'');
+
+        quoted
+    }
+}
+
+fn write_term(output: &mut String, term: Term, style: Style, depth: Option<usize>) {
+    if depth == Some(0) {
+        output.push_str("...");
+        return;
+    }
+
+    match term.to_typed_term().unwrap() {
+        TypedTerm::Boxed(boxed) => {
+            write_typed_term(output, boxed.to_typed_term().unwrap(), style, depth)
+        }
+        typed_term => write_typed_term(output, typed_term, style, depth),
+    }
+}
+
+fn write_typed_term(
+    output: &mut String,
+    typed_term: TypedTerm,
+    style: Style,
+    depth: Option<usize>,
+) {
+    match typed_term {
+        TypedTerm::Atom(value) => output.push_str(&atom(value)),
+        TypedTerm::SmallInteger(small_integer) => write!(output, "{}", small_integer).unwrap(),
+        TypedTerm::BigInteger(big_integer) => write!(output, "{}", big_integer).unwrap(),
+        TypedTerm::Float(float) => write!(output, "{}", float).unwrap(),
+        TypedTerm::Nil => output.push_str("[]"),
+        TypedTerm::List(cons) => {
+            let (elements, tail) = list_elements(cons);
+
+            match string(&elements, style.unicode) {
+                Some(s) if style.strings && tail.is_none() => write_string(output, &s),
+                _ if depth == Some(1) => output.push_str("[...]"),
+                _ => {
+                    let items = items(&elements, tail, depth);
+
+                    output.push('[');
+                    for (index, (element, element_depth)) in items.elements.iter().enumerate() {
+                        if 0 < index {
+                            output.push(',');
+                        }
+                        write_term(output, *element, style, *element_depth);
+                    }
+                    write_tail(output, &items.tail, '|', style);
+                    output.push(']');
+                }
+            }
+        }
+        TypedTerm::Tuple(tuple) => {
+            if tuple.len() == 0 {
+                output.push_str("{}");
+            } else if depth == Some(1) {
+                output.push_str("{...}");
+            } else {
+                let items = items(&tuple, None, depth);
+
+                output.push('{');
+                for (index, (element, element_depth)) in items.elements.iter().enumerate() {
+                    if 0 < index {
+                        output.push(',');
+                    }
+                    write_term(output, *element, style, *element_depth);
+                }
+                write_tail(output, &items.tail, ',', style);
+                output.push('}');
+            }
+        }
+        TypedTerm::Map(map) => {
+            let mut keys = map.keys();
+            keys.sort();
+
+            if !keys.is_empty() && depth == Some(1) {
+                output.push_str("#{...}");
+            } else {
+                let items = items(&keys, None, depth);
+
+                output.push_str("#{");
+                for (index, (key, element_depth)) in items.elements.iter().enumerate() {
+                    if 0 < index {
+                        output.push(',');
+                    }
+                    write_term(output, *key, style, *element_depth);
+                    output.push_str(" => ");
+                    write_term(output, map.get(*key).unwrap(), style, *element_depth);
+                }
+                write_tail(output, &items.tail, ',', style);
+                output.push('}');
+            }
+        }
+        TypedTerm::HeapBinary(heap_binary) => write_binary(output, heap_binary.as_bytes(), style),
+        TypedTerm::ProcBin(process_binary) => {
+            write_binary(output, process_binary.as_bytes(), style)
+        }
+        TypedTerm::SubBinary(subbinary) => {
+            let bytes: Vec<u8> = subbinary.full_byte_iter().collect();
+
+            if subbinary.is_binary() {
+                write_binary(output, &bytes, style)
+            } else {
+                // The bits of the partial byte are printed as an integer with their size
+                let bits: Vec<u8> = subbinary.partial_byte_bit_iter().collect();
+                let value = bits.iter().fold(0u8, |value, bit| (value << 1) | bit);
+
+                output.push_str("<<");
+                for byte in bytes {
+                    write!(output, "{},", byte).unwrap();
+                }
+                write!(output, "{}:{}>>", value, bits.len()).unwrap();
+            }
+        }
+        TypedTerm::Closure(closure) => {
+            let mfa = closure.module_function_arity();
+            write!(
+                output,
+                "#Fun<{}.{}.{}>",
+                atom(mfa.module),
+                atom(mfa.function),
+                mfa.arity
+            )
+            .unwrap();
+        }
+        TypedTerm::Pid(pid) => write!(output, "<0.{}.{}>", pid.number(), pid.serial()).unwrap(),
+        TypedTerm::Reference(reference) => write!(
+            output,
+            "#Ref<0.{}.{}>",
+            reference.scheduler_id(),
+            reference.number()
+        )
+        .unwrap(),
+        TypedTerm::Port(port) => write!(output, "#Port<0.{}>", port.as_usize()).unwrap(),
+        TypedTerm::Boxed(boxed) => write_term(output, *boxed, style, depth),
+        // Terms that only exist inside the runtime use their `Display`
+        typed_term => write!(output, "{}", typed_term).unwrap(),
+    }
+}
+
+fn write_pretty(
+    output: &mut String,
+    term: Term,
+    style: Style,
+    depth: Option<usize>,
+    column: usize,
+    width: usize,
+) {
+    let one_line = flat(term, style, depth);
+    if column + one_line.chars().count() <= width || depth == Some(1) {
+        output.push_str(&one_line);
+        return;
+    }
+
+    let typed_term = match term.to_typed_term().unwrap() {
+        TypedTerm::Boxed(boxed) => boxed.to_typed_term().unwrap(),
+        typed_term => typed_term,
+    };
+
+    match typed_term {
+        TypedTerm::List(cons) => {
+            let (elements, tail) = list_elements(cons);
+            if style.strings && tail.is_none() && string(&elements, style.unicode).is_some() {
+                output.push_str(&one_line);
+                return;
+            }
+
+            let items = items(&elements, tail, depth);
+            let indent = column + 1;
+            let elements: Vec<String> = items
+                .elements
+                .iter()
+                .map(|(element, element_depth)| flat(*element, style, *element_depth))
+                .collect();
+
+            output.push('[');
+            if elements
+                .iter()
+                .all(|element| indent + element.chars().count() < width)
+            {
+                // Fill each line with as many elements as fit
+                let mut line_column = indent;
+                for (index, element) in elements.iter().enumerate() {
+                    let len = element.chars().count() + 1;
+                    if 0 < index {
+                        output.push(',');
+                        if width < line_column + len {
+                            new_line(output, indent);
+                            line_column = indent;
+                        }
+                    }
+                    output.push_str(element);
+                    line_column += len;
+                }
+            } else {
+                for (index, (element, element_depth)) in items.elements.iter().enumerate() {
+                    if 0 < index {
+                        output.push(',');
+                        new_line(output, indent);
+                    }
+                    write_pretty(output, *element, style, *element_depth, indent, width);
+                }
+            }
+            write_tail(output, &items.tail, '|', style);
+            output.push(']');
+        }
+        TypedTerm::Tuple(tuple) => {
+            let items = items(&tuple, None, depth);
+            let indent = column + 1;
+
+            output.push('{');
+            for (index, (element, element_depth)) in items.elements.iter().enumerate() {
+                if 0 < index {
+                    output.push(',');
+                    new_line(output, indent);
+                }
+                write_pretty(output, *element, style, *element_depth, indent, width);
+            }
+            write_tail(output, &items.tail, ',', style);
+            output.push('}');
+        }
+        TypedTerm::Map(map) => {
+            let mut keys = map.keys();
+            keys.sort();
+            let items = items(&keys, None, depth);
+            let indent = column + 2;
+
+            output.push_str("#{");
+            for (index, (key, element_depth)) in items.elements.iter().enumerate() {
+                if 0 < index {
+                    output.push(',');
+                    new_line(output, indent);
+                }
+                let key = format!("{} => ", flat(*key, style, *element_depth));
+                output.push_str(&key);
+                write_pretty(
+                    output,
+                    map.get(keys[index]).unwrap(),
+                    style,
+                    *element_depth,
+                    indent + key.chars().count(),
+                    width,
+                );
+            }
+            write_tail(output, &items.tail, ',', style);
+            output.push('}');
+        }
+        _ => output.push_str(&one_line),
+    }
+}
+
+fn new_line(output: &mut String, indent: usize) {
+    output.push('\n');
+    for _ in 0..indent {
+        output.push(' ');
+    }
+}
+
+/// The elements of a list and its tail, if it is improper
+fn list_elements(cons: Boxed<Cons>) -> (Vec<Term>, Option<Term>) {
+    let mut elements = Vec::new();
+    let mut tail = None;
+    for result in cons.into_iter() {
+        match result {
+            Ok(element) => elements.push(element),
+            Err(improper) => tail = Some(improper.tail),
+        }
+    }
+
+    (elements, tail)
+}
+
+/// The elements of a list, tuple or map that are printed within `depth`, with the depth of each
+struct Items {
+    elements: Vec<(Term, Option<usize>)>,
+    tail: Tail,
+}
+
+enum Tail {
+    None,
+    Improper(Term, Option<usize>),
+    /// The elements beyond the depth, which are printed as `...`
+    Truncated,
+}
+
+/// Like `io_lib:write/2`, each element is a level deeper than the one before it
+fn items(elements: &[Term], tail: Option<Term>, depth: Option<usize>) -> Items {
+    let mut items = Items {
+        elements: Vec::with_capacity(elements.len()),
+        tail: Tail::None,
+    };
+
+    let mut depth = depth.map(|depth| depth - 1);
+    for (index, element) in elements.iter().enumerate() {
+        if 0 < index {
+            if depth == Some(1) {
+                items.tail = Tail::Truncated;
+
+                return items;
+            }
+            depth = depth.map(|depth| depth - 1);
+        }
+        items.elements.push((*element, depth));
+    }
+
+    if let Some(tail) = tail {
+        items.tail = if depth == Some(1) {
+            Tail::Truncated
+        } else {
+            Tail::Improper(tail, depth.map(|depth| depth - 1))
+        };
+    }
+
+    items
+}
+
+fn write_tail(output: &mut String, tail: &Tail, separator: char, style: Style) {
+    match tail {
+        Tail::None => (),
+        Tail::Improper(tail, depth) => {
+            output.push(separator);
+            write_term(output, *tail, style, *depth);
+        }
+        Tail::Truncated => {
+            output.push(separator);
+            output.push_str("...");
+        }
+    }
+}
+
+fn write_binary(output: &mut String, bytes: &[u8], style: Style) {
+    output.push_str("<<");
+
+    match std::str::from_utf8(bytes) {
+        Ok(s) if style.strings && style.unicode && printable(s, is_printable_unicode) => {
+            write_string(output, s);
+            if !s.is_ascii() {
+                output.push_str("/utf8");
+            }
+        }
+        Ok(s) if style.strings && printable(s, is_printable) => write_string(output, s),
+        _ => {
+            for (index, byte) in bytes.iter().enumerate() {
+                if 0 < index {
+                    output.push(',');
+                }
+                write!(output, "{}", byte).unwrap();
+            }
+        }
+    }
+
+    output.push_str(">>");
+}
+
+fn write_string(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        push_escaped(output, c, '"');
+    }
+    output.push('"');
+}
+
+/// The characters of `elements` if they are all printable, either as Latin-1 or, if `unicode`,
+/// as Unicode
+fn string(elements: &[Term], unicode: bool) -> Option<String> {
+    elements
+        .iter()
+        .map(|element| match element.to_typed_term().unwrap() {
+            TypedTerm::SmallInteger(small_integer) => {
+                let code_point: isize = small_integer.into();
+
+                std::char::from_u32(code_point as u32).filter(|c| {
+                    if unicode {
+                        is_printable_unicode(*c)
+                    } else {
+                        is_printable(*c)
+                    }
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn printable(s: &str, is_printable: fn(char) -> bool) -> bool {
+    !s.is_empty() && s.chars().all(is_printable)
+}
+
+fn is_printable(c: char) -> bool {
+    match c {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => true,
+        '\n' | '\r' | '\t' | '\u{b}' | '\u{8}' | '\u{c}' | '\u{1b}' => true,
+        _ => false,
+    }
+}
+
+fn is_printable_unicode(c: char) -> bool {
+    is_printable(c) || ('\u{ff}' < c && !c.is_control())
+}
+
+fn push_escaped(output: &mut String, c: char, quote: char) {
+    match c {
+        '\n' => output.push_str("\\n"),
+        '\r' => output.push_str("\\r"),
+        '\t' => output.push_str("\\t"),
+        '\u{b}' => output.push_str("\\v"),
+        '\u{8}' => output.push_str("\\b"),
+        '\u{c}' => output.push_str("\\f"),
+        '\u{1b}' => output.push_str("\\e"),
+        '\\' => output.push_str("\\\\"),
+        c if c == quote => {
+            output.push('\\');
+            output.push(c);
+        }
+        c => output.push(c),
+    }
+}
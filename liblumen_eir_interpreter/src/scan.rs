@@ -0,0 +1,335 @@
+//! Scans Erlang source into tokens.
+//!
+//! The shell needs to scan its input before it can be parsed: to know when an expression is
+//! complete and which variables it binds, so they can be kept for later lines.  Term literals,
+//! such as the arguments given on the command line, are parsed from these tokens too.
+use std::fmt::{self, Display};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    /// An unquoted atom or a reserved word
+    Name(String),
+    QuotedAtom(String),
+    Var(String),
+    String(String),
+    /// The digits of an integer, including any `Base#`
+    Integer(String),
+    Float(String),
+    Char(char),
+    Punct(&'static str),
+    /// The `.` that ends an expression, at its byte offset in the input
+    Dot(usize),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Name(name) | Token::Var(name) => write!(f, "{}", name),
+            Token::QuotedAtom(name) => write!(f, "'{}'", name.escape_default()),
+            Token::String(s) => write!(f, "\"{}\"", s.escape_default()),
+            Token::Integer(digits) | Token::Float(digits) => write!(f, "{}", digits),
+            Token::Char(c) => write!(f, "${}", c.escape_default()),
+            Token::Punct(punct) => write!(f, "{}", punct),
+            Token::Dot(_) => write!(f, "."),
+        }
+    }
+}
+
+const PUNCTS: &[&str] = &[
+    "=:=", "=/=", "...", "<<", ">>", "||", "->", "<-", "<=", "=>", ":=", "::", "==", "/=", "=<",
+    ">=", "++", "--", "..", "(", ")", "[", "]", "{", "}", ",", ";", ".", ":", "#", "|", "=", "+",
+    "-", "*", "/", "<", ">", "!", "?",
+];
+
+/// The tokens of `input`, or `None` if it ends inside a string, quoted atom or character.
+pub fn tokens(input: &str) -> Option<Vec<Token>> {
+    let (offsets, chars): (Vec<usize>, Vec<char>) = input.char_indices().unzip();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        if c.is_whitespace() {
+            index += 1;
+        } else if c == '%' {
+            while index < chars.len() && chars[index] != '\n' {
+                index += 1;
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = index;
+            while index < chars.len()
+                && (chars[index].is_ascii_alphanumeric()
+                    || chars[index] == '_'
+                    || chars[index] == '@')
+            {
+                index += 1;
+            }
+            let name: String = chars[start..index].iter().collect();
+
+            if c.is_ascii_lowercase() {
+                tokens.push(Token::Name(name));
+            } else {
+                tokens.push(Token::Var(name));
+            }
+        } else if c.is_ascii_digit() {
+            let start = index;
+            index = number_end(&chars, index);
+            let number: String = chars[start..index].iter().filter(|c| **c != '_').collect();
+
+            if number.contains('.') {
+                tokens.push(Token::Float(number));
+            } else {
+                tokens.push(Token::Integer(number));
+            }
+        } else if c == '$' {
+            let (value, end) = match chars.get(index + 1)? {
+                '\\' => escape(&chars, index + 1)?,
+                c => (*c, index + 2),
+            };
+            index = end;
+            tokens.push(Token::Char(value));
+        } else if c == '"' || c == '\'' {
+            let (value, end) = quoted(&chars, index)?;
+            index = end;
+
+            if c == '"' {
+                tokens.push(Token::String(value));
+            } else {
+                tokens.push(Token::QuotedAtom(value));
+            }
+        } else {
+            let rest: String = chars[index..(index + 3).min(chars.len())].iter().collect();
+            let punct = PUNCTS
+                .iter()
+                .find(|punct| rest.starts_with(*punct))
+                .copied()
+                .unwrap_or("?");
+            let offset = offsets[index];
+            index += punct.chars().count();
+
+            // Like `erl_scan`, a `.` only ends an expression if followed by whitespace, a comment
+            // or nothing, so `#record.field` doesn't
+            let ends = punct == "."
+                && chars
+                    .get(index)
+                    .map_or(true, |c| c.is_whitespace() || *c == '%');
+            if ends {
+                tokens.push(Token::Dot(offset));
+            } else {
+                tokens.push(Token::Punct(punct));
+            }
+        }
+    }
+
+    Some(tokens)
+}
+
+/// Whether `tokens` are one or more complete expressions: whether they end in a `.`
+pub fn is_complete(tokens: &[Token]) -> bool {
+    match tokens.last() {
+        Some(Token::Dot(_)) => true,
+        _ => false,
+    }
+}
+
+/// Splits `input` into the source and tokens of each of its expressions, without their ending
+/// `.`
+pub fn expressions<'a>(input: &'a str, tokens: &'a [Token]) -> Vec<(&'a str, &'a [Token])> {
+    let mut expressions = Vec::new();
+    let mut start_offset = 0;
+    let mut start_index = 0;
+
+    for (index, token) in tokens.iter().enumerate() {
+        if let Token::Dot(offset) = token {
+            expressions.push((&input[start_offset..*offset], &tokens[start_index..index]));
+            start_offset = offset + 1;
+            start_index = index + 1;
+        }
+    }
+
+    expressions
+}
+
+/// The variables that `tokens` may bind and that are still bound after them: those outside of any
+/// clause, fun or comprehension, in order of appearance.
+///
+/// Variables bound inside `case`, `if`, `receive` and `try` clauses are only safe to use after
+/// the expression if they are bound in every clause, which can't be known without parsing, so
+/// they are not kept.
+pub fn bound_variables(tokens: &[Token]) -> Vec<String> {
+    // Whether each scope, in order of opening, is local
+    let mut local = Vec::new();
+    // The indices of the open scopes in `local`
+    let mut open: Vec<usize> = Vec::new();
+    let mut occurrences: Vec<(&str, Vec<usize>)> = Vec::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Var(name) if name != "_" => occurrences.push((name.as_str(), open.clone())),
+            Token::Name(name) => match name.as_str() {
+                "begin" => {
+                    local.push(false);
+                    open.push(local.len() - 1);
+                }
+                "case" | "if" | "receive" | "try" => {
+                    local.push(true);
+                    open.push(local.len() - 1);
+                }
+                // `fun f/1` and `fun m:f/1` don't have clauses or an `end`
+                "fun" if is_fun_expression(&tokens[index + 1..]) => {
+                    local.push(true);
+                    open.push(local.len() - 1);
+                }
+                "end" => {
+                    open.pop();
+                }
+                _ => (),
+            },
+            Token::Punct("(") | Token::Punct("[") | Token::Punct("{") | Token::Punct("<<") => {
+                local.push(false);
+                open.push(local.len() - 1);
+            }
+            Token::Punct(")") | Token::Punct("]") | Token::Punct("}") | Token::Punct(">>") => {
+                open.pop();
+            }
+            // The variables in both the expression and the qualifiers of a comprehension are
+            // local to it
+            Token::Punct("||") => {
+                if let Some(scope) = open.last() {
+                    local[*scope] = true;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut bound: Vec<String> = Vec::new();
+    for (name, scopes) in occurrences {
+        if scopes.iter().all(|scope| !local[*scope]) && !bound.iter().any(|b| b == name) {
+            bound.push(name.to_string());
+        }
+    }
+
+    bound
+}
+
+fn is_fun_expression(after: &[Token]) -> bool {
+    match (after.get(0), after.get(1)) {
+        (Some(Token::Punct("(")), _) => true,
+        // Named funs
+        (Some(Token::Var(_)), Some(Token::Punct("("))) => true,
+        _ => false,
+    }
+}
+
+fn number_end(chars: &[char], mut index: usize) -> usize {
+    let is_digit =
+        |chars: &[char], index: usize| index < chars.len() && chars[index].is_ascii_digit();
+
+    while is_digit(chars, index) || (index < chars.len() && chars[index] == '_') {
+        index += 1;
+    }
+
+    if index < chars.len() && chars[index] == '#' {
+        // `Base#Digits`
+        index += 1;
+        while index < chars.len() && chars[index].is_ascii_alphanumeric() {
+            index += 1;
+        }
+    } else if index < chars.len() && chars[index] == '.' && is_digit(chars, index + 1) {
+        // Only a `.` followed by a digit is a fraction, so `X = 1.` ends the expression
+        index += 1;
+        while is_digit(chars, index) {
+            index += 1;
+        }
+
+        if index < chars.len() && (chars[index] == 'e' || chars[index] == 'E') {
+            let mut exponent = index + 1;
+            if exponent < chars.len() && (chars[exponent] == '+' || chars[exponent] == '-') {
+                exponent += 1;
+            }
+            if is_digit(chars, exponent) {
+                index = exponent;
+                while is_digit(chars, index) {
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    index
+}
+
+/// The character and end of the escape sequence starting with the `\` at `index`
+fn escape(chars: &[char], index: usize) -> Option<(char, usize)> {
+    let mut end = index + 2;
+
+    let value = match *chars.get(index + 1)? {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'e' => '\u{1b}',
+        's' => ' ',
+        'd' => '\u{7f}',
+        'b' => '\u{8}',
+        'f' => '\u{c}',
+        'v' => '\u{b}',
+        'x' => {
+            let digits: String = if chars.get(end) == Some(&'{') {
+                let close = end + chars[end..].iter().position(|c| *c == '}')?;
+                let digits = chars[end + 1..close].iter().collect();
+                end = close + 1;
+
+                digits
+            } else {
+                let digits = chars.get(end..end + 2)?.iter().collect();
+                end += 2;
+
+                digits
+            };
+
+            std::char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?
+        }
+        '0'..='7' => {
+            end = index + 1;
+            while end < index + 4 && chars.get(end).map_or(false, |c| ('0'..='7').contains(c)) {
+                end += 1;
+            }
+            let digits: String = chars[index + 1..end].iter().collect();
+
+            std::char::from_u32(u32::from_str_radix(&digits, 8).ok()?)?
+        }
+        // Control characters, such as `\^a` for 1
+        '^' => {
+            end += 1;
+
+            std::char::from_u32(*chars.get(index + 2)? as u32 % 32)?
+        }
+        c => c,
+    };
+
+    Some((value, end))
+}
+
+/// The value and end of the string or quoted atom starting with the quote at `index`
+fn quoted(chars: &[char], index: usize) -> Option<(String, usize)> {
+    let quote = chars[index];
+    let mut value = String::new();
+    let mut index = index + 1;
+
+    loop {
+        match *chars.get(index)? {
+            c if c == quote => return Some((value, index + 1)),
+            '\\' => {
+                let (c, end) = escape(chars, index)?;
+                value.push(c);
+                index = end;
+            }
+            c => {
+                value.push(c);
+                index += 1;
+            }
+        }
+    }
+}
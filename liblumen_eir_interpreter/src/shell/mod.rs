@@ -0,0 +1,467 @@
+//! An interactive shell, like `erl`, that evaluates Erlang expressions in the interpreter.
+//!
+//! There is no evaluator for Erlang abstract code, so each expression is compiled into a
+//! function of a new module that takes the bound variables as arguments and returns the value of
+//! the expression with the variables bound after it.  The module is deleted once the expression
+//! returns, and purged once no process or bound variable uses it.  The expression runs in a new
+//! process on
+//! the current scheduler, and the scheduler keeps running other processes while the shell waits
+//! for input, so processes spawned by expressions keep executing between prompts.
+
+use core::ptr::{self, NonNull};
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use liblumen_alloc::borrow::clone_to_process::CloneToProcess;
+use liblumen_alloc::erts::process::{Process, Status};
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Boxed, Term, Tuple};
+use liblumen_alloc::erts::HeapFragment;
+
+use lumen_runtime::registry;
+use lumen_runtime::scheduler::Scheduler;
+
+use crate::call_result::call_erlang;
+use crate::load::{self, LoadError};
+use crate::native;
+use crate::print;
+use crate::scan::{self, Token};
+use crate::VM;
+
+/// What the shell should do after evaluating input
+pub enum Reply {
+    /// Print the output and prompt for more input
+    Output(String),
+    /// `q()` was evaluated
+    Quit,
+}
+
+pub struct Shell {
+    init_arc_process: Arc<Process>,
+    /// The values of bound variables, which are in `bindings_heap`
+    bindings: BTreeMap<String, Term>,
+    /// The heap fragment that holds the values of `bindings`, if any are bound.  The shell owns
+    /// it and frees it when the bindings are replaced.
+    bindings_heap: Option<NonNull<HeapFragment>>,
+    /// The modules of evaluated expressions that have been deleted, but not purged yet because a
+    /// process or a bound variable still uses them
+    deleted_modules: Vec<Atom>,
+    evaluations: usize,
+}
+
+impl Shell {
+    pub fn new(init_arc_process: Arc<Process>) -> Self {
+        Self {
+            init_arc_process,
+            bindings: BTreeMap::new(),
+            bindings_heap: None,
+            deleted_modules: Vec::new(),
+            evaluations: 0,
+        }
+    }
+
+    /// The prompt for the next expression, such as `1> `
+    pub fn prompt(&self) -> String {
+        format!("{}> ", self.evaluations + 1)
+    }
+
+    /// Whether `input` ends with a complete expression, so it can be evaluated.  Strings, quoted
+    /// atoms and expressions can continue over multiple lines.
+    pub fn is_complete(input: &str) -> bool {
+        match scan::tokens(input) {
+            Some(tokens) => scan::is_complete(&tokens),
+            None => false,
+        }
+    }
+
+    /// Evaluates each of the `.`-terminated expressions in `input` in order.
+    pub fn eval(&mut self, input: &str) -> Reply {
+        let tokens = match scan::tokens(input) {
+            Some(tokens) => tokens,
+            None => Vec::new(),
+        };
+        if !scan::is_complete(&tokens) {
+            return Reply::Output("* syntax error: expected `.` at the end of input".to_string());
+        }
+
+        let mut outputs = Vec::new();
+        for (source, tokens) in scan::expressions(input, &tokens) {
+            match self.eval_expression(source, tokens) {
+                Reply::Output(output) => outputs.push(output),
+                Reply::Quit => return Reply::Quit,
+            }
+        }
+
+        Reply::Output(outputs.join("\n"))
+    }
+
+    fn eval_expression(&mut self, source: &str, tokens: &[Token]) -> Reply {
+        self.evaluations += 1;
+
+        if let Some(reply) = self.command(tokens) {
+            return reply;
+        }
+
+        let arguments: Vec<String> = self.bindings.keys().cloned().collect();
+        let mut bound = arguments.clone();
+        for variable in scan::bound_variables(tokens) {
+            if !self.bindings.contains_key(&variable) {
+                bound.push(variable);
+            }
+        }
+
+        let module_name = format!("$lumen_shell_{}", self.evaluations);
+        let module_source = format!(
+            "-module('{}').\n\neval({}) ->\n    LumenShellValue = begin\n{}\n    end,\n    {{LumenShellValue, {{{}}}}}.\n",
+            module_name,
+            arguments.join(", "),
+            source,
+            bound.join(", ")
+        );
+
+        let eir_module = match load::source_to_eir(&module_source) {
+            Ok(eir_module) => eir_module,
+            Err(error) => return Reply::Output(format!("* {}", error)),
+        };
+        if let Err(error) = VM
+            .modules
+            .write()
+            .unwrap()
+            .register_erlang_module(eir_module)
+        {
+            return Reply::Output(format!("* {}", error));
+        }
+
+        let module = Atom::try_from_str(&module_name).unwrap();
+        let function = Atom::try_from_str("eval").unwrap();
+        let argument_values: Vec<Term> = arguments
+            .iter()
+            .map(|argument| self.bindings[argument])
+            .collect();
+
+        let (result, heap) = self.call(module, function, &argument_values);
+        let output = match result {
+            Ok(result) => {
+                let result: Boxed<Tuple> = result.try_into().unwrap();
+                let values: Boxed<Tuple> = result[1].try_into().unwrap();
+
+                // All bound variables, old and new, are in the values, so the old heap can go
+                for (variable, value) in bound.into_iter().zip(values.iter()) {
+                    self.bindings.insert(variable, *value);
+                }
+                self.replace_bindings_heap(heap);
+
+                print::print(result[0])
+            }
+            Err((class, reason, _stacktrace)) => {
+                let output = exception(class, reason);
+                if let Some(heap) = heap {
+                    free(heap);
+                }
+
+                output
+            }
+        };
+
+        VM.modules.write().unwrap().delete(module);
+        self.deleted_modules.push(module);
+        self.purge_deleted_modules();
+
+        Reply::Output(output)
+    }
+
+    /// The shell's own functions, like `b()`, which can't be compiled because they use the
+    /// shell's state
+    fn command(&mut self, tokens: &[Token]) -> Option<Reply> {
+        let (name, argument) = match tokens {
+            [Token::Name(name), Token::Punct("("), Token::Punct(")")] => (name.as_str(), None),
+            [Token::Name(name), Token::Punct("("), argument, Token::Punct(")")] => {
+                (name.as_str(), Some(argument))
+            }
+            _ => return None,
+        };
+
+        let output = match (name, argument) {
+            ("b", None) => {
+                let mut lines: Vec<String> = self
+                    .bindings
+                    .iter()
+                    .map(|(variable, value)| format!("{} = {}", variable, print::print(*value)))
+                    .collect();
+                lines.push("ok".to_string());
+
+                lines.join("\n")
+            }
+            ("c", Some(Token::String(file)))
+            | ("c", Some(Token::QuotedAtom(file)))
+            | ("c", Some(Token::Name(file))) => self.compile(file),
+            ("f", None) => {
+                self.bindings.clear();
+                self.replace_bindings_heap(None);
+                self.purge_deleted_modules();
+
+                "ok".to_string()
+            }
+            ("f", Some(Token::Var(variable))) => {
+                self.bindings.remove(variable);
+                self.copy_bindings();
+                self.purge_deleted_modules();
+
+                "ok".to_string()
+            }
+            ("i", None) => processes(),
+            ("q", None) => return Some(Reply::Quit),
+            _ => return None,
+        };
+
+        Some(Reply::Output(output))
+    }
+
+    /// `c(File)`: compiles and loads `File`, which can leave off the `.erl` extension.  Like
+    /// `erl`, the old version of the module is purged first, so the current version can become
+    /// the old version.
+    fn compile(&self, file: &str) -> String {
+        let path = if file.ends_with(".erl") {
+            file.to_string()
+        } else {
+            format!("{}.erl", file)
+        };
+
+        let registered = load::erl_to_eir(&path).and_then(|eir_module| {
+            let module = Atom::try_from_str(&*eir_module.name.as_str()).unwrap();
+            native::purge(&self.init_arc_process, module);
+            VM.modules
+                .write()
+                .unwrap()
+                .register_erlang_module(eir_module)?;
+
+            Ok(module)
+        });
+
+        match registered {
+            Ok(module) => format!("{{ok,{}}}", print::atom(module)),
+            Err(error) => format!("{{error,{}}}", compile_error_reason(&error)),
+        }
+    }
+
+    /// Runs `module:function(arguments...)` in a new process until it returns or exits.  The
+    /// result is in the returned heap fragment, which the caller must free.
+    fn call(
+        &self,
+        module: Atom,
+        function: Atom,
+        arguments: &[Term],
+    ) -> (
+        Result<Term, (Term, Term, Term)>,
+        Option<NonNull<HeapFragment>>,
+    ) {
+        let receiver = call_erlang(self.init_arc_process.clone(), module, function, arguments);
+
+        loop {
+            if let Some(process_result) = receiver.try_get() {
+                return (process_result.result, Some(process_result.heap));
+            }
+
+            // Killed by another process before returning or throwing
+            if let Status::Exiting(ref exception) = *receiver.process.status.read() {
+                if let Some(process_result) = receiver.try_get() {
+                    return (process_result.result, Some(process_result.heap));
+                }
+
+                return (
+                    Err((atom_unchecked("exit"), exception.reason, Term::NIL)),
+                    None,
+                );
+            }
+
+            run_or_sleep();
+        }
+    }
+
+    /// Frees the heap of the old bindings after the bindings have been moved to `heap`
+    fn replace_bindings_heap(&mut self, heap: Option<NonNull<HeapFragment>>) {
+        if let Some(old_heap) = std::mem::replace(&mut self.bindings_heap, heap) {
+            free(old_heap);
+        }
+    }
+
+    /// Copies the bindings to a new heap, so that the heap of values that are no longer bound
+    /// can be freed
+    fn copy_bindings(&mut self) {
+        if self.bindings.is_empty() {
+            self.replace_bindings_heap(None);
+
+            return;
+        }
+
+        let need_in_words = self
+            .bindings
+            .values()
+            .map(|value| value.size_in_words())
+            .sum();
+        let mut heap = unsafe { HeapFragment::new_from_word_size(need_in_words) }.unwrap();
+        let heap_mut = unsafe { heap.as_mut() };
+
+        for value in self.bindings.values_mut() {
+            *value = value.clone_to_heap(heap_mut).unwrap();
+        }
+
+        self.replace_bindings_heap(Some(heap));
+    }
+
+    /// Purges the deleted modules of evaluated expressions that no process or binding uses
+    fn purge_deleted_modules(&mut self) {
+        let init_arc_process = &self.init_arc_process;
+        let bound: Vec<Term> = self.bindings.values().cloned().collect();
+
+        self.deleted_modules
+            .retain(|&module| !native::soft_purge(init_arc_process, module, &bound));
+    }
+}
+
+impl Drop for Shell {
+    fn drop(&mut self) {
+        self.bindings.clear();
+        self.replace_bindings_heap(None);
+    }
+}
+
+/// Runs the shell on stdin and stdout until `q()` or the end of input
+pub fn run(init_arc_process: Arc<Process>) {
+    let lines = read_lines();
+    let mut shell = Shell::new(init_arc_process);
+    let mut input = String::new();
+
+    loop {
+        if input.is_empty() {
+            print!("{}", shell.prompt());
+            io::stdout().flush().unwrap();
+        }
+
+        let line = match next_line(&lines) {
+            Some(line) => line,
+            None => break,
+        };
+        input.push_str(&line);
+        input.push('\n');
+
+        if Shell::is_complete(&input) {
+            match shell.eval(&input) {
+                Reply::Output(output) => println!("{}", output),
+                Reply::Quit => break,
+            }
+
+            input.clear();
+        }
+    }
+}
+
+/// Reads stdin on another thread, so that the scheduler can run while waiting for input
+fn read_lines() -> Receiver<String> {
+    let (sender, receiver) = channel();
+
+    thread::spawn(move || {
+        let stdin = io::stdin();
+
+        for line in stdin.lock().lines() {
+            match line {
+                Ok(line) => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    receiver
+}
+
+fn next_line(lines: &Receiver<String>) -> Option<String> {
+    loop {
+        match lines.try_recv() {
+            Ok(line) => return Some(line),
+            Err(TryRecvError::Empty) => run_or_sleep(),
+            Err(TryRecvError::Disconnected) => return None,
+        }
+    }
+}
+
+/// Runs a process on the current scheduler, or sleeps if none can run, such as when all processes
+/// are waiting for messages or timers
+fn run_or_sleep() {
+    if !Scheduler::current().run_once() {
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Frees a heap fragment that no term refers into anymore
+fn free(heap: NonNull<HeapFragment>) {
+    unsafe { ptr::drop_in_place(heap.as_ptr()) };
+}
+
+/// The `Reason` of the `{error, Reason}` that `c/1` returns when `File` can't be loaded.
+/// Diagnostics for source that doesn't parse or lower have already been written to stderr.
+fn compile_error_reason(error: &LoadError) -> &'static str {
+    match error {
+        LoadError::Read(error) => match error.kind() {
+            io::ErrorKind::NotFound => "enoent",
+            io::ErrorKind::PermissionDenied => "eacces",
+            _ => "eio",
+        },
+        LoadError::FromBeam(_) => "badfile",
+        LoadError::Parse => "parse",
+        LoadError::Lower => "lower",
+        LoadError::NotPurged(_) => "not_purged",
+    }
+}
+
+/// Formats an uncaught exception the same as `erl`, such as `** exception error: badarith`
+fn exception(class: Term, reason: Term) -> String {
+    let class: Atom = class.try_into().unwrap();
+
+    format!("** exception {}: {}", class.name(), print::print(reason))
+}
+
+/// `i()`: the processes in the registry, in order of their pids
+fn processes() -> String {
+    let mut arc_processes = registry::processes();
+    arc_processes.sort_by_key(|arc_process| arc_process.pid());
+
+    let mut lines = vec![format!(
+        "{:<16}{:<40}{:>10}{:>6}  {}",
+        "Pid", "Initial Call", "Reds", "Msgs", "Registered"
+    )];
+
+    for arc_process in arc_processes {
+        let pid = print::print(arc_process.pid_term());
+        let mfa = &arc_process.initial_module_function_arity;
+        let initial_call = format!(
+            "{}:{}/{}",
+            print::atom(mfa.module),
+            print::atom(mfa.function),
+            mfa.arity
+        );
+        let reductions = arc_process.total_reductions.load(Ordering::SeqCst);
+        let messages = arc_process.mailbox.lock().borrow().len();
+        let registered = match *arc_process.registered_name.read() {
+            Some(name) => print::atom(name),
+            None => String::new(),
+        };
+
+        lines.push(format!(
+            "{:<16}{:<40}{:>10}{:>6}  {}",
+            pid, initial_call, reductions, messages, registered
+        ));
+    }
+    lines.push("ok".to_string());
+
+    lines.join("\n")
+}
@@ -1,3 +1,10 @@
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
+
+use super::debugger::{self, Breakpoint, Paused, Reason, Resume};
+use super::load;
+use super::shell::{Reply, Shell};
+use super::trace::{self, Event, Filter, Sink};
 use super::VM;
 
 use libeir_diagnostics::{ColorChoice, Emitter, StandardStreamEmitter};
@@ -10,7 +17,8 @@ use libeir_syntax_erl::ast::Module as ErlAstModule;
 use libeir_syntax_erl::lower_module;
 use libeir_syntax_erl::{Parse, ParseConfig, Parser};
 
-use liblumen_alloc::erts::term::{atom_unchecked, Atom};
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Boxed, Term, Tuple, TypedTerm};
 
 use lumen_runtime::scheduler::Scheduler;
 
@@ -32,7 +40,8 @@ where
 }
 
 pub fn lower(input: &str, config: ParseConfig) -> Result<Module, ()> {
-    let (parsed, parser): (ErlAstModule, _) = parse(input, config);
+    let (mut parsed, parser): (ErlAstModule, _) = parse(input, config);
+    load::mark_remote_self_calls(&mut parsed);
     let (res, messages) = lower_module(&parsed);
 
     let emitter =
@@ -76,7 +85,11 @@ run() -> yay.
 ",
     );
 
-    VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
 
     let res = crate::call_result::call_run_erlang(init_arc_process, module, function, &[]);
     assert!(res.result == Ok(atom_unchecked("yay")));
@@ -102,7 +115,11 @@ fib(X) -> fib(X - 1) + fib(X - 2).
 ",
     );
 
-    VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
 
     let int = init_arc_process.integer(5).unwrap();
     let res =
@@ -130,7 +147,11 @@ a() -> 1 + a.
 ",
     );
 
-    VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
 
     let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[]);
 
@@ -161,7 +182,11 @@ fib(X) -> fib(X - 1) + fib(X - 2).
 ",
     );
 
-    VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
 
     let int = init_arc_process.integer(14).unwrap();
     let res =
@@ -171,6 +196,253 @@ fib(X) -> fib(X - 1) + fib(X - 2).
     assert!(res.result == Ok(int));
 }
 
+#[test]
+fn binary_match() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("binary_match_test").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(binary_match_test).
+
+run(<<A:8, B:16/little, C:4/signed, D:4, E/utf8, Rest/binary>>) -> {A, B, C, D, E, Rest}.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let binary = init_arc_process
+        .binary_from_bytes(&[1, 0x34, 0x12, 0xF5, 0xC3, 0xA9, b'o', b'k'])
+        .unwrap();
+    let res =
+        crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[binary]);
+
+    let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+    assert!(tuple[0] == init_arc_process.integer(1).unwrap());
+    assert!(tuple[1] == init_arc_process.integer(0x1234).unwrap());
+    assert!(tuple[2] == init_arc_process.integer(-1).unwrap());
+    assert!(tuple[3] == init_arc_process.integer(5).unwrap());
+    assert!(tuple[4] == init_arc_process.integer('é').unwrap());
+
+    let rest: Vec<u8> = tuple[5].try_into().unwrap();
+    assert_eq!(rest, b"ok".to_vec());
+}
+
+#[test]
+fn binary_construct() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("binary_construct_test").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(binary_construct_test).
+
+build(0, Acc) -> Acc;
+build(N, Acc) -> build(N - 1, <<Acc/binary, N:8>>).
+
+run(N) ->
+    Bits = <<1:4, 16#234:12/little, 2.5:32/float, 233/utf8, $a/utf16-little, 5:3>>,
+    {build(N, <<>>), <<Bits/bitstring, 0:5>>}.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let n = init_arc_process.integer(300).unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[n]);
+
+    let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+
+    // Built in place in a writable binary
+    match tuple[0].to_typed_term().unwrap() {
+        TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
+            TypedTerm::SubBinary(subbinary) => assert!(subbinary.is_writable()),
+            typed_term => panic!("{:?} is not a sub binary", typed_term),
+        },
+        typed_term => panic!("{:?} is not boxed", typed_term),
+    }
+
+    let built: Vec<u8> = tuple[0].try_into().unwrap();
+    let expected: Vec<u8> = (1..=300).rev().map(|n| n as u8).collect();
+    assert_eq!(built, expected);
+
+    let bits: Vec<u8> = tuple[1].try_into().unwrap();
+    assert_eq!(
+        bits,
+        vec![0x13, 0x42, 0x40, 0x20, 0x00, 0x00, 0xC3, 0xA9, 0x61, 0x00, 0xA0]
+    );
+}
+
+#[test]
+fn binary_segment_sizes_that_are_too_large() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("binary_segment_size_test").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(binary_segment_size_test).
+
+run(Size) ->
+    Match = case <<1, 2>> of
+        <<X:Size/bitstring-unit:256, _/bitstring>> -> X;
+        _ -> nomatch
+    end,
+    Construct = try <<0:Size/unit:256>> catch error:badarg -> badarg end,
+    {Match, Construct}.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let nomatch = atom_unchecked("nomatch");
+    let badarg = atom_unchecked("badarg");
+
+    // 2^57 units of 256 bits overflows, and 2^20 units is bigger than any integer
+    for size in &[1_usize << 57, 1 << 20] {
+        let size = init_arc_process.integer(*size).unwrap();
+        let res = crate::call_result::call_run_erlang(
+            init_arc_process.clone(),
+            module,
+            function,
+            &[size],
+        );
+
+        let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+        assert!(tuple[0] == nomatch);
+        assert!(tuple[1] == badarg);
+    }
+}
+
+#[test]
+fn try_catch() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("try_catch_test").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(try_catch_test).
+
+run(X) ->
+    A = try throw(X) catch throw:T -> T end,
+    B = try error(badarg) catch error:R:S -> {R, is_list(S)} end,
+    C = try exit(bye) catch exit:E -> E after put(after_ran, true) end,
+    D = try reraise(X) catch Class:Reason -> {Class, Reason} end,
+    {A, B, C, D, get(after_ran)}.
+
+reraise(X) ->
+    try error(X) catch error:Reason:Stack -> erlang:raise(exit, Reason, Stack) end.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let x = init_arc_process.integer(7).unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[x]);
+
+    let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+    assert!(tuple[0] == init_arc_process.integer(7).unwrap());
+
+    let b: Boxed<Tuple> = tuple[1].try_into().unwrap();
+    assert!(b[0] == atom_unchecked("badarg"));
+    assert!(b[1] == atom_unchecked("true"));
+
+    assert!(tuple[2] == atom_unchecked("bye"));
+
+    let d: Boxed<Tuple> = tuple[3].try_into().unwrap();
+    assert!(d[0] == atom_unchecked("exit"));
+    assert!(d[1] == init_arc_process.integer(7).unwrap());
+
+    assert!(tuple[4] == atom_unchecked("true"));
+}
+
+#[test]
+fn uncaught_error_stacktrace() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("uncaught_error_test").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(uncaught_error_test).
+
+run(X) -> 1 + fail(X).
+
+fail(X) -> error({oops, X}).
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let x = init_arc_process.integer(7).unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[x]);
+
+    let (class, reason, stacktrace) = res.result.unwrap_err();
+    assert!(class == atom_unchecked("error"));
+
+    let reason: Boxed<Tuple> = reason.try_into().unwrap();
+    assert!(reason[0] == atom_unchecked("oops"));
+
+    // Like BEAM, `error/1` is not in the stacktrace, but `fail/1`, which raised the exception, is,
+    // even though it tail called `error/1`
+    let stacktrace: Vec<Term> = match stacktrace.to_typed_term().unwrap() {
+        TypedTerm::List(cons) => cons.into_iter().map(|result| result.unwrap()).collect(),
+        typed_term => panic!("{:?} is not a stacktrace", typed_term),
+    };
+    let entry: Boxed<Tuple> = stacktrace[0].try_into().unwrap();
+    assert!(entry[0] == atom_unchecked("uncaught_error_test"));
+    assert!(entry[1] == atom_unchecked("fail"));
+    assert!(entry[2] == init_arc_process.integer(1).unwrap());
+
+    let entry: Boxed<Tuple> = stacktrace[1].try_into().unwrap();
+    assert!(entry[0] == atom_unchecked("uncaught_error_test"));
+    assert!(entry[1] == atom_unchecked("run"));
+    assert!(entry[2] == init_arc_process.integer(1).unwrap());
+}
+
 #[test]
 fn ping_pong() {
     &*VM;
@@ -209,13 +481,304 @@ run() ->
 ",
     );
 
-    VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
 
     let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[]);
 
     assert!(res.result == Ok(atom_unchecked("d")));
 }
 
+#[test]
+fn receive_after() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("receive_after").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(receive_after).
+
+wait(Timeout) ->
+    receive
+        {msg, X} -> X
+    after Timeout -> timeout
+    end.
+
+reply(Pid) -> Pid ! {msg, hello}.
+
+run() ->
+    self() ! other,
+    A = wait(0),
+    B = wait(10),
+    spawn(receive_after, reply, [self()]),
+    C = wait(60000),
+    D = receive other -> other after 0 -> none end,
+    E = wait(10),
+    {A, B, C, D, E}.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[]);
+
+    let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+    assert!(tuple[0] == atom_unchecked("timeout"));
+    assert!(tuple[1] == atom_unchecked("timeout"));
+    assert!(tuple[2] == atom_unchecked("hello"));
+    assert!(tuple[3] == atom_unchecked("other"));
+    assert!(tuple[4] == atom_unchecked("timeout"));
+}
+
+#[test]
+fn hot_code_loading() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let hot_source = |version: &str| {
+        format!(
+            "
+-module(hot_code).
+
+version() -> {}.
+
+loop() ->
+    receive
+        {{version, Pid}} ->
+            Pid ! {{version, version()}},
+            loop()
+    end.
+",
+            version
+        )
+    };
+
+    let eir_mod = compile(
+        "
+-module(hot_code_test).
+
+start() -> spawn(hot_code, loop, []).
+
+check(Pid) ->
+    Pid ! {version, self()},
+    Local = receive {version, V} -> V end,
+    Remote = hot_code:version(),
+    Old = erlang:check_process_code(Pid, hot_code),
+    SoftPurged = code:soft_purge(hot_code),
+    Purged = code:purge(hot_code),
+    Deleted = code:delete(hot_code),
+    {Local, Remote, Old, SoftPurged, Purged, Deleted, code:delete(hot_code)}.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(compile(&hot_source("1")))
+        .unwrap();
+
+    let start = Atom::try_from_str("start").unwrap();
+    let module = Atom::try_from_str("hot_code_test").unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, start, &[]);
+    let pid = res.result.unwrap();
+
+    // The spawned process is waiting in the `receive` of the first version
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(compile(&hot_source("2")))
+        .unwrap();
+
+    let check = Atom::try_from_str("check").unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, check, &[pid]);
+
+    let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+    // Local calls stay in the old version, while fully-qualified calls go to the current version
+    assert!(tuple[0] == init_arc_process.integer(1).unwrap());
+    assert!(tuple[1] == init_arc_process.integer(2).unwrap());
+    assert!(tuple[2] == atom_unchecked("true"));
+    // Soft purging fails while a process is in the old version, so it has to be killed to purge
+    assert!(tuple[3] == atom_unchecked("false"));
+    assert!(tuple[4] == atom_unchecked("true"));
+    assert!(tuple[5] == atom_unchecked("true"));
+    // The deleted version has to be purged before the module can be deleted again
+    assert!(tuple[6] == atom_unchecked("false"));
+}
+
+#[test]
+fn remote_self_call_loads_current_version() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let hot_source = |version: &str| {
+        format!(
+            "
+-module(hot_loop).
+
+version() -> {}.
+
+loop() ->
+    receive
+        {{version, Pid}} ->
+            Pid ! {{version, version()}},
+            ?MODULE:loop()
+    end.
+
+loop_through(Module) ->
+    receive
+        {{version, Pid}} ->
+            Pid ! {{version, version()}},
+            Module:loop_through(Module)
+    end.
+",
+            version
+        )
+    };
+
+    let eir_mod = compile(
+        "
+-module(hot_loop_test).
+
+start() -> spawn(hot_loop, loop, []).
+
+start_through() -> spawn(hot_loop, loop_through, [hot_loop]).
+
+check(Pid) ->
+    Before = version(Pid),
+    After = version(Pid),
+    {Before, After}.
+
+version(Pid) ->
+    Pid ! {version, self()},
+    receive {version, V} -> V end.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(compile(&hot_source("1")))
+        .unwrap();
+
+    let start = Atom::try_from_str("start").unwrap();
+    let module = Atom::try_from_str("hot_loop_test").unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, start, &[]);
+    let pid = res.result.unwrap();
+    let start_through = Atom::try_from_str("start_through").unwrap();
+    let res =
+        crate::call_result::call_run_erlang(init_arc_process.clone(), module, start_through, &[]);
+    let through_pid = res.result.unwrap();
+
+    // The spawned processes are waiting in the `receive` of the first version
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(compile(&hot_source("2")))
+        .unwrap();
+
+    let check = Atom::try_from_str("check").unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, check, &[pid]);
+
+    // The first message is handled by the first version, which then calls `?MODULE:loop()`, so
+    // the second message is handled by the current version
+    let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+    assert!(tuple[0] == init_arc_process.integer(1).unwrap());
+    assert!(tuple[1] == init_arc_process.integer(2).unwrap());
+
+    // The same goes for a call through a variable bound to the module
+    let res = crate::call_result::call_run_erlang(
+        init_arc_process.clone(),
+        module,
+        check,
+        &[through_pid],
+    );
+    let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+    assert!(tuple[0] == init_arc_process.integer(1).unwrap());
+    assert!(tuple[1] == init_arc_process.integer(2).unwrap());
+
+    // Like `code:load_binary/3`, another version can't be loaded until the old one is purged
+    let hot_loop = Atom::try_from_str("hot_loop").unwrap();
+    let mut modules = VM.modules.write().unwrap();
+    assert!(modules
+        .register_erlang_module(compile(&hot_source("3")))
+        .is_err());
+    assert!(modules.purge(hot_loop));
+    assert!(modules
+        .register_erlang_module(compile(&hot_source("3")))
+        .is_ok());
+}
+
+#[test]
+fn beam_compile_options_are_kept_when_loading() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    // `export_all` has no `-export`, so `double/1` is only exported by `-compile(export_all)`
+    load::register_beam_module(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../liblumen_beam/tests/testdata/ast/export_all.beam"
+    ))
+    .unwrap();
+
+    let module = Atom::try_from_str("export_all").unwrap();
+    let function = Atom::try_from_str("double").unwrap();
+    let int = init_arc_process.integer(21).unwrap();
+    let res =
+        crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[int]);
+
+    let int = init_arc_process.integer(42).unwrap();
+    assert!(res.result == Ok(int));
+}
+
+#[test]
+fn beam_char_escapes_are_parsed_back_the_same() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    load::register_beam_module(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../liblumen_beam/tests/testdata/ast/char_escapes.beam"
+    ))
+    .unwrap();
+
+    let module = Atom::try_from_str("char_escapes").unwrap();
+    let function = Atom::try_from_str("chars").unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[]);
+
+    let chars = init_arc_process.charlist_from_str("\0 \n'\"").unwrap();
+    assert!(res.result == Ok(chars));
+}
+
 #[test]
 fn ping_pong_count() {
     &*VM;
@@ -244,7 +807,11 @@ run(N) -> this_proc(N, 0).
 ",
     );
 
-    VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
 
     let int = init_arc_process.integer(10).unwrap();
     let res =
@@ -283,7 +850,11 @@ run(N) -> this_proc(N, 0).
 ",
     );
 
-    VM.modules.write().unwrap().register_erlang_module(eir_mod);
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
 
     let int = init_arc_process.integer(100).unwrap();
     let res =
@@ -292,3 +863,441 @@ run(N) -> this_proc(N, 0).
     println!("{:?}", res.result);
     //assert!(res.result == Ok(100));
 }
+
+#[test]
+fn long_running_processes_are_preempted() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("preemption").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(preemption).
+
+count(0) -> done;
+count(N) -> count(N - 1).
+
+spin(Parent, N) ->
+    count(N),
+    Parent ! spinner_done.
+
+run() ->
+    Self = self(),
+    spawn(preemption, spin, [Self, 50000]),
+    count(5000),
+    Self ! main_done,
+    receive
+        First ->
+            receive
+                Second -> {First, Second}
+            end
+    end.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[]);
+
+    // Both processes spend many runs of reductions counting, so the one with less to count
+    // finishes first
+    let tuple: Boxed<Tuple> = res.result.unwrap().try_into().unwrap();
+    assert!(tuple[0] == atom_unchecked("main_done"));
+    assert!(tuple[1] == atom_unchecked("spinner_done"));
+}
+
+#[test]
+fn runtime_bifs() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("runtime_bifs").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(runtime_bifs).
+
+run() ->
+    {[a, b], {a, c}, 8, <<\"ell\">>, <<\"a\">>, true, [3, 2, 1]} =:=
+        {tuple_to_list({a, b}),
+         setelement(2, {a, b}, c),
+         1 bsl 3,
+         binary_part(<<\"hello\">>, 1, 3),
+         atom_to_binary(a, utf8),
+         is_reference(make_ref()),
+         lists:reverse([1, 2, 3])}.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[]);
+
+    assert!(res.result == Ok(atom_unchecked("true")));
+}
+
+#[test]
+fn shell_keeps_bindings_between_expressions() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let mut shell = Shell::new(init_arc_process);
+    let mut eval = |input: &str| match shell.eval(input) {
+        Reply::Output(output) => output,
+        Reply::Quit => panic!("{} quit", input),
+    };
+
+    assert_eq!(eval("X = 1, Y = [X, 2]."), "[1,2]");
+    // `A` is local to the comprehension
+    assert_eq!(eval("Z = [A * 2 || A <- Y], {X, Z}."), "{1,[2,4]}");
+    assert_eq!(eval("b()."), "X = 1\nY = [1,2]\nZ = [2,4]\nok");
+    assert_eq!(eval("throw(X)."), "** exception throw: 1");
+    assert_eq!(eval("f(X). X = \"two\"."), "ok\n\"two\"");
+    assert_eq!(eval("f()."), "ok");
+    assert_eq!(eval("b()."), "ok");
+    assert_eq!(
+        eval("{'Quoted', <<\"bin\">>, <<1, 2>>}."),
+        "{'Quoted',<<\"bin\">>,<<1,2>>}"
+    );
+    // The module of the expression that made the fun is deleted, but not purged while it is bound
+    assert_eq!(eval("F = fun(N) -> N * 2 end, ok."), "ok");
+    assert_eq!(eval("F(21)."), "42");
+}
+
+#[test]
+fn shell_compile_purges_before_loading() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let path = std::env::temp_dir().join("shell_compile_test.erl");
+    std::fs::write(&path, "-module(shell_compile_test).\n\nrun() -> ok.\n").unwrap();
+
+    let mut shell = Shell::new(init_arc_process);
+    let mut eval = |input: &str| match shell.eval(input) {
+        Reply::Output(output) => output,
+        Reply::Quit => panic!("{} quit", input),
+    };
+    let compile = format!("c(\"{}\").", path.display());
+
+    for _ in 0..3 {
+        assert_eq!(eval(&compile), "{ok,shell_compile_test}");
+    }
+    assert_eq!(
+        eval("c(\"shell_compile_test_does_not_exist\")."),
+        "{error,enoent}"
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn literal_arguments_are_printed_back_the_same() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let source = "{ok,[1,-2.5,16#ff,\"str\",'Quoted'|tail],#{key => <<\"bin\",0>>},$a}";
+    let term = crate::literal::parse(&init_arc_process, source).unwrap();
+
+    assert_eq!(
+        crate::print::print(term),
+        "{ok,[1,-2.5,255,\"str\",'Quoted'|tail],#{key => <<98,105,110,0>>},97}"
+    );
+    assert!(crate::literal::parse(&init_arc_process, "{ok, Var}").is_err());
+    assert!(crate::literal::parse(&init_arc_process, "[1, 2").is_err());
+}
+
+#[test]
+fn io_lib_format_control_sequences() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let format = |format: &str, arguments: &str| {
+        let format = crate::literal::parse(&init_arc_process, format).unwrap();
+        let arguments = crate::literal::parse(&init_arc_process, arguments).unwrap();
+
+        crate::format::format_terms(format, arguments)
+    };
+
+    assert_eq!(
+        format("\"~5.2f|~e|~g\"", "[3.14159, 12345.678, 0.5]").unwrap(),
+        " 3.14|1.23457e+4|0.500000"
+    );
+    assert_eq!(
+        format("\"~-6s|~s\"", "[\"ab\", [<<\"ab\">>, \"c\"]]").unwrap(),
+        "ab    |abc"
+    );
+    assert_eq!(
+        format("\"~.16B ~.16x ~8.2.0b\"", "[255, -255, \"0x\", 5]").unwrap(),
+        "FF -0xff 00000101"
+    );
+    assert_eq!(format("\"~c~3c~*c\"", "[$a, $b, 2, $c]").unwrap(), "abbbcc");
+    assert_eq!(
+        format("\"~w ~5w|\"", "[{a, [1, 2]}, abcdefg]").unwrap(),
+        "{a,[1,2]} *****|"
+    );
+    assert_eq!(
+        format("\"~p ~lp ~P\"", "[{a, \"abc\"}, \"ab\", [1, 2, 3, 4], 3]").unwrap(),
+        "{a,\"abc\"} [97,98] [1,2|...]"
+    );
+    assert_eq!(format("\"~i~n~~\"", "[ignored]").unwrap(), "\n~");
+    assert!(format("\"~q\"", "[a]").is_err());
+    assert!(format("\"~w ~w\"", "[a]").is_err());
+    assert!(format("\"~w\"", "[a, b]").is_err());
+    assert!(format("\"~1000000000c\"", "[$a]").is_err());
+    assert!(format("\"~*c\"", "[-1000000000, $a]").is_err());
+    assert!(format("\"~99999999999999999999999w\"", "[a]").is_err());
+
+    let mut shell = Shell::new(init_arc_process.clone());
+
+    match shell.eval("io_lib:format(\"~4..0B\", [7]).") {
+        Reply::Output(output) => assert_eq!(output, "\"0007\""),
+        Reply::Quit => panic!("quit"),
+    }
+}
+
+#[test]
+fn io_format_goes_to_group_leader() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("group_leader_test").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(group_leader_test).
+
+leader(Test) ->
+    receive
+        {io_request, From, ReplyAs, {put_chars, unicode, Chars}} ->
+            From ! {io_reply, ReplyAs, ok},
+            Test ! {chars, Chars}
+    end.
+
+write() ->
+    io:format(\"~p~n\", [{a, 1}]).
+
+run() ->
+    Leader = spawn(group_leader_test, leader, [self()]),
+    true = erlang:group_leader(Leader, self()),
+    spawn(group_leader_test, write, []),
+    receive
+        {chars, Chars} -> Chars
+    end.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let res = crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[]);
+
+    let chars: String = crate::format::chars(res.result.unwrap(), true)
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(chars, "{a,1}\n");
+}
+
+/// Collects the events of calls, returns and exceptions, without the pid, as text and JSON
+struct CollectingSink {
+    events: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+impl Sink for CollectingSink {
+    fn event(&self, process: &Process, event: &Event) {
+        let human = trace::human(process, event);
+        let human = human.splitn(2, ' ').nth(1).unwrap().to_string();
+
+        if human.starts_with("call ")
+            || human.starts_with("return ")
+            || human.starts_with("exception ")
+        {
+            let json = trace::json(process, event);
+            self.events.lock().unwrap().push((human, json));
+        }
+    }
+}
+
+#[test]
+fn trace_calls_and_returns() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("trace_test").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(trace_test).
+
+run(X) ->
+    Y = double(X),
+    Z = (catch fail(Y)),
+    {Y, Z}.
+
+double(X) -> X * 2.
+
+fail(X) -> X + a.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut filter = trace::Filter::default();
+    filter.modules.push(module);
+    trace::set(
+        Box::new(CollectingSink {
+            events: events.clone(),
+        }),
+        filter,
+    );
+
+    let int = init_arc_process.integer(3).unwrap();
+    let res = crate::call_result::call_run_erlang(init_arc_process, module, function, &[int]);
+    trace::clear();
+    assert!(res.result.is_ok());
+
+    let events = events.lock().unwrap();
+    let human: Vec<&str> = events.iter().map(|(human, _)| human.as_str()).collect();
+
+    assert_eq!(
+        &human[..5],
+        &[
+            "call trace_test:run(3)",
+            "call trace_test:double(3)",
+            "return trace_test:double/1 -> 6",
+            "call trace_test:fail(6)",
+            "exception trace_test:fail/1 -> error:badarith",
+        ]
+    );
+    assert!(human[5].starts_with("return trace_test:run/1 -> {6,{'EXIT',{badarith,"));
+    assert_eq!(human.len(), 6);
+    assert!(events[0]
+        .1
+        .ends_with(r#""event":"call","module":"trace_test","function":"run","arguments":["3"]}"#));
+}
+
+#[test]
+fn debugger_steps_into_and_over_calls() {
+    &*VM;
+
+    let arc_scheduler = Scheduler::current();
+    let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
+
+    let module = Atom::try_from_str("debugger_test").unwrap();
+    let function = Atom::try_from_str("run").unwrap();
+
+    let eir_mod = compile(
+        "
+-module(debugger_test).
+
+run(X) ->
+    Y = double(X),
+    Y + 1.
+
+double(X) -> X * 2.
+",
+    );
+
+    VM.modules
+        .write()
+        .unwrap()
+        .register_erlang_module(eir_mod)
+        .unwrap();
+
+    // Steps into `double/1` when `run/1` is called with `3`, and over it when called with `5`,
+    // until `Y` is bound to `10`
+    let pauses = Arc::new(Mutex::new(Vec::new()));
+    let frontend_pauses = pauses.clone();
+    debugger::attach(Box::new(move |paused: &Paused| {
+        let bindings: Vec<String> = paused
+            .bindings()
+            .into_iter()
+            .map(|(_, term)| crate::print::print(term))
+            .collect();
+        let has = |value: &str| bindings.iter().any(|binding| binding == value);
+
+        frontend_pauses.lock().unwrap().push(format!(
+            "{} {}",
+            paused.function.name(),
+            match paused.reason {
+                Reason::Breakpoint(_) => "breakpoint",
+                Reason::Step => "step",
+            }
+        ));
+
+        if paused.function.name() == "double" || has("10") {
+            Resume::Continue
+        } else if has("3") {
+            Resume::StepInto
+        } else {
+            Resume::StepOver
+        }
+    }));
+    debugger::add_breakpoint(Breakpoint::parse("debugger_test:run/1").unwrap());
+
+    let seven = init_arc_process.integer(7).unwrap();
+    let eleven = init_arc_process.integer(11).unwrap();
+    let three = init_arc_process.integer(3).unwrap();
+    let stepped_into =
+        crate::call_result::call_run_erlang(init_arc_process.clone(), module, function, &[three]);
+    let five = init_arc_process.integer(5).unwrap();
+    let stepped_over =
+        crate::call_result::call_run_erlang(init_arc_process, module, function, &[five]);
+    debugger::detach();
+
+    assert!(stepped_into.result == Ok(seven));
+    assert!(stepped_over.result == Ok(eleven));
+
+    let pauses = pauses.lock().unwrap();
+    let second_call = pauses
+        .iter()
+        .rposition(|pause| pause == "run breakpoint")
+        .unwrap();
+
+    assert_eq!(pauses[0], "run breakpoint");
+    assert_eq!(pauses[second_call - 1], "double step");
+    assert!(pauses[1..second_call - 1]
+        .iter()
+        .all(|pause| pause == "run step"));
+    assert!(second_call + 1 < pauses.len());
+    assert!(pauses[second_call + 1..]
+        .iter()
+        .all(|pause| pause == "run step"));
+}
@@ -0,0 +1,413 @@
+//! Tracing of the execution of processes in the interpreter.
+//!
+//! The interpreter emits an [`Event`] for function calls and returns, messages sent and received,
+//! processes spawned and exiting, and garbage collections.  Events are given to the [`Sink`] set
+//! with [`set`] if they pass its [`Filter`].  Nothing is traced until a sink is set, so that
+//! events aren't built when no one is listening.
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::{Mutex, RwLock};
+
+use lazy_static::lazy_static;
+
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{Atom, Pid, Term, TypedTerm};
+
+use crate::print;
+
+lazy_static! {
+    static ref TRACER: RwLock<Option<Tracer>> = RwLock::new(None);
+}
+
+pub enum Event<'a> {
+    /// `module:function` is called with `arguments`
+    Call {
+        module: Atom,
+        function: Atom,
+        arguments: &'a [Term],
+    },
+    /// `module:function/arity` returns `value`
+    Return {
+        module: Atom,
+        function: Atom,
+        arity: usize,
+        value: Term,
+    },
+    /// `module:function/arity` returns by raising an exception
+    ExceptionFrom {
+        module: Atom,
+        function: Atom,
+        arity: usize,
+        class: Term,
+        reason: Term,
+    },
+    /// `message` is sent to `destination`, which is a pid or registered name
+    Send { destination: Term, message: Term },
+    /// `message` is matched by a `receive` and removed from the mailbox
+    Receive { message: Term },
+    /// The process with `pid` is spawned to apply `module:function` to the `arguments` list
+    Spawn {
+        pid: Term,
+        module: Term,
+        function: Term,
+        arguments: Term,
+    },
+    /// The process returns from its initial call or an exception is not caught
+    Exit { reason: Term },
+    /// The heap of the process is garbage collected, either the young generation or all of it
+    GarbageCollect { full: bool },
+}
+
+impl<'a> Event<'a> {
+    /// The module and function of calls and returns
+    fn module_function(&self) -> Option<(Atom, Atom, usize)> {
+        match self {
+            Event::Call {
+                module,
+                function,
+                arguments,
+            } => Some((*module, *function, arguments.len())),
+            Event::Return {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | Event::ExceptionFrom {
+                module,
+                function,
+                arity,
+                ..
+            } => Some((*module, *function, *arity)),
+            _ => None,
+        }
+    }
+}
+
+/// Where events are written
+pub trait Sink: Send + Sync {
+    fn event(&self, process: &Process, event: &Event);
+}
+
+/// Discards all events
+pub struct Silent;
+
+impl Sink for Silent {
+    fn event(&self, _process: &Process, _event: &Event) {}
+}
+
+/// Writes each event as a line of text, as formatted by [`human`]
+pub struct Human {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Human {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Writes to stderr, so that the trace isn't mixed with the output of the program
+    pub fn stderr() -> Self {
+        Self::new(Box::new(io::stderr()))
+    }
+}
+
+impl Sink for Human {
+    fn event(&self, process: &Process, event: &Event) {
+        let mut writer = self.writer.lock().unwrap();
+        let _ = writeln!(writer, "{}", human(process, event));
+    }
+}
+
+/// Writes each event as a line of JSON, as formatted by [`json`]
+pub struct JsonLines {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonLines {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn stderr() -> Self {
+        Self::new(Box::new(io::stderr()))
+    }
+}
+
+impl Sink for JsonLines {
+    fn event(&self, process: &Process, event: &Event) {
+        let mut writer = self.writer.lock().unwrap();
+        let _ = writeln!(writer, "{}", json(process, event));
+    }
+}
+
+/// Which events are traced.  An empty list allows everything.
+#[derive(Default)]
+pub struct Filter {
+    /// The modules whose calls and returns are traced
+    pub modules: Vec<Atom>,
+    /// The functions, of any arity or of only the given arity, whose calls and returns are traced
+    pub functions: Vec<(Atom, Option<usize>)>,
+    /// The processes whose events are traced
+    pub pids: Vec<Pid>,
+}
+
+impl Filter {
+    fn allows(&self, process: &Process, event: &Event) -> bool {
+        self.allows_process(process)
+            && match event.module_function() {
+                Some((module, function, arity)) => self.allows_function(module, function, arity),
+                None => true,
+            }
+    }
+
+    fn allows_process(&self, process: &Process) -> bool {
+        self.pids.is_empty() || self.pids.contains(&process.pid())
+    }
+
+    fn allows_function(&self, module: Atom, function: Atom, arity: usize) -> bool {
+        (self.modules.is_empty() || self.modules.contains(&module))
+            && (self.functions.is_empty()
+                || self.functions.iter().any(|(name, filter_arity)| {
+                    *name == function && filter_arity.map_or(true, |a| a == arity)
+                }))
+    }
+}
+
+struct Tracer {
+    sink: Box<dyn Sink>,
+    filter: Filter,
+}
+
+/// Traces the events that pass `filter` to `sink` from now on
+pub fn set(sink: Box<dyn Sink>, filter: Filter) {
+    *TRACER.write().unwrap() = Some(Tracer { sink, filter });
+}
+
+/// Stops tracing
+pub fn clear() {
+    *TRACER.write().unwrap() = None;
+}
+
+/// Whether a sink is set.  Events that are costly to build are only built when it is.
+pub(crate) fn enabled() -> bool {
+    TRACER.read().unwrap().is_some()
+}
+
+/// Whether the return of `module:function/arity` in `process` is traced, so that its
+/// continuations need to be wrapped to see it return
+pub(crate) fn traces_return(process: &Process, module: Atom, function: Atom, arity: usize) -> bool {
+    match &*TRACER.read().unwrap() {
+        Some(tracer) => {
+            tracer.filter.allows_process(process)
+                && tracer.filter.allows_function(module, function, arity)
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn emit(process: &Process, event: Event) {
+    if let Some(tracer) = &*TRACER.read().unwrap() {
+        if tracer.filter.allows(process, &event) {
+            tracer.sink.event(process, &event);
+        }
+    }
+}
+
+/// `event` in `process` as text with terms in Erlang syntax, such as
+/// `<0.42.0> call lists:reverse([1,2,3])`
+pub fn human(process: &Process, event: &Event) -> String {
+    let pid = print::print(process.pid_term());
+
+    match event {
+        Event::Call {
+            module,
+            function,
+            arguments,
+        } => {
+            let arguments: Vec<String> = arguments.iter().map(|a| print::print(*a)).collect();
+
+            format!(
+                "{} call {}:{}({})",
+                pid,
+                print::atom(*module),
+                print::atom(*function),
+                arguments.join(",")
+            )
+        }
+        Event::Return {
+            module,
+            function,
+            arity,
+            value,
+        } => format!(
+            "{} return {}:{}/{} -> {}",
+            pid,
+            print::atom(*module),
+            print::atom(*function),
+            arity,
+            print::print(*value)
+        ),
+        Event::ExceptionFrom {
+            module,
+            function,
+            arity,
+            class,
+            reason,
+        } => format!(
+            "{} exception {}:{}/{} -> {}:{}",
+            pid,
+            print::atom(*module),
+            print::atom(*function),
+            arity,
+            print::print(*class),
+            print::print(*reason)
+        ),
+        Event::Send {
+            destination,
+            message,
+        } => format!(
+            "{} send {} to {}",
+            pid,
+            print::print(*message),
+            print::print(*destination)
+        ),
+        Event::Receive { message } => format!("{} receive {}", pid, print::print(*message)),
+        Event::Spawn {
+            pid: spawned,
+            module,
+            function,
+            arguments,
+        } => format!(
+            "{} spawn {} apply({},{},{})",
+            pid,
+            print::print(*spawned),
+            print::print(*module),
+            print::print(*function),
+            print::print(*arguments)
+        ),
+        Event::Exit { reason } => format!("{} exit {}", pid, print::print(*reason)),
+        Event::GarbageCollect { full: false } => format!("{} gc", pid),
+        Event::GarbageCollect { full: true } => format!("{} full gc", pid),
+    }
+}
+
+/// `event` in `process` as a JSON object on one line, with terms as strings in Erlang syntax, such
+/// as `{"pid":"<0.42.0>","event":"receive","message":"{ok,1}"}`
+pub fn json(process: &Process, event: &Event) -> String {
+    let mut fields: Vec<(&str, String)> = vec![("pid", json_term(process.pid_term()))];
+
+    match event {
+        Event::Call {
+            module,
+            function,
+            arguments,
+        } => {
+            let arguments: Vec<String> = arguments.iter().map(|a| json_term(*a)).collect();
+
+            fields.push(("event", json_string("call")));
+            fields.push(("module", json_string(module.name())));
+            fields.push(("function", json_string(function.name())));
+            fields.push(("arguments", format!("[{}]", arguments.join(","))));
+        }
+        Event::Return {
+            module,
+            function,
+            arity,
+            value,
+        } => {
+            fields.push(("event", json_string("return")));
+            fields.push(("module", json_string(module.name())));
+            fields.push(("function", json_string(function.name())));
+            fields.push(("arity", arity.to_string()));
+            fields.push(("value", json_term(*value)));
+        }
+        Event::ExceptionFrom {
+            module,
+            function,
+            arity,
+            class,
+            reason,
+        } => {
+            fields.push(("event", json_string("exception_from")));
+            fields.push(("module", json_string(module.name())));
+            fields.push(("function", json_string(function.name())));
+            fields.push(("arity", arity.to_string()));
+            fields.push(("class", json_term(*class)));
+            fields.push(("reason", json_term(*reason)));
+        }
+        Event::Send {
+            destination,
+            message,
+        } => {
+            fields.push(("event", json_string("send")));
+            fields.push(("to", json_term(*destination)));
+            fields.push(("message", json_term(*message)));
+        }
+        Event::Receive { message } => {
+            fields.push(("event", json_string("receive")));
+            fields.push(("message", json_term(*message)));
+        }
+        Event::Spawn {
+            pid,
+            module,
+            function,
+            arguments,
+        } => {
+            fields.push(("event", json_string("spawn")));
+            fields.push(("spawned", json_term(*pid)));
+            fields.push(("module", json_term(*module)));
+            fields.push(("function", json_term(*function)));
+            fields.push(("arguments", json_term(*arguments)));
+        }
+        Event::Exit { reason } => {
+            fields.push(("event", json_string("exit")));
+            fields.push(("reason", json_term(*reason)));
+        }
+        Event::GarbageCollect { full } => {
+            fields.push(("event", json_string("gc")));
+            fields.push(("full", full.to_string()));
+        }
+    }
+
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key), value))
+        .collect();
+
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Atoms are their names, so that they can be compared without unquoting, while other terms are
+/// printed in Erlang syntax
+fn json_term(term: Term) -> String {
+    match term.to_typed_term().unwrap() {
+        TypedTerm::Atom(atom) => json_string(atom.name()),
+        _ => json_string(&print::print(term)),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
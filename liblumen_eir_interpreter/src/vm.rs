@@ -9,7 +9,6 @@ use liblumen_alloc::erts::term::{atom_unchecked, Atom, Term};
 
 use lumen_runtime::process::spawn::options::Options;
 use lumen_runtime::scheduler::Scheduler;
-use lumen_runtime::system;
 
 use super::module::ModuleRegistry;
 
@@ -24,12 +23,20 @@ impl VMState {
         lumen_runtime::otp::erlang::apply_3::set_code(crate::code::apply);
 
         let mut modules = ModuleRegistry::new();
+        modules.register_native_module(crate::native::make_beam_lib());
+        modules.register_native_module(crate::native::make_code());
         modules.register_native_module(crate::native::make_erlang());
+        modules.register_native_module(crate::native::make_io_lib());
         modules.register_native_module(crate::native::make_lists());
         modules.register_native_module(crate::native::make_maps());
         modules.register_native_module(crate::native::make_logger());
         modules.register_native_module(crate::native::make_lumen_intrinsics());
 
+        for source in &[crate::native::IO_SOURCE, crate::native::USER_SOURCE] {
+            let module = crate::load::source_to_eir(source).unwrap();
+            modules.register_erlang_module(module).unwrap();
+        }
+
         let arc_scheduler = Scheduler::current();
         let init_arc_process = arc_scheduler.spawn_init(0).unwrap();
 
@@ -92,12 +99,7 @@ impl VMState {
                     }
                 },
                 Status::Waiting => {
-                    if ran {
-                        system::io::puts(&format!(
-                            "WAITING Run queues len = {:?}",
-                            Scheduler::current().run_queues_len()
-                        ));
-                    } else {
+                    if !ran {
                         panic!(
                             "{:?} did not run.  Deadlock likely in {:#?}",
                             run_arc_process,
@@ -105,18 +107,7 @@ impl VMState {
                         );
                     }
                 }
-                Status::Runnable => {
-                    system::io::puts(&format!(
-                        "RUNNABLE Run queues len = {:?}",
-                        Scheduler::current().run_queues_len()
-                    ));
-                }
-                Status::Running => {
-                    system::io::puts(&format!(
-                        "RUNNING Run queues len = {:?}",
-                        Scheduler::current().run_queues_len()
-                    ));
-                }
+                Status::Runnable | Status::Running => (),
             }
         }
     }
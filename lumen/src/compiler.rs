@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::convert::From;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
@@ -7,7 +7,7 @@ use clap::{value_t, ArgMatches};
 use failure::Error;
 
 use libeir_diagnostics::{CodeMap, ColorChoice};
-use liblumen_compiler::{Compiler, CompilerMode, CompilerSettings, Verbosity};
+use liblumen_compiler::{Compiler, CompilerMode, CompilerSettings, OutputType, Verbosity};
 
 /// Dispatches command-line arguments to the compiler backend
 pub fn dispatch<'a>(args: &'a ArgMatches) -> Result<(), Error> {
@@ -20,13 +20,27 @@ pub fn dispatch<'a>(args: &'a ArgMatches) -> Result<(), Error> {
 }
 
 /// Create a CompilerSettings struct from ArgMatches produced by clap
-fn configure<'a>(args: &'a ArgMatches) -> Result<CompilerSettings, Error> {
+///
+/// Also used by `run`, which has no compiler type or output options
+pub(crate) fn configure<'a>(args: &'a ArgMatches) -> Result<CompilerSettings, Error> {
     let codemap = Arc::new(Mutex::new(CodeMap::new()));
-    let mode = value_t!(args, "compiler", CompilerMode).unwrap_or_else(|e| e.exit());
+    let mode = if args.is_present("compiler") {
+        value_t!(args, "compiler", CompilerMode).unwrap_or_else(|e| e.exit())
+    } else {
+        CompilerMode::Erlang
+    };
     let source_dir = args.value_of_os("path").map(PathBuf::from).unwrap();
-    let output_dir = args.value_of_os("output").map(PathBuf::from).unwrap();
+    let output_dir = args
+        .value_of_os("output")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let output_type = if args.is_present("emit") {
+        value_t!(args, "emit", OutputType).unwrap_or_else(|e| e.exit())
+    } else {
+        OutputType::EirText
+    };
     let warnings_as_errors = args.is_present("warnings-as-errors");
-    let no_warn = args.is_present("no_warn");
+    let no_warn = args.is_present("no-warnings");
     let verbosity = Verbosity::from_level(args.occurrences_of("verbose") as isize);
     let include_path = VecDeque::new();
     let mut code_path = match args.values_of_os("prepend-path") {
@@ -38,16 +52,24 @@ fn configure<'a>(args: &'a ArgMatches) -> Result<CompilerSettings, Error> {
         Some(values) => values.map(PathBuf::from).collect(),
     };
     code_path.append(&mut append_dirs);
-    Ok(CompilerSettings {
+    let mut config = CompilerSettings {
         mode,
         color: ColorChoice::Auto,
         source_dir,
         output_dir,
+        output_type,
+        defines: HashMap::new(),
         warnings_as_errors,
         no_warn,
         verbosity,
         code_path,
         include_path,
         codemap,
-    })
+    };
+    if let Some(defines) = args.values_of("define") {
+        for define in defines {
+            config.define(define);
+        }
+    }
+    Ok(config)
 }
@@ -1,9 +1,11 @@
 mod compiler;
+mod run;
+mod shell;
 
 use std::process;
 
 use clap::{crate_description, crate_name, crate_version};
-use clap::{App, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, SubCommand};
 use failure::Error;
 
 use libeir_diagnostics::{ColorChoice, Emitter, StandardStreamEmitter};
@@ -58,9 +60,17 @@ fn main() {
                         .value_name("DIR")
                         .default_value_os(output_dir.as_os_str()),
                 )
+                .arg(
+                    Arg::with_name("emit")
+                        .help("The type of output to produce for each module (`eir` is EIR text; serialized EIR is not supported yet)")
+                        .long("emit")
+                        .value_name("TYPE")
+                        .possible_values(&["eir"])
+                        .default_value("eir"),
+                )
                 .arg(
                     Arg::with_name("define")
-                        .help("Define a macro, e.g. -DTEST")
+                        .help("Define a macro, e.g. -DTEST or -DNAME=VALUE")
                         .short("D")
                         .long("define")
                         .value_name("NAME")
@@ -103,12 +113,97 @@ fn main() {
                         .multiple(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Compiles Erlang and runs it in the EIR interpreter")
+                .setting(AppSettings::TrailingVarArg)
+                .arg(
+                    Arg::with_name("path")
+                        .help("The path to the file or directory of files you wish to run")
+                        .index(1)
+                        .takes_value(true)
+                        .value_name("FILE_OR_DIR")
+                        .default_value_os(cwd.as_os_str())
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("entry")
+                        .help("The function to run, which is passed the arguments as a list of strings")
+                        .short("e")
+                        .long("entry")
+                        .value_name("MODULE:FUNCTION")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("ARGS")
+                        .help("The arguments to pass to the entry function")
+                        .index(2)
+                        .multiple(true)
+                        .last(true),
+                )
+                .arg(
+                    Arg::with_name("define")
+                        .help("Define a macro, e.g. -DTEST or -DNAME=VALUE")
+                        .short("D")
+                        .long("define")
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("warnings-as-errors")
+                        .help("Causes the compiler to treat all warnings as errors")
+                        .long("warnings-as-errors"),
+                )
+                .arg(
+                    Arg::with_name("no-warnings")
+                        .help("Disable warnings")
+                        .long("no-warnings")
+                        .conflicts_with("warnings-as-errors"),
+                )
+                .arg(
+                    Arg::with_name("verbose")
+                        .help("Set verbosity level")
+                        .short("v")
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("shell")
+                .about("Starts an interactive shell that evaluates Erlang in the EIR interpreter")
+                .arg(
+                    Arg::with_name("path")
+                        .help("The path to the file or directory of files to load before starting")
+                        .index(1)
+                        .takes_value(true)
+                        .value_name("FILE_OR_DIR"),
+                )
+                .arg(
+                    Arg::with_name("remote")
+                        .help("Connects a remote shell to the specified node")
+                        .long("remote")
+                        .value_name("NODE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("define")
+                        .help("Define a macro, e.g. -DTEST or -DNAME=VALUE")
+                        .short("D")
+                        .long("define")
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .multiple(true),
+                ),
+        )
         .get_matches();
 
     // Dispatch commands
-    let result: Result<(), Error> = match matches.subcommand() {
-        ("compile", Some(args)) => compiler::dispatch(&args),
-        _ => Ok(()),
+    let result: Result<i32, Error> = match matches.subcommand() {
+        ("compile", Some(args)) => compiler::dispatch(&args).map(|_| 0),
+        ("run", Some(args)) => run::dispatch(&args),
+        ("shell", Some(args)) => shell::dispatch(&args),
+        _ => Ok(0),
     };
 
     // Handle success/failure
@@ -132,6 +227,7 @@ fn main() {
             }
             process::exit(2);
         }
-        _ => return,
+        Ok(0) => return,
+        Ok(status) => process::exit(status),
     };
 }
@@ -0,0 +1,104 @@
+use clap::ArgMatches;
+use failure::{format_err, Error};
+
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{Atom, Term};
+use liblumen_compiler::Compiler;
+use liblumen_eir_interpreter::call_result::{call_run_erlang, exit_code};
+use liblumen_eir_interpreter::print;
+use liblumen_eir_interpreter::VM;
+use lumen_runtime::scheduler::Scheduler;
+
+use crate::compiler;
+
+/// Compiles the sources in `path` and runs the `--entry` function in the EIR interpreter,
+/// returning the status the `lumen` process should exit with, the same as `lumen_eir_interpreter
+/// --exit-code`
+pub fn dispatch<'a>(args: &'a ArgMatches) -> Result<i32, Error> {
+    let config = compiler::configure(args)?;
+    let (module_name, function_name) = parse_entry(args.value_of("entry").unwrap())?;
+
+    let mut compiler = Compiler::new(config);
+    let modules = compiler.parse_modules()?;
+
+    match modules
+        .iter()
+        .find(|(name, _)| &*name.as_str() == module_name)
+    {
+        None => return Err(format_err!("module {} was not found", module_name)),
+        Some((_, module)) => {
+            let defined = module
+                .functions
+                .keys()
+                .any(|ident| &*ident.name.as_str() == function_name && ident.arity == 1);
+            if !defined {
+                return Err(format_err!(
+                    "function {}:{}/1 was not found",
+                    module_name,
+                    function_name
+                ));
+            }
+        }
+    }
+
+    {
+        let mut registry = VM.modules.write().unwrap();
+        for (_, module) in modules {
+            registry
+                .register_erlang_module(module)
+                .map_err(|error| format_err!("{}", error))?;
+        }
+    }
+
+    let init_arc_process = Scheduler::current()
+        .spawn_init(0)
+        .map_err(|alloc| format_err!("could not spawn init process: {:?}", alloc))?;
+
+    let module = Atom::try_from_str(module_name).map_err(|error| format_err!("{}", error))?;
+    let function = Atom::try_from_str(function_name).map_err(|error| format_err!("{}", error))?;
+    let argv = argv(&init_arc_process, args)?;
+
+    let process_result = call_run_erlang(init_arc_process, module, function, &[argv]);
+    let status = exit_code(process_result.result);
+
+    if let Err((class, reason, stacktrace)) = process_result.result {
+        if status != 0 {
+            eprintln!(
+                "** exception {}: {}\n   stacktrace: {}",
+                print::print(class),
+                print::print(reason),
+                print::print(stacktrace)
+            );
+        }
+    }
+
+    Ok(status)
+}
+
+/// Splits `module:function`
+fn parse_entry(entry: &str) -> Result<(&str, &str), Error> {
+    match entry.find(':') {
+        Some(index) if 0 < index && index < entry.len() - 1 => {
+            Ok((&entry[..index], &entry[index + 1..]))
+        }
+        _ => Err(format_err!(
+            "invalid entry {}, expected MODULE:FUNCTION",
+            entry
+        )),
+    }
+}
+
+/// The arguments after `--` as a list of charlists, like `init:get_plain_arguments/0`
+fn argv(init_arc_process: &Process, args: &ArgMatches) -> Result<Term, Error> {
+    let mut charlists = Vec::new();
+    for arg in args.values_of("ARGS").into_iter().flatten() {
+        let charlist = init_arc_process
+            .charlist_from_str(arg)
+            .map_err(|alloc| format_err!("could not allocate argument: {:?}", alloc))?;
+        charlists.push(charlist);
+    }
+
+    init_arc_process
+        .list_from_slice(&charlists)
+        .map_err(|alloc| format_err!("could not allocate arguments: {:?}", alloc))
+}
@@ -0,0 +1,46 @@
+use clap::ArgMatches;
+use failure::{format_err, Error};
+
+use liblumen_compiler::Compiler;
+use liblumen_eir_interpreter::shell;
+use liblumen_eir_interpreter::VM;
+use lumen_runtime::scheduler::Scheduler;
+
+use crate::compiler;
+
+/// Loads the modules in `path`, if given, and then runs the interactive shell until `q()` or the
+/// end of input.
+///
+/// A `--remote` shell can't be run, as there is no distribution to connect to another node with.
+pub fn dispatch<'a>(args: &'a ArgMatches) -> Result<i32, Error> {
+    match args.value_of("remote") {
+        Some(node) => Err(format_err!(
+            "cannot connect a remote shell to {}: distribution is not supported",
+            node
+        )),
+        None => run(args),
+    }
+}
+
+fn run<'a>(args: &'a ArgMatches) -> Result<i32, Error> {
+    if args.is_present("path") {
+        let config = compiler::configure(args)?;
+        let mut compiler = Compiler::new(config);
+        let modules = compiler.parse_modules()?;
+
+        let mut registry = VM.modules.write().unwrap();
+        for (_, module) in modules {
+            registry
+                .register_erlang_module(module)
+                .map_err(|error| format_err!("{}", error))?;
+        }
+    }
+
+    let init_arc_process = Scheduler::current()
+        .spawn_init(0)
+        .map_err(|alloc| format_err!("could not spawn init process: {:?}", alloc))?;
+
+    shell::run(init_arc_process);
+
+    Ok(0)
+}
@@ -51,6 +51,84 @@ pub(crate) fn start_length_to_part_range(
     }
 }
 
+/// Options for `erlang:term_to_binary/2`
+pub struct ToBinaryOptions {
+    /// `0` means no compression; `1` to `9` compress with increasing effort.
+    pub compression_level: u8,
+    pub minor_version: u8,
+}
+
+impl ToBinaryOptions {
+    /// The level used by the bare `compressed` option
+    const DEFAULT_COMPRESSION_LEVEL: u8 = 6;
+
+    fn put_option_term(&mut self, option: Term) -> Result<&ToBinaryOptions, Exception> {
+        match option.to_typed_term().unwrap() {
+            TypedTerm::Atom(atom) => match atom.name() {
+                "compressed" => {
+                    self.compression_level = Self::DEFAULT_COMPRESSION_LEVEL;
+
+                    Ok(self)
+                }
+                _ => Err(badarg!().into()),
+            },
+            TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
+                TypedTerm::Tuple(tuple) if tuple.len() == 2 => {
+                    let atom: Atom = tuple[0].try_into()?;
+                    let value: usize = tuple[1].try_into()?;
+
+                    match (atom.name(), value) {
+                        ("compressed", 0..=9) => {
+                            self.compression_level = value as u8;
+
+                            Ok(self)
+                        }
+                        ("minor_version", 0..=2) => {
+                            self.minor_version = value as u8;
+
+                            Ok(self)
+                        }
+                        _ => Err(badarg!().into()),
+                    }
+                }
+                _ => Err(badarg!().into()),
+            },
+            _ => Err(badarg!().into()),
+        }
+    }
+}
+
+impl Default for ToBinaryOptions {
+    fn default() -> ToBinaryOptions {
+        ToBinaryOptions {
+            compression_level: 0,
+            minor_version: 1,
+        }
+    }
+}
+
+impl TryFrom<Term> for ToBinaryOptions {
+    type Error = Exception;
+
+    fn try_from(term: Term) -> Result<ToBinaryOptions, Exception> {
+        let mut options: ToBinaryOptions = Default::default();
+        let mut options_term = term;
+
+        loop {
+            match options_term.to_typed_term().unwrap() {
+                TypedTerm::Nil => return Ok(options),
+                TypedTerm::List(cons) => {
+                    options.put_option_term(cons.head)?;
+                    options_term = cons.tail;
+
+                    continue;
+                }
+                _ => return Err(badarg!().into()),
+            };
+        }
+    }
+}
+
 pub trait ToTerm {
     fn to_term(&self, options: ToTermOptions, process: &Process) -> exception::Result;
 }
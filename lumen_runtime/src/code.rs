@@ -1,6 +1,13 @@
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 
+use liblumen_alloc::erts::exception::system::Alloc;
+use liblumen_alloc::erts::process::code::stack::frame::Placement;
 use liblumen_alloc::erts::process::{code, Process};
+use liblumen_alloc::erts::term::{AsTerm, Atom, Term};
+use liblumen_alloc::ModuleFunctionArity;
+
+use crate::otp::erlang::apply_3;
 
 /// A stub that just puts the init process into `Status::Waiting`, so it remains alive without
 /// wasting CPU cycles
@@ -9,3 +16,97 @@ pub fn init(arc_process: &Arc<Process>) -> code::Result {
 
     Ok(())
 }
+
+/// Constructs a `fun module:function/arity` closure, such as one decoded from the external term
+/// format, that calls through `erlang:apply/3` when called, so that it uses whatever code is
+/// currently loaded for `module`.
+pub(crate) fn export_closure(
+    process: &Process,
+    module: Atom,
+    function: Atom,
+    arity: u8,
+) -> Result<Term, Alloc> {
+    let module_function_arity = Arc::new(ModuleFunctionArity {
+        module,
+        function,
+        arity,
+    });
+
+    process.closure_with_env_from_slice(module_function_arity, export_code, process.pid_term(), &[])
+}
+
+/// Constructs a closure for the lambda with the given `index` in `module`, such as one decoded
+/// from the external term format.
+///
+/// The free variables are stored as a single list in the env, so that the code knows how many
+/// terms to pop when it calls `module:'-fun-index-'/(arity + free_len)` through `erlang:apply/3`.
+pub(crate) fn lambda_closure(
+    process: &Process,
+    module: Atom,
+    function: Atom,
+    arity: u8,
+    creator: Term,
+    free_slice: &[Term],
+) -> Result<Term, Alloc> {
+    let module_function_arity = Arc::new(ModuleFunctionArity {
+        module,
+        function,
+        arity,
+    });
+    let free_list = process.list_from_slice(free_slice)?;
+
+    process.closure_with_env_from_slice(module_function_arity, lambda_code, creator, &[free_list])
+}
+
+// Private
+
+/// Expects the `arity` arguments of the closure's `module_function_arity` on the stack, as placed
+/// by `Closure::place_frame_with_arguments`.
+fn export_code(arc_process: &Arc<Process>) -> code::Result {
+    let module_function_arity = arc_process.current_module_function_arity().unwrap();
+    let arity = module_function_arity.arity as usize;
+
+    let mut argument_vec: Vec<Term> = Vec::with_capacity(arity);
+
+    for _ in 0..arity {
+        argument_vec.push(arc_process.stack_pop().unwrap());
+    }
+
+    let arguments = arc_process.list_from_slice(&argument_vec)?;
+
+    apply(arc_process, module_function_arity, arguments)
+}
+
+/// Expects the free variable list from the env on top of the `arity` arguments of the closure's
+/// `module_function_arity`, as placed by `Closure::place_frame_with_arguments`.
+fn lambda_code(arc_process: &Arc<Process>) -> code::Result {
+    let module_function_arity = arc_process.current_module_function_arity().unwrap();
+    let arity = module_function_arity.arity as usize;
+
+    let free_list = arc_process.stack_pop().unwrap();
+    let mut argument_vec: Vec<Term> = Vec::with_capacity(arity);
+
+    for _ in 0..arity {
+        argument_vec.push(arc_process.stack_pop().unwrap());
+    }
+
+    let arguments = arc_process.improper_list_from_slice(&argument_vec, free_list)?;
+
+    apply(arc_process, module_function_arity, arguments)
+}
+
+fn apply(
+    arc_process: &Arc<Process>,
+    module_function_arity: Arc<ModuleFunctionArity>,
+    arguments: Term,
+) -> code::Result {
+    apply_3::place_frame_with_arguments(
+        arc_process,
+        Placement::Replace,
+        unsafe { module_function_arity.module.as_term() },
+        unsafe { module_function_arity.function.as_term() },
+        arguments,
+    )?;
+
+    Process::call_code(arc_process)
+}
@@ -47,14 +47,14 @@ mod send;
 mod stacktrace;
 // `pub` for `examples/spawn-chain`
 pub mod system;
-// `pub` for `examples/spawn-chain`
-mod term;
+// `pub` for tooling that converts `liblumen_beam::serialization::etf::Term`s
+pub mod term;
 // `pub` to allow `time::monotonic::set_source(callback)`
 #[cfg(test)]
 mod test;
 pub mod time;
-// Public so that external code can all `timer::expire` to expire timers
-mod timer;
+// `pub` for the timers of `receive ... after` in `liblumen_eir_interpreter`
+pub mod timer;
 mod tuple;
 
 use self::config::Config;
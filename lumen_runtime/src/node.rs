@@ -1 +1,47 @@
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use liblumen_core::locks::RwLock;
+
+use liblumen_alloc::erts::term::Atom;
+
 pub const DEAD: &str = "nonode@nohost";
+
+/// The `node_id` of the local node in `Pid`s, `ExternalPid`s and `ExternalReference`s.
+pub const LOCAL_ID: usize = 0;
+
+/// Returns the `node_id` used for `ExternalPid`s and `ExternalReference`s from the node named
+/// `name`, assigning the next unused `node_id` the first time `name` is seen.
+pub fn id(name: Atom) -> usize {
+    if name.name() == DEAD {
+        return LOCAL_ID;
+    }
+
+    if let Some(id) = RW_LOCK_ID_BY_NAME.read().get(&name) {
+        return *id;
+    }
+
+    let mut writable_id_by_name = RW_LOCK_ID_BY_NAME.write();
+    let mut writable_name_by_id = RW_LOCK_NAME_BY_ID.write();
+
+    *writable_id_by_name.entry(name).or_insert_with(|| {
+        writable_name_by_id.push(name);
+
+        // `LOCAL_ID` is not stored in `RW_LOCK_NAME_BY_ID`
+        writable_name_by_id.len()
+    })
+}
+
+/// Returns the name of the node with `id`, as assigned by `id`.
+pub fn name(id: usize) -> Atom {
+    match id {
+        LOCAL_ID => Atom::try_from_str(DEAD).unwrap(),
+        _ => RW_LOCK_NAME_BY_ID.read()[id - 1],
+    }
+}
+
+lazy_static! {
+    static ref RW_LOCK_ID_BY_NAME: RwLock<HashMap<Atom, usize>> = Default::default();
+    static ref RW_LOCK_NAME_BY_ID: RwLock<Vec<Atom>> = Default::default();
+}
@@ -1,5 +1,7 @@
 //! All modules under the OTP namespace should mirror module shipped with C-BEAM OTP
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod beam_lib;
 pub mod binary;
 pub mod erlang;
 pub mod lists;
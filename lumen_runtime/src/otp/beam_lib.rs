@@ -0,0 +1,1135 @@
+//! Mirrors [beam_lib](http://erlang.org/doc/man/beam_lib.html) module
+//!
+//! BEAM files are read and written with `liblumen_beam`'s reader, so this module is only available
+//! where `std` and a file system are.
+
+#[cfg(test)]
+mod test;
+
+use core::convert::TryInto;
+
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use num_bigint::{BigInt, Sign};
+
+use liblumen_beam::beam::chunk::{
+    self, AtomChunk, Chunk, ExpTChunk, ImpTChunk, LocTChunk, RawChunk,
+};
+use liblumen_beam::beam::reader::{RawBeamFile, ReadError};
+
+use liblumen_alloc::badarg;
+use liblumen_alloc::erts::exception::system::Alloc;
+use liblumen_alloc::erts::exception::{Exception, Result};
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::{atom_unchecked, AsTerm, Atom, Term, TypedTerm};
+
+use crate::system;
+use crate::term::external_format;
+
+/// The chunks needed to load a module, which are the only ones kept by `strip/1`.  The same chunks
+/// minus `"Line"` are the ones that `md5/1` hashes.
+const SIGNIFICANT_CHUNK_IDS: &[&chunk::Id] = &[
+    b"Line", b"Atom", b"AtU8", b"Code", b"StrT", b"ImpT", b"ExpT", b"FunT", b"LitT",
+];
+
+pub fn all_chunks_1(file: Term, process: &Process) -> Result {
+    with_beam(file, process, |beam| {
+        let module = beam.module()?;
+        let mut chunk_vec = Vec::new();
+
+        for chunk in beam.file.chunks() {
+            let id = chunk_id_to_term(&chunk.id, process)?;
+            let data = process.binary_from_bytes(&chunk.data)?;
+            chunk_vec.push(process.tuple_from_slice(&[id, data])?);
+        }
+
+        let chunk_list = process.list_from_slice(&chunk_vec)?;
+
+        process
+            .tuple_from_slice(&[atom_unchecked("ok"), atom_to_term(module), chunk_list])
+            .map_err(|alloc| alloc.into())
+    })
+}
+
+pub fn chunks_2(beam: Term, chunk_refs: Term, process: &Process) -> Result {
+    chunks_3(beam, chunk_refs, Term::NIL, process)
+}
+
+pub fn chunks_3(beam: Term, chunk_refs: Term, options: Term, process: &Process) -> Result {
+    let chunk_ref_vec = chunk_refs_from_term(chunk_refs)?;
+    let allow_missing_chunks = allow_missing_chunks_from_options(options)?;
+
+    with_beam(beam, process, |beam| {
+        let module = beam.module()?;
+        let mut data_vec = Vec::with_capacity(chunk_ref_vec.len());
+
+        for chunk_ref in &chunk_ref_vec {
+            let data = match beam.chunk_data(chunk_ref, process) {
+                Err(Error::BeamLib(Reason::MissingChunk(_))) if allow_missing_chunks => process
+                    .tuple_from_slice(&[
+                        chunk_ref.to_term(process)?,
+                        atom_unchecked("missing_chunk"),
+                    ])?,
+                result => result?,
+            };
+
+            data_vec.push(data);
+        }
+
+        let data_list = process.list_from_slice(&data_vec)?;
+
+        let module_data_list = process.tuple_from_slice(&[atom_to_term(module), data_list])?;
+
+        Ok(ok(module_data_list, process)?)
+    })
+}
+
+pub fn cmp_2(beam1: Term, beam2: Term, process: &Process) -> Result {
+    let source1 = Source::try_from_term(beam1)?;
+    let source2 = Source::try_from_term(beam2)?;
+
+    match cmp(&source1, &source2) {
+        Ok(()) => Ok(atom_unchecked("ok")),
+        Err((source, error)) => to_result(Err(error), source, process),
+    }
+}
+
+/// Compares the `.beam` files with the same names in `directory1` and `directory2`, printing which
+/// files are only in one of the directories and which are different.
+pub fn diff_dirs_2(directory1: Term, directory2: Term, process: &Process) -> Result {
+    let path1 = path_from_term(directory1)?;
+    let path2 = path_from_term(directory2)?;
+
+    let (names1, names2) = match (beam_file_names(&path1), beam_file_names(&path2)) {
+        (Ok(names1), Ok(names2)) => (names1, names2),
+        (Err(error), _) => return to_result(Err(error), &Source::File(path1), process),
+        (_, Err(error)) => return to_result(Err(error), &Source::File(path2), process),
+    };
+
+    let only1: Vec<&String> = names1
+        .iter()
+        .filter(|name| !names2.contains(name))
+        .collect();
+    let only2: Vec<&String> = names2
+        .iter()
+        .filter(|name| !names1.contains(name))
+        .collect();
+
+    if !only1.is_empty() {
+        system::io::puts(&format!("Only in {}: {:?}", path1.display(), only1));
+    }
+
+    if !only2.is_empty() {
+        system::io::puts(&format!("Only in {}: {:?}", path2.display(), only2));
+    }
+
+    for name in names1.iter().filter(|name| names2.contains(name)) {
+        let source1 = Source::File(path1.join(name));
+        let source2 = Source::File(path2.join(name));
+
+        match cmp(&source1, &source2) {
+            Ok(()) => (),
+            Err((_, Error::BeamLib(_))) => system::io::puts(&format!(
+                "** different: {{{:?},{:?}}}",
+                source1.path().unwrap().display(),
+                source2.path().unwrap().display()
+            )),
+            Err((_, Error::Exception(exception))) => return Err(exception),
+        }
+    }
+
+    Ok(atom_unchecked("ok"))
+}
+
+pub fn info_1(beam: Term, process: &Process) -> Result {
+    with_beam(beam, process, |beam| {
+        let module = beam.module()?;
+
+        let source_tuple = match beam.source {
+            Source::File(ref path) => process.tuple_from_slice(&[
+                atom_unchecked("file"),
+                process.charlist_from_str(&path.to_string_lossy())?,
+            ])?,
+            Source::Binary(ref bytes) => process
+                .tuple_from_slice(&[atom_unchecked("binary"), process.binary_from_bytes(bytes)?])?,
+        };
+        let module_tuple =
+            process.tuple_from_slice(&[atom_unchecked("module"), atom_to_term(module)])?;
+
+        // After the `FOR1`, size and `BEAM` header, each chunk has a 4 byte id and 4 byte size
+        // before its data, which is padded to a multiple of 4 bytes.
+        let mut position = 12;
+        let mut chunk_vec = Vec::new();
+
+        for chunk in beam.file.chunks() {
+            position += 8;
+            let size = chunk.data.len();
+
+            chunk_vec.push(process.tuple_from_slice(&[
+                chunk_id_to_term(&chunk.id, process)?,
+                process.integer(position)?,
+                process.integer(size)?,
+            ])?);
+
+            position += (size + 3) / 4 * 4;
+        }
+
+        let chunks_tuple = process.tuple_from_slice(&[
+            atom_unchecked("chunks"),
+            process.list_from_slice(&chunk_vec)?,
+        ])?;
+
+        process
+            .list_from_slice(&[source_tuple, module_tuple, chunks_tuple])
+            .map_err(|alloc| alloc.into())
+    })
+}
+
+pub fn md5_1(beam: Term, process: &Process) -> Result {
+    with_beam(beam, process, |beam| {
+        let module = beam.module()?;
+        let digest = process.binary_from_bytes(&beam.md5())?;
+
+        let module_digest = process.tuple_from_slice(&[atom_to_term(module), digest])?;
+
+        Ok(ok(module_digest, process)?)
+    })
+}
+
+/// Strips all chunks not needed by the loader from `beam`.  If `beam` is a file name, the file is
+/// rewritten and the file name is returned; if `beam` is a binary, the stripped binary is returned.
+pub fn strip_1(beam: Term, process: &Process) -> Result {
+    strip_2(beam, Term::NIL, process)
+}
+
+/// Same as `strip_1`, but also keeps the `additional_chunks`, given as chunk ids, such as `"Attr"`.
+pub fn strip_2(beam: Term, additional_chunks: Term, process: &Process) -> Result {
+    let source = Source::try_from_term(beam)?;
+    let additional_chunk_ids = chunk_ids_from_term(additional_chunks)?;
+
+    match strip(&source, &additional_chunk_ids, process) {
+        Ok(stripped) => ok(stripped, process),
+        error => to_result(error, &source, process),
+    }
+}
+
+pub fn strip_files_1(files: Term, process: &Process) -> Result {
+    let mut source_vec = Vec::new();
+
+    match files.to_typed_term().unwrap() {
+        TypedTerm::Nil => (),
+        TypedTerm::List(cons) => {
+            for result in cons.into_iter() {
+                match result {
+                    Ok(file) => source_vec.push(Source::try_from_term(file)?),
+                    Err(_) => return Err(badarg!().into()),
+                }
+            }
+        }
+        _ => return Err(badarg!().into()),
+    }
+
+    let mut stripped_vec = Vec::with_capacity(source_vec.len());
+
+    for source in &source_vec {
+        match strip(source, &[], process) {
+            Ok(stripped) => stripped_vec.push(stripped),
+            error => return to_result(error, source, process),
+        }
+    }
+
+    let stripped_list = process.list_from_slice(&stripped_vec)?;
+
+    ok(stripped_list, process)
+}
+
+/// Returns the `vsn` attribute, which the compiler sets to the module's MD5 when the module does
+/// not set it explicitly.
+pub fn version_1(beam: Term, process: &Process) -> Result {
+    with_beam(beam, process, |beam| {
+        let module = beam.module()?;
+        let attributes = beam.chunk_data(&ChunkRef::Name(Name::Attributes), process)?;
+        let vsn = atom_unchecked("vsn");
+
+        let version = match keyfind(vsn, value_of_tagged_tuple(attributes)?)? {
+            Some(version) => version,
+            None => {
+                let md5 = BigInt::from_bytes_be(Sign::Plus, &beam.md5());
+                process.list_from_slice(&[process.integer(md5)?])?
+            }
+        };
+
+        let module_version = process.tuple_from_slice(&[atom_to_term(module), version])?;
+
+        Ok(ok(module_version, process)?)
+    })
+}
+
+// Private
+
+struct Beam {
+    source: Source,
+    file: RawBeamFile,
+}
+
+impl Beam {
+    fn read(source: Source) -> std::result::Result<Self, Error> {
+        let bytes = match source {
+            Source::File(ref path) => {
+                fs::read(path).map_err(|error| Reason::FileError(error.kind()))?
+            }
+            Source::Binary(ref bytes) => bytes.clone(),
+        };
+
+        match RawBeamFile::from_reader(&bytes[..]) {
+            Ok(file) => Ok(Beam { source, file }),
+            Err(ReadError::UnexpectedMagicNumber(_)) | Err(ReadError::UnexpectedFormType(_)) => {
+                Err(Reason::NotABeamFile.into())
+            }
+            // The reader does not track how far it got, so the whole input is reported
+            Err(_) => Err(Reason::InvalidBeamFile(bytes.len()).into()),
+        }
+    }
+
+    fn raw_chunk(&self, id: &chunk::Id) -> std::result::Result<&RawChunk, Error> {
+        self.file
+            .get_chunk(id)
+            .ok_or_else(|| Reason::MissingChunk(*id).into())
+    }
+
+    fn atom_chunk(&self) -> std::result::Result<AtomChunk, Error> {
+        let raw_chunk = self
+            .file
+            .get_chunk(b"AtU8")
+            .or_else(|| self.file.get_chunk(b"Atom"))
+            .ok_or(Reason::MissingChunk(*b"Atom"))?;
+
+        decode_chunk(raw_chunk)
+    }
+
+    fn atom_name(atom_chunk: &AtomChunk, id: u32) -> std::result::Result<&str, Error> {
+        (id as usize)
+            .checked_sub(1)
+            .and_then(|index| atom_chunk.atoms.get(index))
+            .map(|atom| atom.name.as_str())
+            .ok_or_else(|| Reason::InvalidChunk(*b"Atom").into())
+    }
+
+    fn module(&self) -> std::result::Result<Atom, Error> {
+        let atom_chunk = self.atom_chunk()?;
+        let name = Self::atom_name(&atom_chunk, 1)?;
+
+        Atom::try_from_str(name).map_err(|_| Reason::InvalidChunk(*b"Atom").into())
+    }
+
+    fn chunk_data(
+        &self,
+        chunk_ref: &ChunkRef,
+        process: &Process,
+    ) -> std::result::Result<Term, Error> {
+        let data = match chunk_ref {
+            ChunkRef::Id(id) => process.binary_from_bytes(&self.raw_chunk(id)?.data)?,
+            ChunkRef::Name(name) => self.named_chunk_data(*name, process)?,
+            ChunkRef::Unknown(atom) => return Err(Reason::UnknownChunk(*atom).into()),
+        };
+
+        process
+            .tuple_from_slice(&[chunk_ref.to_term(process)?, data])
+            .map_err(|alloc| alloc.into())
+    }
+
+    fn named_chunk_data(&self, name: Name, process: &Process) -> std::result::Result<Term, Error> {
+        match name {
+            Name::AbstractCode => self.abstract_code(process),
+            Name::Atoms => {
+                let atom_chunk = self.atom_chunk()?;
+                let mut atom_vec = Vec::with_capacity(atom_chunk.atoms.len());
+
+                for (index, atom) in atom_chunk.atoms.iter().enumerate() {
+                    atom_vec.push(process.tuple_from_slice(&[
+                        process.integer(index + 1)?,
+                        atom_from_str(&atom.name)?,
+                    ])?);
+                }
+
+                process
+                    .list_from_slice(&atom_vec)
+                    .map_err(|alloc| alloc.into())
+            }
+            Name::Attributes => {
+                let attributes = self.term_chunk(b"Attr", process)?;
+
+                merge_attributes(attributes, process)
+            }
+            Name::CompileInfo => self.term_chunk(b"CInf", process),
+            Name::DebugInfo => match self.file.get_chunk(b"Dbgi") {
+                Some(_) => self.term_chunk(b"Dbgi", process),
+                None => Ok(atom_unchecked("no_debug_info")),
+            },
+            Name::Exports | Name::LabeledExports => {
+                let atom_chunk = self.atom_chunk()?;
+                let exp_t_chunk: ExpTChunk = decode_chunk(self.raw_chunk(b"ExpT")?)?;
+                let mut functions: Vec<(&str, u32, u32)> = Vec::new();
+
+                for export in &exp_t_chunk.exports {
+                    let function = Self::atom_name(&atom_chunk, export.function)?;
+                    functions.push((function, export.arity, export.label));
+                }
+
+                function_list(functions, name == Name::LabeledExports, process)
+            }
+            Name::Locals | Name::LabeledLocals => {
+                let atom_chunk = self.atom_chunk()?;
+                let loc_t_chunk: LocTChunk = decode_chunk(self.raw_chunk(b"LocT")?)?;
+                let mut functions: Vec<(&str, u32, u32)> = Vec::new();
+
+                for local in &loc_t_chunk.locals {
+                    let function = Self::atom_name(&atom_chunk, local.function)?;
+                    functions.push((function, local.arity, local.label));
+                }
+
+                function_list(functions, name == Name::LabeledLocals, process)
+            }
+            Name::Imports | Name::IndexedImports => {
+                let atom_chunk = self.atom_chunk()?;
+                let imp_t_chunk: ImpTChunk = decode_chunk(self.raw_chunk(b"ImpT")?)?;
+                let mut import_vec = Vec::with_capacity(imp_t_chunk.imports.len());
+
+                for (index, import) in imp_t_chunk.imports.iter().enumerate() {
+                    let module = atom_from_str(Self::atom_name(&atom_chunk, import.module)?)?;
+                    let function = atom_from_str(Self::atom_name(&atom_chunk, import.function)?)?;
+                    let arity = process.integer(import.arity as usize)?;
+
+                    let import_tuple = if name == Name::IndexedImports {
+                        process.tuple_from_slice(&[
+                            process.integer(index)?,
+                            module,
+                            function,
+                            arity,
+                        ])?
+                    } else {
+                        process.tuple_from_slice(&[module, function, arity])?
+                    };
+
+                    import_vec.push(import_tuple);
+                }
+
+                process
+                    .list_from_slice(&import_vec)
+                    .map_err(|alloc| alloc.into())
+            }
+        }
+    }
+
+    /// The abstract code comes from the `"Abst"` chunk written by older compilers or from the
+    /// `"Dbgi"` chunk when it was written by the `erl_abstract_code` backend.  Other backends, such
+    /// as Elixir's, need their backend module to produce abstract code, so they have none here.
+    fn abstract_code(&self, process: &Process) -> std::result::Result<Term, Error> {
+        let no_abstract_code = atom_unchecked("no_abstract_code");
+
+        if let Some(raw_chunk) = self.file.get_chunk(b"Abst") {
+            return if raw_chunk.data.is_empty() {
+                Ok(no_abstract_code)
+            } else {
+                self.term_chunk(b"Abst", process)
+            };
+        }
+
+        if self.file.get_chunk(b"Dbgi").is_none() {
+            return Ok(no_abstract_code);
+        }
+
+        let debug_info = self.term_chunk(b"Dbgi", process)?;
+        let invalid = || Error::from(Reason::InvalidChunk(*b"Dbgi"));
+
+        // {debug_info_v1, erl_abstract_code, {AbstractCode, CompileOptions}}
+        let debug_info_tuple = tuple_elements(debug_info).ok_or_else(invalid)?;
+
+        match debug_info_tuple.as_slice() {
+            [version, backend, data]
+                if *version == atom_unchecked("debug_info_v1")
+                    && *backend == atom_unchecked("erl_abstract_code") =>
+            {
+                match tuple_elements(*data).ok_or_else(invalid)?.as_slice() {
+                    [forms, _] if *forms == atom_unchecked("none") => Ok(no_abstract_code),
+                    [forms, _] => process
+                        .tuple_from_slice(&[atom_unchecked("raw_abstract_v1"), *forms])
+                        .map_err(|alloc| alloc.into()),
+                    _ => Err(invalid()),
+                }
+            }
+            [version, _, _] if *version == atom_unchecked("debug_info_v1") => Ok(no_abstract_code),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Decodes a chunk that holds a single term in the external term format
+    fn term_chunk(&self, id: &chunk::Id, process: &Process) -> std::result::Result<Term, Error> {
+        let raw_chunk = self.raw_chunk(id)?;
+
+        match external_format::decode(&raw_chunk.data, false, process) {
+            Ok((term, _)) => Ok(term),
+            Err(Exception::System(system)) => Err(Exception::System(system).into()),
+            Err(Exception::Runtime(_)) => Err(Reason::InvalidChunk(*id).into()),
+        }
+    }
+
+    /// The MD5 of the significant chunks, which is the same for modules that only differ in
+    /// debug info, attributes or compile info.
+    fn md5(&self) -> [u8; 16] {
+        let mut context = md5::Context::new();
+
+        for id in SIGNIFICANT_CHUNK_IDS.iter().filter(|id| **id != b"Line") {
+            if let Some(raw_chunk) = self.file.get_chunk(id) {
+                if *id == b"FunT" {
+                    context.consume(&without_old_uniq(&raw_chunk.data));
+                } else {
+                    context.consume(&raw_chunk.data);
+                }
+            }
+        }
+
+        context.compute().0
+    }
+
+    /// The chunks in file order that are significant or whose id is in `additional_chunk_ids`
+    fn retained_chunks<'a>(&'a self, additional_chunk_ids: &'a [chunk::Id]) -> Vec<&'a RawChunk> {
+        self.file
+            .chunks()
+            .into_iter()
+            .filter(|raw_chunk| {
+                SIGNIFICANT_CHUNK_IDS.contains(&&raw_chunk.id)
+                    || additional_chunk_ids.contains(&raw_chunk.id)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Name {
+    AbstractCode,
+    Atoms,
+    Attributes,
+    CompileInfo,
+    DebugInfo,
+    Exports,
+    Imports,
+    IndexedImports,
+    LabeledExports,
+    LabeledLocals,
+    Locals,
+}
+
+impl Name {
+    fn from_str(name: &str) -> Option<Self> {
+        match name {
+            "abstract_code" => Some(Name::AbstractCode),
+            "atoms" => Some(Name::Atoms),
+            "attributes" => Some(Name::Attributes),
+            "compile_info" => Some(Name::CompileInfo),
+            "debug_info" => Some(Name::DebugInfo),
+            "exports" => Some(Name::Exports),
+            "imports" => Some(Name::Imports),
+            "indexed_imports" => Some(Name::IndexedImports),
+            "labeled_exports" => Some(Name::LabeledExports),
+            "labeled_locals" => Some(Name::LabeledLocals),
+            "locals" => Some(Name::Locals),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Name::AbstractCode => "abstract_code",
+            Name::Atoms => "atoms",
+            Name::Attributes => "attributes",
+            Name::CompileInfo => "compile_info",
+            Name::DebugInfo => "debug_info",
+            Name::Exports => "exports",
+            Name::Imports => "imports",
+            Name::IndexedImports => "indexed_imports",
+            Name::LabeledExports => "labeled_exports",
+            Name::LabeledLocals => "labeled_locals",
+            Name::Locals => "locals",
+        }
+    }
+}
+
+/// A chunk is either referenced by the name of the decoded data or by the 4 character chunk id,
+/// in which case the chunk's data is returned as a binary.
+enum ChunkRef {
+    Id(chunk::Id),
+    Name(Name),
+    Unknown(Atom),
+}
+
+impl ChunkRef {
+    fn to_term(&self, process: &Process) -> std::result::Result<Term, Alloc> {
+        match self {
+            ChunkRef::Id(id) => chunk_id_to_term(id, process),
+            ChunkRef::Name(name) => Ok(atom_unchecked(name.as_str())),
+            ChunkRef::Unknown(atom) => Ok(atom_to_term(*atom)),
+        }
+    }
+}
+
+enum Source {
+    File(PathBuf),
+    Binary(Vec<u8>),
+}
+
+impl Source {
+    /// A binary is the contents of a BEAM file, while anything else is a file name, to which
+    /// `.beam` is added if it does not already have that extension.
+    fn try_from_term(term: Term) -> std::result::Result<Self, Exception> {
+        let bytes_result: std::result::Result<Vec<u8>, _> = term.try_into();
+
+        match bytes_result {
+            Ok(bytes) => Ok(Source::Binary(bytes)),
+            Err(_) => {
+                let mut path = path_from_term(term)?;
+
+                if path
+                    .extension()
+                    .map_or(true, |extension| extension != "beam")
+                {
+                    let mut file_name = path.file_name().ok_or_else(|| badarg!())?.to_owned();
+                    file_name.push(".beam");
+                    path.set_file_name(file_name);
+                }
+
+                Ok(Source::File(path))
+            }
+        }
+    }
+
+    fn path(&self) -> Option<&Path> {
+        match self {
+            Source::File(path) => Some(path),
+            Source::Binary(_) => None,
+        }
+    }
+
+    /// The file name or binary as it is reported in error reasons
+    fn to_term(&self, process: &Process) -> std::result::Result<Term, Alloc> {
+        match self {
+            Source::File(path) => process.charlist_from_str(&path.to_string_lossy()),
+            Source::Binary(bytes) => process.binary_from_bytes(bytes),
+        }
+    }
+}
+
+/// The reasons in `{error, beam_lib, Reason}`
+enum Reason {
+    ChunksDifferent(chunk::Id),
+    DifferentChunks,
+    FileError(io::ErrorKind),
+    InvalidBeamFile(usize),
+    InvalidChunk(chunk::Id),
+    MissingChunk(chunk::Id),
+    ModulesDifferent(Atom, Atom),
+    NotABeamFile,
+    UnknownChunk(Atom),
+}
+
+impl Reason {
+    fn to_term(&self, source: &Source, process: &Process) -> std::result::Result<Term, Alloc> {
+        let file = || source.to_term(process);
+
+        match self {
+            Reason::ChunksDifferent(id) => process.tuple_from_slice(&[
+                atom_unchecked("chunks_different"),
+                chunk_id_to_term(id, process)?,
+            ]),
+            Reason::DifferentChunks => Ok(atom_unchecked("different_chunks")),
+            Reason::FileError(kind) => process.tuple_from_slice(&[
+                atom_unchecked("file_error"),
+                file()?,
+                atom_unchecked(posix(*kind)),
+            ]),
+            Reason::InvalidBeamFile(position) => process.tuple_from_slice(&[
+                atom_unchecked("invalid_beam_file"),
+                file()?,
+                process.integer(*position)?,
+            ]),
+            Reason::InvalidChunk(id) => process.tuple_from_slice(&[
+                atom_unchecked("invalid_chunk"),
+                file()?,
+                chunk_id_to_term(id, process)?,
+            ]),
+            Reason::MissingChunk(id) => process.tuple_from_slice(&[
+                atom_unchecked("missing_chunk"),
+                file()?,
+                chunk_id_to_term(id, process)?,
+            ]),
+            Reason::ModulesDifferent(module1, module2) => process.tuple_from_slice(&[
+                atom_unchecked("modules_different"),
+                atom_to_term(*module1),
+                atom_to_term(*module2),
+            ]),
+            Reason::NotABeamFile => {
+                process.tuple_from_slice(&[atom_unchecked("not_a_beam_file"), file()?])
+            }
+            Reason::UnknownChunk(name) => process.tuple_from_slice(&[
+                atom_unchecked("unknown_chunk"),
+                file()?,
+                atom_to_term(*name),
+            ]),
+        }
+    }
+}
+
+/// `beam_lib` returns errors about the BEAM files, such as missing chunks, as
+/// `{error, beam_lib, Reason}`, but still raises for bad arguments and allocation failures.
+enum Error {
+    BeamLib(Reason),
+    Exception(Exception),
+}
+
+impl From<Reason> for Error {
+    fn from(reason: Reason) -> Self {
+        Error::BeamLib(reason)
+    }
+}
+
+impl From<Exception> for Error {
+    fn from(exception: Exception) -> Self {
+        Error::Exception(exception)
+    }
+}
+
+impl From<Alloc> for Error {
+    fn from(alloc: Alloc) -> Self {
+        Error::Exception(alloc.into())
+    }
+}
+
+fn allow_missing_chunks_from_options(options: Term) -> std::result::Result<bool, Exception> {
+    let mut allow_missing_chunks = false;
+
+    match options.to_typed_term().unwrap() {
+        TypedTerm::Nil => (),
+        TypedTerm::List(cons) => {
+            for result in cons.into_iter() {
+                match result {
+                    Ok(option) if option == atom_unchecked("allow_missing_chunks") => {
+                        allow_missing_chunks = true
+                    }
+                    _ => return Err(badarg!().into()),
+                }
+            }
+        }
+        _ => return Err(badarg!().into()),
+    }
+
+    Ok(allow_missing_chunks)
+}
+
+fn atom_from_str(name: &str) -> std::result::Result<Term, Exception> {
+    Atom::try_from_str(name)
+        .map(|atom| unsafe { atom.as_term() })
+        .map_err(|error| error.into())
+}
+
+fn atom_to_term(atom: Atom) -> Term {
+    unsafe { atom.as_term() }
+}
+
+fn beam_file_names(directory: &Path) -> std::result::Result<Vec<String>, Error> {
+    let read_dir = fs::read_dir(directory).map_err(|error| Reason::FileError(error.kind()))?;
+    let mut name_vec = Vec::new();
+
+    for result in read_dir {
+        let path = result
+            .map_err(|error| Reason::FileError(error.kind()))?
+            .path();
+
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "beam")
+        {
+            if let Some(name) = path.file_name() {
+                name_vec.push(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    name_vec.sort();
+
+    Ok(name_vec)
+}
+
+fn chunk_id_from_term(term: Term) -> std::result::Result<chunk::Id, Exception> {
+    let string = string_from_charlist(term)?;
+    let bytes = string.as_bytes();
+
+    if bytes.len() == 4 {
+        let mut id = [0; 4];
+        id.copy_from_slice(bytes);
+
+        Ok(id)
+    } else {
+        Err(badarg!().into())
+    }
+}
+
+fn chunk_id_to_term(id: &chunk::Id, process: &Process) -> std::result::Result<Term, Alloc> {
+    process.charlist_from_str(&String::from_utf8_lossy(id))
+}
+
+fn chunk_ids_from_term(list: Term) -> std::result::Result<Vec<chunk::Id>, Exception> {
+    match list.to_typed_term().unwrap() {
+        TypedTerm::Nil => Ok(Vec::new()),
+        TypedTerm::List(cons) => cons
+            .into_iter()
+            .map(|result| match result {
+                Ok(element) => chunk_id_from_term(element),
+                Err(_) => Err(badarg!().into()),
+            })
+            .collect(),
+        _ => Err(badarg!().into()),
+    }
+}
+
+/// Chunk names that `beam_lib` does not know are reported as `{unknown_chunk, File, Name}` once
+/// the BEAM file is read, so they are kept as `ChunkRef::Unknown` instead of raising `badarg`.
+fn chunk_refs_from_term(list: Term) -> std::result::Result<Vec<ChunkRef>, Exception> {
+    let mut chunk_ref_vec = Vec::new();
+
+    match list.to_typed_term().unwrap() {
+        TypedTerm::Nil => (),
+        TypedTerm::List(cons) => {
+            for result in cons.into_iter() {
+                let element = result.map_err(|_| badarg!())?;
+
+                let chunk_ref = match element.to_typed_term().unwrap() {
+                    TypedTerm::Atom(atom) => match Name::from_str(atom.name()) {
+                        Some(name) => ChunkRef::Name(name),
+                        None => ChunkRef::Unknown(atom),
+                    },
+                    _ => ChunkRef::Id(chunk_id_from_term(element)?),
+                };
+
+                chunk_ref_vec.push(chunk_ref);
+            }
+        }
+        _ => return Err(badarg!().into()),
+    }
+
+    Ok(chunk_ref_vec)
+}
+
+/// On error, also returns the source that the error is reported against
+fn cmp<'a>(
+    source1: &'a Source,
+    source2: &'a Source,
+) -> std::result::Result<(), (&'a Source, Error)> {
+    let beam1 = Beam::read(clone_source(source1)).map_err(|error| (source1, error))?;
+    let beam2 = Beam::read(clone_source(source2)).map_err(|error| (source2, error))?;
+
+    let module1 = beam1.module().map_err(|error| (source1, error))?;
+    let module2 = beam2.module().map_err(|error| (source2, error))?;
+
+    if module1 != module2 {
+        return Err((source1, Reason::ModulesDifferent(module1, module2).into()));
+    }
+
+    let chunks1 = beam1.retained_chunks(&[]);
+    let chunks2 = beam2.retained_chunks(&[]);
+
+    let ids1: Vec<chunk::Id> = chunks1.iter().map(|raw_chunk| raw_chunk.id).collect();
+    let ids2: Vec<chunk::Id> = chunks2.iter().map(|raw_chunk| raw_chunk.id).collect();
+
+    if ids1.len() != ids2.len() || ids1.iter().any(|id| !ids2.contains(id)) {
+        return Err((source1, Reason::DifferentChunks.into()));
+    }
+
+    for raw_chunk1 in chunks1 {
+        let raw_chunk2 = beam2
+            .raw_chunk(&raw_chunk1.id)
+            .map_err(|error| (source2, error))?;
+
+        let equal = if &raw_chunk1.id == b"FunT" {
+            without_old_uniq(&raw_chunk1.data) == without_old_uniq(&raw_chunk2.data)
+        } else {
+            raw_chunk1.data == raw_chunk2.data
+        };
+
+        if !equal {
+            return Err((source1, Reason::ChunksDifferent(raw_chunk1.id).into()));
+        }
+    }
+
+    Ok(())
+}
+
+fn clone_source(source: &Source) -> Source {
+    match source {
+        Source::File(path) => Source::File(path.clone()),
+        Source::Binary(bytes) => Source::Binary(bytes.clone()),
+    }
+}
+
+fn decode_chunk<C: Chunk>(raw_chunk: &RawChunk) -> std::result::Result<C, Error> {
+    C::decode_data(&raw_chunk.id, Cursor::new(&raw_chunk.data))
+        .map_err(|_| Reason::InvalidChunk(raw_chunk.id).into())
+}
+
+fn error_tuple(reason: &Reason, source: &Source, process: &Process) -> Result {
+    let reason_term = reason.to_term(source, process)?;
+
+    process
+        .tuple_from_slice(&[
+            atom_unchecked("error"),
+            atom_unchecked("beam_lib"),
+            reason_term,
+        ])
+        .map_err(|alloc| alloc.into())
+}
+
+/// `[{Function, Arity}]` or `[{Function, Arity, Label}]` sorted by function and arity
+fn function_list(
+    mut functions: Vec<(&str, u32, u32)>,
+    labeled: bool,
+    process: &Process,
+) -> std::result::Result<Term, Error> {
+    functions.sort();
+
+    let mut function_vec = Vec::with_capacity(functions.len());
+
+    for (function, arity, label) in functions {
+        let function_term = atom_from_str(function)?;
+        let arity_term = process.integer(arity as usize)?;
+
+        let function_tuple = if labeled {
+            process.tuple_from_slice(&[
+                function_term,
+                arity_term,
+                process.integer(label as usize)?,
+            ])?
+        } else {
+            process.tuple_from_slice(&[function_term, arity_term])?
+        };
+
+        function_vec.push(function_tuple);
+    }
+
+    process
+        .list_from_slice(&function_vec)
+        .map_err(|alloc| alloc.into())
+}
+
+fn keyfind(key: Term, list: Term) -> std::result::Result<Option<Term>, Error> {
+    match list.to_typed_term().unwrap() {
+        TypedTerm::Nil => Ok(None),
+        TypedTerm::List(cons) => {
+            for result in cons.into_iter() {
+                let element = result.map_err(|_| Reason::InvalidChunk(*b"Attr"))?;
+
+                match tuple_elements(element).as_ref().map(Vec::as_slice) {
+                    Some([element_key, value]) if *element_key == key => return Ok(Some(*value)),
+                    _ => (),
+                }
+            }
+
+            Ok(None)
+        }
+        _ => Err(Reason::InvalidChunk(*b"Attr").into()),
+    }
+}
+
+/// Attributes that appear more than once, such as `-export`, are merged into one entry whose value
+/// is the concatenation of the values, sorted by attribute name.
+fn merge_attributes(attributes: Term, process: &Process) -> std::result::Result<Term, Error> {
+    let invalid = || Error::from(Reason::InvalidChunk(*b"Attr"));
+    let mut merged: Vec<(Atom, Vec<Term>)> = Vec::new();
+
+    match attributes.to_typed_term().unwrap() {
+        TypedTerm::Nil => (),
+        TypedTerm::List(cons) => {
+            for result in cons.into_iter() {
+                let attribute = result.map_err(|_| invalid())?;
+                let (key, value) = match tuple_elements(attribute).ok_or_else(invalid)?.as_slice() {
+                    [key, value] => {
+                        let key: Atom = (*key).try_into().map_err(|_| invalid())?;
+
+                        (key, *value)
+                    }
+                    _ => return Err(invalid()),
+                };
+
+                let index = match merged.iter().position(|(merged_key, _)| *merged_key == key) {
+                    Some(index) => index,
+                    None => {
+                        merged.push((key, Vec::new()));
+                        merged.len() - 1
+                    }
+                };
+                let values = &mut merged[index].1;
+
+                match value.to_typed_term().unwrap() {
+                    TypedTerm::Nil => (),
+                    TypedTerm::List(value_cons) => {
+                        for value_result in value_cons.into_iter() {
+                            values.push(value_result.map_err(|_| invalid())?);
+                        }
+                    }
+                    _ => values.push(value),
+                }
+            }
+        }
+        _ => return Err(invalid()),
+    }
+
+    merged.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+
+    let mut attribute_vec = Vec::with_capacity(merged.len());
+
+    for (key, values) in merged {
+        attribute_vec.push(
+            process.tuple_from_slice(&[atom_to_term(key), process.list_from_slice(&values)?])?,
+        );
+    }
+
+    process
+        .list_from_slice(&attribute_vec)
+        .map_err(|alloc| alloc.into())
+}
+
+fn ok(value: Term, process: &Process) -> Result {
+    process
+        .tuple_from_slice(&[atom_unchecked("ok"), value])
+        .map_err(|alloc| alloc.into())
+}
+
+fn path_from_term(term: Term) -> std::result::Result<PathBuf, Exception> {
+    match term.to_typed_term().unwrap() {
+        TypedTerm::Atom(atom) => Ok(PathBuf::from(atom.name())),
+        _ => string_from_charlist(term).map(PathBuf::from),
+    }
+}
+
+fn posix(kind: io::ErrorKind) -> &'static str {
+    match kind {
+        io::ErrorKind::NotFound => "enoent",
+        io::ErrorKind::PermissionDenied => "eacces",
+        io::ErrorKind::AlreadyExists => "eexist",
+        io::ErrorKind::Interrupted => "eintr",
+        io::ErrorKind::WouldBlock => "eagain",
+        _ => "einval",
+    }
+}
+
+fn string_from_charlist(list: Term) -> std::result::Result<String, Exception> {
+    let mut string = String::new();
+
+    match list.to_typed_term().unwrap() {
+        TypedTerm::Nil => (),
+        TypedTerm::List(cons) => {
+            for result in cons.into_iter() {
+                let element = result.map_err(|_| badarg!())?;
+                let c: char = element.try_into().map_err(|_| badarg!())?;
+
+                string.push(c);
+            }
+        }
+        _ => return Err(badarg!().into()),
+    }
+
+    Ok(string)
+}
+
+fn strip(
+    source: &Source,
+    additional_chunk_ids: &[chunk::Id],
+    process: &Process,
+) -> std::result::Result<Term, Error> {
+    let beam = Beam::read(clone_source(source))?;
+    let module = beam.module()?;
+
+    let mut stripped = RawBeamFile::new();
+
+    for raw_chunk in beam.retained_chunks(additional_chunk_ids) {
+        stripped.push_chunk(RawChunk {
+            id: raw_chunk.id,
+            data: raw_chunk.data.clone(),
+        });
+    }
+
+    let mut bytes = Vec::new();
+    stripped
+        .to_writer(&mut bytes)
+        .map_err(|_| Reason::InvalidBeamFile(0))?;
+
+    let beam_term = match source {
+        Source::File(path) => {
+            fs::write(path, &bytes).map_err(|error| Reason::FileError(error.kind()))?;
+
+            source.to_term(process)?
+        }
+        Source::Binary(_) => process.binary_from_bytes(&bytes)?,
+    };
+
+    process
+        .tuple_from_slice(&[atom_to_term(module), beam_term])
+        .map_err(|alloc| alloc.into())
+}
+
+fn to_result(
+    result: std::result::Result<Term, Error>,
+    source: &Source,
+    process: &Process,
+) -> Result {
+    match result {
+        Ok(term) => Ok(term),
+        Err(Error::Exception(exception)) => Err(exception),
+        Err(Error::BeamLib(reason)) => error_tuple(&reason, source, process),
+    }
+}
+
+fn tuple_elements(term: Term) -> Option<Vec<Term>> {
+    match term.to_typed_term().unwrap() {
+        TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
+            TypedTerm::Tuple(tuple) => Some(tuple.iter().collect()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn value_of_tagged_tuple(tuple: Term) -> std::result::Result<Term, Error> {
+    match tuple_elements(tuple).as_ref().map(Vec::as_slice) {
+        Some([_, value]) => Ok(*value),
+        _ => Err(Reason::InvalidChunk(*b"Attr").into()),
+    }
+}
+
+/// Each `"FunT"` entry is 6 words, the last of which is the old unique, which changes whenever the
+/// module is recompiled, so it is zeroed before comparing or hashing.
+fn without_old_uniq(data: &[u8]) -> Vec<u8> {
+    let mut bytes = data.to_vec();
+
+    // The entries follow a 4 byte count
+    for entry in bytes.get_mut(4..).unwrap_or(&mut []).chunks_mut(24) {
+        if entry.len() == 24 {
+            for byte in &mut entry[20..] {
+                *byte = 0;
+            }
+        }
+    }
+
+    bytes
+}
+
+fn with_beam<F>(beam: Term, process: &Process, f: F) -> Result
+where
+    F: FnOnce(&Beam) -> std::result::Result<Term, Error>,
+{
+    let source = Source::try_from_term(beam)?;
+
+    let result = Beam::read(clone_source(&source)).and_then(|beam| f(&beam));
+
+    to_result(result, &source, process)
+}
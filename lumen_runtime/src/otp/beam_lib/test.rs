@@ -0,0 +1,359 @@
+use super::*;
+
+use crate::scheduler::with_process;
+
+const TEST_BEAM: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../liblumen_beam/tests/testdata/reader/test.beam"
+);
+
+#[test]
+fn chunks_2_with_names_returns_decoded_chunks() {
+    with_process(|process| {
+        let beam = test_beam(process);
+        let chunk_refs = process
+            .list_from_slice(&[atom_unchecked("exports"), atom_unchecked("imports")])
+            .unwrap();
+
+        let exports = process
+            .list_from_slice(&[
+                process
+                    .tuple_from_slice(&[atom_unchecked("hello"), process.integer(1).unwrap()])
+                    .unwrap(),
+                process
+                    .tuple_from_slice(&[atom_unchecked("module_info"), process.integer(0).unwrap()])
+                    .unwrap(),
+                process
+                    .tuple_from_slice(&[atom_unchecked("module_info"), process.integer(1).unwrap()])
+                    .unwrap(),
+            ])
+            .unwrap();
+        let imports = process
+            .list_from_slice(&[
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("erlang"),
+                        atom_unchecked("get_module_info"),
+                        process.integer(1).unwrap(),
+                    ])
+                    .unwrap(),
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("erlang"),
+                        atom_unchecked("get_module_info"),
+                        process.integer(2).unwrap(),
+                    ])
+                    .unwrap(),
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("io"),
+                        atom_unchecked("format"),
+                        process.integer(2).unwrap(),
+                    ])
+                    .unwrap(),
+            ])
+            .unwrap();
+
+        let result = chunks_2(beam, chunk_refs, process).unwrap();
+
+        assert_eq!(
+            result,
+            ok(
+                process,
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("test"),
+                        process
+                            .list_from_slice(&[
+                                process
+                                    .tuple_from_slice(&[atom_unchecked("exports"), exports])
+                                    .unwrap(),
+                                process
+                                    .tuple_from_slice(&[atom_unchecked("imports"), imports])
+                                    .unwrap(),
+                            ])
+                            .unwrap(),
+                    ])
+                    .unwrap()
+            )
+        );
+    });
+}
+
+#[test]
+fn chunks_2_with_chunk_id_returns_binary() {
+    with_process(|process| {
+        let beam = test_beam(process);
+        let chunk_id = process.charlist_from_str("LitT").unwrap();
+        let chunk_refs = process.list_from_slice(&[chunk_id]).unwrap();
+
+        let data = process.binary_from_bytes(&lit_t_data()).unwrap();
+
+        assert_eq!(
+            chunks_2(beam, chunk_refs, process),
+            Ok(ok(
+                process,
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("test"),
+                        process
+                            .list_from_slice(&[process
+                                .tuple_from_slice(&[chunk_id, data])
+                                .unwrap()])
+                            .unwrap()
+                    ])
+                    .unwrap()
+            ))
+        );
+    });
+}
+
+#[test]
+fn chunks_2_with_missing_chunk_returns_error() {
+    with_process(|process| {
+        let beam = test_beam(process);
+        let chunk_id = process.charlist_from_str("Dbgi").unwrap();
+        let chunk_refs = process.list_from_slice(&[chunk_id]).unwrap();
+
+        assert_eq!(
+            chunks_2(beam, chunk_refs, process),
+            Ok(beam_lib_error(
+                process,
+                process
+                    .tuple_from_slice(&[atom_unchecked("missing_chunk"), beam, chunk_id])
+                    .unwrap()
+            ))
+        );
+    });
+}
+
+#[test]
+fn chunks_3_with_allow_missing_chunks_returns_missing_chunk() {
+    with_process(|process| {
+        let beam = test_beam(process);
+        let chunk_id = process.charlist_from_str("Dbgi").unwrap();
+        let chunk_refs = process.list_from_slice(&[chunk_id]).unwrap();
+        let options = process
+            .list_from_slice(&[atom_unchecked("allow_missing_chunks")])
+            .unwrap();
+
+        assert_eq!(
+            chunks_3(beam, chunk_refs, options, process),
+            Ok(ok(
+                process,
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("test"),
+                        process
+                            .list_from_slice(&[process
+                                .tuple_from_slice(&[chunk_id, atom_unchecked("missing_chunk")])
+                                .unwrap()])
+                            .unwrap()
+                    ])
+                    .unwrap()
+            ))
+        );
+    });
+}
+
+#[test]
+fn chunks_2_with_unknown_chunk_name_returns_error() {
+    with_process(|process| {
+        let beam = test_beam(process);
+        let chunk_refs = process
+            .list_from_slice(&[atom_unchecked("unknown")])
+            .unwrap();
+
+        assert_eq!(
+            chunks_2(beam, chunk_refs, process),
+            Ok(beam_lib_error(
+                process,
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("unknown_chunk"),
+                        beam,
+                        atom_unchecked("unknown")
+                    ])
+                    .unwrap()
+            ))
+        );
+    });
+}
+
+#[test]
+fn chunks_2_with_file_name_adds_beam_extension() {
+    with_process(|process| {
+        let file_name = process
+            .charlist_from_str(TEST_BEAM.trim_end_matches(".beam"))
+            .unwrap();
+        let chunk_refs = process.list_from_slice(&[atom_unchecked("atoms")]).unwrap();
+
+        let result = chunks_2(file_name, chunk_refs, process).unwrap();
+        let beam = test_beam(process);
+
+        assert_eq!(result, chunks_2(beam, chunk_refs, process).unwrap());
+    });
+}
+
+#[test]
+fn chunks_2_with_non_existent_file_returns_file_error() {
+    with_process(|process| {
+        let file_name = process.charlist_from_str("does_not_exist.beam").unwrap();
+        let chunk_refs = process.list_from_slice(&[atom_unchecked("atoms")]).unwrap();
+
+        assert_eq!(
+            chunks_2(file_name, chunk_refs, process),
+            Ok(beam_lib_error(
+                process,
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("file_error"),
+                        file_name,
+                        atom_unchecked("enoent")
+                    ])
+                    .unwrap()
+            ))
+        );
+    });
+}
+
+#[test]
+fn chunks_2_with_non_beam_binary_returns_not_a_beam_file() {
+    with_process(|process| {
+        let binary = process.binary_from_bytes(b"not a beam file").unwrap();
+        let chunk_refs = process.list_from_slice(&[atom_unchecked("atoms")]).unwrap();
+
+        assert_eq!(
+            chunks_2(binary, chunk_refs, process),
+            Ok(beam_lib_error(
+                process,
+                process
+                    .tuple_from_slice(&[atom_unchecked("not_a_beam_file"), binary])
+                    .unwrap()
+            ))
+        );
+    });
+}
+
+#[test]
+fn cmp_2_with_same_beam_returns_ok() {
+    with_process(|process| {
+        let beam = test_beam(process);
+
+        assert_eq!(cmp_2(beam, beam, process), Ok(atom_unchecked("ok")));
+    });
+}
+
+#[test]
+fn cmp_2_with_stripped_beam_returns_ok() {
+    with_process(|process| {
+        let beam = test_beam(process);
+        let stripped = stripped_beam(beam, process);
+
+        assert_eq!(cmp_2(beam, stripped, process), Ok(atom_unchecked("ok")));
+    });
+}
+
+#[test]
+fn md5_1_is_the_same_after_strip_1() {
+    with_process(|process| {
+        let beam = test_beam(process);
+        let stripped = stripped_beam(beam, process);
+
+        assert_eq!(md5_1(beam, process), md5_1(stripped, process));
+    });
+}
+
+#[test]
+fn strip_1_removes_debug_chunks() {
+    with_process(|process| {
+        let beam = test_beam(process);
+        let stripped = stripped_beam(beam, process);
+
+        let result = all_chunks_1(stripped, process).unwrap();
+        let ids: Vec<String> = match tuple_elements(result).unwrap().as_slice() {
+            [_, _, chunks] => list_elements(*chunks)
+                .into_iter()
+                .map(|chunk| list_to_string(tuple_elements(chunk).unwrap()[0]))
+                .collect(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            ids,
+            vec!["Atom", "Code", "StrT", "ImpT", "ExpT", "FunT", "LitT", "Line"]
+        );
+    });
+}
+
+#[test]
+fn info_1_returns_chunk_positions() {
+    with_process(|process| {
+        let beam = test_beam(process);
+
+        let result = info_1(beam, process).unwrap();
+        let info = list_elements(result);
+
+        assert_eq!(
+            info[0],
+            process
+                .tuple_from_slice(&[atom_unchecked("binary"), beam])
+                .unwrap()
+        );
+        assert_eq!(
+            info[1],
+            process
+                .tuple_from_slice(&[atom_unchecked("module"), atom_unchecked("test")])
+                .unwrap()
+        );
+
+        let chunks = list_elements(tuple_elements(info[2]).unwrap()[1]);
+        let first_chunk = tuple_elements(chunks[0]).unwrap();
+
+        assert_eq!(list_to_string(first_chunk[0]), "Atom");
+        assert_eq!(first_chunk[1], process.integer(20).unwrap());
+    });
+}
+
+fn beam_lib_error(process: &Process, reason: Term) -> Term {
+    process
+        .tuple_from_slice(&[atom_unchecked("error"), atom_unchecked("beam_lib"), reason])
+        .unwrap()
+}
+
+fn lit_t_data() -> Vec<u8> {
+    let file = RawBeamFile::from_file(TEST_BEAM).unwrap();
+
+    file.get_chunk(b"LitT").unwrap().data.clone()
+}
+
+fn list_elements(list: Term) -> Vec<Term> {
+    match list.to_typed_term().unwrap() {
+        TypedTerm::Nil => Vec::new(),
+        TypedTerm::List(cons) => cons.into_iter().map(|result| result.unwrap()).collect(),
+        _ => panic!("{:?} is not a list", list),
+    }
+}
+
+fn list_to_string(list: Term) -> String {
+    string_from_charlist(list).unwrap()
+}
+
+fn ok(process: &Process, value: Term) -> Term {
+    process
+        .tuple_from_slice(&[atom_unchecked("ok"), value])
+        .unwrap()
+}
+
+fn stripped_beam(beam: Term, process: &Process) -> Term {
+    let result = strip_1(beam, process).unwrap();
+    let module_beam = tuple_elements(result).unwrap()[1];
+
+    tuple_elements(module_beam).unwrap()[1]
+}
+
+fn test_beam(process: &Process) -> Term {
+    process
+        .binary_from_bytes(&fs::read(TEST_BEAM).unwrap())
+        .unwrap()
+}
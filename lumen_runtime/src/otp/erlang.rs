@@ -54,13 +54,14 @@ use liblumen_alloc::erts::term::{
 };
 use liblumen_alloc::{badarg, badarith, badkey, badmap, error, raise, throw};
 
-use crate::binary::{start_length_to_part_range, PartRange, ToTermOptions};
+use crate::binary::{start_length_to_part_range, PartRange, ToBinaryOptions, ToTermOptions};
 use crate::node;
 use crate::otp;
 use crate::process::SchedulerDependentAlloc;
 use crate::registry::{self, pid_to_self_or_process};
 use crate::send::{self, send, Sent};
 use crate::stacktrace;
+use crate::term::external_format;
 use crate::time::monotonic::{self, Milliseconds};
 use crate::timer::start::ReferenceFrame;
 use crate::timer::{self, Timeout};
@@ -475,17 +476,19 @@ pub fn binary_to_term_1(binary: Term, process: &Process) -> Result {
     binary_to_term_2(binary, Term::NIL, process)
 }
 
-pub fn binary_to_term_2(binary: Term, options: Term, _process: &Process) -> Result {
-    let _to_term_options: ToTermOptions = options.try_into()?;
+pub fn binary_to_term_2(binary: Term, options: Term, process: &Process) -> Result {
+    let to_term_options: ToTermOptions = options.try_into()?;
+    let bytes = process.bytes_from_binary(binary)?;
+    let (term, used) = external_format::decode(bytes, to_term_options.existing, process)?;
 
-    match binary.to_typed_term().unwrap() {
-        TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
-            TypedTerm::HeapBinary(_heap_binary) => unimplemented!(),
-            TypedTerm::ProcBin(_process_binary) => unimplemented!(),
-            TypedTerm::SubBinary(_subbinary) => unimplemented!(),
-            _ => Err(badarg!().into()),
-        },
-        _ => Err(badarg!().into()),
+    if to_term_options.used {
+        let used_term = process.integer(used)?;
+
+        process
+            .tuple_from_slice(&[term, used_term])
+            .map_err(|error| error.into())
+    } else {
+        Ok(term)
     }
 }
 
@@ -1588,6 +1591,19 @@ pub fn subtract_list_2(minuend: Term, subtrahend: Term, process: &Process) -> Re
     }
 }
 
+pub fn term_to_binary_1(term: Term, process: &Process) -> Result {
+    term_to_binary_2(term, Term::NIL, process)
+}
+
+pub fn term_to_binary_2(term: Term, options: Term, process: &Process) -> Result {
+    let to_binary_options: ToBinaryOptions = options.try_into()?;
+    let byte_vec = external_format::encode(term, &to_binary_options)?;
+
+    process
+        .binary_from_bytes(&byte_vec)
+        .map_err(|error| error.into())
+}
+
 pub fn throw_1(reason: Term) -> Result {
     Err(throw!(reason).into())
 }
@@ -12,10 +12,12 @@ use liblumen_alloc::erts::exception;
 use liblumen_alloc::erts::exception::system::Alloc;
 use liblumen_alloc::erts::process::code::stack::frame::{Frame, Placement};
 use liblumen_alloc::erts::process::code::{self, result_from_exception};
-use liblumen_alloc::erts::process::Process;
-use liblumen_alloc::erts::term::{Atom, Term};
+use liblumen_alloc::erts::process::{self, Priority, Process};
+use liblumen_alloc::erts::term::{atom_unchecked, Atom, Term};
 use liblumen_alloc::{badarg, ModuleFunctionArity};
 
+use crate::process::spawn::options::{MaxHeapSize, MessageQueueData};
+
 pub fn place_frame_with_arguments(
     process: &Process,
     placement: Placement,
@@ -63,18 +65,77 @@ fn module_function_arity() -> Arc<ModuleFunctionArity> {
     })
 }
 
+/// Sets `flag` of `process` to `value`, returning the old value.
+///
+/// `error_handler`, `message_queue_data`, `save_calls` and `sensitive` are accepted and stored,
+/// so the next call returns them, but they are inert: undefined functions are not passed to the
+/// error handler, messages stay in the same queue, calls are not saved and sensitive processes
+/// are not hidden from tracing.
 pub fn native(process: &Process, flag: Term, value: Term) -> exception::Result {
     let flag_atom: Atom = flag.try_into()?;
 
     match flag_atom.name() {
-        "error_handler" => unimplemented!(),
-        "max_heap_size" => unimplemented!(),
-        "message_queue_data" => unimplemented!(),
-        "min_bin_vheap_size" => unimplemented!(),
-        "min_heap_size" => unimplemented!(),
-        "priority" => unimplemented!(),
-        "save_calls" => unimplemented!(),
-        "sensitive" => unimplemented!(),
+        "error_handler" => {
+            let value_atom: Atom = value.try_into()?;
+
+            Ok(atom_unchecked(process.set_error_handler(value_atom).name()))
+        }
+        "max_heap_size" => {
+            let max_heap_size: MaxHeapSize = value.try_into()?;
+            let max_heap_size = max_heap_size.cascaded();
+
+            if max_heap_size.size != 0 && max_heap_size.size < process.min_heap_size() {
+                return Err(badarg!().into());
+            }
+
+            let old_max_heap_size = process.set_max_heap_size(max_heap_size);
+
+            max_heap_size_to_term(process, old_max_heap_size)
+        }
+        "message_queue_data" => {
+            let message_queue_data: MessageQueueData = value.try_into()?;
+            let off_heap = match message_queue_data {
+                MessageQueueData::OnHeap => false,
+                MessageQueueData::OffHeap => true,
+            };
+
+            let old_value = if process.off_heap_message_queue(off_heap) {
+                "off_heap"
+            } else {
+                "on_heap"
+            };
+
+            Ok(atom_unchecked(old_value))
+        }
+        "min_bin_vheap_size" => {
+            let value_usize: usize = value.try_into()?;
+
+            Ok(process.integer(process.set_min_vheap_size(value_usize))?)
+        }
+        "min_heap_size" => {
+            let value_usize: usize = value.try_into()?;
+
+            Ok(process.integer(process.set_min_heap_size(value_usize))?)
+        }
+        "priority" => {
+            let priority: Priority = value.try_into()?;
+
+            Ok(process.set_priority(priority).into())
+        }
+        "save_calls" => {
+            let value_usize: usize = value.try_into()?;
+
+            if MAX_SAVE_CALLS < value_usize {
+                return Err(badarg!().into());
+            }
+
+            Ok(process.integer(process.set_save_calls(value_usize))?)
+        }
+        "sensitive" => {
+            let value_bool: bool = value.try_into()?;
+
+            Ok(process.sensitive(value_bool).into())
+        }
         "trap_exit" => {
             let value_bool: bool = value.try_into()?;
 
@@ -83,3 +144,24 @@ pub fn native(process: &Process, flag: Term, value: Term) -> exception::Result {
         _ => Err(badarg!().into()),
     }
 }
+
+/// The most calls that can be saved, the same as BEAM
+const MAX_SAVE_CALLS: usize = 10_000;
+
+fn max_heap_size_to_term(
+    process: &Process,
+    max_heap_size: process::MaxHeapSize,
+) -> exception::Result {
+    let size = process.integer(max_heap_size.size)?;
+
+    process
+        .map_from_slice(&[
+            (
+                atom_unchecked("error_logger"),
+                max_heap_size.error_logger.into(),
+            ),
+            (atom_unchecked("kill"), max_heap_size.kill.into()),
+            (atom_unchecked("size"), size),
+        ])
+        .map_err(|error| error.into())
+}
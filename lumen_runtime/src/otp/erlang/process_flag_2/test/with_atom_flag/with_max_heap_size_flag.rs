@@ -0,0 +1,140 @@
+use super::*;
+
+use liblumen_alloc::erts::process::{MaxHeapSize, Process, Status};
+use liblumen_alloc::erts::term::atom_unchecked;
+use liblumen_alloc::exit;
+
+use crate::process;
+
+#[test]
+fn without_non_negative_integer_or_map_value_errors_badarg() {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &strategy::term::is_not_non_negative_integer(arc_process.clone())
+                    .prop_filter("Cannot be a map", |value| !value.is_map()),
+                |value| {
+                    prop_assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
+#[test]
+fn with_size_less_than_min_heap_size_errors_badarg() {
+    let arc_process = process::test(&process::test_init());
+    let value = arc_process
+        .integer(arc_process.min_heap_size() - 1)
+        .unwrap();
+
+    assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+}
+
+#[test]
+fn with_unknown_map_key_errors_badarg() {
+    let arc_process = process::test(&process::test_init());
+    let value = arc_process
+        .map_from_slice(&[(atom_unchecked("unknown"), true.into())])
+        .unwrap();
+
+    assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+}
+
+#[test]
+fn with_integer_value_returns_old_value_and_keeps_default_kill_and_error_logger() {
+    let arc_process = process::test(&process::test_init());
+    let size = arc_process.min_heap_size() * 2;
+    let value = arc_process.integer(size).unwrap();
+
+    assert_eq!(
+        native(&arc_process, flag(), value),
+        Ok(max_heap_size_map(&arc_process, 0, true, true))
+    );
+    assert_eq!(
+        arc_process.max_heap_size(),
+        MaxHeapSize {
+            size,
+            kill: true,
+            error_logger: true
+        }
+    );
+}
+
+#[test]
+fn with_map_value_returns_old_value() {
+    let arc_process = process::test(&process::test_init());
+    let size = arc_process.min_heap_size() * 2;
+    let value = max_heap_size_map(&arc_process, size, false, true);
+
+    assert_eq!(
+        native(&arc_process, flag(), value),
+        Ok(max_heap_size_map(&arc_process, 0, true, true))
+    );
+    assert_eq!(
+        native(&arc_process, flag(), arc_process.integer(0).unwrap()),
+        Ok(max_heap_size_map(&arc_process, size, false, true))
+    );
+}
+
+#[test]
+fn with_kill_when_garbage_collection_exceeds_size_exits_killed() {
+    let arc_process = process::test(&process::test_init());
+    let size = arc_process.min_heap_size();
+    let value = max_heap_size_map(&arc_process, size, true, false);
+
+    assert!(native(&arc_process, flag(), value).is_ok());
+
+    arc_process.garbage_collect(size * 4, &mut []).unwrap();
+
+    match *arc_process.status.read() {
+        Status::Exiting(ref runtime_exception) => {
+            assert_eq!(runtime_exception, &exit!(atom_unchecked("killed")));
+        }
+        ref status => panic!("Process status ({:?}) is not exiting.", status),
+    };
+
+    assert!(arc_process.take_max_heap_size_report().is_none());
+}
+
+#[test]
+fn without_kill_when_garbage_collection_exceeds_size_reports_and_does_not_exit() {
+    let arc_process = process::test(&process::test_init());
+    let size = arc_process.min_heap_size();
+    let value = max_heap_size_map(&arc_process, size, false, true);
+
+    assert!(native(&arc_process, flag(), value).is_ok());
+
+    arc_process.garbage_collect(size * 4, &mut []).unwrap();
+
+    assert!(!arc_process.is_exiting());
+
+    let report = arc_process.take_max_heap_size_report().unwrap();
+
+    assert!(size < report.heap_size);
+    assert_eq!(
+        report.max_heap_size,
+        MaxHeapSize {
+            size,
+            kill: false,
+            error_logger: true
+        }
+    );
+    assert!(arc_process.take_max_heap_size_report().is_none());
+}
+
+fn flag() -> Term {
+    atom_unchecked("max_heap_size")
+}
+
+fn max_heap_size_map(process: &Process, size: usize, kill: bool, error_logger: bool) -> Term {
+    process
+        .map_from_slice(&[
+            (atom_unchecked("error_logger"), error_logger.into()),
+            (atom_unchecked("kill"), kill.into()),
+            (atom_unchecked("size"), process.integer(size).unwrap()),
+        ])
+        .unwrap()
+}
@@ -0,0 +1,46 @@
+use super::*;
+
+use liblumen_alloc::erts::term::atom_unchecked;
+
+use crate::process;
+
+#[test]
+fn without_on_heap_or_off_heap_value_errors_badarg() {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &strategy::term(arc_process.clone()).prop_filter(
+                    "Cannot be on_heap or off_heap",
+                    |value| {
+                        *value != atom_unchecked("on_heap") && *value != atom_unchecked("off_heap")
+                    },
+                ),
+                |value| {
+                    prop_assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
+#[test]
+fn with_off_heap_value_returns_old_value() {
+    let arc_process = process::test(&process::test_init());
+
+    assert_eq!(
+        native(&arc_process, flag(), atom_unchecked("off_heap")),
+        Ok(atom_unchecked("on_heap"))
+    );
+    assert!(arc_process.has_off_heap_message_queue());
+    assert_eq!(
+        native(&arc_process, flag(), atom_unchecked("on_heap")),
+        Ok(atom_unchecked("off_heap"))
+    );
+    assert!(!arc_process.has_off_heap_message_queue());
+}
+
+fn flag() -> Term {
+    atom_unchecked("message_queue_data")
+}
@@ -0,0 +1,37 @@
+use super::*;
+
+use liblumen_alloc::erts::term::atom_unchecked;
+
+use crate::process;
+
+#[test]
+fn without_non_negative_integer_value_errors_badarg() {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &strategy::term::is_not_non_negative_integer(arc_process.clone()),
+                |value| {
+                    prop_assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
+#[test]
+fn with_non_negative_integer_value_returns_old_value() {
+    let arc_process = process::test(&process::test_init());
+    let value = arc_process.integer(46_422).unwrap();
+
+    assert_eq!(
+        native(&arc_process, flag(), value),
+        Ok(arc_process.integer(0).unwrap())
+    );
+    assert_eq!(arc_process.min_vheap_size(), 46_422);
+}
+
+fn flag() -> Term {
+    atom_unchecked("min_bin_vheap_size")
+}
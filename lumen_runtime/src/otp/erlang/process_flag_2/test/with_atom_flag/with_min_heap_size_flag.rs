@@ -0,0 +1,39 @@
+use super::*;
+
+use liblumen_alloc::erts::process::next_heap_size;
+use liblumen_alloc::erts::term::atom_unchecked;
+
+use crate::process;
+
+#[test]
+fn without_non_negative_integer_value_errors_badarg() {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &strategy::term::is_not_non_negative_integer(arc_process.clone()),
+                |value| {
+                    prop_assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
+#[test]
+fn with_non_negative_integer_value_returns_old_value_and_rounds_up_to_heap_size() {
+    let arc_process = process::test(&process::test_init());
+    let old_min_heap_size = arc_process.min_heap_size();
+    let value = arc_process.integer(1_000).unwrap();
+
+    assert_eq!(
+        native(&arc_process, flag(), value),
+        Ok(arc_process.integer(old_min_heap_size).unwrap())
+    );
+    assert_eq!(arc_process.min_heap_size(), next_heap_size(1_000));
+}
+
+fn flag() -> Term {
+    atom_unchecked("min_heap_size")
+}
@@ -0,0 +1,72 @@
+use super::*;
+
+use liblumen_alloc::erts::process::code::stack::frame::Placement;
+use liblumen_alloc::erts::process::Priority;
+use liblumen_alloc::erts::term::atom_unchecked;
+
+use crate::otp::erlang::process_flag_2::place_frame_with_arguments;
+use crate::process;
+use crate::scheduler::Scheduler;
+
+#[test]
+fn without_priority_value_errors_badarg() {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &strategy::term(arc_process.clone()).prop_filter("Cannot be a priority", |value| {
+                    let priority: Result<Priority, _> = (*value).try_into();
+
+                    priority.is_err()
+                }),
+                |value| {
+                    prop_assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
+#[test]
+fn with_priority_value_returns_old_value() {
+    let arc_process = process::test(&process::test_init());
+
+    assert_eq!(
+        native(&arc_process, flag(), atom_unchecked("low")),
+        Ok(atom_unchecked("normal"))
+    );
+    assert_eq!(
+        native(&arc_process, flag(), atom_unchecked("max")),
+        Ok(atom_unchecked("low"))
+    );
+    assert_eq!(arc_process.priority(), Priority::Max);
+}
+
+#[test]
+fn with_priority_value_moves_process_to_run_queue_for_priority() {
+    let arc_scheduler = Scheduler::current();
+    let arc_process = process::test(&process::test_init());
+
+    place_frame_with_arguments(
+        &arc_process,
+        Placement::Push,
+        flag(),
+        atom_unchecked("high"),
+    )
+    .unwrap();
+
+    let high_run_queue_len_before = arc_scheduler.run_queue_len(Priority::High);
+
+    assert!(arc_scheduler.run_through(&arc_process));
+
+    assert_eq!(arc_process.priority(), Priority::High);
+    assert_eq!(
+        arc_scheduler.run_queue_len(Priority::High),
+        high_run_queue_len_before + 1
+    );
+}
+
+fn flag() -> Term {
+    atom_unchecked("priority")
+}
@@ -0,0 +1,48 @@
+use super::*;
+
+use liblumen_alloc::erts::term::atom_unchecked;
+
+use crate::process;
+
+#[test]
+fn without_non_negative_integer_value_errors_badarg() {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &strategy::term::is_not_non_negative_integer(arc_process.clone()),
+                |value| {
+                    prop_assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
+#[test]
+fn with_more_than_10000_errors_badarg() {
+    let arc_process = process::test(&process::test_init());
+    let value = arc_process.integer(10_001).unwrap();
+
+    assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+}
+
+#[test]
+fn with_at_most_10000_returns_old_value() {
+    let arc_process = process::test(&process::test_init());
+    let value = arc_process.integer(10_000).unwrap();
+
+    assert_eq!(
+        native(&arc_process, flag(), value),
+        Ok(arc_process.integer(0).unwrap())
+    );
+    assert_eq!(
+        native(&arc_process, flag(), arc_process.integer(0).unwrap()),
+        Ok(value)
+    );
+}
+
+fn flag() -> Term {
+    atom_unchecked("save_calls")
+}
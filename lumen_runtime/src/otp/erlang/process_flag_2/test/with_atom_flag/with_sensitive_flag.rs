@@ -0,0 +1,44 @@
+use super::*;
+
+use proptest::prop_assert;
+
+use liblumen_alloc::erts::term::atom_unchecked;
+
+use crate::process;
+
+#[test]
+fn without_boolean_value_errors_badarg() {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &strategy::term::is_not_boolean(arc_process.clone()),
+                |value| {
+                    prop_assert_eq!(native(&arc_process, flag(), value), Err(badarg!().into()));
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
+#[test]
+fn with_true_value_then_boolean_value_returns_old_value_true() {
+    TestRunner::new(Config::with_source_file(file!()))
+        .run(&strategy::term::is_boolean(), |value| {
+            let arc_process = process::test(&process::test_init());
+
+            let old_value = true.into();
+            prop_assert_eq!(native(&arc_process, flag(), old_value), Ok(false.into()));
+            prop_assert!(arc_process.is_sensitive());
+
+            prop_assert_eq!(native(&arc_process, flag(), value), Ok(old_value));
+
+            Ok(())
+        })
+        .unwrap();
+}
+
+fn flag() -> Term {
+    atom_unchecked("sensitive")
+}
@@ -42,3 +42,58 @@ fn without_proper_list_options_errors_badarg() {
             .unwrap();
     });
 }
+
+#[test]
+fn with_max_heap_size_less_than_min_heap_size_in_options_errors_badarg() {
+    let parent_arc_process = process::test_init();
+    let options = max_heap_size_options(&parent_arc_process, 1000, 10);
+
+    assert_eq!(
+        native(
+            &parent_arc_process,
+            atom_unchecked("erlang"),
+            atom_unchecked("self"),
+            Term::NIL,
+            options
+        ),
+        Err(badarg!().into())
+    );
+}
+
+#[test]
+fn with_max_heap_size_in_options_sets_max_heap_size_of_child() {
+    let parent_arc_process = process::test_init();
+    let options = max_heap_size_options(&parent_arc_process, 1000, 100_000);
+
+    let child_pid = native(
+        &parent_arc_process,
+        atom_unchecked("erlang"),
+        atom_unchecked("self"),
+        Term::NIL,
+        options,
+    )
+    .unwrap();
+    let child_pid_pid: Pid = child_pid.try_into().unwrap();
+    let child_arc_process = pid_to_process(&child_pid_pid).unwrap();
+
+    assert_eq!(child_arc_process.max_heap_size().size, 100_000);
+}
+
+fn max_heap_size_options(process: &Process, min_heap_size: usize, max_heap_size: usize) -> Term {
+    let min_heap_size = process
+        .tuple_from_slice(&[
+            atom_unchecked("min_heap_size"),
+            process.integer(min_heap_size).unwrap(),
+        ])
+        .unwrap();
+    let max_heap_size = process
+        .tuple_from_slice(&[
+            atom_unchecked("max_heap_size"),
+            process.integer(max_heap_size).unwrap(),
+        ])
+        .unwrap();
+
+    process
+        .list_from_slice(&[min_heap_size, max_heap_size])
+        .unwrap()
+}
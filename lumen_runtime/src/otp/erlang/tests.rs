@@ -112,6 +112,8 @@ mod split_binary_2;
 mod start_timer_3;
 mod start_timer_4;
 mod subtract_list_2;
+mod term_to_binary_1;
+mod term_to_binary_2;
 mod throw_1;
 mod tl_1;
 mod tuple_size_1;
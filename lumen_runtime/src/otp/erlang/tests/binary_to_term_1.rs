@@ -1,7 +1,12 @@
 use super::*;
 
+use liblumen_alloc::erts::scheduler;
+use liblumen_alloc::erts::term::Atom;
+
+use crate::code;
+use crate::term::external_format::MAX_DEPTH;
+
 #[test]
-#[ignore]
 fn without_binary_errors_badarg() {
     with_process_arc(|arc_process| {
         TestRunner::new(Config::with_source_file(file!()))
@@ -21,7 +26,6 @@ fn without_binary_errors_badarg() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_atom_returns_atom() {
     with_binary_returns_term(
         // :erlang.term_to_binary(:atom)
@@ -31,7 +35,6 @@ fn with_binary_encoding_atom_returns_atom() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_empty_list_returns_empty_list() {
     with_binary_returns_term(
         // :erlang.term_to_binary([])
@@ -41,7 +44,6 @@ fn with_binary_encoding_empty_list_returns_empty_list() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_list_returns_list() {
     with_binary_returns_term(
         // :erlang.term_to_binary([:zero, 1])
@@ -62,7 +64,6 @@ fn with_binary_encoding_list_returns_list() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_small_integer_returns_small_integer() {
     with_binary_returns_term(
         // :erlang.term_to_binary(0)
@@ -72,7 +73,6 @@ fn with_binary_encoding_small_integer_returns_small_integer() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_integer_returns_integer() {
     with_binary_returns_term(
         // :erlang.term_to_binary(-2147483648)
@@ -82,7 +82,6 @@ fn with_binary_encoding_integer_returns_integer() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_new_float_returns_float() {
     with_binary_returns_term(
         // :erlang.term_to_binary(1.0)
@@ -92,7 +91,6 @@ fn with_binary_encoding_new_float_returns_float() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_small_tuple_returns_tuple() {
     with_binary_returns_term(
         // :erlang.term_to_binary({:zero, 1})
@@ -106,7 +104,6 @@ fn with_binary_encoding_small_tuple_returns_tuple() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_byte_list_returns_list() {
     with_binary_returns_term(
         // :erlang.term_to_binary([?0, ?1])
@@ -125,7 +122,6 @@ fn with_binary_encoding_byte_list_returns_list() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_binary_returns_binary() {
     with_binary_returns_term(
         // :erlang.term_to_binary(<<0, 1>>)
@@ -135,7 +131,6 @@ fn with_binary_encoding_binary_returns_binary() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_small_big_integer_returns_big_integer() {
     with_binary_returns_term(
         // :erlang.term_to_binary(4294967295)
@@ -145,7 +140,6 @@ fn with_binary_encoding_small_big_integer_returns_big_integer() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_bit_string_returns_subbinary() {
     with_binary_returns_term(
         // :erlang.term_to_binary(<<1, 2::3>>)
@@ -165,7 +159,6 @@ fn with_binary_encoding_bit_string_returns_subbinary() {
 }
 
 #[test]
-#[ignore]
 fn with_binary_encoding_small_atom_utf8_returns_atom() {
     with_binary_returns_term(
         // :erlang.term_to_binary(:"😈")
@@ -174,6 +167,170 @@ fn with_binary_encoding_small_atom_utf8_returns_atom() {
     );
 }
 
+#[test]
+fn with_binary_encoding_map_returns_map() {
+    with_binary_returns_term(
+        // :erlang.term_to_binary(%{a: 1})
+        vec![131, 116, 0, 0, 0, 1, 100, 0, 1, 97, 97, 1],
+        |process| {
+            process
+                .map_from_slice(&[(atom_unchecked("a"), process.integer(1).unwrap())])
+                .unwrap()
+        },
+    );
+}
+
+#[test]
+fn with_binary_encoding_pid_returns_pid() {
+    with_binary_returns_term(
+        // :erlang.term_to_binary(:c.pid(0, 1, 2))
+        vec![
+            131, 103, 100, 0, 13, 110, 111, 110, 111, 100, 101, 64, 110, 111, 104, 111, 115, 116,
+            0, 0, 0, 1, 0, 0, 0, 2, 0,
+        ],
+        |_| make_pid(1, 2).unwrap(),
+    );
+}
+
+#[test]
+fn with_binary_encoding_newer_reference_returns_reference() {
+    with_binary_returns_term(
+        // A local reference with ID words 1, 0 and 2
+        vec![
+            131, 90, 0, 3, 100, 0, 13, 110, 111, 110, 111, 100, 101, 64, 110, 111, 104, 111, 115,
+            116, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2,
+        ],
+        |process| {
+            process
+                .reference_from_scheduler(unsafe { scheduler::ID::from_raw(2) }, 1)
+                .unwrap()
+        },
+    );
+}
+
+#[test]
+fn with_binary_encoding_export_returns_function() {
+    with_binary_returns_term(
+        // :erlang.term_to_binary(&:erlang.self/0)
+        vec![
+            131, 113, 100, 0, 6, 101, 114, 108, 97, 110, 103, 100, 0, 4, 115, 101, 108, 102, 97, 0,
+        ],
+        |process| {
+            code::export_closure(
+                process,
+                Atom::try_from_str("erlang").unwrap(),
+                Atom::try_from_str("self").unwrap(),
+                0,
+            )
+            .unwrap()
+        },
+    );
+}
+
+#[test]
+fn with_binary_encoding_new_function_returns_function() {
+    with_binary_returns_term(
+        // The first fun in module `test`, with arity 1, created by `<0.1.2>` and closing over `a`
+        vec![
+            131, 112, 0, 0, 0, 70, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 1, 100, 0, 4, 116, 101, 115, 116, 97, 0, 97, 0, 103, 100, 0, 13, 110, 111, 110,
+            111, 100, 101, 64, 110, 111, 104, 111, 115, 116, 0, 0, 0, 1, 0, 0, 0, 2, 0, 100, 0, 1,
+            97,
+        ],
+        |process| {
+            code::lambda_closure(
+                process,
+                Atom::try_from_str("test").unwrap(),
+                Atom::try_from_str("-fun-0-").unwrap(),
+                1,
+                make_pid(1, 2).unwrap(),
+                &[atom_unchecked("a")],
+            )
+            .unwrap()
+        },
+    );
+}
+
+#[test]
+fn with_binary_encoding_compressed_returns_uncompressed_term() {
+    with_binary_returns_term(
+        // :erlang.term_to_binary(List.duplicate(?a, 100), [:compressed])
+        vec![
+            131, 80, 0, 0, 0, 103, 120, 156, 203, 102, 72, 73, 164, 3, 0, 0, 204, 203, 38, 180,
+        ],
+        |process| {
+            process
+                .list_from_slice(&vec![process.integer(97).unwrap(); 100])
+                .unwrap()
+        },
+    );
+}
+
+#[test]
+fn with_binary_encoding_compressed_longer_than_uncompressed_size_errors_badarg() {
+    with_binary_errors_badarg(
+        // `:erlang.term_to_binary(List.duplicate(?a, 100), [:compressed])` with an uncompressed
+        // size of 10 instead of 103
+        vec![
+            131, 80, 0, 0, 0, 10, 120, 156, 203, 102, 72, 73, 164, 3, 0, 0, 204, 203, 38, 180,
+        ],
+    );
+}
+
+#[test]
+fn with_binary_encoding_atom_cache_reference_returns_atom_from_distribution_header() {
+    with_binary_returns_term(
+        // A distribution header with a new cache entry for `atom` followed by a reference to it
+        vec![131, 68, 1, 0b0000_1000, 0, 4, 97, 116, 111, 109, 82, 0],
+        |_| atom_unchecked("atom"),
+    );
+}
+
+#[test]
+fn with_binary_nested_max_depth_returns_term() {
+    with_binary_returns_term(nested_tuples_byte_vec(MAX_DEPTH), |process| {
+        (1..MAX_DEPTH).fold(Term::NIL, |term, _| {
+            process.tuple_from_slice(&[term]).unwrap()
+        })
+    });
+}
+
+#[test]
+fn with_binary_nested_deeper_than_max_depth_errors_badarg() {
+    with_binary_errors_badarg(nested_tuples_byte_vec(MAX_DEPTH + 1));
+}
+
+/// `{{...{[]}...}}`, with `depth` terms counting the innermost `[]`
+fn nested_tuples_byte_vec(depth: usize) -> Vec<u8> {
+    let mut byte_vec = vec![131];
+
+    for _ in 1..depth {
+        byte_vec.extend_from_slice(&[104, 1]);
+    }
+
+    byte_vec.push(106);
+
+    byte_vec
+}
+
+fn with_binary_errors_badarg(byte_vec: Vec<u8>) {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &strategy::term::binary::containing_bytes(byte_vec, arc_process.clone()),
+                |binary| {
+                    prop_assert_eq!(
+                        erlang::binary_to_term_1(binary, &arc_process),
+                        Err(badarg!().into())
+                    );
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
 fn with_binary_returns_term<T>(byte_vec: Vec<u8>, term: T)
 where
     T: Fn(&Process) -> Term,
@@ -0,0 +1,157 @@
+use super::*;
+
+#[test]
+fn with_atom_returns_atom_ext() {
+    returns_bytes(
+        |_| atom_unchecked("atom"),
+        // :erlang.term_to_binary(:atom)
+        vec![131, 100, 0, 4, 97, 116, 111, 109],
+    );
+}
+
+#[test]
+fn with_empty_list_returns_nil_ext() {
+    returns_bytes(
+        |_| Term::NIL,
+        // :erlang.term_to_binary([])
+        vec![131, 106],
+    );
+}
+
+#[test]
+fn with_list_returns_list_ext() {
+    returns_bytes(
+        |process| {
+            process
+                .cons(
+                    atom_unchecked("zero"),
+                    process
+                        .cons(process.integer(1).unwrap(), Term::NIL)
+                        .unwrap(),
+                )
+                .unwrap()
+        },
+        // :erlang.term_to_binary([:zero, 1])
+        vec![
+            131, 108, 0, 0, 0, 2, 100, 0, 4, 122, 101, 114, 111, 97, 1, 106,
+        ],
+    );
+}
+
+#[test]
+fn with_byte_list_returns_string_ext() {
+    returns_bytes(
+        |process| {
+            process
+                .list_from_slice(&[process.integer(0).unwrap(), process.integer(1).unwrap()])
+                .unwrap()
+        },
+        // :erlang.term_to_binary([0, 1])
+        vec![131, 107, 0, 2, 0, 1],
+    );
+}
+
+#[test]
+fn with_integer_returns_integer_ext() {
+    returns_bytes(
+        |process| process.integer(-2147483648_isize).unwrap(),
+        // :erlang.term_to_binary(-2147483648)
+        vec![131, 98, 128, 0, 0, 0],
+    );
+}
+
+#[test]
+fn with_integer_larger_than_32_bits_returns_small_big_ext() {
+    returns_bytes(
+        |process| process.integer(4294967295_usize).unwrap(),
+        // :erlang.term_to_binary(4294967295)
+        vec![131, 110, 4, 0, 255, 255, 255, 255],
+    );
+}
+
+#[test]
+fn with_float_returns_new_float_ext() {
+    returns_bytes(
+        |process| process.float(1.0).unwrap(),
+        // :erlang.term_to_binary(1.0)
+        vec![131, 70, 63, 240, 0, 0, 0, 0, 0, 0],
+    );
+}
+
+#[test]
+fn with_tuple_returns_small_tuple_ext() {
+    returns_bytes(
+        |process| {
+            process
+                .tuple_from_slice(&[atom_unchecked("zero"), process.integer(1).unwrap()])
+                .unwrap()
+        },
+        // :erlang.term_to_binary({:zero, 1})
+        vec![131, 104, 2, 100, 0, 4, 122, 101, 114, 111, 97, 1],
+    );
+}
+
+#[test]
+fn with_binary_returns_binary_ext() {
+    returns_bytes(
+        |process| process.binary_from_bytes(&[0, 1]).unwrap(),
+        // :erlang.term_to_binary(<<0, 1>>)
+        vec![131, 109, 0, 0, 0, 2, 0, 1],
+    );
+}
+
+#[test]
+fn with_bitstring_returns_bit_binary_ext() {
+    returns_bytes(
+        |process| {
+            process
+                .subbinary_from_original(
+                    process.binary_from_bytes(&[1, 0b010_00000]).unwrap(),
+                    0,
+                    0,
+                    1,
+                    3,
+                )
+                .unwrap()
+        },
+        // :erlang.term_to_binary(<<1, 2::3>>)
+        vec![131, 77, 0, 0, 0, 2, 3, 1, 64],
+    );
+}
+
+#[test]
+fn is_inverse_of_binary_to_term_1() {
+    with_process_arc(|arc_process| {
+        TestRunner::new(Config::with_source_file(file!()))
+            .run(
+                &(
+                    strategy::term::atom(),
+                    strategy::term::is_integer(arc_process.clone()),
+                    strategy::term::float(arc_process.clone()),
+                ),
+                |(atom, integer, float)| {
+                    let term = arc_process
+                        .tuple_from_slice(&[atom, integer, float])
+                        .unwrap();
+                    let binary = erlang::term_to_binary_1(term, &arc_process).unwrap();
+
+                    prop_assert_eq!(erlang::binary_to_term_1(binary, &arc_process), Ok(term));
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+    });
+}
+
+fn returns_bytes<T>(term: T, byte_vec: Vec<u8>)
+where
+    T: Fn(&Process) -> Term,
+{
+    with_process(|process| {
+        assert_eq!(
+            erlang::term_to_binary_1(term(process), process),
+            Ok(process.binary_from_bytes(&byte_vec).unwrap())
+        );
+    });
+}
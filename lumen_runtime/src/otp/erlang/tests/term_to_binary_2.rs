@@ -0,0 +1,167 @@
+use super::*;
+
+#[test]
+fn without_proper_list_options_errors_badarg() {
+    with_process(|process| {
+        let options = atom_unchecked("compressed");
+
+        assert_eq!(
+            erlang::term_to_binary_2(Term::NIL, options, process),
+            Err(badarg!().into())
+        );
+    });
+}
+
+#[test]
+fn with_unknown_option_errors_badarg() {
+    with_process(|process| {
+        let options = process
+            .cons(atom_unchecked("unknown"), Term::NIL)
+            .unwrap();
+
+        assert_eq!(
+            erlang::term_to_binary_2(Term::NIL, options, process),
+            Err(badarg!().into())
+        );
+    });
+}
+
+#[test]
+fn with_compressed_level_out_of_range_errors_badarg() {
+    with_process(|process| {
+        let options = process
+            .cons(
+                process
+                    .tuple_from_slice(&[
+                        atom_unchecked("compressed"),
+                        process.integer(10).unwrap(),
+                    ])
+                    .unwrap(),
+                Term::NIL,
+            )
+            .unwrap();
+
+        assert_eq!(
+            erlang::term_to_binary_2(Term::NIL, options, process),
+            Err(badarg!().into())
+        );
+    });
+}
+
+#[test]
+fn with_compressed_when_compression_is_smaller_returns_compressed_ext() {
+    with_process(|process| {
+        let options = process
+            .cons(atom_unchecked("compressed"), Term::NIL)
+            .unwrap();
+        let term = process.binary_from_bytes(&[0; 1024]).unwrap();
+
+        let binary = erlang::term_to_binary_2(term, options, process).unwrap();
+        let bytes = process.bytes_from_binary(binary).unwrap();
+
+        assert_eq!(&bytes[0..2], &[131, 80]);
+        assert_eq!(&bytes[2..6], &1029_u32.to_be_bytes());
+        assert!(bytes.len() < 1024);
+        assert_eq!(erlang::binary_to_term_1(binary, process), Ok(term));
+    });
+}
+
+#[test]
+fn with_compressed_when_compression_is_not_smaller_returns_uncompressed() {
+    with_process(|process| {
+        let options = process
+            .cons(atom_unchecked("compressed"), Term::NIL)
+            .unwrap();
+
+        assert_eq!(
+            erlang::term_to_binary_2(atom_unchecked("atom"), options, process),
+            Ok(process
+                .binary_from_bytes(&[131, 100, 0, 4, 97, 116, 111, 109])
+                .unwrap())
+        );
+    });
+}
+
+#[test]
+fn with_compressed_level_only_finds_repeats_within_its_window() {
+    with_process(|process| {
+        // Repeats every 512 bytes, which is further back than level 1 searches.
+        let period: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+        let bytes = period.repeat(8);
+        let term = process.binary_from_bytes(&bytes).unwrap();
+
+        let fastest_binary =
+            erlang::term_to_binary_2(term, compressed_options(1, process), process).unwrap();
+        let fastest_bytes = process.bytes_from_binary(fastest_binary).unwrap();
+
+        assert_eq!(&fastest_bytes[0..2], &[131, 109]);
+
+        let best_binary =
+            erlang::term_to_binary_2(term, compressed_options(9, process), process).unwrap();
+        let best_bytes = process.bytes_from_binary(best_binary).unwrap();
+
+        assert_eq!(&best_bytes[0..2], &[131, 80]);
+        assert!(best_bytes.len() < bytes.len());
+        assert_eq!(erlang::binary_to_term_1(best_binary, process), Ok(term));
+    });
+}
+
+#[test]
+fn with_minor_version_0_returns_float_ext() {
+    with_process(|process| {
+        let options = minor_version_options(0, process);
+        let mut byte_vec = vec![131, 99];
+        byte_vec.extend_from_slice(b"1.00000000000000000000e+00");
+        byte_vec.resize(2 + 31, 0);
+
+        let binary = erlang::term_to_binary_2(process.float(1.0).unwrap(), options, process);
+
+        assert_eq!(binary, Ok(process.binary_from_bytes(&byte_vec).unwrap()));
+        assert_eq!(
+            erlang::binary_to_term_1(binary.unwrap(), process),
+            Ok(process.float(1.0).unwrap())
+        );
+    });
+}
+
+#[test]
+fn with_minor_version_2_returns_small_atom_utf8_ext() {
+    with_process(|process| {
+        let options = minor_version_options(2, process);
+
+        assert_eq!(
+            erlang::term_to_binary_2(atom_unchecked("atom"), options, process),
+            Ok(process
+                .binary_from_bytes(&[131, 119, 4, 97, 116, 111, 109])
+                .unwrap())
+        );
+    });
+}
+
+fn compressed_options(level: u8, process: &Process) -> Term {
+    process
+        .cons(
+            process
+                .tuple_from_slice(&[
+                    atom_unchecked("compressed"),
+                    process.integer(level).unwrap(),
+                ])
+                .unwrap(),
+            Term::NIL,
+        )
+        .unwrap()
+}
+
+fn minor_version_options(minor_version: u8, process: &Process) -> Term {
+    process
+        .cons(
+            process
+                .tuple_from_slice(&[
+                    atom_unchecked("minor_version"),
+                    process.integer(minor_version).unwrap(),
+                ])
+                .unwrap(),
+            Term::NIL,
+        )
+        .unwrap()
+}
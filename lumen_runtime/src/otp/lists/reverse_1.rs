@@ -61,6 +61,6 @@ fn module_function_arity() -> Arc<ModuleFunctionArity> {
     })
 }
 
-fn native(process: &Process, list: Term) -> exception::Result {
+pub fn native(process: &Process, list: Term) -> exception::Result {
     reverse_2::native(process, list, Term::NIL)
 }
@@ -11,7 +11,7 @@ use liblumen_alloc::erts::exception::runtime;
 use liblumen_alloc::erts::exception::system::Alloc;
 use liblumen_alloc::erts::process::alloc::heap_alloc::HeapAlloc;
 use liblumen_alloc::erts::process::code::stack::frame::Frame;
-use liblumen_alloc::erts::process::{self, Process};
+use liblumen_alloc::erts::process::{self, MaxHeapSizeReport, Process};
 use liblumen_alloc::erts::term::{atom_unchecked, Atom, Term, Tuple, TypedTerm};
 use liblumen_alloc::erts::ModuleFunctionArity;
 use liblumen_alloc::HeapFragment;
@@ -21,6 +21,7 @@ use crate::code;
 use crate::process::spawn::options::Options;
 use crate::registry::*;
 use crate::scheduler::Scheduler;
+use crate::stacktrace;
 use crate::system;
 #[cfg(test)]
 use crate::test;
@@ -65,16 +66,49 @@ pub fn log_exit(process: &Process, exception: &runtime::Exception) {
                 ));
             }
         }
-        runtime::Class::Error { .. } => system::io::puts(&format!(
-            "** (EXIT from {}) exited with reason: an exception was raised: {}\n{}",
-            process,
-            exception.reason,
-            process.stacktrace()
-        )),
+        runtime::Class::Error { .. } => {
+            // Interpreted code has no native stack, so it raises with the stacktrace instead
+            let stacktrace = match exception.stacktrace {
+                Some(stacktrace) => stacktrace::format(stacktrace),
+                None => process.stacktrace().to_string(),
+            };
+
+            system::io::puts(&format!(
+                "** (EXIT from {}) exited with reason: an exception was raised: {}\n{}",
+                process, exception.reason, stacktrace
+            ))
+        }
         _ => unimplemented!("{:?}", exception),
     }
 }
 
+pub fn log_max_heap_size_report(process: &Process, report: &MaxHeapSizeReport) {
+    let current_function = match report.module_function_arity {
+        Some(ref module_function_arity) => format!(
+            "{}:{}/{}",
+            module_function_arity.module.name(),
+            module_function_arity.function.name(),
+            module_function_arity.arity
+        ),
+        None => "undefined".to_string(),
+    };
+
+    system::io::puts(&format!(
+        "** (MAX HEAP SIZE from {}) maximum heap size reached\n    \
+         Max Heap Size:    {}\n    \
+         Total Heap Size:  {}\n    \
+         Kill:             {}\n    \
+         Error Logger:     {}\n    \
+         Current Function: {}",
+        process,
+        report.max_heap_size.size,
+        report.heap_size,
+        report.max_heap_size.kill,
+        report.max_heap_size.error_logger,
+        current_function
+    ));
+}
+
 pub fn propagate_exit(process: &Process, exception: &runtime::Exception) {
     monitor::propagate_exit(process, exception);
     propagate_exit_to_links(process, exception);
@@ -4,11 +4,10 @@ use std::sync::Arc;
 use liblumen_alloc::erts::exception::system::Alloc;
 use liblumen_alloc::erts::exception::Exception;
 use liblumen_alloc::erts::process::alloc::{default_heap_size, heap, next_heap_size};
-use liblumen_alloc::erts::process::{Priority, Process};
+use liblumen_alloc::erts::process::{self, Priority, Process};
 use liblumen_alloc::erts::term::{Atom, Boxed, Cons, Term, Tuple, TypedTerm};
 use liblumen_alloc::{badarg, ModuleFunctionArity};
 
-#[allow(dead_code)]
 #[derive(Clone, Copy)]
 pub struct MaxHeapSize {
     size: Option<usize>,
@@ -16,6 +15,54 @@ pub struct MaxHeapSize {
     error_logger: Option<bool>,
 }
 
+impl MaxHeapSize {
+    /// The limit for the process, with the default for anything that isn't set
+    pub fn cascaded(&self) -> process::MaxHeapSize {
+        let default = process::MaxHeapSize::default();
+
+        process::MaxHeapSize {
+            size: self.size.unwrap_or(default.size),
+            kill: self.kill.unwrap_or(default.kill),
+            error_logger: self.error_logger.unwrap_or(default.error_logger),
+        }
+    }
+}
+
+impl TryFrom<Term> for MaxHeapSize {
+    type Error = Exception;
+
+    /// Either the size in words or a map with any of the `size`, `kill` and `error_logger` keys
+    fn try_from(term: Term) -> Result<Self, Self::Error> {
+        let mut max_heap_size = MaxHeapSize {
+            size: None,
+            kill: None,
+            error_logger: None,
+        };
+
+        match term.to_typed_term().unwrap() {
+            TypedTerm::Boxed(boxed) => match boxed.to_typed_term().unwrap() {
+                TypedTerm::Map(map) => {
+                    for key in map.keys() {
+                        let key_atom: Atom = key.try_into()?;
+                        let value = map.get(key).unwrap();
+
+                        match key_atom.name() {
+                            "size" => max_heap_size.size = Some(value.try_into()?),
+                            "kill" => max_heap_size.kill = Some(value.try_into()?),
+                            "error_logger" => max_heap_size.error_logger = Some(value.try_into()?),
+                            _ => return Err(badarg!().into()),
+                        }
+                    }
+                }
+                _ => max_heap_size.size = Some(term.try_into()?),
+            },
+            _ => max_heap_size.size = Some(term.try_into()?),
+        }
+
+        Ok(max_heap_size)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum MessageQueueData {
     OnHeap,
@@ -94,6 +141,18 @@ impl Options {
             heap_size,
         );
 
+        if let MessageQueueData::OffHeap = self.message_queue_data {
+            process.off_heap_message_queue(true);
+        }
+
+        if let Some(min_bin_vheap_size) = self.min_bin_vheap_size {
+            process.set_min_vheap_size(min_bin_vheap_size);
+        }
+
+        if let Some(max_heap_size) = self.max_heap_size {
+            process.set_max_heap_size(max_heap_size.cascaded());
+        }
+
         Ok(process)
     }
 
@@ -103,12 +162,25 @@ impl Options {
         match self.priority {
             Some(priority) => priority,
             None => match parent_process {
-                Some(process) => process.priority,
+                Some(process) => process.priority(),
                 None => Default::default(),
             },
         }
     }
 
+    /// Whether `max_heap_size` is unlimited or no less than the heap size, the same as
+    /// `process_flag(max_heap_size, ...)` requires
+    fn has_valid_max_heap_size(&self) -> bool {
+        match self.max_heap_size {
+            Some(max_heap_size) => {
+                let size = max_heap_size.cascaded().size;
+
+                size == 0 || self.heap_size() <= size
+            }
+            None => true,
+        }
+    }
+
     /// `heap` size in words.
     fn heap_size(&self) -> usize {
         match self.min_heap_size {
@@ -163,7 +235,14 @@ impl Options {
                         }
                         Err(_) => false,
                     },
-                    "max_heap_size" => unimplemented!(),
+                    "max_heap_size" => match tuple[1].try_into() {
+                        Ok(max_heap_size) => {
+                            self.max_heap_size = Some(max_heap_size);
+
+                            true
+                        }
+                        Err(_) => false,
+                    },
                     "message_queue_data" => match tuple[1].try_into() {
                         Ok(message_queue_data) => {
                             self.message_queue_data = message_queue_data;
@@ -237,7 +316,7 @@ impl TryFrom<Boxed<Cons>> for Options {
             }
         }
 
-        if valid {
+        if valid && options.has_valid_max_heap_size() {
             Ok(options)
         } else {
             Err(badarg!().into())
@@ -136,6 +136,10 @@ impl Scheduler {
                         arc_process.reduce()
                     }
 
+                    if let Some(report) = arc_process.take_max_heap_size_report() {
+                        process::log_max_heap_size_report(&arc_process, &report);
+                    }
+
                     match self.run_queues.write().requeue(arc_process) {
                         Some(exiting_arc_process) => match *exiting_arc_process.status.read() {
                             Status::Exiting(ref exception) => {
@@ -16,6 +16,99 @@ pub fn is(term: Term) -> bool {
     }
 }
 
+/// Formats `stacktrace` with one `Module:Function/Arity (File:Line)` entry per line
+pub fn format(stacktrace: Term) -> String {
+    let mut formatted = String::new();
+
+    if let TypedTerm::List(cons) = stacktrace.to_typed_term().unwrap() {
+        for result in cons.into_iter() {
+            match result {
+                Ok(item) => {
+                    formatted.push_str("  ");
+                    formatted.push_str(&format_item(item));
+                    formatted.push('\n');
+                }
+                Err(_) => break,
+            }
+        }
+    }
+
+    formatted
+}
+
+fn format_item(term: Term) -> String {
+    let result: Result<Boxed<Tuple>, _> = term.try_into();
+
+    match result {
+        Ok(tuple) if tuple.len() == 4 => {
+            let module: Result<Atom, _> = tuple[0].try_into();
+            let function: Result<Atom, _> = tuple[1].try_into();
+
+            match (module, function) {
+                (Ok(module), Ok(function)) => {
+                    let arity = match tuple[2].to_typed_term().unwrap() {
+                        TypedTerm::Nil => 0.to_string(),
+                        TypedTerm::List(arguments) => arguments.into_iter().count().to_string(),
+                        _ => tuple[2].to_string(),
+                    };
+
+                    match format_location(tuple[3]) {
+                        Some(location) => format!(
+                            "{}:{}/{} ({})",
+                            module.name(),
+                            function.name(),
+                            arity,
+                            location
+                        ),
+                        None => format!("{}:{}/{}", module.name(), function.name(), arity),
+                    }
+                }
+                _ => term.to_string(),
+            }
+        }
+        _ => term.to_string(),
+    }
+}
+
+/// `File:Line` from `[{file, File}, {line, Line}]`
+fn format_location(term: Term) -> Option<String> {
+    let mut file = None;
+    let mut line = None;
+
+    if let TypedTerm::List(cons) = term.to_typed_term().unwrap() {
+        for result in cons.into_iter() {
+            let pair: Boxed<Tuple> = result.ok()?.try_into().ok()?;
+            let key: Atom = pair[0].try_into().ok()?;
+
+            match key.name() {
+                "file" => file = Some(charlist_to_string(pair[1])?),
+                "line" => line = Some(pair[1]),
+                _ => (),
+            }
+        }
+    }
+
+    match (file, line) {
+        (Some(file), Some(line)) => Some(format!("{}:{}", file, line)),
+        (Some(file), None) => Some(file),
+        _ => None,
+    }
+}
+
+fn charlist_to_string(term: Term) -> Option<String> {
+    match term.try_into().ok()? {
+        list::List::Empty => Some(String::new()),
+        list::List::NonEmpty(cons) => cons
+            .into_iter()
+            .map(|result| {
+                let c: char = result.ok()?.try_into().ok()?;
+
+                Some(c)
+            })
+            .collect(),
+    }
+}
+
 fn term_is_location(term: Term) -> bool {
     match term.to_typed_term().unwrap() {
         TypedTerm::Nil => true,
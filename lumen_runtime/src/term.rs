@@ -1 +1,3 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod etf;
 pub mod external_format;
@@ -0,0 +1,42 @@
+//! Converts between `liblumen_beam::serialization::etf::Term`, the `std` term model used by BEAM
+//! tooling, and `Term`s on a `Process`'s heap.
+//!
+//! Both directions go through the external term format, so that the conversions support exactly
+//! the terms that `erlang:binary_to_term/1` and `erlang:term_to_binary/1` do.
+
+// wasm32 proptest cannot be compiled at the same time as non-wasm32 proptest, so disable tests that
+// use proptest completely for wasm32
+//
+// See https://github.com/rust-lang/cargo/issues/4866
+#[cfg(all(not(target_arch = "wasm32"), test))]
+mod test;
+
+use alloc::vec::Vec;
+
+use liblumen_beam::serialization::etf;
+
+use liblumen_alloc::badarg;
+use liblumen_alloc::erts::exception::Exception;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::term::Term;
+
+use crate::term::external_format;
+
+/// Allocates `etf_term` on the heap of `process`, such as to materialize a literal from a BEAM
+/// file's `LitT` chunk.
+pub fn from_etf(etf_term: &etf::Term, process: &Process) -> Result<Term, Exception> {
+    let mut byte_vec: Vec<u8> = Vec::new();
+    etf_term.encode(&mut byte_vec).map_err(|_| badarg!())?;
+
+    let (term, _used) = external_format::decode(&byte_vec, false, process)?;
+
+    Ok(term)
+}
+
+/// Copies `term` out of its process's heap, so that it can be inspected with tooling written
+/// against `etf::Term`, such as `etf::Term::as_match`.
+pub fn to_etf(term: Term) -> Result<etf::Term, Exception> {
+    let byte_vec = external_format::encode(term, &Default::default())?;
+
+    etf::Term::decode(&byte_vec[..]).map_err(|_| badarg!().into())
+}
@@ -0,0 +1,39 @@
+use super::*;
+
+use liblumen_alloc::erts::term::atom_unchecked;
+
+use crate::scheduler::with_process;
+
+#[test]
+fn from_etf_allocates_on_process_heap() {
+    with_process(|process| {
+        let etf_term = etf::Term::from(etf::Tuple::from(vec![
+            etf::Term::from(etf::Atom::from("zero")),
+            etf::Term::from(etf::FixInteger::from(1)),
+        ]));
+
+        assert_eq!(
+            from_etf(&etf_term, process),
+            Ok(process
+                .tuple_from_slice(&[atom_unchecked("zero"), process.integer(1).unwrap()])
+                .unwrap())
+        );
+    });
+}
+
+#[test]
+fn to_etf_is_inverse_of_from_etf() {
+    with_process(|process| {
+        let term = process
+            .list_from_slice(&[
+                atom_unchecked("zero"),
+                process.float(1.0).unwrap(),
+                process.binary_from_bytes(&[2, 3]).unwrap(),
+            ])
+            .unwrap();
+
+        let etf_term = to_etf(term).unwrap();
+
+        assert_eq!(from_etf(&etf_term, process), Ok(term));
+    });
+}
@@ -1,21 +1,49 @@
 //! [External Term Format](http://erlang.org/doc/apps/erts/erl_ext_dist.html)
+mod decode;
+mod encode;
+
+pub use decode::{decode, MAX_DEPTH};
+pub use encode::encode;
+
 use core::convert::TryFrom;
 
 use liblumen_alloc::badarg;
 use liblumen_alloc::erts::exception::runtime::Exception;
 
+/// The first byte of every term encoded by `erlang:term_to_binary`
+pub const VERSION_NUMBER: u8 = 131;
+
 pub enum Tag {
+    DistributionHeader = 68,
     NewFloat = 70,
     BitBinary = 77,
+    Compressed = 80,
+    AtomCacheReference = 82,
+    NewPid = 88,
+    NewPort = 89,
+    NewerReference = 90,
     SmallInteger = 97,
     Integer = 98,
+    Float = 99,
     Atom = 100,
+    Reference = 101,
+    Port = 102,
+    Pid = 103,
     SmallTuple = 104,
+    LargeTuple = 105,
     EmptyList = 106,
     ByteList = 107,
     List = 108,
     Binary = 109,
     SmallBigInteger = 110,
+    LargeBigInteger = 111,
+    NewFunction = 112,
+    Export = 113,
+    NewReference = 114,
+    SmallAtom = 115,
+    Map = 116,
+    Function = 117,
+    AtomUTF8 = 118,
     SmallAtomUTF8 = 119,
 }
 
@@ -26,17 +54,36 @@ impl TryFrom<u8> for Tag {
         use crate::term::external_format::Tag::*;
 
         match tag_byte {
+            68 => Ok(DistributionHeader),
             70 => Ok(NewFloat),
             77 => Ok(BitBinary),
+            80 => Ok(Compressed),
+            82 => Ok(AtomCacheReference),
+            88 => Ok(NewPid),
+            89 => Ok(NewPort),
+            90 => Ok(NewerReference),
             97 => Ok(SmallInteger),
             98 => Ok(Integer),
+            99 => Ok(Float),
             100 => Ok(Atom),
+            101 => Ok(Reference),
+            102 => Ok(Port),
+            103 => Ok(Pid),
             104 => Ok(SmallTuple),
+            105 => Ok(LargeTuple),
             106 => Ok(EmptyList),
             107 => Ok(ByteList),
             108 => Ok(List),
             109 => Ok(Binary),
             110 => Ok(SmallBigInteger),
+            111 => Ok(LargeBigInteger),
+            112 => Ok(NewFunction),
+            113 => Ok(Export),
+            114 => Ok(NewReference),
+            115 => Ok(SmallAtom),
+            116 => Ok(Map),
+            117 => Ok(Function),
+            118 => Ok(AtomUTF8),
             119 => Ok(SmallAtomUTF8),
             _ => Err(badarg!()),
         }
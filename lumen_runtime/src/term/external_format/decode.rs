@@ -0,0 +1,564 @@
+use core::convert::{TryFrom, TryInto};
+use core::mem;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use std::io::{Cursor, Read};
+
+use libflate::zlib;
+
+use num_bigint::{BigInt, Sign};
+
+use liblumen_alloc::badarg;
+use liblumen_alloc::erts::exception::Exception;
+use liblumen_alloc::erts::process::Process;
+use liblumen_alloc::erts::scheduler;
+use liblumen_alloc::erts::term::{AsTerm, Atom, Port, Term};
+
+use crate::code;
+use crate::node;
+use crate::term::external_format::{Tag, VERSION_NUMBER};
+
+/// The deepest that terms can be nested, such as tuples in tuples, and still be decoded.
+///
+/// Decoding recurses for each level of nesting, so a binary that is nested any deeper fails with
+/// `badarg` instead of overflowing the stack.
+pub const MAX_DEPTH: usize = 256;
+
+/// Decodes the term encoded in `bytes` onto the heap of `process`.
+///
+/// Returns the term along with the number of bytes of `bytes` that were used to encode it, so that
+/// `erlang:binary_to_term/2` can support the `used` option.
+///
+/// When `existing` is `true`, as it is with the `safe` option, decoding fails with `badarg` instead
+/// of creating new atoms.
+pub fn decode(bytes: &[u8], existing: bool, process: &Process) -> Result<(Term, usize), Exception> {
+    let mut decoder = Decoder::new(bytes, existing, process);
+
+    if decoder.read_u8()? != VERSION_NUMBER {
+        return Err(badarg!().into());
+    }
+
+    let term = match Tag::try_from(decoder.read_u8()?)? {
+        Tag::Compressed => decoder.decode_compressed()?,
+        Tag::DistributionHeader => {
+            decoder.decode_distribution_header()?;
+
+            decoder.decode_term()?
+        }
+        tag => decoder.decode_term_with_tag(tag)?,
+    };
+
+    Ok((term, decoder.position))
+}
+
+// Private
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// How many terms are being decoded that contain the one being decoded now
+    depth: usize,
+    existing: bool,
+    /// Atoms from the distribution header's `AtomCacheRefs`, indexed by `ATOM_CACHE_REF`s.
+    atom_cache: Vec<Atom>,
+    process: &'a Process,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8], existing: bool, process: &'a Process) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            depth: 0,
+            existing,
+            atom_cache: Vec::new(),
+            process,
+        }
+    }
+
+    fn decode_term(&mut self) -> Result<Term, Exception> {
+        let tag = Tag::try_from(self.read_u8()?)?;
+
+        self.decode_term_with_tag(tag)
+    }
+
+    fn decode_term_with_tag(&mut self, tag: Tag) -> Result<Term, Exception> {
+        if self.depth == MAX_DEPTH {
+            return Err(badarg!().into());
+        }
+
+        self.depth += 1;
+        let result = self.decode_nested_term_with_tag(tag);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn decode_nested_term_with_tag(&mut self, tag: Tag) -> Result<Term, Exception> {
+        match tag {
+            Tag::NewFloat => self.decode_new_float(),
+            Tag::BitBinary => self.decode_bit_binary(),
+            Tag::AtomCacheReference => self.decode_atom_cache_reference(),
+            Tag::NewPid => self.decode_pid(4),
+            Tag::NewPort => self.decode_port(4),
+            Tag::NewerReference => self.decode_new_reference(4),
+            Tag::SmallInteger => {
+                let small_integer = self.read_u8()?;
+
+                Ok(small_integer.into())
+            }
+            Tag::Integer => {
+                let integer = self.read_u32()? as i32;
+
+                self.process
+                    .integer(integer as isize)
+                    .map_err(|alloc| alloc.into())
+            }
+            Tag::Float => self.decode_float(),
+            Tag::Atom | Tag::AtomUTF8 => {
+                let len = self.read_u16()? as usize;
+
+                self.decode_atom(tag, len)
+            }
+            Tag::SmallAtom | Tag::SmallAtomUTF8 => {
+                let len = self.read_u8()? as usize;
+
+                self.decode_atom(tag, len)
+            }
+            Tag::Reference => self.decode_reference(),
+            Tag::Port => self.decode_port(1),
+            Tag::Pid => self.decode_pid(1),
+            Tag::SmallTuple => {
+                let arity = self.read_u8()? as usize;
+
+                self.decode_tuple(arity)
+            }
+            Tag::LargeTuple => {
+                let arity = self.read_u32()? as usize;
+
+                self.decode_tuple(arity)
+            }
+            Tag::EmptyList => Ok(Term::NIL),
+            Tag::ByteList => {
+                let len = self.read_u16()? as usize;
+                let bytes = self.read_bytes(len)?;
+
+                self.process
+                    .list_from_iter(bytes.iter().map(|byte| (*byte).into()))
+                    .map_err(|alloc| alloc.into())
+            }
+            Tag::List => self.decode_list(),
+            Tag::Binary => {
+                let len = self.read_u32()? as usize;
+                let bytes = self.read_bytes(len)?;
+
+                self.process
+                    .binary_from_bytes(bytes)
+                    .map_err(|alloc| alloc.into())
+            }
+            Tag::SmallBigInteger => {
+                let len = self.read_u8()? as usize;
+
+                self.decode_big_integer(len)
+            }
+            Tag::LargeBigInteger => {
+                let len = self.read_u32()? as usize;
+
+                self.decode_big_integer(len)
+            }
+            Tag::NewFunction => self.decode_new_function(),
+            Tag::Export => self.decode_export(),
+            Tag::NewReference => self.decode_new_reference(1),
+            Tag::Map => self.decode_map(),
+            Tag::Function => self.decode_function(),
+            // Only valid as the first tag after the version number
+            Tag::Compressed | Tag::DistributionHeader => Err(badarg!().into()),
+        }
+    }
+
+    fn decode_atom(&mut self, tag: Tag, len: usize) -> Result<Term, Exception> {
+        let atom = self.decode_atom_bytes(tag, len)?;
+
+        Ok(unsafe { atom.as_term() })
+    }
+
+    fn decode_atom_bytes(&mut self, tag: Tag, len: usize) -> Result<Atom, Exception> {
+        let bytes = self.read_bytes(len)?;
+
+        let name = match tag {
+            Tag::AtomUTF8 | Tag::SmallAtomUTF8 => {
+                String::from_utf8(bytes.to_vec()).map_err(|_| badarg!())?
+            }
+            // Latin-1 code points are the first 256 Unicode code points
+            _ => bytes.iter().map(|byte| *byte as char).collect(),
+        };
+
+        self.atom_from_str(&name)
+    }
+
+    /// Decodes a term that must be an atom, such as the node name in a pid.
+    fn decode_atom_term(&mut self) -> Result<Atom, Exception> {
+        let term = self.decode_term()?;
+
+        term.try_into().map_err(|_| badarg!().into())
+    }
+
+    fn decode_atom_cache_reference(&mut self) -> Result<Term, Exception> {
+        let index = self.read_u8()? as usize;
+
+        match self.atom_cache.get(index) {
+            Some(atom) => Ok(unsafe { atom.as_term() }),
+            None => Err(badarg!().into()),
+        }
+    }
+
+    fn decode_big_integer(&mut self, len: usize) -> Result<Term, Exception> {
+        let sign = match self.read_u8()? {
+            0 => Sign::Plus,
+            1 => Sign::Minus,
+            _ => return Err(badarg!().into()),
+        };
+        let bytes = self.read_bytes(len)?;
+        let big_int = BigInt::from_bytes_le(sign, bytes);
+
+        self.process
+            .integer(big_int)
+            .map_err(|alloc| alloc.into())
+    }
+
+    fn decode_bit_binary(&mut self) -> Result<Term, Exception> {
+        let len = self.read_u32()? as usize;
+        let partial_byte_bit_len = self.read_u8()?;
+        let bytes = self.read_bytes(len)?;
+
+        match (len, partial_byte_bit_len) {
+            (0, _) => self
+                .process
+                .binary_from_bytes(bytes)
+                .map_err(|alloc| alloc.into()),
+            (_, 1..=7) => {
+                // The bits of the partial byte are stored in its most significant bits, which is
+                // the same layout as `SubBinary`, so no shifting is needed.
+                let original = self.process.binary_from_bytes(bytes)?;
+
+                self.process
+                    .subbinary_from_original(original, 0, 0, len - 1, partial_byte_bit_len)
+                    .map_err(|alloc| alloc.into())
+            }
+            (_, 8) => self
+                .process
+                .binary_from_bytes(bytes)
+                .map_err(|alloc| alloc.into()),
+            _ => Err(badarg!().into()),
+        }
+    }
+
+    fn decode_compressed(&mut self) -> Result<Term, Exception> {
+        let uncompressed_len = self.read_u32()? as usize;
+        let mut cursor = Cursor::new(&self.bytes[self.position..]);
+        let mut uncompressed = Vec::new();
+
+        // Inflating stops one byte past the claimed length, so a small payload that inflates to
+        // far more than it claims can't exhaust memory.
+        zlib::Decoder::new(&mut cursor)
+            .and_then(|zlib_decoder| {
+                zlib_decoder
+                    .take(uncompressed_len as u64 + 1)
+                    .read_to_end(&mut uncompressed)
+            })
+            .map_err(|_| badarg!())?;
+
+        if uncompressed.len() != uncompressed_len {
+            return Err(badarg!().into());
+        }
+
+        self.position += cursor.position() as usize;
+
+        let mut uncompressed_decoder = Decoder::new(&uncompressed, self.existing, self.process);
+        let term = uncompressed_decoder.decode_term()?;
+
+        if uncompressed_decoder.position == uncompressed_len {
+            Ok(term)
+        } else {
+            Err(badarg!().into())
+        }
+    }
+
+    /// Decodes the [distribution header](http://erlang.org/doc/apps/erts/erl_ext_dist.html#distribution-header)
+    /// into `atom_cache`, so that `ATOM_CACHE_REF`s in the terms that follow it can be resolved.
+    ///
+    /// There is no connection that owns the decoded terms, so only the atoms that are new in this
+    /// header can be resolved; references to atoms cached by earlier messages on a connection fail
+    /// with `badarg`.
+    fn decode_distribution_header(&mut self) -> Result<(), Exception> {
+        let atom_cache_reference_count = self.read_u8()? as usize;
+
+        if 0 < atom_cache_reference_count {
+            let flags = self.read_bytes(atom_cache_reference_count / 2 + 1)?;
+            let half_byte = |index: usize| (flags[index / 2] >> ((index % 2) * 4)) & 0b1111;
+            let long_atoms = (half_byte(atom_cache_reference_count) & 0b0001) != 0;
+            let new_cache_entry_vec: Vec<bool> = (0..atom_cache_reference_count)
+                .map(|index| (half_byte(index) & 0b1000) != 0)
+                .collect();
+
+            for new_cache_entry in new_cache_entry_vec {
+                let _internal_segment_index = self.read_u8()?;
+
+                if new_cache_entry {
+                    let len = if long_atoms {
+                        self.read_u16()? as usize
+                    } else {
+                        self.read_u8()? as usize
+                    };
+                    let atom = self.decode_atom_bytes(Tag::AtomUTF8, len)?;
+
+                    self.atom_cache.push(atom);
+                } else {
+                    return Err(badarg!().into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn decode_export(&mut self) -> Result<Term, Exception> {
+        let module = self.decode_atom_term()?;
+        let function = self.decode_atom_term()?;
+        let arity = match Tag::try_from(self.read_u8()?)? {
+            Tag::SmallInteger => self.read_u8()?,
+            _ => return Err(badarg!().into()),
+        };
+
+        code::export_closure(self.process, module, function, arity).map_err(|alloc| alloc.into())
+    }
+
+    fn decode_float(&mut self) -> Result<Term, Exception> {
+        let bytes = self.read_bytes(31)?;
+        let string = core::str::from_utf8(bytes).map_err(|_| badarg!())?;
+        let f: f64 = string
+            .trim_end_matches('\0')
+            .parse()
+            .map_err(|_| badarg!())?;
+
+        self.process.float(f).map_err(|alloc| alloc.into())
+    }
+
+    /// Decodes a `FUN_EXT`.
+    ///
+    /// `FUN_EXT` does not encode the arity of the fun, so the `-fun-index-` function it calls can't
+    /// be distinguished from the free variables it closes over, and it is rejected with `badarg`
+    /// as it is by OTP 23 and later.
+    fn decode_function(&mut self) -> Result<Term, Exception> {
+        Err(badarg!().into())
+    }
+
+    fn decode_list(&mut self) -> Result<Term, Exception> {
+        let len = self.read_u32()? as usize;
+        let element_vec = self.decode_terms(len)?;
+        let tail = self.decode_term()?;
+
+        self.process
+            .improper_list_from_slice(&element_vec, tail)
+            .map_err(|alloc| alloc.into())
+    }
+
+    fn decode_map(&mut self) -> Result<Term, Exception> {
+        let len = self.read_u32()? as usize;
+        let mut key_value_vec = Vec::with_capacity(self.capacity(len));
+
+        for _ in 0..len {
+            let key = self.decode_term()?;
+            let value = self.decode_term()?;
+
+            key_value_vec.push((key, value));
+        }
+
+        self.process
+            .map_from_slice(&key_value_vec)
+            .map_err(|alloc| alloc.into())
+    }
+
+    fn decode_new_float(&mut self) -> Result<Term, Exception> {
+        let bits = self.read_u64()?;
+
+        self.process
+            .float(f64::from_bits(bits))
+            .map_err(|alloc| alloc.into())
+    }
+
+    fn decode_new_function(&mut self) -> Result<Term, Exception> {
+        let start = self.position;
+        let size = self.read_u32()? as usize;
+        let arity = self.read_u8()?;
+        let _uniq = self.read_bytes(16)?;
+        let index = self.read_u32()? as usize;
+        let free_len = self.read_u32()? as usize;
+        let module = self.decode_atom_term()?;
+        let _old_index = self.decode_term()?;
+        let _old_uniq = self.decode_term()?;
+        let creator = self.decode_term()?;
+        let free_vec = self.decode_terms(free_len)?;
+
+        if self.position - start == size {
+            self.lambda_closure(module, index, arity, creator, &free_vec)
+        } else {
+            Err(badarg!().into())
+        }
+    }
+
+    /// Decodes a `REFERENCE_EXT`, which only has one ID word
+    fn decode_reference(&mut self) -> Result<Term, Exception> {
+        let node = self.decode_atom_term()?;
+        let id = self.read_u32()?;
+        let _creation = self.read_u8()?;
+
+        self.reference(node, &[id])
+    }
+
+    /// Decodes a `NEW_REFERENCE_EXT` or `NEWER_REFERENCE_EXT`, which only differ in the size of
+    /// `creation`.
+    fn decode_new_reference(&mut self, creation_len: usize) -> Result<Term, Exception> {
+        let id_len = self.read_u16()? as usize;
+        let node = self.decode_atom_term()?;
+        let _creation = self.read_bytes(creation_len)?;
+        let mut id_vec = Vec::with_capacity(self.capacity(id_len));
+
+        for _ in 0..id_len {
+            id_vec.push(self.read_u32()?);
+        }
+
+        self.reference(node, &id_vec)
+    }
+
+    /// Decodes a `PID_EXT` or `NEW_PID_EXT`, which only differ in the size of `creation`.
+    fn decode_pid(&mut self, creation_len: usize) -> Result<Term, Exception> {
+        let node = self.decode_atom_term()?;
+        let number = self.read_u32()? as usize;
+        let serial = self.read_u32()? as usize;
+        let _creation = self.read_bytes(creation_len)?;
+
+        self.process
+            .pid_with_node_id(node::id(node), number, serial)
+            .map_err(|error| error.into())
+    }
+
+    /// Decodes a `PORT_EXT` or `NEW_PORT_EXT`, which only differ in the size of `creation`.
+    fn decode_port(&mut self, creation_len: usize) -> Result<Term, Exception> {
+        let node = self.decode_atom_term()?;
+        let id = self.read_u32()? as usize;
+        let _creation = self.read_bytes(creation_len)?;
+
+        // `ExternalPort`s can't be allocated yet.
+        if node::id(node) == node::LOCAL_ID {
+            Ok(unsafe { Port::from_raw(id).as_term() })
+        } else {
+            Err(badarg!().into())
+        }
+    }
+
+    fn decode_terms(&mut self, len: usize) -> Result<Vec<Term>, Exception> {
+        let mut term_vec = Vec::with_capacity(self.capacity(len));
+
+        for _ in 0..len {
+            term_vec.push(self.decode_term()?);
+        }
+
+        Ok(term_vec)
+    }
+
+    fn decode_tuple(&mut self, arity: usize) -> Result<Term, Exception> {
+        let element_vec = self.decode_terms(arity)?;
+
+        self.process
+            .tuple_from_slice(&element_vec)
+            .map_err(|alloc| alloc.into())
+    }
+
+    fn atom_from_str(&self, name: &str) -> Result<Atom, Exception> {
+        if self.existing {
+            Atom::try_from_str_existing(name)
+        } else {
+            Atom::try_from_str(name)
+        }
+        .map_err(|atom_error| atom_error.into())
+    }
+
+    /// Every encoded term is at least 1 byte, so counts larger than the remaining bytes can't be
+    /// valid and shouldn't be used to preallocate.
+    fn capacity(&self, len: usize) -> usize {
+        len.min(self.bytes.len() - self.position)
+    }
+
+    fn lambda_closure(
+        &self,
+        module: Atom,
+        index: usize,
+        arity: u8,
+        creator: Term,
+        free_slice: &[Term],
+    ) -> Result<Term, Exception> {
+        if !creator.is_pid() {
+            return Err(badarg!().into());
+        }
+
+        let function = self.atom_from_str(&format!("-fun-{}-", index))?;
+
+        code::lambda_closure(self.process, module, function, arity, creator, free_slice)
+            .map_err(|alloc| alloc.into())
+    }
+
+    /// Local references are encoded with the `number` in the first two ID words and the
+    /// `scheduler_id` in the third, which is the same layout `erlang:term_to_binary` uses.
+    fn reference(&self, node: Atom, id_slice: &[u32]) -> Result<Term, Exception> {
+        let id_word = |index: usize| id_slice.get(index).cloned().unwrap_or(0);
+        let number = (id_word(0) as u64) | ((id_word(1) as u64) << 32);
+        let scheduler_id = unsafe { scheduler::ID::from_raw(id_word(2) as usize) };
+
+        match node::id(node) {
+            node::LOCAL_ID => self.process.reference_from_scheduler(scheduler_id, number),
+            node_id => self
+                .process
+                .external_reference_with_node_id(node_id, scheduler_id, number),
+        }
+        .map_err(|alloc| alloc.into())
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Exception> {
+        let end = self.position.checked_add(len).ok_or_else(|| badarg!())?;
+
+        match self.bytes.get(self.position..end) {
+            Some(bytes) => {
+                self.position = end;
+
+                Ok(bytes)
+            }
+            None => Err(badarg!().into()),
+        }
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Exception> {
+        self.read_bytes(mem::size_of::<u8>()).map(|bytes| bytes[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, Exception> {
+        self.read_bytes(mem::size_of::<u16>())
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Exception> {
+        self.read_bytes(mem::size_of::<u32>())
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, Exception> {
+        self.read_bytes(mem::size_of::<u64>()).map(|bytes| {
+            let mut be_bytes = [0; 8];
+            be_bytes.copy_from_slice(bytes);
+
+            u64::from_be_bytes(be_bytes)
+        })
+    }
+}
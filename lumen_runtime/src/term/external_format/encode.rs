@@ -0,0 +1,504 @@
+use core::mem;
+
+use alloc::vec::Vec;
+
+use std::io::Write;
+
+use hashbrown::HashMap;
+
+use libflate::lz77::{self, DefaultLz77Encoder};
+use libflate::zlib;
+
+use num_bigint::{BigInt, Sign};
+
+use liblumen_alloc::badarg;
+use liblumen_alloc::erts::exception::Exception;
+use liblumen_alloc::erts::term::binary::aligned_binary::AlignedBinary;
+use liblumen_alloc::erts::term::binary::maybe_aligned_maybe_binary::MaybeAlignedMaybeBinary;
+use liblumen_alloc::erts::term::binary::{IterableBitstring, MaybePartialByte};
+use liblumen_alloc::erts::term::{Atom, Closure, Cons, SubBinary, Term, TypedTerm};
+
+use crate::binary::ToBinaryOptions;
+use crate::node;
+use crate::term::external_format::{Tag, VERSION_NUMBER};
+
+/// Encodes `term` in the external term format, honoring the `compressed` and `minor_version`
+/// options of `erlang:term_to_binary/2`.
+pub fn encode(term: Term, options: &ToBinaryOptions) -> Result<Vec<u8>, Exception> {
+    let mut encoder = Encoder::new(options.minor_version);
+    encoder.push_u8(VERSION_NUMBER);
+    encoder.encode_term(term)?;
+
+    let bytes = encoder.bytes;
+
+    // Level `0` means no compression, as in `erlang:term_to_binary/2`.
+    if 0 < options.compression_level {
+        Ok(compress(bytes, options.compression_level))
+    } else {
+        Ok(bytes)
+    }
+}
+
+// Private
+
+/// Like `erlang:term_to_binary/2`, returns the uncompressed encoding when compressing does not
+/// make it any smaller.
+fn compress(bytes: Vec<u8>, level: u8) -> Vec<u8> {
+    // The version number is not part of the compressed data.
+    let uncompressed = &bytes[1..];
+
+    // `libflate` has no zlib levels, so each level instead doubles how far back repeated bytes
+    // are searched for, from 128 bytes at level `1` to the full 32KiB window at level `9`.
+    let window_size = lz77::MAX_WINDOW_SIZE >> (9 - level);
+    let lz77_encoder = DefaultLz77Encoder::with_window_size(window_size);
+    let zlib_options = zlib::EncodeOptions::with_lz77(lz77_encoder);
+
+    let compressed_data =
+        zlib::Encoder::with_options(Vec::new(), zlib_options).and_then(|mut zlib_encoder| {
+            zlib_encoder.write_all(uncompressed)?;
+
+            zlib_encoder.finish().into_result()
+        });
+
+    match compressed_data {
+        Ok(compressed_data)
+            if (compressed_data.len() + 1 + mem::size_of::<u32>()) < uncompressed.len() =>
+        {
+            let mut compressed = Vec::with_capacity(2 + 4 + compressed_data.len());
+            compressed.push(VERSION_NUMBER);
+            compressed.push(Tag::Compressed as u8);
+            compressed.extend_from_slice(&(uncompressed.len() as u32).to_be_bytes());
+            compressed.extend_from_slice(&compressed_data);
+
+            compressed
+        }
+        _ => bytes,
+    }
+}
+
+struct Encoder {
+    bytes: Vec<u8>,
+    minor_version: u8,
+}
+
+impl Encoder {
+    fn new(minor_version: u8) -> Self {
+        Self {
+            bytes: Vec::new(),
+            minor_version,
+        }
+    }
+
+    fn encode_term(&mut self, term: Term) -> Result<(), Exception> {
+        match term.to_typed_term().unwrap() {
+            TypedTerm::Atom(atom) => self.encode_atom(atom),
+            TypedTerm::Nil => {
+                self.push_tag(Tag::EmptyList);
+
+                Ok(())
+            }
+            TypedTerm::List(cons) => self.encode_list(&cons),
+            TypedTerm::SmallInteger(small_integer) => {
+                let i: isize = small_integer.into();
+
+                self.encode_integer(i);
+
+                Ok(())
+            }
+            TypedTerm::Pid(pid) => {
+                self.encode_pid(node::LOCAL_ID, pid.number(), pid.serial());
+
+                Ok(())
+            }
+            TypedTerm::Port(port) => {
+                self.encode_port(node::LOCAL_ID, port.as_usize());
+
+                Ok(())
+            }
+            TypedTerm::Boxed(boxed) => self.encode_boxed(boxed.to_typed_term().unwrap()),
+            _ => Err(badarg!().into()),
+        }
+    }
+
+    fn encode_boxed(&mut self, typed_term: TypedTerm) -> Result<(), Exception> {
+        match typed_term {
+            TypedTerm::BigInteger(big_integer) => {
+                let big_int: &BigInt = big_integer.as_ref().into();
+
+                self.encode_big_int(big_int);
+
+                Ok(())
+            }
+            TypedTerm::Float(float) => {
+                self.encode_float(float.into());
+
+                Ok(())
+            }
+            TypedTerm::Tuple(tuple) => {
+                let len = tuple.len();
+
+                if len <= (core::u8::MAX as usize) {
+                    self.push_tag(Tag::SmallTuple);
+                    self.push_u8(len as u8);
+                } else {
+                    self.push_tag(Tag::LargeTuple);
+                    self.push_u32(len as u32);
+                }
+
+                for element in tuple.iter() {
+                    self.encode_term(element)?;
+                }
+
+                Ok(())
+            }
+            TypedTerm::Map(map) => {
+                let hash_map: &HashMap<Term, Term> = map.as_ref();
+                let mut key_vec: Vec<&Term> = hash_map.keys().collect();
+                key_vec.sort();
+
+                self.push_tag(Tag::Map);
+                self.push_u32(key_vec.len() as u32);
+
+                for key in key_vec {
+                    self.encode_term(*key)?;
+                    self.encode_term(hash_map[key])?;
+                }
+
+                Ok(())
+            }
+            TypedTerm::HeapBinary(heap_binary) => {
+                self.encode_binary(heap_binary.as_bytes());
+
+                Ok(())
+            }
+            TypedTerm::ProcBin(process_binary) => {
+                self.encode_binary(process_binary.as_bytes());
+
+                Ok(())
+            }
+            TypedTerm::SubBinary(subbinary) => {
+                self.encode_subbinary(&subbinary);
+
+                Ok(())
+            }
+            TypedTerm::Reference(reference) => {
+                self.encode_reference(
+                    node::LOCAL_ID,
+                    reference.scheduler_id().as_usize(),
+                    reference.number(),
+                );
+
+                Ok(())
+            }
+            TypedTerm::ExternalPid(external_pid) => {
+                let pid = external_pid.pid();
+
+                self.encode_pid(external_pid.node().id(), pid.number(), pid.serial());
+
+                Ok(())
+            }
+            TypedTerm::ExternalPort(external_port) => {
+                self.encode_port(external_port.node().id(), external_port.port().as_usize());
+
+                Ok(())
+            }
+            TypedTerm::ExternalReference(external_reference) => {
+                let reference = external_reference.reference();
+
+                self.encode_reference(
+                    external_reference.node().id(),
+                    reference.scheduler_id().as_usize(),
+                    reference.number(),
+                );
+
+                Ok(())
+            }
+            TypedTerm::Closure(closure) => self.encode_closure(&closure),
+            _ => Err(badarg!().into()),
+        }
+    }
+
+    /// Like `liblumen_beam::serialization::etf::Encoder`, atoms use `ATOM_EXT` when they are ASCII
+    /// and `ATOM_UTF8_EXT` otherwise, unless `minor_version` is `2`, in which case all atoms are
+    /// encoded as UTF-8 using the smallest tag that fits.
+    fn encode_atom(&mut self, atom: Atom) -> Result<(), Exception> {
+        let name = atom.name();
+        let len = name.len();
+
+        if (core::u16::MAX as usize) < len {
+            return Err(badarg!().into());
+        }
+
+        if self.minor_version < 2 {
+            if name.is_ascii() {
+                self.push_tag(Tag::Atom);
+            } else {
+                self.push_tag(Tag::AtomUTF8);
+            }
+
+            self.push_u16(len as u16);
+        } else if len <= (core::u8::MAX as usize) {
+            self.push_tag(Tag::SmallAtomUTF8);
+            self.push_u8(len as u8);
+        } else {
+            self.push_tag(Tag::AtomUTF8);
+            self.push_u16(len as u16);
+        }
+
+        self.bytes.extend_from_slice(name.as_bytes());
+
+        Ok(())
+    }
+
+    fn encode_big_int(&mut self, big_int: &BigInt) {
+        let (sign, bytes) = big_int.to_bytes_le();
+        let len = bytes.len();
+
+        if len <= (core::u8::MAX as usize) {
+            self.push_tag(Tag::SmallBigInteger);
+            self.push_u8(len as u8);
+        } else {
+            self.push_tag(Tag::LargeBigInteger);
+            self.push_u32(len as u32);
+        }
+
+        self.push_u8(match sign {
+            Sign::Minus => 1,
+            _ => 0,
+        });
+        self.bytes.extend_from_slice(&bytes);
+    }
+
+    fn encode_binary(&mut self, bytes: &[u8]) {
+        self.push_tag(Tag::Binary);
+        self.push_u32(bytes.len() as u32);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Closures with no env are encoded as `EXPORT_EXT`.  Closures made by
+    /// `crate::code::lambda_closure` are encoded as `NEW_FUN_EXT` for the `-fun-index-` function
+    /// they call.  Any other closure's env is native code state that can't be encoded, so it fails
+    /// with `badarg`.
+    fn encode_closure(&mut self, closure: &Closure) -> Result<(), Exception> {
+        let module_function_arity = closure.module_function_arity();
+
+        if closure.env_len == 0 {
+            self.push_tag(Tag::Export);
+            self.encode_atom(module_function_arity.module)?;
+            self.encode_atom(module_function_arity.function)?;
+            self.encode_integer(module_function_arity.arity as isize);
+
+            return Ok(());
+        }
+
+        let index = lambda_index(module_function_arity.function).ok_or_else(|| badarg!())?;
+        let free_list = closure.env_slice()[0];
+        let free_vec: Vec<Term> = match free_list.to_typed_term().unwrap() {
+            TypedTerm::Nil => Vec::new(),
+            TypedTerm::List(cons) => cons
+                .into_iter()
+                .collect::<core::result::Result<_, _>>()
+                .map_err(|_| badarg!())?,
+            _ => return Err(badarg!().into()),
+        };
+
+        self.push_tag(Tag::NewFunction);
+
+        let size_position = self.bytes.len();
+        self.push_u32(0);
+
+        self.push_u8(module_function_arity.arity);
+        // The MD5 of the module's code isn't known, so `Uniq` is all zeros.
+        self.bytes.extend_from_slice(&[0; 16]);
+        self.push_u32(index);
+        self.push_u32(free_vec.len() as u32);
+        self.encode_atom(module_function_arity.module)?;
+        self.encode_integer(index as isize);
+        self.encode_integer(0);
+        self.encode_term(closure.creator())?;
+
+        for free in free_vec {
+            self.encode_term(free)?;
+        }
+
+        let size = (self.bytes.len() - size_position) as u32;
+        self.bytes[size_position..size_position + 4].copy_from_slice(&size.to_be_bytes());
+
+        Ok(())
+    }
+
+    /// `minor_version` `0` encodes floats as `FLOAT_EXT`, the `%.20e` text format padded to 31
+    /// bytes, while later versions use the 8 byte IEEE format of `NEW_FLOAT_EXT`.
+    fn encode_float(&mut self, f: f64) {
+        if self.minor_version == 0 {
+            let formatted = format!("{:.20e}", f);
+            let (mantissa, exponent) = formatted.split_at(formatted.find('e').unwrap());
+            let exponent: i32 = exponent[1..].parse().unwrap();
+            let sign = if exponent < 0 { '-' } else { '+' };
+            let float_string = format!("{}e{}{:02}", mantissa, sign, exponent.abs());
+
+            let mut float_bytes = [0; 31];
+            float_bytes[..float_string.len()].copy_from_slice(float_string.as_bytes());
+
+            self.push_tag(Tag::Float);
+            self.bytes.extend_from_slice(&float_bytes);
+        } else {
+            self.push_tag(Tag::NewFloat);
+            self.bytes.extend_from_slice(&f.to_bits().to_be_bytes());
+        }
+    }
+
+    fn encode_integer(&mut self, i: isize) {
+        if 0 <= i && i <= (core::u8::MAX as isize) {
+            self.push_tag(Tag::SmallInteger);
+            self.push_u8(i as u8);
+        } else if (core::i32::MIN as isize) <= i && i <= (core::i32::MAX as isize) {
+            self.push_tag(Tag::Integer);
+            self.push_u32(i as i32 as u32);
+        } else {
+            self.encode_big_int(&i.into());
+        }
+    }
+
+    /// Proper lists of only bytes that are short enough are encoded as `STRING_EXT`, the same as
+    /// `liblumen_beam::serialization::etf::Encoder`.
+    fn encode_list(&mut self, cons: &Cons) -> Result<(), Exception> {
+        let mut element_vec: Vec<Term> = Vec::new();
+        let mut tail = Term::NIL;
+
+        for result in cons.into_iter() {
+            match result {
+                Ok(element) => element_vec.push(element),
+                Err(improper_list) => tail = improper_list.tail,
+            }
+        }
+
+        let byte_vec: Option<Vec<u8>> = if tail.is_nil()
+            && element_vec.len() <= (core::u16::MAX as usize)
+        {
+            element_vec
+                .iter()
+                .map(|element| match element.to_typed_term().unwrap() {
+                    TypedTerm::SmallInteger(small_integer) => {
+                        let i: isize = small_integer.into();
+
+                        if 0 <= i && i <= (core::u8::MAX as isize) {
+                            Some(i as u8)
+                        } else {
+                            None
+                        }
+                    }
+                    _ => None,
+                })
+                .collect()
+        } else {
+            None
+        };
+
+        match byte_vec {
+            Some(byte_vec) => {
+                self.push_tag(Tag::ByteList);
+                self.push_u16(byte_vec.len() as u16);
+                self.bytes.extend_from_slice(&byte_vec);
+            }
+            None => {
+                self.push_tag(Tag::List);
+                self.push_u32(element_vec.len() as u32);
+
+                for element in element_vec {
+                    self.encode_term(element)?;
+                }
+
+                self.encode_term(tail)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn encode_node(&mut self, node_id: usize) {
+        let node_name = node::name(node_id);
+
+        // Node names are always shorter than `u16::MAX`
+        self.encode_atom(node_name).unwrap();
+    }
+
+    fn encode_pid(&mut self, node_id: usize, number: usize, serial: usize) {
+        self.push_tag(Tag::Pid);
+        self.encode_node(node_id);
+        self.push_u32(number as u32);
+        self.push_u32(serial as u32);
+        // creation
+        self.push_u8(0);
+    }
+
+    fn encode_port(&mut self, node_id: usize, id: usize) {
+        self.push_tag(Tag::Port);
+        self.encode_node(node_id);
+        self.push_u32(id as u32);
+        // creation
+        self.push_u8(0);
+    }
+
+    /// The `number` is split across the first two ID words and the `scheduler_id` is in the third,
+    /// which is the layout that `crate::term::external_format::decode` expects.
+    fn encode_reference(&mut self, node_id: usize, scheduler_id: usize, number: u64) {
+        self.push_tag(Tag::NewReference);
+        self.push_u16(3);
+        self.encode_node(node_id);
+        // creation
+        self.push_u8(0);
+        self.push_u32(number as u32);
+        self.push_u32((number >> 32) as u32);
+        self.push_u32(scheduler_id as u32);
+    }
+
+    /// Byte-aligned subbinaries are encoded as `BINARY_EXT`, while those with a partial byte are
+    /// encoded as `BIT_BINARY_EXT` with the partial byte's bits in its most significant bits.
+    fn encode_subbinary(&mut self, subbinary: &SubBinary) {
+        let mut byte_vec: Vec<u8> = subbinary.full_byte_iter().collect();
+
+        if subbinary.is_binary() {
+            self.encode_binary(&byte_vec);
+        } else {
+            let partial_byte_bit_len = subbinary.partial_byte_bit_len();
+            let partial_byte = subbinary
+                .partial_byte_bit_iter()
+                .enumerate()
+                .fold(0_u8, |acc, (index, bit)| acc | (bit << (7 - index)));
+            byte_vec.push(partial_byte);
+
+            self.push_tag(Tag::BitBinary);
+            self.push_u32(byte_vec.len() as u32);
+            self.push_u8(partial_byte_bit_len);
+            self.bytes.extend_from_slice(&byte_vec);
+        }
+    }
+
+    fn push_tag(&mut self, tag: Tag) {
+        self.push_u8(tag as u8);
+    }
+
+    fn push_u8(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    fn push_u16(&mut self, u: u16) {
+        self.bytes.extend_from_slice(&u.to_be_bytes());
+    }
+
+    fn push_u32(&mut self, u: u32) {
+        self.bytes.extend_from_slice(&u.to_be_bytes());
+    }
+}
+
+/// Parses the `index` out of the `-fun-index-` function name used by lambda closures.
+fn lambda_index(function: Atom) -> Option<u32> {
+    let name = function.name();
+
+    if name.starts_with("-fun-") && name.ends_with('-') && 6 < name.len() {
+        name[5..name.len() - 1].parse().ok()
+    } else {
+        None
+    }
+}
@@ -15,6 +15,7 @@ use hashbrown::HashMap;
 use liblumen_core::locks::Mutex;
 
 use liblumen_alloc::erts::exception::system::Alloc;
+use liblumen_alloc::erts::process::ProcessFlags;
 use liblumen_alloc::erts::term::{atom_unchecked, reference, Atom, Reference, Term};
 use liblumen_alloc::CloneToProcess;
 use liblumen_alloc::Process;
@@ -56,6 +57,23 @@ pub fn start(
     result
 }
 
+/// Starts the timer of a `receive ... after`.  Instead of sending a message, when it times out
+/// `ProcessFlags::TimedOut` is set on `arc_process` and `arc_process` is woken if it is waiting.
+pub fn start_receive(
+    monotonic_time_milliseconds: Milliseconds,
+    arc_process: &Arc<Process>,
+) -> Reference {
+    let scheduler = Scheduler::current();
+
+    let reference_number = scheduler.hierarchy.write().start_receive(
+        monotonic_time_milliseconds,
+        arc_process,
+        &scheduler,
+    );
+
+    Reference::new(scheduler.id, reference_number)
+}
+
 /// Times out the timers for the thread that have timed out since the last time `timeout` was
 /// called.
 #[cfg(all(not(target_arch = "wasm32"), test))]
@@ -152,18 +170,43 @@ impl Hierarchy {
                 process_tuple.clone_to_fragment()?
             }
         };
-        let position = self.position(monotonic_time_milliseconds);
 
-        let timer = Timer {
+        self.insert(Timer {
             reference_number,
             monotonic_time_milliseconds,
             destination,
-            message_heap: Mutex::new(message::HeapFragment {
+            message_heap: Mutex::new(Some(message::HeapFragment {
                 heap_fragment,
                 term: heap_fragment_message,
-            }),
-            position: Mutex::new(position),
-        };
+            })),
+            position: Mutex::new(self.position(monotonic_time_milliseconds)),
+        });
+
+        Ok(process_reference)
+    }
+
+    fn start_receive(
+        &mut self,
+        monotonic_time_milliseconds: Milliseconds,
+        arc_process: &Arc<Process>,
+        scheduler: &Scheduler,
+    ) -> reference::Number {
+        let reference_number = scheduler.next_reference_number();
+
+        self.insert(Timer {
+            reference_number,
+            monotonic_time_milliseconds,
+            destination: Destination::Process(Arc::downgrade(arc_process)),
+            message_heap: Mutex::new(None),
+            position: Mutex::new(self.position(monotonic_time_milliseconds)),
+        });
+
+        reference_number
+    }
+
+    fn insert(&mut self, timer: Timer) {
+        let reference_number = timer.reference_number;
+        let position = *timer.position.lock();
 
         let arc_timer = Arc::new(timer);
         let timeoutable = Arc::clone(&arc_timer);
@@ -178,8 +221,11 @@ impl Hierarchy {
 
         self.timer_by_reference_number
             .insert(reference_number, cancellable);
+    }
 
-        Ok(process_reference)
+    /// Whether there are no timers left to time out
+    pub fn is_empty(&self) -> bool {
+        self.timer_by_reference_number.is_empty()
     }
 
     pub fn timeout(&mut self) {
@@ -358,7 +404,9 @@ struct Timer {
     reference_number: reference::Number,
     monotonic_time_milliseconds: Milliseconds,
     destination: Destination,
-    message_heap: Mutex<message::HeapFragment>,
+    // `None` for the timer of a `receive`, which times out the `receive` instead of sending a
+    // message
+    message_heap: Mutex<Option<message::HeapFragment>>,
     position: Mutex<Position>,
 }
 
@@ -383,12 +431,21 @@ impl Timer {
         };
 
         if let Some(destination_arc_process) = option_destination_arc_process {
-            let message::HeapFragment {
-                heap_fragment,
-                term,
-            } = self.message_heap.into_inner();
+            match self.message_heap.into_inner() {
+                Some(message::HeapFragment {
+                    heap_fragment,
+                    term,
+                }) => destination_arc_process.send_heap_message(heap_fragment, term),
+                None => {
+                    destination_arc_process.set_flags(ProcessFlags::TimedOut);
+                }
+            }
 
-            destination_arc_process.send_heap_message(heap_fragment, term);
+            if destination_arc_process.stop_waiting() {
+                if let Some(scheduler) = destination_arc_process.scheduler() {
+                    scheduler.stop_waiting(&destination_arc_process);
+                }
+            }
         }
     }
 }